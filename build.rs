@@ -53,7 +53,7 @@ pub fn main() -> anyhow::Result<()> {
                         entry.to_str().unwrap(),
                     ])
                     .output()?,
-                Some("spirv") | Some("ptx") | Some("cubin") => continue,
+                Some("spirv") | Some("ptx") | Some("cubin") | Some("hlsli") => continue,
                 _ => Command::new("glslc")
                     .args([
                         entry.to_str().unwrap(),
@@ -74,17 +74,5 @@ pub fn main() -> anyhow::Result<()> {
         }
     }
 
-    #[cfg(feature = "nvpro_sample_gop")]
-    {
-        println!("cargo:rerun-if-changed=src/VkVideoGopStructure.cpp");
-        println!("cargo:rerun-if-changed=src/VkVideoGopStructure.h");
-        cc::Build::new()
-            .file("src/VkVideoGopStructure.cpp")
-            .cpp(true)
-            .flag_if_supported("-fkeep-inline-functions")
-            .include("src")
-            .compile("vkvideogop");
-    }
-
     Ok(())
 }
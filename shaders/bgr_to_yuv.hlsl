@@ -0,0 +1,30 @@
+// Read through a UNORM view, so sRGB swapchains return the encoded values the matrix applies to
+[[vk::binding(0), vk::image_format("rgba8")]] RWTexture2D<float4> rgba;
+[[vk::binding(1), vk::image_format("r8")]] RWTexture2D<float> y;
+[[vk::binding(2), vk::image_format("rg8")]] RWTexture2D<float2> uv;
+
+#include "scale.hlsli"
+
+// InputTransfer in color.rs
+static const uint TRANSFER_LINEAR = 2;
+
+float3 linear_to_srgb(float3 c) {
+  return lerp(1.055 * pow(c, 1.0 / 2.4) - 0.055, c * 12.92, step(c, 0.0031308));
+}
+
+float3 load_rgb(int2 pos) {
+  float3 rgb = scaled_input(pos);
+  return cb.input_transfer == TRANSFER_LINEAR ? linear_to_srgb(saturate(rgb)) : rgb;
+}
+
+#include "ycbcr.hlsli"
+
+[numthreads(8, 8, 1)]
+void main(uint3 id : SV_DispatchThreadID) {
+  y[id.xy] = round(luma_code(luma(load_rgb(id.xy)), 255.0)) / 255.0;
+
+  [branch]
+  if ((id.x & 1) == 0 && (id.y & 1) == 0) {
+    uv[id.xy / 2] = round(chroma_code(chroma(subsampled_rgb(id.xy)), 255.0)) / 255.0;
+  }
+}
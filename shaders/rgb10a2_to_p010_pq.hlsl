@@ -0,0 +1,3 @@
+[[vk::binding(0), vk::image_format("rgb10a2")]] RWTexture2D<float4> rgba;
+
+#include "rgb_to_p010_pq.hlsli"
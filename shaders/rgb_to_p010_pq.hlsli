@@ -0,0 +1,74 @@
+// Converts HDR10, sRGB or scRGB input to P010 with PQ transfer and BT.2020 primaries, the
+// BT.2020 matrix is set by ColorConversion in color.rs.
+// Included by the shaders declaring the input image `rgba` in the swapchain's storage format.
+
+// P010 planes written through R16/R16G16 UNORM views, 10 bit in the high bits
+[[vk::binding(1), vk::image_format("r16")]] RWTexture2D<float> y;
+[[vk::binding(2), vk::image_format("rg16")]] RWTexture2D<float2> uv;
+
+#include "scale.hlsli"
+
+// InputTransfer in color.rs
+static const uint TRANSFER_PQ = 0;
+static const uint TRANSFER_SRGB = 1;
+static const uint TRANSFER_LINEAR = 2;
+
+// BT.2408 reference white for SDR content and the scRGB 1.0 level in cd/m²
+static const float SDR_WHITE_NITS = 203.0;
+static const float SCRGB_WHITE_NITS = 80.0;
+
+// BT.709 to BT.2020 primaries for linear light (BT.2087)
+static const float3x3 BT709_TO_BT2020 = {
+  0.627404, 0.329283, 0.043313,
+  0.069097, 0.919541, 0.011362,
+  0.016391, 0.088013, 0.895595,
+};
+
+float3 srgb_to_linear(float3 c) {
+  return lerp(pow((c + 0.055) / 1.055, 2.4), c / 12.92, step(c, 0.04045));
+}
+
+// SMPTE ST 2084 inverse EOTF, absolute luminance in cd/m² to non-linear [0, 1]
+float3 pq_from_nits(float3 nits) {
+  const float m1 = 2610.0 / 16384.0;
+  const float m2 = 2523.0 / 4096.0 * 128.0;
+  const float c1 = 3424.0 / 4096.0;
+  const float c2 = 2413.0 / 4096.0 * 32.0;
+  const float c3 = 2392.0 / 4096.0 * 32.0;
+  float3 ym = pow(saturate(nits / 10000.0), m1);
+  return pow((c1 + c2 * ym) / (1.0 + c3 * ym), m2);
+}
+
+float3 to_pq_bt2020(float3 rgb) {
+  [branch]
+  if (cb.input_transfer == TRANSFER_PQ) {
+    return rgb;
+  }
+  float3 nits = cb.input_transfer == TRANSFER_LINEAR
+                    ? rgb * SCRGB_WHITE_NITS
+                    : srgb_to_linear(saturate(rgb)) * SDR_WHITE_NITS;
+  // scRGB may be negative outside of the BT.709 gamut
+  return pq_from_nits(max(mul(BT709_TO_BT2020, nits), 0.0));
+}
+
+float3 load_rgb(int2 pos) {
+  return to_pq_bt2020(scaled_input(pos));
+}
+
+#include "ycbcr.hlsli"
+
+// 10 bit code value in the high bits of a 16 bit UNORM
+float quantize(float code) {
+  return round(code) * 64.0 / 65535.0;
+}
+
+[numthreads(8, 8, 1)]
+void main(uint3 id : SV_DispatchThreadID) {
+  y[id.xy] = quantize(luma_code(luma(load_rgb(id.xy)), 1023.0));
+
+  [branch]
+  if ((id.x & 1) == 0 && (id.y & 1) == 0) {
+    float2 cbcr = chroma_code(chroma(subsampled_rgb(id.xy)), 1023.0);
+    uv[id.xy / 2] = float2(quantize(cbcr.x), quantize(cbcr.y));
+  }
+}
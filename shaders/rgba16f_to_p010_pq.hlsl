@@ -0,0 +1,3 @@
+[[vk::binding(0), vk::image_format("rgba16f")]] RWTexture2D<float4> rgba;
+
+#include "rgb_to_p010_pq.hlsli"
@@ -0,0 +1,96 @@
+// Scales the input image `rgba` declared by the includer to the image rectangle of the output,
+// see Scaling in scale.rs. The rest of the output is black.
+
+// ScaleFilter in settings.rs
+[[vk::constant_id(4)]] const uint SCALE_FILTER = 0;
+static const uint FILTER_BILINEAR = 0;
+static const uint FILTER_LANCZOS = 1;
+
+struct PushConstants {
+  uint2 input_size;
+  int2 image_offset;
+  uint2 image_size;
+  // InputTransfer in color.rs
+  uint input_transfer;
+};
+[[vk::push_constant]] PushConstants cb;
+
+static const float PI = 3.14159265;
+static const float LANCZOS_LOBES = 3.0;
+
+float3 load_input(int2 pos) {
+  return rgba[clamp(pos, 0, int2(cb.input_size) - 1)].rgb;
+}
+
+float3 bilinear(float2 src) {
+  int2 pos = int2(floor(src));
+  float2 f = src - pos;
+  return lerp(lerp(load_input(pos), load_input(pos + int2(1, 0)), f.x),
+              lerp(load_input(pos + int2(0, 1)), load_input(pos + int2(1, 1)), f.x), f.y);
+}
+
+float lanczos(float x) {
+  if (abs(x) < 1e-5) {
+    return 1.0;
+  }
+  if (abs(x) >= LANCZOS_LOBES) {
+    return 0.0;
+  }
+  float px = PI * x;
+  return LANCZOS_LOBES * sin(px) * sin(px / LANCZOS_LOBES) / (px * px);
+}
+
+// The kernel is widened by the scale factor when downscaling, otherwise it would alias
+float3 lanczos_filter(float2 src, float2 scale) {
+  float2 width = max(scale, 1.0);
+  int2 first = int2(floor(src - LANCZOS_LOBES * width)) + 1;
+  int2 last = int2(floor(src + LANCZOS_LOBES * width));
+  float3 sum = 0.0;
+  float weight_sum = 0.0;
+  for (int row = first.y; row <= last.y; row++) {
+    float weight_y = lanczos((row - src.y) / width.y);
+    for (int column = first.x; column <= last.x; column++) {
+      float weight = weight_y * lanczos((column - src.x) / width.x);
+      sum += weight * load_input(int2(column, row));
+      weight_sum += weight;
+    }
+  }
+  return sum / weight_sum;
+}
+
+// Average of the input area from `start` to `end` covered by an output pixel
+float3 box_filter(float2 start, float2 end) {
+  float3 sum = 0.0;
+  for (int row = int(floor(start.y)); row < int(ceil(end.y)); row++) {
+    float weight_y = min(end.y, row + 1.0) - max(start.y, float(row));
+    for (int column = int(floor(start.x)); column < int(ceil(end.x)); column++) {
+      float weight = weight_y * (min(end.x, column + 1.0) - max(start.x, float(column)));
+      sum += weight * load_input(int2(column, row));
+    }
+  }
+  return sum / ((end.x - start.x) * (end.y - start.y));
+}
+
+// Input pixel at the output position `pos`
+float3 scaled_input(int2 pos) {
+  int2 local = pos - cb.image_offset;
+  [branch]
+  if (any(local < 0) || any(local >= int2(cb.image_size))) {
+    return 0.0;
+  }
+  [branch]
+  if (all(cb.image_size == cb.input_size)) {
+    return load_input(local);
+  }
+  float2 scale = float2(cb.input_size) / float2(cb.image_size);
+  // input position of the output pixel center
+  float2 src = (local + 0.5) * scale - 0.5;
+  [branch]
+  if (SCALE_FILTER == FILTER_BILINEAR) {
+    return bilinear(src);
+  } else if (SCALE_FILTER == FILTER_LANCZOS) {
+    return lanczos_filter(src, scale);
+  } else {
+    return box_filter(local * scale, (local + 1.0) * scale);
+  }
+}
@@ -0,0 +1,55 @@
+// R'G'B' to Y'CbCr with the matrix, range and chroma siting of ColorConversion in color.rs.
+// Included after the shader defined `float3 load_rgb(int2 pos)`, which returns the non-linear
+// pixel at the output position `pos` in [0, 1].
+
+[[vk::constant_id(0)]] const float KR = 0.2126;
+[[vk::constant_id(1)]] const float KB = 0.0722;
+[[vk::constant_id(2)]] const bool FULL_RANGE = false;
+[[vk::constant_id(3)]] const uint CHROMA_SITING = 0;
+
+// ChromaSiting in settings.rs
+static const uint SITING_LEFT = 0;
+static const uint SITING_CENTER = 1;
+
+float luma(float3 rgb) {
+  return dot(float3(KR, 1.0 - KR - KB, KB), rgb);
+}
+
+// Cb and Cr in [-0.5, 0.5]
+float2 chroma(float3 rgb) {
+  float l = luma(rgb);
+  return float2((rgb.b - l) / (2.0 * (1.0 - KB)), (rgb.r - l) / (2.0 * (1.0 - KR)));
+}
+
+// Code values of ITU-T H.273 for a bit depth with the maximum code `max_code`
+float luma_code(float value, float max_code) {
+  float code = FULL_RANGE ? value * max_code : (219.0 * value + 16.0) * (max_code + 1.0) / 256.0;
+  return clamp(code, 0.0, max_code);
+}
+
+float2 chroma_code(float2 value, float max_code) {
+  float2 code = FULL_RANGE ? value * max_code + (max_code + 1.0) / 2.0
+                           : (224.0 * value + 128.0) * (max_code + 1.0) / 256.0;
+  return clamp(code, 0.0, max_code);
+}
+
+// [1 2 1] / 4 filter around `pos` horizontally
+float3 horizontal_121(int2 pos) {
+  return 0.25 * (load_rgb(pos - int2(1, 0)) + 2.0 * load_rgb(pos) + load_rgb(pos + int2(1, 0)));
+}
+
+// R'G'B' at the chroma sample position of the 2x2 block with the top left pixel `pos`
+float3 subsampled_rgb(int2 pos) {
+  [branch]
+  if (CHROMA_SITING == SITING_CENTER) {
+    return 0.25 * (load_rgb(pos) + load_rgb(pos + int2(1, 0)) + load_rgb(pos + int2(0, 1)) +
+                   load_rgb(pos + int2(1, 1)));
+  } else if (CHROMA_SITING == SITING_LEFT) {
+    // co-sited with the left column, between the two rows
+    return 0.5 * (horizontal_121(pos) + horizontal_121(pos + int2(0, 1)));
+  } else {
+    // co-sited with the top left pixel
+    return 0.25 * (horizontal_121(pos - int2(0, 1)) + 2.0 * horizontal_121(pos) +
+                   horizontal_121(pos + int2(0, 1)));
+  }
+}
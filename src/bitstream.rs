@@ -2,32 +2,56 @@ use ash::vk;
 use bitstream_io::{BigEndian, BitWrite, BitWriter};
 use std::io::Write;
 
+use crate::color::HdrMetadata;
+
 const START_CODE: u32 = 0x00_00_00_01;
-const CONSTRAINED_SET_FLAGS3_RESERVED_ZERO_BITS5: u32 = 0x00000000;
 const FORBIDDEN_ZERO_BIT: u32 = 0;
 const NAL_REF_IDC_SPS: u32 = 3;
 const NAL_REF_IDC_PPS: u32 = 3;
 const NAL_NAL_UNIT_TYPE_SPS: u32 = 7;
 const NAL_NAL_UNIT_TYPE_PPS: u32 = 8;
 const RBSP_STOP_ONE_BIT: u32 = 1;
+const H265_NAL_UNIT_TYPE_VPS: u32 = 32;
+const H265_NAL_UNIT_TYPE_SPS: u32 = 33;
+const H265_NAL_UNIT_TYPE_PPS: u32 = 34;
+const H265_NAL_UNIT_TYPE_PREFIX_SEI: u32 = 39;
+const SEI_PAYLOAD_TYPE_MASTERING_DISPLAY_COLOUR_VOLUME: u32 = 137;
+const SEI_PAYLOAD_TYPE_CONTENT_LIGHT_LEVEL_INFO: u32 = 144;
+const OBU_TYPE_METADATA: u8 = 5;
+const METADATA_TYPE_HDR_CLL: u8 = 1;
+const METADATA_TYPE_HDR_MDCV: u8 = 2;
+const H265_NUH_TEMPORAL_ID_PLUS1: u32 = 1;
+const EMULATION_PREVENTION_THREE_BYTE: u8 = 0x03;
+
+/// Profiles which signal chroma format, bit depth and scaling matrices in the SPS
+const H264_HIGH_PROFILE_IDCS: [u32; 13] =
+    [100, 110, 122, 244, 44, 83, 86, 118, 128, 138, 139, 134, 135];
+const H264_CHROMA_FORMAT_IDC_444: u32 = 3;
 
-// TODO: remove? does not currently handle 0x000003
 pub fn write_h264_sps(
     writer: &mut impl Write,
     sps: &vk::native::StdVideoH264SequenceParameterSet,
 ) -> std::io::Result<()> {
-    let mut writer = BitWriter::<_, BigEndian>::new(writer);
-
-    u(32, &mut writer, START_CODE)?;
-    u(1, &mut writer, FORBIDDEN_ZERO_BIT)?;
-    u(2, &mut writer, NAL_REF_IDC_SPS)?;
-    u(5, &mut writer, NAL_NAL_UNIT_TYPE_SPS)?;
+    let mut rbsp = Vec::new();
+    write_h264_sps_rbsp(&mut BitWriter::<_, BigEndian>::new(&mut rbsp), sps)?;
+    write_h264_nal_unit(writer, NAL_REF_IDC_SPS, NAL_NAL_UNIT_TYPE_SPS, &rbsp)
+}
 
-    u(8, &mut writer, sps.profile_idc)?;
-    u(8, &mut writer, CONSTRAINED_SET_FLAGS3_RESERVED_ZERO_BITS5)?;
+fn write_h264_sps_rbsp<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    sps: &vk::native::StdVideoH264SequenceParameterSet,
+) -> std::io::Result<()> {
+    u(8, writer, sps.profile_idc)?;
+    u(1, writer, sps.flags.constraint_set0_flag())?;
+    u(1, writer, sps.flags.constraint_set1_flag())?;
+    u(1, writer, sps.flags.constraint_set2_flag())?;
+    u(1, writer, sps.flags.constraint_set3_flag())?;
+    u(1, writer, sps.flags.constraint_set4_flag())?;
+    u(1, writer, sps.flags.constraint_set5_flag())?;
+    u(2, writer, 0)?; // reserved_zero_2bits
     u(
         8,
-        &mut writer,
+        writer,
         match sps.level_idc {
             vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_1_0 => 10,
             vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_1_1 => 11,
@@ -51,42 +75,204 @@ pub fn write_h264_sps(
             _ => unreachable!(),
         },
     )?;
-    ue(&mut writer, sps.seq_parameter_set_id.into())?;
-    ue(&mut writer, sps.log2_max_frame_num_minus4.into())?;
-    ue(&mut writer, sps.pic_order_cnt_type.into())?;
-    if sps.pic_order_cnt_type == 0 {
-        ue(&mut writer, sps.log2_max_pic_order_cnt_lsb_minus4.into())?;
-    } else {
-        todo!();
+    ue(writer, sps.seq_parameter_set_id.into())?;
+    if H264_HIGH_PROFILE_IDCS.contains(&sps.profile_idc) {
+        ue(writer, sps.chroma_format_idc.into())?;
+        if sps.chroma_format_idc == H264_CHROMA_FORMAT_IDC_444 {
+            u(1, writer, sps.flags.separate_colour_plane_flag())?;
+        }
+        ue(writer, sps.bit_depth_luma_minus8.into())?;
+        ue(writer, sps.bit_depth_chroma_minus8.into())?;
+        u(1, writer, sps.flags.qpprime_y_zero_transform_bypass_flag())?;
+        u(1, writer, sps.flags.seq_scaling_matrix_present_flag())?;
+        if sps.flags.seq_scaling_matrix_present_flag() == 1 {
+            let list_count = if sps.chroma_format_idc == H264_CHROMA_FORMAT_IDC_444 {
+                12
+            } else {
+                8
+            };
+            // SAFETY: the scaling list pointer has to be valid if the present flag is set
+            let scaling_lists = unsafe { sps.pScalingLists.as_ref() }
+                .ok_or_else(|| std::io::Error::other("scaling matrix is present but missing"))?;
+            write_h264_scaling_lists(writer, scaling_lists, list_count)?;
+        }
     }
-    ue(&mut writer, sps.max_num_ref_frames.into())?;
-    u(
-        1,
-        &mut writer,
-        sps.flags.gaps_in_frame_num_value_allowed_flag(),
-    )?;
-    ue(&mut writer, sps.pic_width_in_mbs_minus1.into())?;
-    ue(&mut writer, sps.pic_height_in_map_units_minus1.into())?;
-    u(1, &mut writer, sps.flags.frame_mbs_only_flag())?;
+    ue(writer, sps.log2_max_frame_num_minus4.into())?;
+    ue(writer, sps.pic_order_cnt_type.into())?;
+    match sps.pic_order_cnt_type {
+        vk::native::StdVideoH264PocType_STD_VIDEO_H264_POC_TYPE_0 => {
+            ue(writer, sps.log2_max_pic_order_cnt_lsb_minus4.into())?;
+        }
+        vk::native::StdVideoH264PocType_STD_VIDEO_H264_POC_TYPE_1 => {
+            u(1, writer, sps.flags.delta_pic_order_always_zero_flag())?;
+            se(writer, sps.offset_for_non_ref_pic.into())?;
+            se(writer, sps.offset_for_top_to_bottom_field.into())?;
+            ue(writer, sps.num_ref_frames_in_pic_order_cnt_cycle.into())?;
+            let offsets = if sps.num_ref_frames_in_pic_order_cnt_cycle == 0 {
+                &[][..]
+            } else if sps.pOffsetForRefFrame.is_null() {
+                return Err(std::io::Error::other(
+                    "reference frame offsets are present but missing",
+                ));
+            } else {
+                // SAFETY: the pointer holds num_ref_frames_in_pic_order_cnt_cycle offsets
+                unsafe {
+                    std::slice::from_raw_parts(
+                        sps.pOffsetForRefFrame,
+                        sps.num_ref_frames_in_pic_order_cnt_cycle.into(),
+                    )
+                }
+            };
+            for offset in offsets {
+                se(writer, (*offset).into())?;
+            }
+        }
+        _ => {}
+    }
+    ue(writer, sps.max_num_ref_frames.into())?;
+    u(1, writer, sps.flags.gaps_in_frame_num_value_allowed_flag())?;
+    ue(writer, sps.pic_width_in_mbs_minus1.into())?;
+    ue(writer, sps.pic_height_in_map_units_minus1.into())?;
+    u(1, writer, sps.flags.frame_mbs_only_flag())?;
     if sps.flags.frame_mbs_only_flag() != 1 {
-        writer.write(1, sps.flags.mb_adaptive_frame_field_flag())?;
+        u(1, writer, sps.flags.mb_adaptive_frame_field_flag())?;
     }
-    u(1, &mut writer, sps.flags.direct_8x8_inference_flag())?;
-    u(1, &mut writer, sps.flags.frame_cropping_flag())?;
+    u(1, writer, sps.flags.direct_8x8_inference_flag())?;
+    u(1, writer, sps.flags.frame_cropping_flag())?;
     if sps.flags.frame_cropping_flag() == 1 {
-        ue(&mut writer, sps.frame_crop_left_offset.into())?;
-        ue(&mut writer, sps.frame_crop_right_offset.into())?;
-        ue(&mut writer, sps.frame_crop_top_offset.into())?;
-        ue(&mut writer, sps.frame_crop_bottom_offset.into())?;
+        ue(writer, sps.frame_crop_left_offset.into())?;
+        ue(writer, sps.frame_crop_right_offset.into())?;
+        ue(writer, sps.frame_crop_top_offset.into())?;
+        ue(writer, sps.frame_crop_bottom_offset.into())?;
     }
 
-    u(1, &mut writer, sps.flags.vui_parameters_present_flag())?;
+    u(1, writer, sps.flags.vui_parameters_present_flag())?;
     if sps.flags.vui_parameters_present_flag() == 1 {
-        todo!();
+        // SAFETY: the VUI pointer has to be valid if the present flag is set
+        let vui = unsafe { sps.pSequenceParameterSetVui.as_ref() }
+            .ok_or_else(|| std::io::Error::other("VUI is present but missing"))?;
+        write_h264_vui(writer, vui)?;
     }
-    u(1, &mut writer, RBSP_STOP_ONE_BIT)?;
-    writer.byte_align()?;
+    rbsp_trailing_bits(writer)
+}
 
+fn write_h264_vui<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    vui: &vk::native::StdVideoH264SequenceParameterSetVui,
+) -> std::io::Result<()> {
+    const EXTENDED_SAR: u32 = 255;
+
+    u(1, writer, vui.flags.aspect_ratio_info_present_flag())?;
+    if vui.flags.aspect_ratio_info_present_flag() == 1 {
+        u(8, writer, vui.aspect_ratio_idc)?;
+        if vui.aspect_ratio_idc == EXTENDED_SAR {
+            u(16, writer, vui.sar_width.into())?;
+            u(16, writer, vui.sar_height.into())?;
+        }
+    }
+    u(1, writer, vui.flags.overscan_info_present_flag())?;
+    if vui.flags.overscan_info_present_flag() == 1 {
+        u(1, writer, vui.flags.overscan_appropriate_flag())?;
+    }
+    u(1, writer, vui.flags.video_signal_type_present_flag())?;
+    if vui.flags.video_signal_type_present_flag() == 1 {
+        u(3, writer, vui.video_format.into())?;
+        u(1, writer, vui.flags.video_full_range_flag())?;
+        u(1, writer, vui.flags.color_description_present_flag())?;
+        if vui.flags.color_description_present_flag() == 1 {
+            u(8, writer, vui.colour_primaries.into())?;
+            u(8, writer, vui.transfer_characteristics.into())?;
+            u(8, writer, vui.matrix_coefficients.into())?;
+        }
+    }
+    u(1, writer, vui.flags.chroma_loc_info_present_flag())?;
+    if vui.flags.chroma_loc_info_present_flag() == 1 {
+        ue(writer, vui.chroma_sample_loc_type_top_field.into())?;
+        ue(writer, vui.chroma_sample_loc_type_bottom_field.into())?;
+    }
+    u(1, writer, vui.flags.timing_info_present_flag())?;
+    if vui.flags.timing_info_present_flag() == 1 {
+        u(32, writer, vui.num_units_in_tick)?;
+        u(32, writer, vui.time_scale)?;
+        u(1, writer, vui.flags.fixed_frame_rate_flag())?;
+    }
+    let nal_hrd = vui.flags.nal_hrd_parameters_present_flag();
+    let vcl_hrd = vui.flags.vcl_hrd_parameters_present_flag();
+    // SAFETY: the HRD pointer has to be valid if one of the present flags is set
+    let hrd = unsafe { vui.pHrdParameters.as_ref() };
+    // Vulkan has a single set of HRD parameters used for both NAL and VCL
+    for present in [nal_hrd, vcl_hrd] {
+        u(1, writer, present)?;
+        if present == 1 {
+            let hrd =
+                hrd.ok_or_else(|| std::io::Error::other("HRD parameters are present but missing"))?;
+            write_h264_hrd(writer, hrd)?;
+        }
+    }
+    if nal_hrd == 1 || vcl_hrd == 1 {
+        u(1, writer, 0)?; // low_delay_hrd_flag
+    }
+    u(1, writer, 0)?; // pic_struct_present_flag
+    u(1, writer, vui.flags.bitstream_restriction_flag())?;
+    if vui.flags.bitstream_restriction_flag() == 1 {
+        // not part of the Vulkan VUI, these are the values inferred when absent
+        u(1, writer, 1)?; // motion_vectors_over_pic_boundaries_flag
+        ue(writer, 2)?; // max_bytes_per_pic_denom
+        ue(writer, 1)?; // max_bits_per_mb_denom
+        ue(writer, 16)?; // log2_max_mv_length_horizontal
+        ue(writer, 16)?; // log2_max_mv_length_vertical
+        ue(writer, vui.max_num_reorder_frames.into())?;
+        ue(writer, vui.max_dec_frame_buffering.into())?;
+    }
+    Ok(())
+}
+
+fn write_h264_hrd<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    hrd: &vk::native::StdVideoH264HrdParameters,
+) -> std::io::Result<()> {
+    ue(writer, hrd.cpb_cnt_minus1.into())?;
+    u(4, writer, hrd.bit_rate_scale.into())?;
+    u(4, writer, hrd.cpb_size_scale.into())?;
+    for i in 0..=usize::from(hrd.cpb_cnt_minus1) {
+        ue(writer, hrd.bit_rate_value_minus1[i].into())?;
+        ue(writer, hrd.cpb_size_value_minus1[i].into())?;
+        u(1, writer, hrd.cbr_flag[i].into())?;
+    }
+    u(5, writer, hrd.initial_cpb_removal_delay_length_minus1)?;
+    u(5, writer, hrd.cpb_removal_delay_length_minus1)?;
+    u(5, writer, hrd.dpb_output_delay_length_minus1)?;
+    u(5, writer, hrd.time_offset_length)
+}
+
+/// Writes the first `count` scaling lists, 4x4 lists first followed by the 8x8 lists
+fn write_h264_scaling_lists<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    scaling_lists: &vk::native::StdVideoH264ScalingLists,
+    count: usize,
+) -> std::io::Result<()> {
+    for i in 0..count {
+        let present = (scaling_lists.scaling_list_present_mask >> i) & 1;
+        u(1, writer, present.into())?;
+        if present == 0 {
+            continue;
+        }
+        let list = if i < 6 {
+            &scaling_lists.ScalingList4x4[i][..]
+        } else {
+            &scaling_lists.ScalingList8x8[i - 6][..]
+        };
+        if (scaling_lists.use_default_scaling_matrix_mask >> i) & 1 == 1 {
+            // a next scale of 0 for the first entry selects the default matrix
+            se(writer, -8)?;
+            continue;
+        }
+        let mut last_scale = 8u8;
+        for scale in list {
+            se(writer, scale.wrapping_sub(last_scale) as i8 as i64)?;
+            last_scale = *scale;
+        }
+    }
     Ok(())
 }
 
@@ -95,44 +281,1000 @@ pub fn write_h264_pps(
     sps: &vk::native::StdVideoH264SequenceParameterSet,
     pps: &vk::native::StdVideoH264PictureParameterSet,
 ) -> std::io::Result<()> {
-    let mut writer = BitWriter::<_, BigEndian>::new(writer);
+    let mut rbsp = Vec::new();
+    write_h264_pps_rbsp(&mut BitWriter::<_, BigEndian>::new(&mut rbsp), sps, pps)?;
+    write_h264_nal_unit(writer, NAL_REF_IDC_PPS, NAL_NAL_UNIT_TYPE_PPS, &rbsp)
+}
+
+fn write_h264_pps_rbsp<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    sps: &vk::native::StdVideoH264SequenceParameterSet,
+    pps: &vk::native::StdVideoH264PictureParameterSet,
+) -> std::io::Result<()> {
+    ue(writer, pps.pic_parameter_set_id.into())?;
+    ue(writer, sps.seq_parameter_set_id.into())?;
+    u(1, writer, pps.flags.entropy_coding_mode_flag())?;
+    u(
+        1,
+        writer,
+        pps.flags.bottom_field_pic_order_in_frame_present_flag(),
+    )?;
+    ue(writer, 0)?; /*num_slice_groups_minus1*/
+    ue(writer, pps.num_ref_idx_l0_default_active_minus1.into())?;
+    ue(writer, pps.num_ref_idx_l1_default_active_minus1.into())?;
+    u(1, writer, pps.flags.weighted_pred_flag())?;
+    u(2, writer, pps.weighted_bipred_idc)?;
+    se(writer, pps.pic_init_qp_minus26.into())?;
+    se(writer, pps.pic_init_qs_minus26.into())?;
+    se(writer, pps.chroma_qp_index_offset.into())?;
+    u(
+        1,
+        writer,
+        pps.flags.deblocking_filter_control_present_flag(),
+    )?;
+    u(1, writer, pps.flags.constrained_intra_pred_flag())?;
+    u(1, writer, pps.flags.redundant_pic_cnt_present_flag())?;
+
+    // the High profile extension is only written when it differs from the inferred values
+    if pps.flags.transform_8x8_mode_flag() == 1
+        || pps.flags.pic_scaling_matrix_present_flag() == 1
+        || pps.second_chroma_qp_index_offset != pps.chroma_qp_index_offset
+    {
+        u(1, writer, pps.flags.transform_8x8_mode_flag())?;
+        u(1, writer, pps.flags.pic_scaling_matrix_present_flag())?;
+        if pps.flags.pic_scaling_matrix_present_flag() == 1 {
+            let chroma_8x8_lists = if sps.chroma_format_idc == H264_CHROMA_FORMAT_IDC_444 {
+                6
+            } else {
+                2
+            };
+            let list_count = 6 + chroma_8x8_lists * pps.flags.transform_8x8_mode_flag() as usize;
+            // SAFETY: the scaling list pointer has to be valid if the present flag is set
+            let scaling_lists = unsafe { pps.pScalingLists.as_ref() }
+                .ok_or_else(|| std::io::Error::other("scaling matrix is present but missing"))?;
+            write_h264_scaling_lists(writer, scaling_lists, list_count)?;
+        }
+        se(writer, pps.second_chroma_qp_index_offset.into())?;
+    }
+    rbsp_trailing_bits(writer)
+}
+
+pub fn write_h265_vps(
+    writer: &mut impl Write,
+    vps: &vk::native::StdVideoH265VideoParameterSet,
+) -> std::io::Result<()> {
+    let mut rbsp = Vec::new();
+    write_h265_vps_rbsp(&mut BitWriter::<_, BigEndian>::new(&mut rbsp), vps)?;
+    write_h265_nal_unit(writer, H265_NAL_UNIT_TYPE_VPS, &rbsp)
+}
+
+fn write_h265_vps_rbsp<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    vps: &vk::native::StdVideoH265VideoParameterSet,
+) -> std::io::Result<()> {
+    u(4, writer, vps.vps_video_parameter_set_id.into())?;
+    u(1, writer, 1)?; // vps_base_layer_internal_flag
+    u(1, writer, 1)?; // vps_base_layer_available_flag
+    u(6, writer, 0)?; // vps_max_layers_minus1
+    u(3, writer, vps.vps_max_sub_layers_minus1.into())?;
+    u(1, writer, vps.flags.vps_temporal_id_nesting_flag())?;
+    u(16, writer, 0xffff)?; // vps_reserved_0xffff_16bits
+
+    // SAFETY: the profile, tier and level pointer is required by Vulkan
+    let profile_tier_level = unsafe { vps.pProfileTierLevel.as_ref() }
+        .ok_or_else(|| std::io::Error::other("profile tier level is missing"))?;
+    write_h265_profile_tier_level(writer, profile_tier_level, vps.vps_max_sub_layers_minus1)?;
+
+    // SAFETY: the decoded picture buffer pointer is required by Vulkan
+    let dec_pic_buf_mgr = unsafe { vps.pDecPicBufMgr.as_ref() }
+        .ok_or_else(|| std::io::Error::other("decoded picture buffer info is missing"))?;
+    write_h265_sub_layer_ordering_info(
+        writer,
+        dec_pic_buf_mgr,
+        vps.flags.vps_sub_layer_ordering_info_present_flag(),
+        vps.vps_max_sub_layers_minus1,
+    )?;
+    u(6, writer, 0)?; // vps_max_layer_id
+    ue(writer, 0)?; // vps_num_layer_sets_minus1
+
+    u(1, writer, vps.flags.vps_timing_info_present_flag())?;
+    if vps.flags.vps_timing_info_present_flag() == 1 {
+        u(32, writer, vps.vps_num_units_in_tick)?;
+        u(32, writer, vps.vps_time_scale)?;
+        u(1, writer, vps.flags.vps_poc_proportional_to_timing_flag())?;
+        if vps.flags.vps_poc_proportional_to_timing_flag() == 1 {
+            ue(writer, vps.vps_num_ticks_poc_diff_one_minus1.into())?;
+        }
+        // SAFETY: the HRD parameters are optional, null if absent
+        match unsafe { vps.pHrdParameters.as_ref() } {
+            Some(hrd) => {
+                ue(writer, 1)?; // vps_num_hrd_parameters
+                ue(writer, 0)?; // hrd_layer_set_idx
+                write_h265_hrd(writer, hrd, vps.vps_max_sub_layers_minus1)?;
+            }
+            None => ue(writer, 0)?,
+        }
+    }
+    u(1, writer, 0)?; // vps_extension_flag
+    rbsp_trailing_bits(writer)
+}
+
+pub fn write_h265_sps(
+    writer: &mut impl Write,
+    sps: &vk::native::StdVideoH265SequenceParameterSet,
+) -> std::io::Result<()> {
+    let mut rbsp = Vec::new();
+    write_h265_sps_rbsp(&mut BitWriter::<_, BigEndian>::new(&mut rbsp), sps)?;
+    write_h265_nal_unit(writer, H265_NAL_UNIT_TYPE_SPS, &rbsp)
+}
+
+fn write_h265_sps_rbsp<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    sps: &vk::native::StdVideoH265SequenceParameterSet,
+) -> std::io::Result<()> {
+    u(4, writer, sps.sps_video_parameter_set_id.into())?;
+    u(3, writer, sps.sps_max_sub_layers_minus1.into())?;
+    u(1, writer, sps.flags.sps_temporal_id_nesting_flag())?;
 
-    u(32, &mut writer, START_CODE)?;
-    u(1, &mut writer, FORBIDDEN_ZERO_BIT)?;
-    u(2, &mut writer, NAL_REF_IDC_PPS)?;
-    u(5, &mut writer, NAL_NAL_UNIT_TYPE_PPS)?;
+    // SAFETY: the profile, tier and level pointer is required by Vulkan
+    let profile_tier_level = unsafe { sps.pProfileTierLevel.as_ref() }
+        .ok_or_else(|| std::io::Error::other("profile tier level is missing"))?;
+    write_h265_profile_tier_level(writer, profile_tier_level, sps.sps_max_sub_layers_minus1)?;
+
+    ue(writer, sps.sps_seq_parameter_set_id.into())?;
+    ue(writer, sps.chroma_format_idc.into())?;
+    if sps.chroma_format_idc
+        == vk::native::StdVideoH265ChromaFormatIdc_STD_VIDEO_H265_CHROMA_FORMAT_IDC_444
+    {
+        u(1, writer, sps.flags.separate_colour_plane_flag())?;
+    }
+    ue(writer, sps.pic_width_in_luma_samples.into())?;
+    ue(writer, sps.pic_height_in_luma_samples.into())?;
+    u(1, writer, sps.flags.conformance_window_flag())?;
+    if sps.flags.conformance_window_flag() == 1 {
+        ue(writer, sps.conf_win_left_offset.into())?;
+        ue(writer, sps.conf_win_right_offset.into())?;
+        ue(writer, sps.conf_win_top_offset.into())?;
+        ue(writer, sps.conf_win_bottom_offset.into())?;
+    }
+    ue(writer, sps.bit_depth_luma_minus8.into())?;
+    ue(writer, sps.bit_depth_chroma_minus8.into())?;
+    ue(writer, sps.log2_max_pic_order_cnt_lsb_minus4.into())?;
 
-    ue(&mut writer, pps.pic_parameter_set_id.into())?;
-    ue(&mut writer, sps.seq_parameter_set_id.into())?;
-    u(1, &mut writer, pps.flags.entropy_coding_mode_flag())?;
+    // SAFETY: the decoded picture buffer pointer is required by Vulkan
+    let dec_pic_buf_mgr = unsafe { sps.pDecPicBufMgr.as_ref() }
+        .ok_or_else(|| std::io::Error::other("decoded picture buffer info is missing"))?;
+    write_h265_sub_layer_ordering_info(
+        writer,
+        dec_pic_buf_mgr,
+        sps.flags.sps_sub_layer_ordering_info_present_flag(),
+        sps.sps_max_sub_layers_minus1,
+    )?;
+
+    ue(writer, sps.log2_min_luma_coding_block_size_minus3.into())?;
+    ue(writer, sps.log2_diff_max_min_luma_coding_block_size.into())?;
+    ue(writer, sps.log2_min_luma_transform_block_size_minus2.into())?;
+    ue(
+        writer,
+        sps.log2_diff_max_min_luma_transform_block_size.into(),
+    )?;
+    ue(writer, sps.max_transform_hierarchy_depth_inter.into())?;
+    ue(writer, sps.max_transform_hierarchy_depth_intra.into())?;
+    u(1, writer, sps.flags.scaling_list_enabled_flag())?;
+    if sps.flags.scaling_list_enabled_flag() == 1 {
+        u(1, writer, sps.flags.sps_scaling_list_data_present_flag())?;
+        if sps.flags.sps_scaling_list_data_present_flag() == 1 {
+            // SAFETY: the scaling list pointer has to be valid if the present flag is set
+            let scaling_lists = unsafe { sps.pScalingLists.as_ref() }
+                .ok_or_else(|| std::io::Error::other("scaling list data is present but missing"))?;
+            write_h265_scaling_list_data(writer, scaling_lists)?;
+        }
+    }
+    u(1, writer, sps.flags.amp_enabled_flag())?;
+    u(1, writer, sps.flags.sample_adaptive_offset_enabled_flag())?;
+    u(1, writer, sps.flags.pcm_enabled_flag())?;
+    if sps.flags.pcm_enabled_flag() == 1 {
+        u(4, writer, sps.pcm_sample_bit_depth_luma_minus1.into())?;
+        u(4, writer, sps.pcm_sample_bit_depth_chroma_minus1.into())?;
+        ue(
+            writer,
+            sps.log2_min_pcm_luma_coding_block_size_minus3.into(),
+        )?;
+        ue(
+            writer,
+            sps.log2_diff_max_min_pcm_luma_coding_block_size.into(),
+        )?;
+        u(1, writer, sps.flags.pcm_loop_filter_disabled_flag())?;
+    }
+
+    ue(writer, sps.num_short_term_ref_pic_sets.into())?;
+    let short_term_ref_pic_sets = if sps.num_short_term_ref_pic_sets == 0 {
+        &[][..]
+    } else if sps.pShortTermRefPicSet.is_null() {
+        return Err(std::io::Error::other(
+            "short term reference picture sets are missing",
+        ));
+    } else {
+        // SAFETY: the pointer holds num_short_term_ref_pic_sets entries
+        unsafe {
+            std::slice::from_raw_parts(
+                sps.pShortTermRefPicSet,
+                sps.num_short_term_ref_pic_sets.into(),
+            )
+        }
+    };
+    for idx in 0..short_term_ref_pic_sets.len() {
+        write_h265_st_ref_pic_set(writer, short_term_ref_pic_sets, idx)?;
+    }
+
+    u(1, writer, sps.flags.long_term_ref_pics_present_flag())?;
+    if sps.flags.long_term_ref_pics_present_flag() == 1 {
+        ue(writer, sps.num_long_term_ref_pics_sps.into())?;
+        if sps.num_long_term_ref_pics_sps > 0 {
+            // SAFETY: the pointer has to be valid if long term pictures are signaled
+            let long_term = unsafe { sps.pLongTermRefPicsSps.as_ref() }
+                .ok_or_else(|| std::io::Error::other("long term reference pictures are missing"))?;
+            let lsb_bits = u32::from(sps.log2_max_pic_order_cnt_lsb_minus4) + 4;
+            for i in 0..usize::from(sps.num_long_term_ref_pics_sps) {
+                u(lsb_bits, writer, long_term.lt_ref_pic_poc_lsb_sps[i])?;
+                u(1, writer, (long_term.used_by_curr_pic_lt_sps_flag >> i) & 1)?;
+            }
+        }
+    }
+    u(1, writer, sps.flags.sps_temporal_mvp_enabled_flag())?;
+    u(1, writer, sps.flags.strong_intra_smoothing_enabled_flag())?;
+
+    u(1, writer, sps.flags.vui_parameters_present_flag())?;
+    if sps.flags.vui_parameters_present_flag() == 1 {
+        // SAFETY: the VUI pointer has to be valid if the present flag is set
+        let vui = unsafe { sps.pSequenceParameterSetVui.as_ref() }
+            .ok_or_else(|| std::io::Error::other("VUI is present but missing"))?;
+        write_h265_vui(writer, vui, sps.sps_max_sub_layers_minus1)?;
+    }
+
+    u(1, writer, sps.flags.sps_extension_present_flag())?;
+    if sps.flags.sps_extension_present_flag() == 1 {
+        u(1, writer, sps.flags.sps_range_extension_flag())?;
+        u(1, writer, 0)?; // sps_multilayer_extension_flag
+        u(1, writer, 0)?; // sps_3d_extension_flag
+        u(1, writer, sps.flags.sps_scc_extension_flag())?;
+        u(4, writer, 0)?; // sps_extension_4bits
+        if sps.flags.sps_range_extension_flag() == 1 {
+            u(1, writer, sps.flags.transform_skip_rotation_enabled_flag())?;
+            u(1, writer, sps.flags.transform_skip_context_enabled_flag())?;
+            u(1, writer, sps.flags.implicit_rdpcm_enabled_flag())?;
+            u(1, writer, sps.flags.explicit_rdpcm_enabled_flag())?;
+            u(1, writer, sps.flags.extended_precision_processing_flag())?;
+            u(1, writer, sps.flags.intra_smoothing_disabled_flag())?;
+            u(1, writer, sps.flags.high_precision_offsets_enabled_flag())?;
+            u(
+                1,
+                writer,
+                sps.flags.persistent_rice_adaptation_enabled_flag(),
+            )?;
+            u(1, writer, sps.flags.cabac_bypass_alignment_enabled_flag())?;
+        }
+        if sps.flags.sps_scc_extension_flag() == 1 {
+            write_h265_sps_scc_extension(writer, sps)?;
+        }
+    }
+    rbsp_trailing_bits(writer)
+}
+
+fn write_h265_sps_scc_extension<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    sps: &vk::native::StdVideoH265SequenceParameterSet,
+) -> std::io::Result<()> {
+    u(1, writer, sps.flags.sps_curr_pic_ref_enabled_flag())?;
+    u(1, writer, sps.flags.palette_mode_enabled_flag())?;
+    if sps.flags.palette_mode_enabled_flag() == 1 {
+        ue(writer, sps.palette_max_size.into())?;
+        ue(writer, sps.delta_palette_max_predictor_size.into())?;
+        u(
+            1,
+            writer,
+            sps.flags.sps_palette_predictor_initializers_present_flag(),
+        )?;
+        if sps.flags.sps_palette_predictor_initializers_present_flag() == 1 {
+            ue(
+                writer,
+                sps.sps_num_palette_predictor_initializers_minus1.into(),
+            )?;
+            // SAFETY: the entries have to be valid if the present flag is set
+            let entries = unsafe { sps.pPredictorPaletteEntries.as_ref() }.ok_or_else(|| {
+                std::io::Error::other("palette predictor initializers are missing")
+            })?;
+            let bit_depths = [
+                sps.bit_depth_luma_minus8,
+                sps.bit_depth_chroma_minus8,
+                sps.bit_depth_chroma_minus8,
+            ];
+            let components = if sps.chroma_format_idc == 0 { 1 } else { 3 };
+            for (comp, bit_depth_minus8) in bit_depths.iter().enumerate().take(components) {
+                for entry in &entries.PredictorPaletteEntries[comp]
+                    [..=usize::from(sps.sps_num_palette_predictor_initializers_minus1)]
+                {
+                    u(u32::from(*bit_depth_minus8) + 8, writer, (*entry).into())?;
+                }
+            }
+        }
+    }
+    u(2, writer, sps.motion_vector_resolution_control_idc.into())?;
     u(
         1,
-        &mut writer,
-        0, /* pic_order_present_flag, expressed via pps.pic_order_cnt_type??*/
-    )?;
-    ue(&mut writer, 0)?; /*num_slice_groups_minus1*/
-    ue(&mut writer, pps.num_ref_idx_l0_default_active_minus1.into())?;
-    ue(&mut writer, pps.num_ref_idx_l1_default_active_minus1.into())?;
-    u(1, &mut writer, pps.flags.weighted_pred_flag())?;
-    u(2, &mut writer, pps.weighted_bipred_idc)?;
-    se(&mut writer, pps.pic_init_qp_minus26.into())?;
-    se(&mut writer, pps.pic_init_qs_minus26.into())?;
-    se(&mut writer, pps.chroma_qp_index_offset.into())?;
+        writer,
+        sps.flags.intra_boundary_filtering_disabled_flag(),
+    )
+}
+
+pub fn write_h265_pps(
+    writer: &mut impl Write,
+    pps: &vk::native::StdVideoH265PictureParameterSet,
+) -> std::io::Result<()> {
+    let mut rbsp = Vec::new();
+    write_h265_pps_rbsp(&mut BitWriter::<_, BigEndian>::new(&mut rbsp), pps)?;
+    write_h265_nal_unit(writer, H265_NAL_UNIT_TYPE_PPS, &rbsp)
+}
+
+fn write_h265_pps_rbsp<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    pps: &vk::native::StdVideoH265PictureParameterSet,
+) -> std::io::Result<()> {
+    ue(writer, pps.pps_pic_parameter_set_id.into())?;
+    ue(writer, pps.pps_seq_parameter_set_id.into())?;
+    u(1, writer, pps.flags.dependent_slice_segments_enabled_flag())?;
+    u(1, writer, pps.flags.output_flag_present_flag())?;
+    u(3, writer, pps.num_extra_slice_header_bits.into())?;
+    u(1, writer, pps.flags.sign_data_hiding_enabled_flag())?;
+    u(1, writer, pps.flags.cabac_init_present_flag())?;
+    ue(writer, pps.num_ref_idx_l0_default_active_minus1.into())?;
+    ue(writer, pps.num_ref_idx_l1_default_active_minus1.into())?;
+    se(writer, pps.init_qp_minus26.into())?;
+    u(1, writer, pps.flags.constrained_intra_pred_flag())?;
+    u(1, writer, pps.flags.transform_skip_enabled_flag())?;
+    u(1, writer, pps.flags.cu_qp_delta_enabled_flag())?;
+    if pps.flags.cu_qp_delta_enabled_flag() == 1 {
+        ue(writer, pps.diff_cu_qp_delta_depth.into())?;
+    }
+    se(writer, pps.pps_cb_qp_offset.into())?;
+    se(writer, pps.pps_cr_qp_offset.into())?;
     u(
         1,
-        &mut writer,
+        writer,
+        pps.flags.pps_slice_chroma_qp_offsets_present_flag(),
+    )?;
+    u(1, writer, pps.flags.weighted_pred_flag())?;
+    u(1, writer, pps.flags.weighted_bipred_flag())?;
+    u(1, writer, pps.flags.transquant_bypass_enabled_flag())?;
+    u(1, writer, pps.flags.tiles_enabled_flag())?;
+    u(1, writer, pps.flags.entropy_coding_sync_enabled_flag())?;
+    if pps.flags.tiles_enabled_flag() == 1 {
+        ue(writer, pps.num_tile_columns_minus1.into())?;
+        ue(writer, pps.num_tile_rows_minus1.into())?;
+        u(1, writer, pps.flags.uniform_spacing_flag())?;
+        if pps.flags.uniform_spacing_flag() == 0 {
+            for width in &pps.column_width_minus1[..usize::from(pps.num_tile_columns_minus1)] {
+                ue(writer, (*width).into())?;
+            }
+            for height in &pps.row_height_minus1[..usize::from(pps.num_tile_rows_minus1)] {
+                ue(writer, (*height).into())?;
+            }
+        }
+        u(1, writer, pps.flags.loop_filter_across_tiles_enabled_flag())?;
+    }
+    u(
+        1,
+        writer,
+        pps.flags.pps_loop_filter_across_slices_enabled_flag(),
+    )?;
+    u(
+        1,
+        writer,
         pps.flags.deblocking_filter_control_present_flag(),
     )?;
-    u(1, &mut writer, pps.flags.constrained_intra_pred_flag())?;
-    u(1, &mut writer, pps.flags.redundant_pic_cnt_present_flag())?;
+    if pps.flags.deblocking_filter_control_present_flag() == 1 {
+        u(
+            1,
+            writer,
+            pps.flags.deblocking_filter_override_enabled_flag(),
+        )?;
+        u(1, writer, pps.flags.pps_deblocking_filter_disabled_flag())?;
+        if pps.flags.pps_deblocking_filter_disabled_flag() == 0 {
+            se(writer, pps.pps_beta_offset_div2.into())?;
+            se(writer, pps.pps_tc_offset_div2.into())?;
+        }
+    }
+    u(1, writer, pps.flags.pps_scaling_list_data_present_flag())?;
+    if pps.flags.pps_scaling_list_data_present_flag() == 1 {
+        // SAFETY: the scaling list pointer has to be valid if the present flag is set
+        let scaling_lists = unsafe { pps.pScalingLists.as_ref() }
+            .ok_or_else(|| std::io::Error::other("scaling list data is present but missing"))?;
+        write_h265_scaling_list_data(writer, scaling_lists)?;
+    }
+    u(1, writer, pps.flags.lists_modification_present_flag())?;
+    ue(writer, pps.log2_parallel_merge_level_minus2.into())?;
+    u(
+        1,
+        writer,
+        pps.flags.slice_segment_header_extension_present_flag(),
+    )?;
 
-    u(1, &mut writer, RBSP_STOP_ONE_BIT)?;
-    writer.byte_align()?;
+    // Vulkan has no flag for the screen content extension, it is present if any of its tools are
+    let scc_extension = pps.flags.pps_curr_pic_ref_enabled_flag() == 1
+        || pps.flags.residual_adaptive_colour_transform_enabled_flag() == 1
+        || pps.flags.pps_palette_predictor_initializers_present_flag() == 1;
+    u(1, writer, pps.flags.pps_extension_present_flag())?;
+    if pps.flags.pps_extension_present_flag() == 1 {
+        u(1, writer, pps.flags.pps_range_extension_flag())?;
+        u(1, writer, 0)?; // pps_multilayer_extension_flag
+        u(1, writer, 0)?; // pps_3d_extension_flag
+        u(1, writer, scc_extension as u32)?;
+        u(4, writer, 0)?; // pps_extension_4bits
+        if pps.flags.pps_range_extension_flag() == 1 {
+            if pps.flags.transform_skip_enabled_flag() == 1 {
+                ue(writer, pps.log2_max_transform_skip_block_size_minus2.into())?;
+            }
+            u(
+                1,
+                writer,
+                pps.flags.cross_component_prediction_enabled_flag(),
+            )?;
+            u(1, writer, pps.flags.chroma_qp_offset_list_enabled_flag())?;
+            if pps.flags.chroma_qp_offset_list_enabled_flag() == 1 {
+                ue(writer, pps.diff_cu_chroma_qp_offset_depth.into())?;
+                ue(writer, pps.chroma_qp_offset_list_len_minus1.into())?;
+                for i in 0..=usize::from(pps.chroma_qp_offset_list_len_minus1) {
+                    se(writer, pps.cb_qp_offset_list[i].into())?;
+                    se(writer, pps.cr_qp_offset_list[i].into())?;
+                }
+            }
+            ue(writer, pps.log2_sao_offset_scale_luma.into())?;
+            ue(writer, pps.log2_sao_offset_scale_chroma.into())?;
+        }
+        if scc_extension {
+            write_h265_pps_scc_extension(writer, pps)?;
+        }
+    }
+    rbsp_trailing_bits(writer)
+}
 
+fn write_h265_pps_scc_extension<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    pps: &vk::native::StdVideoH265PictureParameterSet,
+) -> std::io::Result<()> {
+    u(1, writer, pps.flags.pps_curr_pic_ref_enabled_flag())?;
+    u(
+        1,
+        writer,
+        pps.flags.residual_adaptive_colour_transform_enabled_flag(),
+    )?;
+    if pps.flags.residual_adaptive_colour_transform_enabled_flag() == 1 {
+        u(1, writer, pps.flags.pps_slice_act_qp_offsets_present_flag())?;
+        se(writer, pps.pps_act_y_qp_offset_plus5.into())?;
+        se(writer, pps.pps_act_cb_qp_offset_plus5.into())?;
+        se(writer, pps.pps_act_cr_qp_offset_plus3.into())?;
+    }
+    u(
+        1,
+        writer,
+        pps.flags.pps_palette_predictor_initializers_present_flag(),
+    )?;
+    if pps.flags.pps_palette_predictor_initializers_present_flag() == 1 {
+        ue(writer, pps.pps_num_palette_predictor_initializers.into())?;
+        if pps.pps_num_palette_predictor_initializers > 0 {
+            u(1, writer, pps.flags.monochrome_palette_flag())?;
+            ue(writer, pps.luma_bit_depth_entry_minus8.into())?;
+            if pps.flags.monochrome_palette_flag() == 0 {
+                ue(writer, pps.chroma_bit_depth_entry_minus8.into())?;
+            }
+            // SAFETY: the entries have to be valid if the present flag is set
+            let entries = unsafe { pps.pPredictorPaletteEntries.as_ref() }.ok_or_else(|| {
+                std::io::Error::other("palette predictor initializers are missing")
+            })?;
+            let bit_depths = [
+                pps.luma_bit_depth_entry_minus8,
+                pps.chroma_bit_depth_entry_minus8,
+                pps.chroma_bit_depth_entry_minus8,
+            ];
+            let components = if pps.flags.monochrome_palette_flag() == 1 {
+                1
+            } else {
+                3
+            };
+            for (comp, bit_depth_minus8) in bit_depths.iter().enumerate().take(components) {
+                for entry in &entries.PredictorPaletteEntries[comp]
+                    [..usize::from(pps.pps_num_palette_predictor_initializers)]
+                {
+                    u(u32::from(*bit_depth_minus8) + 8, writer, (*entry).into())?;
+                }
+            }
+        }
+    }
     Ok(())
 }
 
-#[allow(dead_code)]
+fn write_h265_profile_tier_level<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    profile_tier_level: &vk::native::StdVideoH265ProfileTierLevel,
+    max_sub_layers_minus1: u8,
+) -> std::io::Result<()> {
+    let profile_idc = profile_tier_level.general_profile_idc;
+    u(2, writer, 0)?; // general_profile_space
+    u(1, writer, profile_tier_level.flags.general_tier_flag())?;
+    u(5, writer, profile_idc)?;
+    // Main decoders are a subset of Main 10 decoders
+    let mut compatibility = 1u32 << (31 - profile_idc);
+    if profile_idc == vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN {
+        compatibility |=
+            1 << (31 - vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN_10);
+    }
+    u(32, writer, compatibility)?;
+    u(
+        1,
+        writer,
+        profile_tier_level.flags.general_progressive_source_flag(),
+    )?;
+    u(
+        1,
+        writer,
+        profile_tier_level.flags.general_interlaced_source_flag(),
+    )?;
+    u(
+        1,
+        writer,
+        profile_tier_level
+            .flags
+            .general_non_packed_constraint_flag(),
+    )?;
+    u(
+        1,
+        writer,
+        profile_tier_level
+            .flags
+            .general_frame_only_constraint_flag(),
+    )?;
+    // general_reserved_zero_43bits and general_inbld_flag
+    u(32, writer, 0)?;
+    u(12, writer, 0)?;
+    u(
+        8,
+        writer,
+        match profile_tier_level.general_level_idc {
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_1_0 => 30,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_2_0 => 60,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_2_1 => 63,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_3_0 => 90,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_3_1 => 93,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_4_0 => 120,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_4_1 => 123,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_0 => 150,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_1 => 153,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_2 => 156,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_0 => 180,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_1 => 183,
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_2 => 186,
+            _ => unreachable!(),
+        },
+    )?;
+    // no sub-layer profiles or levels are signaled
+    for _ in 0..max_sub_layers_minus1 {
+        u(1, writer, 0)?; // sub_layer_profile_present_flag
+        u(1, writer, 0)?; // sub_layer_level_present_flag
+    }
+    if max_sub_layers_minus1 > 0 {
+        for _ in max_sub_layers_minus1..8 {
+            u(2, writer, 0)?; // reserved_zero_2bits
+        }
+    }
+    Ok(())
+}
+
+fn write_h265_sub_layer_ordering_info<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    dec_pic_buf_mgr: &vk::native::StdVideoH265DecPicBufMgr,
+    present_flag: u32,
+    max_sub_layers_minus1: u8,
+) -> std::io::Result<()> {
+    u(1, writer, present_flag)?;
+    let first = if present_flag == 1 {
+        0
+    } else {
+        max_sub_layers_minus1
+    };
+    for i in usize::from(first)..=usize::from(max_sub_layers_minus1) {
+        ue(
+            writer,
+            dec_pic_buf_mgr.max_dec_pic_buffering_minus1[i].into(),
+        )?;
+        ue(writer, dec_pic_buf_mgr.max_num_reorder_pics[i].into())?;
+        ue(writer, dec_pic_buf_mgr.max_latency_increase_plus1[i].into())?;
+    }
+    Ok(())
+}
+
+fn write_h265_st_ref_pic_set<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    sets: &[vk::native::StdVideoH265ShortTermRefPicSet],
+    idx: usize,
+) -> std::io::Result<()> {
+    let set = &sets[idx];
+    if idx != 0 {
+        u(1, writer, set.flags.inter_ref_pic_set_prediction_flag())?;
+    }
+    if idx != 0 && set.flags.inter_ref_pic_set_prediction_flag() == 1 {
+        // in the SPS the prediction is always from the previous set
+        let reference = &sets[idx - 1];
+        u(1, writer, set.flags.delta_rps_sign())?;
+        ue(writer, set.abs_delta_rps_minus1.into())?;
+        let num_delta_pocs = reference.num_negative_pics + reference.num_positive_pics;
+        for j in 0..=num_delta_pocs {
+            let used_by_curr_pic = (set.used_by_curr_pic_flag >> j) & 1;
+            u(1, writer, used_by_curr_pic.into())?;
+            if used_by_curr_pic == 0 {
+                u(1, writer, ((set.use_delta_flag >> j) & 1).into())?;
+            }
+        }
+        return Ok(());
+    }
+    ue(writer, set.num_negative_pics.into())?;
+    ue(writer, set.num_positive_pics.into())?;
+    for i in 0..usize::from(set.num_negative_pics) {
+        ue(writer, set.delta_poc_s0_minus1[i].into())?;
+        u(1, writer, ((set.used_by_curr_pic_s0_flag >> i) & 1).into())?;
+    }
+    for i in 0..usize::from(set.num_positive_pics) {
+        ue(writer, set.delta_poc_s1_minus1[i].into())?;
+        u(1, writer, ((set.used_by_curr_pic_s1_flag >> i) & 1).into())?;
+    }
+    Ok(())
+}
+
+/// Writes all lists explicitly, Vulkan does not keep how they were predicted
+fn write_h265_scaling_list_data<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    scaling_lists: &vk::native::StdVideoH265ScalingLists,
+) -> std::io::Result<()> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for matrix_id in (0..6).step_by(step) {
+            u(1, writer, 1)?; // scaling_list_pred_mode_flag
+            let (list, dc) = match size_id {
+                0 => (&scaling_lists.ScalingList4x4[matrix_id][..], None),
+                1 => (&scaling_lists.ScalingList8x8[matrix_id][..], None),
+                2 => (
+                    &scaling_lists.ScalingList16x16[matrix_id][..],
+                    Some(scaling_lists.ScalingListDCCoef16x16[matrix_id]),
+                ),
+                _ => (
+                    &scaling_lists.ScalingList32x32[matrix_id / 3][..],
+                    Some(scaling_lists.ScalingListDCCoef32x32[matrix_id / 3]),
+                ),
+            };
+            let mut next_coef = 8u8;
+            if let Some(dc) = dc {
+                se(writer, i64::from(dc) - 8)?; // scaling_list_dc_coef_minus8
+                next_coef = dc;
+            }
+            for coef in list {
+                se(writer, coef.wrapping_sub(next_coef) as i8 as i64)?;
+                next_coef = *coef;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_h265_vui<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    vui: &vk::native::StdVideoH265SequenceParameterSetVui,
+    max_sub_layers_minus1: u8,
+) -> std::io::Result<()> {
+    const EXTENDED_SAR: u32 = 255;
+
+    u(1, writer, vui.flags.aspect_ratio_info_present_flag())?;
+    if vui.flags.aspect_ratio_info_present_flag() == 1 {
+        u(8, writer, vui.aspect_ratio_idc)?;
+        if vui.aspect_ratio_idc == EXTENDED_SAR {
+            u(16, writer, vui.sar_width.into())?;
+            u(16, writer, vui.sar_height.into())?;
+        }
+    }
+    u(1, writer, vui.flags.overscan_info_present_flag())?;
+    if vui.flags.overscan_info_present_flag() == 1 {
+        u(1, writer, vui.flags.overscan_appropriate_flag())?;
+    }
+    u(1, writer, vui.flags.video_signal_type_present_flag())?;
+    if vui.flags.video_signal_type_present_flag() == 1 {
+        u(3, writer, vui.video_format.into())?;
+        u(1, writer, vui.flags.video_full_range_flag())?;
+        u(1, writer, vui.flags.colour_description_present_flag())?;
+        if vui.flags.colour_description_present_flag() == 1 {
+            u(8, writer, vui.colour_primaries.into())?;
+            u(8, writer, vui.transfer_characteristics.into())?;
+            u(8, writer, vui.matrix_coeffs.into())?;
+        }
+    }
+    u(1, writer, vui.flags.chroma_loc_info_present_flag())?;
+    if vui.flags.chroma_loc_info_present_flag() == 1 {
+        ue(writer, vui.chroma_sample_loc_type_top_field.into())?;
+        ue(writer, vui.chroma_sample_loc_type_bottom_field.into())?;
+    }
+    u(1, writer, vui.flags.neutral_chroma_indication_flag())?;
+    u(1, writer, vui.flags.field_seq_flag())?;
+    u(1, writer, vui.flags.frame_field_info_present_flag())?;
+    u(1, writer, vui.flags.default_display_window_flag())?;
+    if vui.flags.default_display_window_flag() == 1 {
+        ue(writer, vui.def_disp_win_left_offset.into())?;
+        ue(writer, vui.def_disp_win_right_offset.into())?;
+        ue(writer, vui.def_disp_win_top_offset.into())?;
+        ue(writer, vui.def_disp_win_bottom_offset.into())?;
+    }
+    u(1, writer, vui.flags.vui_timing_info_present_flag())?;
+    if vui.flags.vui_timing_info_present_flag() == 1 {
+        u(32, writer, vui.vui_num_units_in_tick)?;
+        u(32, writer, vui.vui_time_scale)?;
+        u(1, writer, vui.flags.vui_poc_proportional_to_timing_flag())?;
+        if vui.flags.vui_poc_proportional_to_timing_flag() == 1 {
+            ue(writer, vui.vui_num_ticks_poc_diff_one_minus1.into())?;
+        }
+        u(1, writer, vui.flags.vui_hrd_parameters_present_flag())?;
+        if vui.flags.vui_hrd_parameters_present_flag() == 1 {
+            // SAFETY: the HRD pointer has to be valid if the present flag is set
+            let hrd = unsafe { vui.pHrdParameters.as_ref() }
+                .ok_or_else(|| std::io::Error::other("HRD parameters are present but missing"))?;
+            write_h265_hrd(writer, hrd, max_sub_layers_minus1)?;
+        }
+    }
+    u(1, writer, vui.flags.bitstream_restriction_flag())?;
+    if vui.flags.bitstream_restriction_flag() == 1 {
+        u(1, writer, vui.flags.tiles_fixed_structure_flag())?;
+        u(
+            1,
+            writer,
+            vui.flags.motion_vectors_over_pic_boundaries_flag(),
+        )?;
+        u(1, writer, vui.flags.restricted_ref_pic_lists_flag())?;
+        ue(writer, vui.min_spatial_segmentation_idc.into())?;
+        ue(writer, vui.max_bytes_per_pic_denom.into())?;
+        ue(writer, vui.max_bits_per_min_cu_denom.into())?;
+        ue(writer, vui.log2_max_mv_length_horizontal.into())?;
+        ue(writer, vui.log2_max_mv_length_vertical.into())?;
+    }
+    Ok(())
+}
+
+/// HRD parameters with the common information present
+fn write_h265_hrd<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    hrd: &vk::native::StdVideoH265HrdParameters,
+    max_sub_layers_minus1: u8,
+) -> std::io::Result<()> {
+    let nal_hrd = hrd.flags.nal_hrd_parameters_present_flag();
+    let vcl_hrd = hrd.flags.vcl_hrd_parameters_present_flag();
+    let sub_pic_hrd = hrd.flags.sub_pic_hrd_params_present_flag();
+    u(1, writer, nal_hrd)?;
+    u(1, writer, vcl_hrd)?;
+    if nal_hrd == 1 || vcl_hrd == 1 {
+        u(1, writer, sub_pic_hrd)?;
+        if sub_pic_hrd == 1 {
+            u(8, writer, hrd.tick_divisor_minus2.into())?;
+            u(
+                5,
+                writer,
+                hrd.du_cpb_removal_delay_increment_length_minus1.into(),
+            )?;
+            u(
+                1,
+                writer,
+                hrd.flags.sub_pic_cpb_params_in_pic_timing_sei_flag(),
+            )?;
+            u(5, writer, hrd.dpb_output_delay_du_length_minus1.into())?;
+        }
+        u(4, writer, hrd.bit_rate_scale.into())?;
+        u(4, writer, hrd.cpb_size_scale.into())?;
+        if sub_pic_hrd == 1 {
+            u(4, writer, hrd.cpb_size_du_scale.into())?;
+        }
+        u(
+            5,
+            writer,
+            hrd.initial_cpb_removal_delay_length_minus1.into(),
+        )?;
+        u(5, writer, hrd.au_cpb_removal_delay_length_minus1.into())?;
+        u(5, writer, hrd.dpb_output_delay_length_minus1.into())?;
+    }
+
+    for i in 0..=usize::from(max_sub_layers_minus1) {
+        // the per sub-layer flags are bit masks
+        let fixed_pic_rate_general = (hrd.flags.fixed_pic_rate_general_flag() >> i) & 1;
+        u(1, writer, fixed_pic_rate_general)?;
+        let fixed_pic_rate_within_cvs = if fixed_pic_rate_general == 1 {
+            1
+        } else {
+            let flag = (hrd.flags.fixed_pic_rate_within_cvs_flag() >> i) & 1;
+            u(1, writer, flag)?;
+            flag
+        };
+        let mut low_delay_hrd = 0;
+        if fixed_pic_rate_within_cvs == 1 {
+            ue(writer, hrd.elemental_duration_in_tc_minus1[i].into())?;
+        } else {
+            low_delay_hrd = (hrd.flags.low_delay_hrd_flag() >> i) & 1;
+            u(1, writer, low_delay_hrd)?;
+        }
+        if low_delay_hrd == 0 {
+            ue(writer, hrd.cpb_cnt_minus1[i].into())?;
+        }
+        for (present, sub_layers) in [
+            (nal_hrd, hrd.pSubLayerHrdParametersNal),
+            (vcl_hrd, hrd.pSubLayerHrdParametersVcl),
+        ] {
+            if present == 0 {
+                continue;
+            }
+            // SAFETY: the pointers hold one entry per sub-layer if the present flag is set
+            let sub_layer = unsafe { sub_layers.add(i).as_ref() }.ok_or_else(|| {
+                std::io::Error::other("sub-layer HRD parameters are present but missing")
+            })?;
+            for j in 0..=usize::from(hrd.cpb_cnt_minus1[i]) {
+                ue(writer, sub_layer.bit_rate_value_minus1[j].into())?;
+                ue(writer, sub_layer.cpb_size_value_minus1[j].into())?;
+                if sub_pic_hrd == 1 {
+                    ue(writer, sub_layer.cpb_size_du_value_minus1[j].into())?;
+                    ue(writer, sub_layer.bit_rate_du_value_minus1[j].into())?;
+                }
+                u(1, writer, (sub_layer.cbr_flag >> j) & 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prefix SEI with the mastering display colour volume and the content light level
+pub fn write_h265_hdr_sei(writer: &mut impl Write, metadata: &HdrMetadata) -> std::io::Result<()> {
+    let mut mastering_display = Vec::with_capacity(24);
+    for xy in metadata
+        .display_primaries
+        .iter()
+        .chain([&metadata.white_point])
+    {
+        mastering_display.extend_from_slice(&xy[0].to_be_bytes());
+        mastering_display.extend_from_slice(&xy[1].to_be_bytes());
+    }
+    mastering_display.extend_from_slice(&metadata.max_display_mastering_luminance.to_be_bytes());
+    mastering_display.extend_from_slice(&metadata.min_display_mastering_luminance.to_be_bytes());
+    let mut content_light_level = metadata.max_content_light_level.to_be_bytes().to_vec();
+    content_light_level.extend_from_slice(&metadata.max_pic_average_light_level.to_be_bytes());
+
+    let mut rbsp = Vec::new();
+    let mut rbsp_writer = BitWriter::<_, BigEndian>::new(&mut rbsp);
+    write_sei_message(
+        &mut rbsp_writer,
+        SEI_PAYLOAD_TYPE_MASTERING_DISPLAY_COLOUR_VOLUME,
+        &mastering_display,
+    )?;
+    write_sei_message(
+        &mut rbsp_writer,
+        SEI_PAYLOAD_TYPE_CONTENT_LIGHT_LEVEL_INFO,
+        &content_light_level,
+    )?;
+    rbsp_trailing_bits(&mut rbsp_writer)?;
+    write_h265_nal_unit(writer, H265_NAL_UNIT_TYPE_PREFIX_SEI, &rbsp)
+}
+
+fn write_sei_message<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+    payload_type: u32,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    for mut value in [payload_type, payload.len() as u32] {
+        while value >= 0xff {
+            u(8, writer, 0xff)?;
+            value -= 0xff;
+        }
+        u(8, writer, value)?;
+    }
+    writer.write_bytes(payload)
+}
+
+/// Metadata OBUs with the mastering display colour volume and the content light level. AV1 uses
+/// fixed point values and lists the primaries in red, green, blue order.
+pub fn write_av1_hdr_metadata(
+    writer: &mut impl Write,
+    metadata: &HdrMetadata,
+) -> std::io::Result<()> {
+    // 0.00002 units to 0.16 fixed point
+    let chromaticity = |value: u16| ((value as u32 * 65536 + 25000) / 50000) as u16;
+    let mut mastering_display = vec![METADATA_TYPE_HDR_MDCV];
+    let [green, blue, red] = metadata.display_primaries;
+    for xy in [red, green, blue, metadata.white_point] {
+        mastering_display.extend_from_slice(&chromaticity(xy[0]).to_be_bytes());
+        mastering_display.extend_from_slice(&chromaticity(xy[1]).to_be_bytes());
+    }
+    // 0.0001 cd/m² to 24.8 and 18.14 fixed point
+    let max_luminance = metadata.max_display_mastering_luminance as u64 * 256 / 10000;
+    let min_luminance = metadata.min_display_mastering_luminance as u64 * 16384 / 10000;
+    mastering_display.extend_from_slice(&(max_luminance as u32).to_be_bytes());
+    mastering_display.extend_from_slice(&(min_luminance as u32).to_be_bytes());
+    let mut content_light_level = vec![METADATA_TYPE_HDR_CLL];
+    content_light_level.extend_from_slice(&metadata.max_content_light_level.to_be_bytes());
+    content_light_level.extend_from_slice(&metadata.max_pic_average_light_level.to_be_bytes());
+
+    for mut payload in [mastering_display, content_light_level] {
+        payload.push(0x80); // trailing_bits
+        let mut obu = vec![OBU_TYPE_METADATA << 3 | 0x02]; // obu_has_size_field
+        let mut size = payload.len();
+        loop {
+            let byte = (size & 0x7f) as u8;
+            size >>= 7;
+            if size == 0 {
+                obu.push(byte);
+                break;
+            }
+            obu.push(byte | 0x80);
+        }
+        obu.extend_from_slice(&payload);
+        writer.write_all(&obu)?;
+    }
+    Ok(())
+}
+
+fn write_h265_nal_unit(
+    writer: &mut impl Write,
+    nal_unit_type: u32,
+    rbsp: &[u8],
+) -> std::io::Result<()> {
+    let mut header = Vec::new();
+    let mut header_writer = BitWriter::<_, BigEndian>::new(&mut header);
+    u(32, &mut header_writer, START_CODE)?;
+    u(1, &mut header_writer, FORBIDDEN_ZERO_BIT)?;
+    u(6, &mut header_writer, nal_unit_type)?;
+    u(6, &mut header_writer, 0)?; // nuh_layer_id
+    u(3, &mut header_writer, H265_NUH_TEMPORAL_ID_PLUS1)?;
+
+    writer.write_all(&header)?;
+    writer.write_all(&insert_emulation_prevention(rbsp))
+}
+
+fn write_h264_nal_unit(
+    writer: &mut impl Write,
+    nal_ref_idc: u32,
+    nal_unit_type: u32,
+    rbsp: &[u8],
+) -> std::io::Result<()> {
+    let mut header = Vec::new();
+    let mut header_writer = BitWriter::<_, BigEndian>::new(&mut header);
+    u(32, &mut header_writer, START_CODE)?;
+    u(1, &mut header_writer, FORBIDDEN_ZERO_BIT)?;
+    u(2, &mut header_writer, nal_ref_idc)?;
+    u(5, &mut header_writer, nal_unit_type)?;
+
+    writer.write_all(&header)?;
+    writer.write_all(&insert_emulation_prevention(rbsp))
+}
+
+/// Escapes every `0x000000`-`0x000003` sequence in the RBSP with an emulation prevention byte.
+pub fn insert_emulation_prevention(rbsp: &[u8]) -> Vec<u8> {
+    let mut rtn = Vec::with_capacity(rbsp.len() + rbsp.len() / 64);
+    let mut zeros = 0;
+    for byte in rbsp {
+        if zeros >= 2 && *byte <= EMULATION_PREVENTION_THREE_BYTE {
+            rtn.push(EMULATION_PREVENTION_THREE_BYTE);
+            zeros = 0;
+        }
+        rtn.push(*byte);
+        zeros = if *byte == 0 { zeros + 1 } else { 0 };
+    }
+    rtn
+}
+
+pub(crate) fn rbsp_trailing_bits<W: std::io::Write, E: bitstream_io::Endianness>(
+    writer: &mut BitWriter<W, E>,
+) -> std::io::Result<()> {
+    u(1, writer, RBSP_STOP_ONE_BIT)?;
+    writer.byte_align()
+}
+
 fn se<W: std::io::Write, E: bitstream_io::Endianness>(
     writer: &mut BitWriter<W, E>,
     data: i64,
@@ -144,7 +1286,7 @@ fn se<W: std::io::Write, E: bitstream_io::Endianness>(
     ue(writer, k)
 }
 
-fn ue<W: std::io::Write, E: bitstream_io::Endianness>(
+pub(crate) fn ue<W: std::io::Write, E: bitstream_io::Endianness>(
     writer: &mut BitWriter<W, E>,
     data: u64,
 ) -> std::io::Result<()> {
@@ -158,7 +1300,7 @@ fn ue<W: std::io::Write, E: bitstream_io::Endianness>(
     Ok(())
 }
 
-fn u<W: std::io::Write, E: bitstream_io::Endianness>(
+pub(crate) fn u<W: std::io::Write, E: bitstream_io::Endianness>(
     bits: u32,
     writer: &mut BitWriter<W, E>,
     data: u32,
@@ -223,4 +1365,314 @@ mod tests {
 
         assert_eq!(buffer.as_slice(), [0x00, 0x00, 0x00, 0x01, 0x67]);
     }
+
+    fn zeroed<T>() -> T {
+        // SAFETY: only used for the plain C structs of the video std headers
+        unsafe { std::mem::MaybeUninit::zeroed().assume_init() }
+    }
+
+    fn write_nal(write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        write(&mut buffer).unwrap();
+        assert_eq!(buffer[..4], [0x00, 0x00, 0x00, 0x01]);
+        buffer.split_off(4)
+    }
+
+    #[test]
+    fn emulation_prevention_test() {
+        assert_eq!(
+            insert_emulation_prevention(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x04]),
+            [0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x04]
+        );
+        assert_eq!(
+            insert_emulation_prevention(&[0x10, 0x00, 0x00, 0x03, 0x00, 0x00]),
+            [0x10, 0x00, 0x00, 0x03, 0x03, 0x00, 0x00]
+        );
+    }
+
+    /// x264 1280x720 High@3.1 SPS with the bitstream restriction replaced by the inferred
+    /// motion vector limits, the Vulkan VUI has no fields for them.
+    #[test]
+    fn h264_high_sps_test() {
+        let mut vui: vk::native::StdVideoH264SequenceParameterSetVui = zeroed();
+        vui.flags.set_aspect_ratio_info_present_flag(1);
+        vui.aspect_ratio_idc =
+            vk::native::StdVideoH264AspectRatioIdc_STD_VIDEO_H264_ASPECT_RATIO_IDC_SQUARE;
+        vui.flags.set_timing_info_present_flag(1);
+        vui.num_units_in_tick = 1;
+        vui.time_scale = 60;
+        vui.flags.set_bitstream_restriction_flag(1);
+        vui.max_num_reorder_frames = 2;
+        vui.max_dec_frame_buffering = 4;
+
+        let mut sps: vk::native::StdVideoH264SequenceParameterSet = zeroed();
+        sps.profile_idc = vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_HIGH;
+        sps.level_idc = vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_1;
+        sps.chroma_format_idc =
+            vk::native::StdVideoH264ChromaFormatIdc_STD_VIDEO_H264_CHROMA_FORMAT_IDC_420;
+        sps.log2_max_pic_order_cnt_lsb_minus4 = 2;
+        sps.max_num_ref_frames = 4;
+        sps.pic_width_in_mbs_minus1 = 79;
+        sps.pic_height_in_map_units_minus1 = 44;
+        sps.flags.set_frame_mbs_only_flag(1);
+        sps.flags.set_direct_8x8_inference_flag(1);
+        sps.flags.set_vui_parameters_present_flag(1);
+        sps.pSequenceParameterSetVui = &vui;
+
+        assert_eq!(
+            write_nal(|buffer| write_h264_sps(buffer, &sps)),
+            [
+                0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x50, 0x05, 0xbb, 0x01, 0x10, 0x00, 0x00,
+                0x03, 0x00, 0x10, 0x00, 0x00, 0x03, 0x03, 0xc0, 0xda, 0x08, 0x84, 0x59, 0x60,
+            ]
+        );
+    }
+
+    #[test]
+    fn h264_poc_type_1_hrd_sps_test() {
+        let mut hrd: vk::native::StdVideoH264HrdParameters = zeroed();
+        hrd.bit_rate_scale = 4;
+        hrd.cpb_size_scale = 6;
+        hrd.bit_rate_value_minus1[0] = 1249;
+        hrd.cpb_size_value_minus1[0] = 4999;
+        hrd.cbr_flag[0] = 1;
+        hrd.initial_cpb_removal_delay_length_minus1 = 23;
+        hrd.cpb_removal_delay_length_minus1 = 23;
+        hrd.dpb_output_delay_length_minus1 = 23;
+        hrd.time_offset_length = 24;
+
+        let mut vui: vk::native::StdVideoH264SequenceParameterSetVui = zeroed();
+        vui.flags.set_aspect_ratio_info_present_flag(1);
+        vui.aspect_ratio_idc =
+            vk::native::StdVideoH264AspectRatioIdc_STD_VIDEO_H264_ASPECT_RATIO_IDC_EXTENDED_SAR;
+        vui.sar_width = 4;
+        vui.sar_height = 3;
+        vui.flags.set_video_signal_type_present_flag(1);
+        vui.video_format = 5;
+        vui.flags.set_video_full_range_flag(1);
+        vui.flags.set_color_description_present_flag(1);
+        vui.colour_primaries = 1;
+        vui.transfer_characteristics = 1;
+        vui.matrix_coefficients = 1;
+        vui.flags.set_timing_info_present_flag(1);
+        vui.num_units_in_tick = 1001;
+        vui.time_scale = 60000;
+        vui.flags.set_fixed_frame_rate_flag(1);
+        vui.flags.set_nal_hrd_parameters_present_flag(1);
+        vui.pHrdParameters = &hrd;
+
+        let offsets = [2, -1];
+        let mut sps: vk::native::StdVideoH264SequenceParameterSet = zeroed();
+        sps.profile_idc = vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_MAIN;
+        sps.flags.set_constraint_set1_flag(1);
+        sps.level_idc = vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_0;
+        sps.seq_parameter_set_id = 1;
+        sps.log2_max_frame_num_minus4 = 6;
+        sps.pic_order_cnt_type = vk::native::StdVideoH264PocType_STD_VIDEO_H264_POC_TYPE_1;
+        sps.offset_for_non_ref_pic = -2;
+        sps.num_ref_frames_in_pic_order_cnt_cycle = offsets.len() as u8;
+        sps.pOffsetForRefFrame = offsets.as_ptr();
+        sps.max_num_ref_frames = 2;
+        sps.pic_width_in_mbs_minus1 = 119;
+        sps.pic_height_in_map_units_minus1 = 67;
+        sps.flags.set_frame_mbs_only_flag(1);
+        sps.flags.set_direct_8x8_inference_flag(1);
+        sps.flags.set_frame_cropping_flag(1);
+        sps.frame_crop_bottom_offset = 4;
+        sps.flags.set_vui_parameters_present_flag(1);
+        sps.pSequenceParameterSetVui = &vui;
+
+        assert_eq!(
+            write_nal(|buffer| write_h264_sps(buffer, &sps)),
+            [
+                0x67, 0x4d, 0x40, 0x28, 0x47, 0x42, 0xd9, 0x1b, 0x01, 0xe0, 0x08, 0x9f, 0x97, 0xff,
+                0x00, 0x04, 0x00, 0x03, 0x6e, 0x02, 0x02, 0x02, 0x80, 0x00, 0x01, 0xf4, 0x80, 0x00,
+                0x75, 0x30, 0x74, 0x60, 0x02, 0x71, 0x00, 0x04, 0xe2, 0x37, 0xbd, 0xf0, 0x10,
+            ]
+        );
+    }
+
+    /// Default x264 High profile PPS
+    #[test]
+    fn h264_high_pps_test() {
+        let sps: vk::native::StdVideoH264SequenceParameterSet = zeroed();
+        let mut pps: vk::native::StdVideoH264PictureParameterSet = zeroed();
+        pps.flags.set_entropy_coding_mode_flag(1);
+        pps.num_ref_idx_l0_default_active_minus1 = 2;
+        pps.flags.set_weighted_pred_flag(1);
+        pps.weighted_bipred_idc =
+            vk::native::StdVideoH264WeightedBipredIdc_STD_VIDEO_H264_WEIGHTED_BIPRED_IDC_IMPLICIT;
+        pps.pic_init_qp_minus26 = -3;
+        pps.chroma_qp_index_offset = -2;
+        pps.second_chroma_qp_index_offset = -2;
+        pps.flags.set_deblocking_filter_control_present_flag(1);
+        pps.flags.set_transform_8x8_mode_flag(1);
+
+        assert_eq!(
+            write_nal(|buffer| write_h264_pps(buffer, &sps, &pps)),
+            [0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0]
+        );
+    }
+
+    #[test]
+    fn h264_scaling_matrix_pps_test() {
+        let mut scaling_lists: vk::native::StdVideoH264ScalingLists = zeroed();
+        scaling_lists.scaling_list_present_mask = 0b11;
+        scaling_lists.use_default_scaling_matrix_mask = 0b01;
+        scaling_lists.ScalingList4x4[1] = std::array::from_fn(|i| 16 + i as u8);
+
+        let mut sps: vk::native::StdVideoH264SequenceParameterSet = zeroed();
+        sps.seq_parameter_set_id = 1;
+        sps.chroma_format_idc =
+            vk::native::StdVideoH264ChromaFormatIdc_STD_VIDEO_H264_CHROMA_FORMAT_IDC_420;
+        let mut pps: vk::native::StdVideoH264PictureParameterSet = zeroed();
+        pps.pic_parameter_set_id = 1;
+        pps.flags.set_entropy_coding_mode_flag(1);
+        pps.flags.set_deblocking_filter_control_present_flag(1);
+        pps.flags.set_transform_8x8_mode_flag(1);
+        pps.flags.set_pic_scaling_matrix_present_flag(1);
+        pps.second_chroma_qp_index_offset = 3;
+        pps.pScalingLists = &scaling_lists;
+
+        assert_eq!(
+            write_nal(|buffer| write_h264_pps(buffer, &sps, &pps)),
+            [0x68, 0x4a, 0xe3, 0xce, 0x11, 0x84, 0x12, 0x49, 0x24, 0x92, 0x49, 0x24, 0x01, 0xa0,]
+        );
+    }
+
+    fn h265_profile_tier_level() -> vk::native::StdVideoH265ProfileTierLevel {
+        let mut profile_tier_level: vk::native::StdVideoH265ProfileTierLevel = zeroed();
+        profile_tier_level.general_profile_idc =
+            vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN;
+        profile_tier_level.general_level_idc =
+            vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_3_1;
+        profile_tier_level
+            .flags
+            .set_general_progressive_source_flag(1);
+        profile_tier_level
+            .flags
+            .set_general_frame_only_constraint_flag(1);
+        profile_tier_level
+    }
+
+    fn h265_dec_pic_buf_mgr() -> vk::native::StdVideoH265DecPicBufMgr {
+        let mut dec_pic_buf_mgr: vk::native::StdVideoH265DecPicBufMgr = zeroed();
+        dec_pic_buf_mgr.max_dec_pic_buffering_minus1[0] = 4;
+        dec_pic_buf_mgr.max_num_reorder_pics[0] = 2;
+        dec_pic_buf_mgr.max_latency_increase_plus1[0] = 5;
+        dec_pic_buf_mgr
+    }
+
+    /// x265 Main@3.1 VPS
+    #[test]
+    fn h265_vps_test() {
+        let profile_tier_level = h265_profile_tier_level();
+        let dec_pic_buf_mgr = h265_dec_pic_buf_mgr();
+        let mut vps: vk::native::StdVideoH265VideoParameterSet = zeroed();
+        vps.flags.set_vps_temporal_id_nesting_flag(1);
+        vps.flags.set_vps_sub_layer_ordering_info_present_flag(1);
+        vps.pProfileTierLevel = &profile_tier_level;
+        vps.pDecPicBufMgr = &dec_pic_buf_mgr;
+
+        assert_eq!(
+            write_nal(|buffer| write_h265_vps(buffer, &vps)),
+            [
+                0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00,
+                0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0x95, 0x98, 0x09,
+            ]
+        );
+    }
+
+    /// 1280x720 Main@3.1 SPS with timing information in the VUI
+    #[test]
+    fn h265_sps_test() {
+        let profile_tier_level = h265_profile_tier_level();
+        let dec_pic_buf_mgr = h265_dec_pic_buf_mgr();
+        let mut vui: vk::native::StdVideoH265SequenceParameterSetVui = zeroed();
+        vui.flags.set_aspect_ratio_info_present_flag(1);
+        vui.aspect_ratio_idc =
+            vk::native::StdVideoH265AspectRatioIdc_STD_VIDEO_H265_ASPECT_RATIO_IDC_SQUARE;
+        vui.flags.set_video_signal_type_present_flag(1);
+        vui.video_format = 5;
+        vui.flags.set_chroma_loc_info_present_flag(1);
+        vui.flags.set_vui_timing_info_present_flag(1);
+        vui.vui_num_units_in_tick = 1001;
+        vui.vui_time_scale = 30000;
+
+        let mut sps: vk::native::StdVideoH265SequenceParameterSet = zeroed();
+        sps.flags.set_sps_temporal_id_nesting_flag(1);
+        sps.flags.set_sps_sub_layer_ordering_info_present_flag(1);
+        sps.flags.set_sample_adaptive_offset_enabled_flag(1);
+        sps.flags.set_sps_temporal_mvp_enabled_flag(1);
+        sps.flags.set_strong_intra_smoothing_enabled_flag(1);
+        sps.flags.set_vui_parameters_present_flag(1);
+        sps.chroma_format_idc =
+            vk::native::StdVideoH265ChromaFormatIdc_STD_VIDEO_H265_CHROMA_FORMAT_IDC_420;
+        sps.pic_width_in_luma_samples = 1280;
+        sps.pic_height_in_luma_samples = 720;
+        sps.log2_max_pic_order_cnt_lsb_minus4 = 4;
+        sps.log2_diff_max_min_luma_coding_block_size = 3;
+        sps.log2_diff_max_min_luma_transform_block_size = 3;
+        sps.pProfileTierLevel = &profile_tier_level;
+        sps.pDecPicBufMgr = &dec_pic_buf_mgr;
+        sps.pSequenceParameterSetVui = &vui;
+
+        assert_eq!(
+            write_nal(|buffer| write_h265_sps(buffer, &sps)),
+            [
+                0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00,
+                0x00, 0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93,
+                0x2b, 0xc0, 0x5a, 0x70, 0x80, 0x00, 0x01, 0xf4, 0x80, 0x00, 0x3a, 0x98, 0x04,
+            ]
+        );
+    }
+
+    /// x265 PPS with wavefront parallel processing
+    #[test]
+    fn h265_pps_test() {
+        let mut pps: vk::native::StdVideoH265PictureParameterSet = zeroed();
+        pps.flags.set_sign_data_hiding_enabled_flag(1);
+        pps.flags.set_cu_qp_delta_enabled_flag(1);
+        pps.diff_cu_qp_delta_depth = 1;
+        pps.flags.set_weighted_pred_flag(1);
+        pps.flags.set_entropy_coding_sync_enabled_flag(1);
+        pps.flags.set_pps_loop_filter_across_slices_enabled_flag(1);
+
+        assert_eq!(
+            write_nal(|buffer| write_h265_pps(buffer, &pps)),
+            [0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40]
+        );
+    }
+
+    #[test]
+    fn h265_hdr_sei_test() {
+        let metadata = HdrMetadata::new(&crate::settings::Settings::default());
+        assert_eq!(
+            write_nal(|buffer| write_h265_hdr_sei(buffer, &metadata)),
+            [
+                0x4e, 0x01, 0x89, 0x18, 0x21, 0x34, 0x9b, 0xaa, 0x19, 0x96, 0x08, 0xfc, 0x8a, 0x48,
+                0x39, 0x08, 0x3d, 0x13, 0x40, 0x42, 0x00, 0x98, 0x96, 0x80, 0x00, 0x00, 0x03, 0x00,
+                0x32, 0x90, 0x04, 0x03, 0xe8, 0x01, 0x90, 0x80,
+            ]
+        );
+    }
+
+    #[test]
+    fn av1_hdr_metadata_test() {
+        let metadata = HdrMetadata::new(&crate::settings::Settings::default());
+        let mut buffer = Vec::new();
+        write_av1_hdr_metadata(&mut buffer, &metadata).unwrap();
+        // red primary x 0.708 and white point x 0.3127 in 0.16, 1000 cd/m² in 24.8 and
+        // 0.005 cd/m² in 18.14 fixed point
+        assert_eq!(buffer[..5], [0x2a, 0x1a, 0x02, 0xb5, 0x3f]);
+        assert_eq!(buffer[15..17], [0x50, 0x0d]);
+        assert_eq!(
+            buffer[19..27],
+            [0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x51]
+        );
+        assert_eq!(
+            buffer[28..],
+            [0x2a, 0x06, 0x01, 0x03, 0xe8, 0x01, 0x90, 0x80]
+        );
+    }
 }
@@ -1,11 +1,97 @@
 use core::slice;
-use std::io::Write;
+use std::time::{Duration, Instant};
 
 use ash::{prelude::VkResult, vk};
 use log::{debug, error, warn};
 
+use crate::dpb::PictureType;
+use crate::frame_stats::FrameStats;
+use crate::muxer::{EncodedFrame, Muxer};
+#[cfg(debug_assertions)]
+use crate::nal::StreamValidator;
+use crate::reorder::ScheduledFrame;
 use crate::vulkan_utils::find_memorytype_index;
 
+/// Information about the frame that gets encoded into a bitstream buffer, required by the muxer
+/// once the encoded data is read back.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameInfo {
+    pub picture_type: PictureType,
+    pub pts: Duration,
+    pub dts: Duration,
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    pub poc: u32,
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    pub frame_num: u32,
+    pub display_index: u64,
+    pub decode_index: u64,
+    pub submit_time: Instant,
+}
+
+impl FrameInfo {
+    /// `display_index` counts repeated frames, `decode_index` is the position in decode order
+    pub fn new<T>(frame: &ScheduledFrame<T>, display_index: u64, decode_index: u64) -> Self {
+        Self {
+            picture_type: frame.picture_type,
+            pts: frame.pts,
+            dts: frame.dts,
+            poc: frame.poc,
+            frame_num: frame.frame_num,
+            display_index,
+            decode_index,
+            submit_time: Instant::now(),
+        }
+    }
+}
+
+/// Result of the encode feedback query of one frame
+#[derive(Default, Debug, Copy, Clone)]
+#[repr(C)]
+struct QueryStatus {
+    offset: u32,
+    size: u32,
+    status: vk::QueryResultStatusKHR,
+}
+
+impl QueryStatus {
+    fn is_complete(&self) -> bool {
+        self.status == vk::QueryResultStatusKHR::COMPLETE && self.size != 0
+    }
+}
+
+/// Writes the statistics of every encoded frame to `output` but the bitstream only of complete
+/// ones. A failed query is reported as an empty `NOT_READY` result.
+fn write_output(
+    output: &mut (impl Muxer + ?Sized),
+    info: &FrameInfo,
+    result: QueryStatus,
+    data: Option<&[u8]>,
+    read_back_time: Instant,
+) {
+    if let Some(data) = data {
+        let res = output.write_frame(&EncodedFrame {
+            data,
+            picture_type: info.picture_type,
+            pts: info.pts,
+            dts: info.dts,
+        });
+        debug!("Wrote {}B to output file: {res:?}", data.len());
+    }
+    let res = output.write_frame_stats(&FrameStats {
+        display_index: info.display_index,
+        decode_index: info.decode_index,
+        picture_type: info.picture_type,
+        offset: result.offset,
+        size: result.size,
+        status: result.status,
+        pts: info.pts,
+        readback_latency: read_back_time.saturating_duration_since(info.submit_time),
+    });
+    if let Err(err) = res {
+        warn!("Failed to write frame statistics: {err}");
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct BufferPair {
     pub device: Buffer,
@@ -94,10 +180,14 @@ pub struct BitstreamBufferRing {
     buffers: Vec<Buffer>,
     host_buffers: Vec<Buffer>,
     buffer_generation: Vec<u64>,
+    frame_infos: Vec<Option<FrameInfo>>,
     current: usize,
     generation: u64,
     semaphore: vk::Semaphore,
     query_pool: vk::QueryPool,
+    /// Checks the encoded frames against their [`FrameInfo`] in debug builds
+    #[cfg(debug_assertions)]
+    validator: Option<StreamValidator>,
 }
 
 impl BitstreamBufferRing {
@@ -115,10 +205,13 @@ impl BitstreamBufferRing {
             buffers: Vec::with_capacity(count),
             host_buffers: Vec::with_capacity(count),
             buffer_generation: vec![0; count],
+            frame_infos: vec![None; count],
             semaphore: buffer_result_timeline_semaphore,
             current: 0,
             generation: 0,
             query_pool: vk::QueryPool::null(),
+            #[cfg(debug_assertions)]
+            validator: None,
         };
         if buffer_result_timeline_semaphore == vk::Semaphore::null() {
             warn!("Could not create bitstream buffers because no valid timeline semaphore was provided!");
@@ -192,17 +285,26 @@ impl BitstreamBufferRing {
         }
     }
 
-    pub fn next(
+    #[cfg(debug_assertions)]
+    pub fn set_validator(&mut self, validator: StreamValidator) {
+        self.validator = Some(validator);
+    }
+
+    pub fn set_frame_info(&mut self, slot: u32, info: FrameInfo) {
+        self.frame_infos[slot as usize] = Some(info);
+    }
+
+    /// Waits for the encode into buffer `idx` to finish and hands the result to `output`.
+    fn read_back(
         &mut self,
         device: &ash::Device,
+        idx: usize,
         timeout: u64,
-        output: Option<&mut impl Write>,
-    ) -> VkResult<BufferPair> {
-        let buffer = &self.buffers[self.current];
-
-        let host = &self.host_buffers[self.current];
+        output: Option<&mut (impl Muxer + ?Sized)>,
+    ) -> VkResult<()> {
+        let host = &self.host_buffers[idx];
         let semaphores = [self.semaphore];
-        let values = [self.buffer_generation[self.current]];
+        let values = [self.buffer_generation[idx]];
         let info = vk::SemaphoreWaitInfo::default()
             .values(&values)
             .semaphores(&semaphores);
@@ -215,18 +317,11 @@ impl BitstreamBufferRing {
                 );
             })?;
         }
-
-        #[derive(Default, Debug, Copy, Clone)]
-        #[repr(C)]
-        struct QueryStatus {
-            offset: u32,
-            size: u32,
-            status: vk::QueryResultStatusKHR,
-        }
-        let mut result = [QueryStatus::default()];
+        let read_back_time = Instant::now();
 
         if values[0] != 0 {
             let slot = ((values[0] as usize - 1) % self.buffer_generation.len()) as u32;
+            let mut result = [QueryStatus::default()];
             let result = unsafe {
                 let result = device
                     .get_query_pool_results(
@@ -243,35 +338,80 @@ impl BitstreamBufferRing {
                     })
                     .map(|_| result[0]);
                 device.reset_query_pool(self.query_pool, slot, 1);
-                if result.is_err()
-                    || result.unwrap().status != vk::QueryResultStatusKHR::COMPLETE
-                    || result.unwrap().size == 0
-                {
-                    warn!("{:?} slot {slot} encoding {}", result, values[0]);
-                }
                 result
             };
+            if !result.is_ok_and(|result| result.is_complete()) {
+                warn!("{:?} slot {slot} encoding {}", result, values[0]);
+            }
+            let result = result.unwrap_or_default();
             // TODO: offload to IO thread
-            if let Ok(result) = result {
-                let size = host.size().min(result.size.into());
+            let size = host.size().min(result.size.into());
+            let data = if result.is_complete() {
                 unsafe {
-                    let data = device.map_memory(
-                        host.memory(),
-                        0, //result.offset.into(),
-                        size,
-                        vk::MemoryMapFlags::default(),
-                    );
-                    if let (Ok(data), Some(output)) = (data, output) {
-                        let res = output
-                            .write_all(slice::from_raw_parts(data as *const u8, size as usize));
-                        debug!("Wrote {}B to output file: {res:?}", size);
-                    }
-                    device.unmap_memory(host.memory());
+                    device
+                        .map_memory(
+                            host.memory(),
+                            0, //result.offset.into(),
+                            size,
+                            vk::MemoryMapFlags::default(),
+                        )
+                        .inspect_err(|e| warn!("Failed to map bitstream buffer {idx}: {e}"))
+                        .ok()
+                        .map(|data| slice::from_raw_parts(data as *const u8, size as usize))
                 }
             } else {
-                warn!("Failed with query: {result:?}")
+                None
+            };
+            if let Some(info) = self.frame_infos[idx] {
+                #[cfg(debug_assertions)]
+                if let (Some(data), Some(validator)) = (data, self.validator.as_mut()) {
+                    if let Err(e) = validator.check_access_unit(
+                        data,
+                        info.picture_type,
+                        info.poc,
+                        info.frame_num,
+                    ) {
+                        error!("Encoded frame does not match {info:?}: {e:#}");
+                    }
+                }
+                if let Some(output) = output {
+                    write_output(output, &info, result, data, read_back_time);
+                }
+            }
+            if data.is_some() {
+                unsafe { device.unmap_memory(host.memory()) };
             }
         }
+        self.buffer_generation[idx] = 0;
+        self.frame_infos[idx] = None;
+        Ok(())
+    }
+
+    /// Writes out all buffers that still hold encoded data, oldest first.
+    pub fn flush(
+        &mut self,
+        device: &ash::Device,
+        timeout: u64,
+        mut output: Option<&mut (impl Muxer + ?Sized)>,
+    ) -> VkResult<()> {
+        for i in 0..self.buffers.len() {
+            let idx = (self.current + i) % self.buffers.len();
+            if self.buffer_generation[idx] != 0 {
+                self.read_back(device, idx, timeout, output.as_deref_mut())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn next(
+        &mut self,
+        device: &ash::Device,
+        timeout: u64,
+        output: Option<&mut (impl Muxer + ?Sized)>,
+    ) -> VkResult<BufferPair> {
+        self.read_back(device, self.current, timeout, output)?;
+        let buffer = &self.buffers[self.current];
+        let host = &self.host_buffers[self.current];
 
         self.buffer_generation[self.current] = self.generation + 1;
         let rtn = BufferPair {
@@ -288,3 +428,128 @@ impl BitstreamBufferRing {
         Ok(rtn)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gop::GopStructure;
+    use crate::muxer::RecordingMuxer;
+    use crate::reorder::FrameReorderer;
+
+    fn frame_info(display_index: u64) -> FrameInfo {
+        FrameInfo {
+            picture_type: PictureType::P,
+            pts: Duration::from_millis(10 * display_index),
+            dts: Duration::from_millis(10 * display_index),
+            poc: display_index as u32,
+            frame_num: display_index as u32,
+            display_index,
+            decode_index: display_index,
+            submit_time: Instant::now(),
+        }
+    }
+
+    /// Passes frames captured at `capture_times` through the reorderer to a muxer
+    fn mux_captured(gop: GopStructure, capture_times: &[Duration]) -> RecordingMuxer {
+        let mut reorderer = FrameReorderer::new(gop, Duration::from_millis(10));
+        let mut frames = Vec::new();
+        for (i, &pts) in capture_times.iter().enumerate() {
+            frames.extend(reorderer.push(i as u64, pts, false));
+        }
+        frames.extend(reorderer.flush());
+        let mut output = RecordingMuxer::default();
+        for (decode_index, frame) in frames.iter().enumerate() {
+            let info = FrameInfo::new(frame, frame.input, decode_index as u64);
+            let result = QueryStatus {
+                offset: 0,
+                size: 1,
+                status: vk::QueryResultStatusKHR::COMPLETE,
+            };
+            write_output(&mut output, &info, result, Some(&[0]), Instant::now());
+        }
+        output
+    }
+
+    #[test]
+    fn presentation_timestamps_test() {
+        // presented irregularly, the muxer gets the present times
+        let capture_times: Vec<_> = [0, 16, 35, 50, 70]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        let output = mux_captured(
+            GopStructure::new(16, 16, 0, 1, PictureType::P).unwrap(),
+            &capture_times,
+        );
+        let pts: Vec<_> = output.frames.iter().map(|frame| frame.pts).collect();
+        assert_eq!(pts, capture_times);
+        let stats_pts: Vec<_> = output.stats.iter().map(|stats| stats.pts).collect();
+        assert_eq!(stats_pts, capture_times);
+
+        // with B frames they arrive in decode order, delayed by the reorder depth
+        let output = mux_captured(
+            GopStructure::new(16, 16, 2, 1, PictureType::P).unwrap(),
+            &capture_times,
+        );
+        let mut pts: Vec<_> = output.frames.iter().map(|frame| frame.pts).collect();
+        assert_ne!(pts, capture_times);
+        pts.sort();
+        let delay = Duration::from_millis(20);
+        assert_eq!(
+            pts,
+            capture_times.iter().map(|t| *t + delay).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn write_output_test() {
+        let mut output = RecordingMuxer::default();
+        let complete = QueryStatus {
+            offset: 0,
+            size: 3,
+            status: vk::QueryResultStatusKHR::COMPLETE,
+        };
+        write_output(
+            &mut output,
+            &frame_info(0),
+            complete,
+            Some(&[1, 2, 3]),
+            Instant::now(),
+        );
+        let overflow = QueryStatus {
+            offset: 0,
+            size: 0,
+            status: vk::QueryResultStatusKHR::INSUFFICIENTSTREAM_BUFFER_RANGE,
+        };
+        write_output(&mut output, &frame_info(1), overflow, None, Instant::now());
+        // a failed query
+        write_output(
+            &mut output,
+            &frame_info(2),
+            QueryStatus::default(),
+            None,
+            Instant::now(),
+        );
+
+        assert_eq!(output.frames.len(), 1);
+        assert_eq!(output.frames[0].data, [1, 2, 3]);
+        let stats: Vec<_> = output
+            .stats
+            .iter()
+            .map(|stats| (stats.display_index, stats.size, stats.status))
+            .collect();
+        assert_eq!(
+            stats,
+            [
+                (0, 3, vk::QueryResultStatusKHR::COMPLETE),
+                (
+                    1,
+                    0,
+                    vk::QueryResultStatusKHR::INSUFFICIENTSTREAM_BUFFER_RANGE
+                ),
+                (2, 0, vk::QueryResultStatusKHR::NOT_READY),
+            ]
+        );
+        assert!(complete.is_complete() && !overflow.is_complete());
+    }
+}
@@ -0,0 +1,793 @@
+use std::ffi::{c_void, CStr};
+use std::ptr::{null, null_mut};
+
+use ash::khr;
+use ash::prelude::VkResult;
+use ash::vk;
+use log::{debug, error, info, warn};
+
+use crate::color::{bit_depth, ColorConversion, ColourDescription, HdrMetadata};
+use crate::dpb::{CqpOptions, RateControlKind};
+use crate::level::{h264_level, h264_level_name, h265_level, h265_level_name, LevelParameters};
+use crate::profile::VideoProfile;
+use crate::reorder::DPB_SLOT_COUNT;
+use crate::scale::fit_extent;
+use crate::settings::{Codec, RateControlMode, Settings};
+use crate::state::get_state;
+use crate::vk_beta::VideoEncodeAV1CapabilitiesKHR;
+
+/// Encode limits of the physical device for a video profile
+#[derive(Debug, Clone)]
+pub struct EncodeCapabilities {
+    pub min_coded_extent: vk::Extent2D,
+    pub max_coded_extent: vk::Extent2D,
+    /// Granularity of the image accesses, the coded extent is aligned to it
+    pub picture_access_granularity: vk::Extent2D,
+    pub max_dpb_slots: u32,
+    pub max_active_reference_pictures: u32,
+    /// Whether B frames can reference one picture in each direction
+    pub supports_b_frames: bool,
+    pub rate_control_modes: vk::VideoEncodeRateControlModeFlagsKHR,
+    pub max_bitrate: u64,
+    pub max_quality_levels: u32,
+    /// Preferred rate control mode of each quality level
+    pub preferred_rate_control_modes: Vec<vk::VideoEncodeRateControlModeFlagsKHR>,
+    /// Range of the constant QP, the quantizer index for AV1
+    pub min_qp: u32,
+    pub max_qp: u32,
+    /// Highest level as `StdVideoH264LevelIdc`, `StdVideoH265LevelIdc` or `StdVideoAV1Level`
+    pub max_level: u32,
+    pub h265_ctb_sizes: vk::VideoEncodeH265CtbSizeFlagsKHR,
+    pub av1_coded_picture_alignment: vk::Extent2D,
+    /// Formats usable as encode input and as reference pictures
+    pub src_formats: Vec<vk::Format>,
+    pub dpb_formats: Vec<vk::Format>,
+}
+
+impl EncodeCapabilities {
+    /// Queries the capabilities of the encode `profile` of `codec`
+    pub fn query(
+        physical_device: vk::PhysicalDevice,
+        profile: &VideoProfile,
+        codec: Codec,
+    ) -> VkResult<Self> {
+        let state = get_state();
+        let lock = state.instance.read().unwrap();
+        let instance = lock
+            .as_ref()
+            .ok_or(vk::Result::ERROR_INITIALIZATION_FAILED)?;
+        let get_instance_proc_addr = (*state.instance_get_fn.read().unwrap())
+            .ok_or(vk::Result::ERROR_INITIALIZATION_FAILED)?;
+        let load = |name: &CStr| unsafe {
+            get_instance_proc_addr(instance.handle(), name.as_ptr())
+                .map_or(null(), |f| f as *const c_void)
+        };
+        let video_queue_fn = khr::video_queue::InstanceFn::load(load);
+        let encode_queue_fn = khr::video_encode_queue::InstanceFn::load(load);
+
+        let mut encode = vk::VideoEncodeCapabilitiesKHR::default();
+        let mut h264 = vk::VideoEncodeH264CapabilitiesKHR::default();
+        let mut h265 = vk::VideoEncodeH265CapabilitiesKHR::default();
+        let mut av1 = VideoEncodeAV1CapabilitiesKHR::default();
+        let capabilities = vk::VideoCapabilitiesKHR::default().push_next(&mut encode);
+        let mut capabilities = match codec {
+            Codec::H264 => capabilities.push_next(&mut h264),
+            Codec::H265 => capabilities.push_next(&mut h265),
+            Codec::AV1 => capabilities.push_next(&mut av1),
+        };
+        unsafe {
+            (video_queue_fn.get_physical_device_video_capabilities_khr)(
+                physical_device,
+                profile.profile(),
+                &mut capabilities,
+            )
+        }
+        .result()
+        .inspect_err(|err| error!("Failed to query video capabilities: {err}"))?;
+        debug!("Video capabilities: {capabilities:?}");
+        let vk::VideoCapabilitiesKHR {
+            min_coded_extent,
+            max_coded_extent,
+            picture_access_granularity,
+            max_dpb_slots,
+            max_active_reference_pictures,
+            ..
+        } = capabilities;
+        debug!("Encode capabilities: {encode:?}");
+
+        let (min_qp, max_qp, max_level, supports_b_frames) = match codec {
+            Codec::H264 => {
+                debug!("H.264 encode capabilities: {h264:?}");
+                (
+                    h264.min_qp.max(0) as u32,
+                    h264.max_qp.max(0) as u32,
+                    h264.max_level_idc,
+                    h264.max_b_picture_l0_reference_count > 0 && h264.max_l1_reference_count > 0,
+                )
+            }
+            Codec::H265 => {
+                debug!("H.265 encode capabilities: {h265:?}");
+                (
+                    h265.min_qp.max(0) as u32,
+                    h265.max_qp.max(0) as u32,
+                    h265.max_level_idc,
+                    h265.max_b_picture_l0_reference_count > 0 && h265.max_l1_reference_count > 0,
+                )
+            }
+            Codec::AV1 => {
+                debug!("AV1 encode capabilities: {av1:?}");
+                (av1.min_q_index, av1.max_q_index, av1.max_level, false)
+            }
+        };
+
+        let preferred_rate_control_modes = (0..encode.max_quality_levels)
+            .map(|quality_level| {
+                let info = vk::PhysicalDeviceVideoEncodeQualityLevelInfoKHR::default()
+                    .video_profile(profile.profile())
+                    .quality_level(quality_level);
+                let mut properties = vk::VideoEncodeQualityLevelPropertiesKHR::default();
+                unsafe {
+                    (encode_queue_fn.get_physical_device_video_encode_quality_level_properties_khr)(
+                        physical_device,
+                        &info,
+                        &mut properties,
+                    )
+                }
+                .result_with_success(properties.preferred_rate_control_mode)
+            })
+            .collect::<VkResult<_>>()
+            .inspect_err(|err| error!("Failed to query quality level properties: {err}"))
+            .unwrap_or_default();
+
+        let formats = |image_usage: vk::ImageUsageFlags| -> VkResult<Vec<vk::Format>> {
+            let mut profiles = vk::VideoProfileListInfoKHR::default()
+                .profiles(std::slice::from_ref(profile.profile()));
+            let info = vk::PhysicalDeviceVideoFormatInfoKHR::default()
+                .image_usage(image_usage)
+                .push_next(&mut profiles);
+            let mut count = 0;
+            unsafe {
+                (video_queue_fn.get_physical_device_video_format_properties_khr)(
+                    physical_device,
+                    &info,
+                    &mut count,
+                    null_mut(),
+                )
+                .result()?;
+                let mut properties = vec![vk::VideoFormatPropertiesKHR::default(); count as usize];
+                (video_queue_fn.get_physical_device_video_format_properties_khr)(
+                    physical_device,
+                    &info,
+                    &mut count,
+                    properties.as_mut_ptr(),
+                )
+                .result()?;
+                properties.truncate(count as usize);
+                Ok(properties.iter().map(|p| p.format).collect())
+            }
+        };
+        let src_formats = formats(vk::ImageUsageFlags::VIDEO_ENCODE_SRC_KHR)
+            .inspect_err(|err| error!("Failed to query encode input formats: {err}"))?;
+        let dpb_formats = formats(vk::ImageUsageFlags::VIDEO_ENCODE_DPB_KHR)
+            .inspect_err(|err| error!("Failed to query reference picture formats: {err}"))?;
+
+        Ok(Self {
+            min_coded_extent,
+            max_coded_extent,
+            picture_access_granularity,
+            max_dpb_slots,
+            max_active_reference_pictures,
+            supports_b_frames,
+            rate_control_modes: encode.rate_control_modes,
+            max_bitrate: encode.max_bitrate,
+            max_quality_levels: encode.max_quality_levels,
+            preferred_rate_control_modes,
+            min_qp,
+            max_qp,
+            max_level,
+            h265_ctb_sizes: h265.ctb_sizes,
+            av1_coded_picture_alignment: av1.coded_picture_alignment,
+            src_formats,
+            dpb_formats,
+        })
+    }
+
+    /// Falls back to [`RateControlKind::Disabled`] if the mode isn't supported, and clamps the
+    /// bitrate and constant QPs to the supported range.
+    pub fn validate_rate_control(&self, mut kind: RateControlKind) -> RateControlKind {
+        if kind.mode() != vk::VideoEncodeRateControlModeFlagsKHR::DEFAULT
+            && !self.rate_control_modes.contains(kind.mode())
+        {
+            error!(
+                "Rate control mode {:?} is not supported by the device (supported: {:?}), disabling rate control",
+                kind.mode(),
+                self.rate_control_modes
+            );
+            return RateControlKind::Disabled;
+        }
+        if let Some(bitrate) = kind.bitrate_mut() {
+            if self.max_bitrate > 0 && bitrate.max_bitrate > self.max_bitrate {
+                warn!(
+                    "Bitrate {} exceeds the maximum of the device, using {}",
+                    bitrate.max_bitrate, self.max_bitrate
+                );
+                bitrate.max_bitrate = self.max_bitrate;
+                bitrate.average_bitrate = bitrate.average_bitrate.min(self.max_bitrate);
+            }
+        }
+        match kind {
+            RateControlKind::Cqp(cqp) => {
+                let clamp = |qp: u32| {
+                    let clamped = qp.clamp(self.min_qp, self.max_qp);
+                    if clamped != qp {
+                        warn!(
+                            "QP {qp} is outside of the supported range [{}, {}], using {clamped}",
+                            self.min_qp, self.max_qp
+                        );
+                    }
+                    clamped
+                };
+                RateControlKind::Cqp(CqpOptions {
+                    intra: clamp(cqp.intra),
+                    predictive: clamp(cqp.predictive),
+                    bipredictive: clamp(cqp.bipredictive),
+                })
+            }
+            kind => kind,
+        }
+    }
+}
+
+/// Coding tree block size in luma samples, 32 if supported as it is what most encoders use
+fn select_h265_ctb_size(ctb_sizes: vk::VideoEncodeH265CtbSizeFlagsKHR) -> Option<u32> {
+    [
+        (vk::VideoEncodeH265CtbSizeFlagsKHR::TYPE_32, 32),
+        (vk::VideoEncodeH265CtbSizeFlagsKHR::TYPE_64, 64),
+        (vk::VideoEncodeH265CtbSizeFlagsKHR::TYPE_16, 16),
+    ]
+    .into_iter()
+    .find_map(|(flag, size)| ctb_sizes.contains(flag).then_some(size))
+}
+
+/// Least common multiple, a zero granularity doesn't constrain the alignment
+fn lcm(a: u32, b: u32) -> u32 {
+    if a == 0 || b == 0 {
+        return a.max(b);
+    }
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+/// Encoder parameters derived from the settings and validated against [`EncodeCapabilities`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderConfig {
+    pub codec: Codec,
+    /// Picture format of the encoded images, NV12 or P010
+    pub format: vk::Format,
+    /// Signalled for P010 pictures, which are always HDR10
+    pub hdr_metadata: Option<HdrMetadata>,
+    pub conversion: ColorConversion,
+    /// Size of the recorded pictures
+    pub extent: vk::Extent2D,
+    /// Granularity the coded extent is rounded up to
+    pub alignment: vk::Extent2D,
+    /// Width and height of the H.265 coding tree blocks in luma samples
+    pub h265_ctb_size: u32,
+    pub quality_level: u32,
+    /// `StdVideoH264LevelIdc`, `StdVideoH265LevelIdc` or `StdVideoAV1Level` depending on the codec
+    pub level: u32,
+    pub dpb_slots: u32,
+    pub max_active_reference_pictures: u32,
+    pub consecutive_b_frames: u8,
+}
+
+impl EncoderConfig {
+    /// Without `capabilities` only the level is derived from the stream. Fails if the device
+    /// can't encode the format or extent at all.
+    pub fn new(
+        settings: &Settings,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        capabilities: Option<&EncodeCapabilities>,
+    ) -> VkResult<Self> {
+        let codec = settings.codec;
+        let consecutive_b_frames = settings.consecutive_b_frames();
+        let mut config = Self {
+            codec,
+            format,
+            hdr_metadata: (bit_depth(format) == 10).then(|| HdrMetadata::new(settings)),
+            conversion: ColorConversion::new(settings, bit_depth(format) == 10),
+            extent,
+            alignment: match codec {
+                // macroblocks
+                Codec::H264 => vk::Extent2D {
+                    width: 16,
+                    height: 16,
+                },
+                // the coding tree block size of the SPS
+                Codec::H265 => vk::Extent2D {
+                    width: 32,
+                    height: 32,
+                },
+                Codec::AV1 => vk::Extent2D {
+                    width: 8,
+                    height: 8,
+                },
+            },
+            h265_ctb_size: 32,
+            quality_level: settings.quality_level,
+            // selected once the B frames are known
+            level: 0,
+            dpb_slots: DPB_SLOT_COUNT as u32,
+            max_active_reference_pictures: 1 + (consecutive_b_frames > 0) as u32,
+            consecutive_b_frames,
+        };
+        if let Some(capabilities) = capabilities {
+            config.limit_to(capabilities)?;
+        }
+        config.level = config.select_level(settings, capabilities.map(|c| c.max_level));
+        info!(
+            "Encoder config: quality level {}, level {}, {} DPB slots, {} active references, {} consecutive B frames, coded extent {:?}, {:?} {:?}",
+            config.quality_level,
+            config.level_name(),
+            config.dpb_slots,
+            config.max_active_reference_pictures,
+            config.consecutive_b_frames,
+            config.coded_extent(),
+            config.format,
+            config.conversion
+        );
+        Ok(config)
+    }
+
+    fn limit_to(&mut self, capabilities: &EncodeCapabilities) -> VkResult<()> {
+        let format = self.format;
+        if !capabilities.src_formats.contains(&format)
+            || !capabilities.dpb_formats.contains(&format)
+        {
+            error!(
+                "{format:?} is not supported by the device, encode input formats: {:?}, reference picture formats: {:?}",
+                capabilities.src_formats, capabilities.dpb_formats
+            );
+            return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+        }
+
+        match self.codec {
+            Codec::H264 => {}
+            Codec::H265 => {
+                self.h265_ctb_size =
+                    select_h265_ctb_size(capabilities.h265_ctb_sizes).ok_or_else(|| {
+                        error!(
+                            "The device supports none of the coding tree block sizes {:?}",
+                            capabilities.h265_ctb_sizes
+                        );
+                        vk::Result::ERROR_FORMAT_NOT_SUPPORTED
+                    })?;
+                self.alignment = vk::Extent2D {
+                    width: self.h265_ctb_size,
+                    height: self.h265_ctb_size,
+                };
+            }
+            Codec::AV1 => {
+                let alignment = capabilities.av1_coded_picture_alignment;
+                if alignment.width > 0 && alignment.height > 0 {
+                    self.alignment = alignment;
+                }
+            }
+        }
+        let granularity = capabilities.picture_access_granularity;
+        self.alignment = vk::Extent2D {
+            width: lcm(self.alignment.width, granularity.width),
+            height: lcm(self.alignment.height, granularity.height),
+        };
+        info!(
+            "Aligning the coded extent to {}x{}",
+            self.alignment.width, self.alignment.height
+        );
+
+        // the coded extent is rounded up to the alignment and has to stay within the maximum
+        let max = capabilities.max_coded_extent;
+        let max = vk::Extent2D {
+            width: max.width - max.width % self.alignment.width,
+            height: max.height - max.height % self.alignment.height,
+        };
+        let vk::Extent2D { width, height } = self.extent;
+        let extent = fit_extent(self.extent, max);
+        if extent != self.extent {
+            warn!(
+                "{width}x{height} is larger than the maximum coded extent {}x{} supported by the device, recording at {}x{}",
+                max.width, max.height, extent.width, extent.height
+            );
+            self.extent = extent;
+        }
+        let min = capabilities.min_coded_extent;
+        let coded_extent = self.coded_extent();
+        if coded_extent.width < min.width || coded_extent.height < min.height {
+            error!(
+                "{}x{} is below the minimum coded extent {}x{} supported by the device",
+                self.extent.width, self.extent.height, min.width, min.height
+            );
+            return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+        }
+
+        if capabilities.max_quality_levels == 0 {
+            warn!("The device reports no quality levels, using quality level 0");
+            self.quality_level = 0;
+        } else if self.quality_level >= capabilities.max_quality_levels {
+            warn!(
+                "Quality level {} is not supported, using the highest supported level {}",
+                self.quality_level,
+                capabilities.max_quality_levels - 1
+            );
+            self.quality_level = capabilities.max_quality_levels - 1;
+        }
+        if let Some(mode) = capabilities
+            .preferred_rate_control_modes
+            .get(self.quality_level as usize)
+        {
+            info!(
+                "Quality level {} prefers rate control mode {mode:?}",
+                self.quality_level
+            );
+        }
+
+        if capabilities.max_dpb_slots < self.dpb_slots {
+            error!(
+                "The device supports only {} DPB slots, but {} are needed",
+                capabilities.max_dpb_slots, self.dpb_slots
+            );
+        }
+        if self.consecutive_b_frames > 0
+            && (!capabilities.supports_b_frames || capabilities.max_active_reference_pictures < 2)
+        {
+            warn!("The device can't reference two pictures from B frames, not using B frames");
+            self.consecutive_b_frames = 0;
+        }
+        self.max_active_reference_pictures = (1 + (self.consecutive_b_frames > 0) as u32)
+            .min(capabilities.max_active_reference_pictures);
+        Ok(())
+    }
+
+    /// The lowest level that fits the stream, but at most `max_level`
+    fn select_level(&self, settings: &Settings, max_level: Option<u32>) -> u32 {
+        let reference_frames = 1 + (self.consecutive_b_frames > 0) as u32;
+        let parameters = LevelParameters {
+            coded_extent: self.coded_extent(),
+            frame_rate_numerator: settings.frame_rate_numerator,
+            frame_rate_denominator: settings.frame_rate_denominator,
+            max_bitrate: match settings.rate_control_mode {
+                RateControlMode::Cbr | RateControlMode::Vbr => {
+                    settings.max_bitrate.max(settings.average_bitrate)
+                }
+                RateControlMode::Cqp | RateControlMode::Disabled => 0,
+            },
+            // the SPS counts the current picture for H.265
+            dpb_size: match self.codec {
+                Codec::H264 | Codec::AV1 => reference_frames,
+                Codec::H265 => reference_frames + 1,
+            },
+        };
+        let level = match self.codec {
+            Codec::H264 => h264_level(
+                &parameters,
+                vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_MAIN,
+            )
+            .unwrap_or(vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_6_2),
+            Codec::H265 => h265_level(&parameters, self.h265_profile_idc())
+                .unwrap_or(vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_2),
+            Codec::AV1 => vk::native::StdVideoAV1Level_STD_VIDEO_AV1_LEVEL_5_1,
+        };
+        let vk::Extent2D { width, height } = parameters.coded_extent;
+        info!(
+            "{width}x{height} at {}/{} fps, {} bit/s and {} reference frames needs level {}",
+            parameters.frame_rate_numerator,
+            parameters.frame_rate_denominator,
+            parameters.max_bitrate,
+            reference_frames,
+            Self { level, ..*self }.level_name()
+        );
+        match max_level {
+            Some(max_level) if level > max_level => {
+                warn!(
+                    "The device supports at most level {}, the stream may not conform to it",
+                    Self {
+                        level: max_level,
+                        ..*self
+                    }
+                    .level_name()
+                );
+                max_level
+            }
+            _ => level,
+        }
+    }
+
+    fn level_name(&self) -> String {
+        match self.codec {
+            Codec::H264 => h264_level_name(self.level).into(),
+            Codec::H265 => h265_level_name(self.level).into(),
+            // StdVideoAV1Level counts from 2.0 with four minor levels each
+            Codec::AV1 => format!("{}.{}", 2 + self.level / 4, self.level % 4),
+        }
+    }
+
+    pub fn bit_depth(&self) -> u32 {
+        bit_depth(self.format)
+    }
+
+    /// Main10 for P010 pictures, Main otherwise
+    pub fn h265_profile_idc(&self) -> vk::native::StdVideoH265ProfileIdc {
+        if self.bit_depth() == 10 {
+            vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN_10
+        } else {
+            vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN
+        }
+    }
+
+    /// Colour description to signal in the stream
+    pub fn colour_description(&self) -> ColourDescription {
+        self.conversion.colour_description()
+    }
+
+    /// Extent of the encoded pictures, the extent rounded up to the alignment
+    pub fn coded_extent(&self) -> vk::Extent2D {
+        vk::Extent2D {
+            width: self.extent.width.next_multiple_of(self.alignment.width),
+            height: self.extent.height.next_multiple_of(self.alignment.height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{VIDEO_FORMAT_10BIT, VIDEO_FORMAT_8BIT};
+    use crate::dpb::BitrateOptions;
+
+    fn capabilities() -> EncodeCapabilities {
+        EncodeCapabilities {
+            min_coded_extent: vk::Extent2D {
+                width: 64,
+                height: 64,
+            },
+            max_coded_extent: vk::Extent2D {
+                width: 4096,
+                height: 4096,
+            },
+            picture_access_granularity: vk::Extent2D {
+                width: 64,
+                height: 16,
+            },
+            max_dpb_slots: 16,
+            max_active_reference_pictures: 1,
+            supports_b_frames: false,
+            rate_control_modes: vk::VideoEncodeRateControlModeFlagsKHR::DISABLED
+                | vk::VideoEncodeRateControlModeFlagsKHR::CBR,
+            max_bitrate: 50_000_000,
+            max_quality_levels: 4,
+            preferred_rate_control_modes: Vec::new(),
+            min_qp: 10,
+            max_qp: 40,
+            max_level: vk::native::StdVideoAV1Level_STD_VIDEO_AV1_LEVEL_4_0,
+            h265_ctb_sizes: vk::VideoEncodeH265CtbSizeFlagsKHR::TYPE_32,
+            av1_coded_picture_alignment: vk::Extent2D {
+                width: 64,
+                height: 16,
+            },
+            src_formats: vec![VIDEO_FORMAT_8BIT],
+            dpb_formats: vec![VIDEO_FORMAT_8BIT],
+        }
+    }
+
+    fn bitrate(average_bitrate: u64, max_bitrate: u64) -> BitrateOptions {
+        BitrateOptions {
+            max_bitrate,
+            average_bitrate,
+            frame_rate_numerator: 60,
+            frame_rate_denominator: 1,
+        }
+    }
+
+    #[test]
+    fn validate_rate_control_test() {
+        let capabilities = capabilities();
+        let cqp = capabilities.validate_rate_control(RateControlKind::Cqp(CqpOptions {
+            intra: 5,
+            predictive: 30,
+            bipredictive: 51,
+        }));
+        assert!(matches!(
+            cqp,
+            RateControlKind::Cqp(CqpOptions {
+                intra: 10,
+                predictive: 30,
+                bipredictive: 40
+            })
+        ));
+        let cbr = capabilities
+            .validate_rate_control(RateControlKind::Cbr(bitrate(60_000_000, 80_000_000)));
+        let cbr = cbr.bitrate().unwrap();
+        assert_eq!(
+            (cbr.average_bitrate, cbr.max_bitrate),
+            (50_000_000, 50_000_000)
+        );
+        assert!(matches!(
+            capabilities
+                .validate_rate_control(RateControlKind::Vbr(bitrate(10_000_000, 20_000_000))),
+            RateControlKind::Disabled
+        ));
+        assert!(matches!(
+            capabilities.validate_rate_control(RateControlKind::Disabled),
+            RateControlKind::Disabled
+        ));
+    }
+
+    #[test]
+    fn encoder_config_test() {
+        let extent = vk::Extent2D {
+            width: 1366,
+            height: 768,
+        };
+        let settings = Settings {
+            codec: Codec::AV1,
+            quality_level: 7,
+            ..Default::default()
+        };
+        let config = EncoderConfig::new(&settings, extent, VIDEO_FORMAT_8BIT, None).unwrap();
+        assert_eq!(config.quality_level, 7);
+        assert_eq!(
+            config.coded_extent(),
+            vk::Extent2D {
+                width: 1368,
+                height: 768
+            }
+        );
+        let config =
+            EncoderConfig::new(&settings, extent, VIDEO_FORMAT_8BIT, Some(&capabilities()))
+                .unwrap();
+        assert_eq!(config.quality_level, 3);
+        assert_eq!(
+            config.level,
+            vk::native::StdVideoAV1Level_STD_VIDEO_AV1_LEVEL_4_0
+        );
+        assert_eq!(
+            config.coded_extent(),
+            vk::Extent2D {
+                width: 1408,
+                height: 768
+            }
+        );
+
+        let settings = Settings {
+            codec: Codec::H264,
+            max_consecutive_b_frames: 2,
+            ..Default::default()
+        };
+        let config = EncoderConfig::new(&settings, extent, VIDEO_FORMAT_8BIT, None).unwrap();
+        assert_eq!(config.consecutive_b_frames, 2);
+        assert_eq!(config.max_active_reference_pictures, 2);
+        // 4128 macroblocks at 60 fps exceed level 4.1
+        assert_eq!(
+            config.level,
+            vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_2
+        );
+        assert_eq!(
+            config.coded_extent(),
+            vk::Extent2D {
+                width: 1376,
+                height: 768
+            }
+        );
+        // the device can't reference two pictures
+        let config =
+            EncoderConfig::new(&settings, extent, VIDEO_FORMAT_8BIT, Some(&capabilities()))
+                .unwrap();
+        assert_eq!(config.consecutive_b_frames, 0);
+        assert_eq!(config.max_active_reference_pictures, 1);
+        // macroblocks aligned to the picture access granularity
+        assert_eq!(
+            config.coded_extent(),
+            vk::Extent2D {
+                width: 1408,
+                height: 768
+            }
+        );
+        assert_eq!(config.colour_description().transfer_characteristics, 1);
+        // 8K is scaled down to the maximum coded extent
+        let uhd_8k = vk::Extent2D {
+            width: 7680,
+            height: 4320,
+        };
+        let config =
+            EncoderConfig::new(&settings, uhd_8k, VIDEO_FORMAT_8BIT, Some(&capabilities()))
+                .unwrap();
+        assert_eq!(
+            config.coded_extent(),
+            vk::Extent2D {
+                width: 4096,
+                height: 2304
+            }
+        );
+
+        let settings = Settings {
+            codec: Codec::H265,
+            hdr_max_cll: 600,
+            ..Default::default()
+        };
+        let config = EncoderConfig::new(&settings, extent, VIDEO_FORMAT_10BIT, None).unwrap();
+        assert_eq!(config.bit_depth(), 10);
+        assert_eq!(
+            config.h265_profile_idc(),
+            vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN_10
+        );
+        assert_eq!(config.colour_description().transfer_characteristics, 16);
+        assert_eq!(
+            config.hdr_metadata.map(|metadata| (
+                metadata.max_display_mastering_luminance,
+                metadata.max_content_light_level
+            )),
+            Some((10_000_000, 600))
+        );
+    }
+
+    #[test]
+    fn unsupported_capabilities_test() {
+        let extent = vk::Extent2D {
+            width: 1366,
+            height: 768,
+        };
+        let settings = Settings {
+            codec: Codec::H265,
+            ..Default::default()
+        };
+        let mut capabilities = capabilities();
+        capabilities.h265_ctb_sizes = vk::VideoEncodeH265CtbSizeFlagsKHR::TYPE_16
+            | vk::VideoEncodeH265CtbSizeFlagsKHR::TYPE_64;
+        capabilities.picture_access_granularity = vk::Extent2D {
+            width: 32,
+            height: 32,
+        };
+        let config =
+            EncoderConfig::new(&settings, extent, VIDEO_FORMAT_8BIT, Some(&capabilities)).unwrap();
+        assert_eq!(config.h265_ctb_size, 64);
+        assert_eq!(
+            config.coded_extent(),
+            vk::Extent2D {
+                width: 1408,
+                height: 768
+            }
+        );
+        capabilities.h265_ctb_sizes = vk::VideoEncodeH265CtbSizeFlagsKHR::empty();
+        assert_eq!(
+            EncoderConfig::new(&settings, extent, VIDEO_FORMAT_8BIT, Some(&capabilities)),
+            Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+        );
+
+        let settings = Settings {
+            codec: Codec::H264,
+            ..Default::default()
+        };
+        assert_eq!(
+            EncoderConfig::new(&settings, extent, VIDEO_FORMAT_10BIT, Some(&capabilities)),
+            Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+        );
+        let small = vk::Extent2D {
+            width: 32,
+            height: 32,
+        };
+        assert_eq!(
+            EncoderConfig::new(&settings, small, VIDEO_FORMAT_8BIT, Some(&capabilities)),
+            Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+        );
+    }
+
+    #[test]
+    fn lcm_test() {
+        assert_eq!(lcm(16, 64), 64);
+        assert_eq!(lcm(32, 48), 96);
+        assert_eq!(lcm(16, 0), 16);
+    }
+}
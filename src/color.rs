@@ -0,0 +1,393 @@
+use ash::vk;
+
+use crate::settings::{ChromaSiting, Codec, ColorMatrix, ColorRange, Settings};
+
+/// 4:2:0 with 8 bit per component, used for SDR swapchains
+pub const VIDEO_FORMAT_8BIT: vk::Format = vk::Format::G8_B8R8_2PLANE_420_UNORM;
+/// P010, 4:2:0 with 10 bit per component in the high bits of 16, used for HDR swapchains
+pub const VIDEO_FORMAT_10BIT: vk::Format = vk::Format::G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16;
+
+/// How the swapchain images encode their colours. The conversion shader reads them through a
+/// UNORM view and re-encodes them with the transfer function of the output, sRGB for 8 bit and
+/// PQ for 10 bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum InputTransfer {
+    /// SMPTE ST 2084 with BT.2020 primaries, i.e. already HDR10
+    Pq = 0,
+    /// sRGB with BT.709 primaries, the reference white is mapped to 203 cd/m² (BT.2408)
+    Srgb = 1,
+    /// Linear scRGB with BT.709 primaries, 1.0 is 80 cd/m²
+    Linear = 2,
+}
+
+/// Format and colour space of the swapchain images the conversion reads from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputFormat {
+    pub format: vk::Format,
+    pub color_space: vk::ColorSpaceKHR,
+}
+
+impl InputFormat {
+    /// Whether the swapchain is converted to P010 with PQ and BT.2020
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.format,
+            vk::Format::A2B10G10R10_UNORM_PACK32
+                | vk::Format::A2R10G10B10_UNORM_PACK32
+                | vk::Format::R16G16B16A16_SFLOAT
+        )
+    }
+
+    pub fn transfer(&self) -> InputTransfer {
+        match self.color_space {
+            vk::ColorSpaceKHR::HDR10_ST2084_EXT => InputTransfer::Pq,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => InputTransfer::Linear,
+            _ => InputTransfer::Srgb,
+        }
+    }
+
+    /// Format of the storage view the conversion shader reads the swapchain images through. sRGB
+    /// formats can't be storage images and would decode to linear anyway, so they're read as
+    /// UNORM, which needs a mutable format swapchain.
+    pub fn storage_view_format(&self) -> vk::Format {
+        match self.format {
+            vk::Format::B8G8R8A8_SRGB => vk::Format::B8G8R8A8_UNORM,
+            vk::Format::R8G8B8A8_SRGB => vk::Format::R8G8B8A8_UNORM,
+            vk::Format::A8B8G8R8_SRGB_PACK32 => vk::Format::A8B8G8R8_UNORM_PACK32,
+            format => format,
+        }
+    }
+
+    /// Picture format the swapchain is encoded in, `None` if there's no conversion for it.
+    /// Vulkan Video has no 10 bit H.264 profile, so HDR swapchains need H.265 or AV1.
+    pub fn video_format(&self, codec: Codec) -> Option<vk::Format> {
+        match self.format {
+            vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => Some(VIDEO_FORMAT_8BIT),
+            _ if self.is_hdr() && codec != Codec::H264 => Some(VIDEO_FORMAT_10BIT),
+            _ => None,
+        }
+    }
+}
+
+pub fn bit_depth(video_format: vk::Format) -> u32 {
+    if video_format == VIDEO_FORMAT_10BIT {
+        10
+    } else {
+        8
+    }
+}
+
+/// Formats of the luma and chroma plane views the conversion shader writes to. P010 planes are
+/// written as 16 bit UNORM, which needs a mutable format image.
+pub fn plane_view_formats(video_format: vk::Format) -> [vk::Format; 2] {
+    if video_format == VIDEO_FORMAT_10BIT {
+        [vk::Format::R16_UNORM, vk::Format::R16G16_UNORM]
+    } else {
+        [vk::Format::R8_UNORM, vk::Format::R8G8_UNORM]
+    }
+}
+
+/// Colour description code points of ITU-T H.273, shared by the H.264/H.265 VUI and the AV1
+/// colour config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColourDescription {
+    pub colour_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub full_range: bool,
+}
+
+/// How the conversion shader maps RGB to YCbCr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorConversion {
+    pub matrix: ColorMatrix,
+    pub range: ColorRange,
+    pub chroma_siting: ChromaSiting,
+    /// PQ and BT.2020 primaries instead of the BT.709 ones of SDR swapchains
+    pub hdr: bool,
+}
+
+impl ColorConversion {
+    pub fn new(settings: &Settings, hdr: bool) -> Self {
+        Self {
+            matrix: if hdr {
+                ColorMatrix::Bt2020
+            } else {
+                settings.color_matrix
+            },
+            range: settings.color_range,
+            chroma_siting: settings.chroma_siting,
+            hdr,
+        }
+    }
+
+    /// Luma weights of red and blue
+    pub fn kr_kb(&self) -> (f32, f32) {
+        match self.matrix {
+            ColorMatrix::Bt601 => (0.299, 0.114),
+            ColorMatrix::Bt709 => (0.2126, 0.0722),
+            ColorMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+
+    /// Values of the specialization constants 0-3 of the conversion shaders, see `ycbcr.hlsli`
+    pub fn specialization_constants(&self) -> [u32; 4] {
+        let (kr, kb) = self.kr_kb();
+        [
+            kr.to_bits(),
+            kb.to_bits(),
+            (self.range == ColorRange::Full) as u32,
+            self.chroma_siting as u32,
+        ]
+    }
+
+    /// The swapchain primaries don't change with the matrix, SDR is signalled as BT.709
+    pub fn colour_description(&self) -> ColourDescription {
+        let (colour_primaries, transfer_characteristics) = if self.hdr { (9, 16) } else { (1, 1) };
+        ColourDescription {
+            colour_primaries,
+            transfer_characteristics,
+            matrix_coefficients: match self.matrix {
+                ColorMatrix::Bt601 => 6,
+                ColorMatrix::Bt709 => 1,
+                // non-constant luminance
+                ColorMatrix::Bt2020 => 9,
+            },
+            full_range: self.range == ColorRange::Full,
+        }
+    }
+
+    /// `chroma_sample_loc_type` of the H.264/H.265 VUI
+    pub fn chroma_sample_loc_type(&self) -> u8 {
+        self.chroma_siting as u8
+    }
+}
+
+/// Static HDR metadata: the mastering display colour volume (SMPTE ST 2086) and the content
+/// light levels (CTA-861.3), in the units of the H.265 SEI messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HdrMetadata {
+    /// x and y of the green, blue and red primaries in units of 0.00002
+    pub display_primaries: [[u16; 2]; 3],
+    /// x and y of the white point in units of 0.00002
+    pub white_point: [u16; 2],
+    /// In units of 0.0001 cd/m²
+    pub max_display_mastering_luminance: u32,
+    pub min_display_mastering_luminance: u32,
+    /// Maximum content light level in cd/m²
+    pub max_content_light_level: u16,
+    /// Maximum frame-average light level in cd/m²
+    pub max_pic_average_light_level: u16,
+}
+
+impl HdrMetadata {
+    /// BT.2020 primaries and D65 white point with the luminances from the settings
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            display_primaries: [[8500, 39850], [6550, 2300], [35400, 14600]],
+            white_point: [15635, 16450],
+            max_display_mastering_luminance: settings.hdr_max_luminance.saturating_mul(10000),
+            min_display_mastering_luminance: settings.hdr_min_luminance,
+            max_content_light_level: settings.hdr_max_cll.min(u16::MAX as u32) as u16,
+            max_pic_average_light_level: settings.hdr_max_fall.min(u16::MAX as u32) as u16,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_format_test() {
+        let input = |format, color_space| InputFormat {
+            format,
+            color_space,
+        };
+        let sdr = input(vk::Format::B8G8R8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR);
+        assert_eq!(sdr.video_format(Codec::H264), Some(VIDEO_FORMAT_8BIT));
+        assert!(!sdr.is_hdr());
+
+        let hdr10 = input(
+            vk::Format::A2B10G10R10_UNORM_PACK32,
+            vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+        );
+        assert_eq!(hdr10.transfer(), InputTransfer::Pq);
+        assert_eq!(hdr10.video_format(Codec::H265), Some(VIDEO_FORMAT_10BIT));
+        assert_eq!(hdr10.video_format(Codec::H264), None);
+
+        let scrgb = input(
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        );
+        assert_eq!(scrgb.transfer(), InputTransfer::Linear);
+        assert_eq!(scrgb.video_format(Codec::AV1), Some(VIDEO_FORMAT_10BIT));
+
+        assert_eq!(
+            input(
+                vk::Format::R8G8B8A8_UNORM,
+                vk::ColorSpaceKHR::SRGB_NONLINEAR
+            )
+            .video_format(Codec::H265),
+            None
+        );
+        assert_eq!(bit_depth(VIDEO_FORMAT_10BIT), 10);
+    }
+
+    #[test]
+    fn color_conversion_test() {
+        let settings = Settings {
+            color_matrix: ColorMatrix::Bt601,
+            color_range: ColorRange::Full,
+            chroma_siting: ChromaSiting::Center,
+            ..Default::default()
+        };
+        let sdr = ColorConversion::new(&settings, false);
+        assert_eq!(
+            sdr.specialization_constants(),
+            [0.299f32.to_bits(), 0.114f32.to_bits(), 1, 1]
+        );
+        assert_eq!(
+            sdr.colour_description(),
+            ColourDescription {
+                colour_primaries: 1,
+                transfer_characteristics: 1,
+                matrix_coefficients: 6,
+                full_range: true,
+            }
+        );
+        assert_eq!(sdr.chroma_sample_loc_type(), 1);
+
+        let hdr = ColorConversion::new(&Settings::default(), true);
+        assert_eq!(hdr.matrix, ColorMatrix::Bt2020);
+        assert_eq!(
+            hdr.colour_description(),
+            ColourDescription {
+                colour_primaries: 9,
+                transfer_characteristics: 16,
+                matrix_coefficients: 9,
+                full_range: false,
+            }
+        );
+        assert_eq!(hdr.chroma_sample_loc_type(), 0);
+    }
+
+    /// Port of `ycbcr.hlsli` evaluated with the specialization constants the pipelines are
+    /// created with, so the test covers what the GPU computes rather than the textbook formulas
+    struct YcbcrShader {
+        kr: f32,
+        kb: f32,
+        full_range: bool,
+        chroma_siting: u32,
+    }
+
+    impl YcbcrShader {
+        fn new(conversion: &ColorConversion) -> Self {
+            let [kr, kb, full_range, chroma_siting] = conversion.specialization_constants();
+            Self {
+                kr: f32::from_bits(kr),
+                kb: f32::from_bits(kb),
+                full_range: full_range != 0,
+                chroma_siting,
+            }
+        }
+
+        fn luma(&self, rgb: [f32; 3]) -> f32 {
+            self.kr * rgb[0] + (1.0 - self.kr - self.kb) * rgb[1] + self.kb * rgb[2]
+        }
+
+        fn chroma(&self, rgb: [f32; 3]) -> [f32; 2] {
+            let l = self.luma(rgb);
+            [
+                (rgb[2] - l) / (2.0 * (1.0 - self.kb)),
+                (rgb[0] - l) / (2.0 * (1.0 - self.kr)),
+            ]
+        }
+
+        fn luma_code(&self, value: f32, max_code: f32) -> f32 {
+            let code = if self.full_range {
+                value * max_code
+            } else {
+                (219.0 * value + 16.0) * (max_code + 1.0) / 256.0
+            };
+            code.clamp(0.0, max_code)
+        }
+
+        fn chroma_code(&self, value: f32, max_code: f32) -> f32 {
+            let code = if self.full_range {
+                value * max_code + (max_code + 1.0) / 2.0
+            } else {
+                (224.0 * value + 128.0) * (max_code + 1.0) / 256.0
+            };
+            code.clamp(0.0, max_code)
+        }
+
+        /// Rounded code values of a pixel as stored by the `main` of the conversion shaders
+        fn ycbcr(&self, rgb: [f32; 3], max_code: f32) -> [u32; 3] {
+            let [cb, cr] = self.chroma(rgb);
+            [
+                self.luma_code(self.luma(rgb), max_code),
+                self.chroma_code(cb, max_code),
+                self.chroma_code(cr, max_code),
+            ]
+            .map(|code| code.round() as u32)
+        }
+    }
+
+    /// `linear_to_srgb(saturate(rgb))` of `bgr_to_yuv.hlsl`
+    fn linear_to_srgb(rgb: [f32; 3]) -> [f32; 3] {
+        rgb.map(|c| {
+            let c = c.clamp(0.0, 1.0);
+            if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        })
+    }
+
+    #[test]
+    fn ycbcr_reference_test() {
+        let sdr = InputFormat {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        // read as the encoded values instead of decoding to linear
+        assert_eq!(sdr.storage_view_format(), vk::Format::B8G8R8A8_UNORM);
+        assert_eq!(sdr.transfer(), InputTransfer::Srgb);
+
+        let bt709 = YcbcrShader::new(&ColorConversion::new(&Settings::default(), false));
+        assert_eq!(bt709.chroma_siting, ChromaSiting::Left as u32);
+        let srgb = |rgb| bt709.ycbcr(rgb, 255.0);
+        assert_eq!(srgb([1.0, 1.0, 1.0]), [235, 128, 128]);
+        assert_eq!(srgb([0.0, 0.0, 0.0]), [16, 128, 128]);
+        assert_eq!(srgb([1.0, 0.0, 0.0]), [63, 102, 240]);
+        assert_eq!(srgb([0.0, 1.0, 0.0]), [173, 42, 26]);
+        assert_eq!(srgb([0.0, 0.0, 1.0]), [32, 240, 118]);
+        // linear mid grey is encoded as sRGB 0.735 before the matrix
+        assert_eq!(
+            bt709.ycbcr(linear_to_srgb([0.5, 0.5, 0.5]), 255.0),
+            [177, 128, 128]
+        );
+
+        let bt601_full = YcbcrShader::new(&ColorConversion::new(
+            &Settings {
+                color_matrix: ColorMatrix::Bt601,
+                color_range: ColorRange::Full,
+                chroma_siting: ChromaSiting::Center,
+                ..Default::default()
+            },
+            false,
+        ));
+        assert_eq!(bt601_full.chroma_siting, ChromaSiting::Center as u32);
+        assert_eq!(bt601_full.ycbcr([1.0, 0.0, 0.0], 255.0), [76, 85, 255]);
+
+        // P010 codes of the HDR shaders, which get PQ encoded BT.2020 values
+        let bt2020 = YcbcrShader::new(&ColorConversion::new(&Settings::default(), true));
+        let p010 = |rgb| bt2020.ycbcr(rgb, 1023.0);
+        assert_eq!(p010([1.0, 1.0, 1.0]), [940, 512, 512]);
+        assert_eq!(p010([0.0, 0.0, 0.0]), [64, 512, 512]);
+        assert_eq!(p010([1.0, 0.0, 0.0]), [294, 387, 960]);
+    }
+}
@@ -0,0 +1,241 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, Once};
+
+use anyhow::{anyhow, bail};
+use log::{debug, error, info, warn};
+
+use crate::settings::ControlSocket;
+use crate::state::get_state;
+
+/// Recording state requested at runtime, applied by every swapchain on its next present.
+pub struct ControlState {
+    pub recording: AtomicBool,
+    pub paused: AtomicBool,
+    /// Set to write the replay buffer to a file on the next present
+    pub save_replay: AtomicBool,
+    /// Average and maximum bitrate requested at runtime, 0 keeps the configured one
+    pub average_bitrate: AtomicU64,
+    pub max_bitrate: AtomicU64,
+    /// Constant QP of all picture types requested at runtime
+    pub qp: Mutex<Option<u32>>,
+    pub encoded_frames: AtomicU64,
+    pub output_file: Mutex<Option<PathBuf>>,
+}
+
+impl Default for ControlState {
+    fn default() -> Self {
+        Self {
+            recording: AtomicBool::new(true),
+            paused: AtomicBool::new(false),
+            save_replay: AtomicBool::new(false),
+            average_bitrate: AtomicU64::new(0),
+            max_bitrate: AtomicU64::new(0),
+            qp: Mutex::new(None),
+            encoded_frames: AtomicU64::new(0),
+            output_file: Mutex::new(None),
+        }
+    }
+}
+
+impl ControlState {
+    fn status(&self) -> String {
+        let output_file = self.output_file.lock().unwrap();
+        format!(
+            "recording={} paused={} frames={} average_bitrate={} output={}",
+            self.recording.load(Ordering::Relaxed),
+            self.paused.load(Ordering::Relaxed),
+            self.encoded_frames.load(Ordering::Relaxed),
+            self.average_bitrate.load(Ordering::Relaxed),
+            output_file
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Starts a new output file after `Stop`
+    Start,
+    /// Finalizes the current output file
+    Stop,
+    Pause,
+    Resume,
+    SaveReplay,
+    Status,
+    SetBitrate(u64),
+    /// Ignored in constant bitrate mode, where the maximum is the average bitrate
+    SetMaxBitrate(u64),
+    /// Only used in constant QP mode, in the H.264 and H.265 QP range
+    SetQp(u32),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some("start") => Self::Start,
+            Some("stop") => Self::Stop,
+            Some("pause") => Self::Pause,
+            Some("resume") => Self::Resume,
+            Some("save-replay") => Self::SaveReplay,
+            Some("status") => Self::Status,
+            Some("set") => {
+                let argument = words.next().unwrap_or_default();
+                let (key, value) = match argument.split_once('=') {
+                    Some((key @ ("bitrate" | "max_bitrate" | "qp"), value)) => (key, value),
+                    _ => bail!("expected set bitrate=N, set max_bitrate=N or set qp=N"),
+                };
+                let value: u64 = value
+                    .parse()
+                    .map_err(|err| anyhow!("invalid {key} {value:?}: {err}"))?;
+                match key {
+                    "qp" if value > 51 => bail!("qp must be between 0 and 51"),
+                    "qp" => Self::SetQp(value as u32),
+                    _ if value == 0 => bail!("{key} must not be 0"),
+                    "bitrate" => Self::SetBitrate(value),
+                    _ => Self::SetMaxBitrate(value),
+                }
+            }
+            Some(command) => bail!("unknown command {command:?}"),
+            None => bail!("empty command"),
+        };
+        if let Some(extra) = words.next() {
+            bail!("unexpected argument {extra:?}");
+        }
+        Ok(command)
+    }
+
+    /// Records the command in `control` and returns the reply to the client
+    fn apply(self, control: &ControlState) -> String {
+        match self {
+            Self::Start => control.recording.store(true, Ordering::Relaxed),
+            Self::Stop => control.recording.store(false, Ordering::Relaxed),
+            Self::Pause => control.paused.store(true, Ordering::Relaxed),
+            Self::Resume => control.paused.store(false, Ordering::Relaxed),
+            Self::SaveReplay => control.save_replay.store(true, Ordering::Relaxed),
+            Self::Status => return control.status(),
+            Self::SetBitrate(bitrate) => control.average_bitrate.store(bitrate, Ordering::Relaxed),
+            Self::SetMaxBitrate(bitrate) => control.max_bitrate.store(bitrate, Ordering::Relaxed),
+            Self::SetQp(qp) => *control.qp.lock().unwrap() = Some(qp),
+        }
+        "ok".into()
+    }
+}
+
+/// Starts listening for commands on the configured control socket, at most once per process.
+pub fn start_control_socket(socket: ControlSocket, application_name: Option<&str>) {
+    static STARTED: Once = Once::new();
+    if socket == ControlSocket::None {
+        return;
+    }
+    STARTED.call_once(|| match bind(socket, application_name) {
+        Ok(listener) => {
+            let res = std::thread::Builder::new()
+                .name("vk_video_record control".into())
+                .spawn(move || listen(listener));
+            if let Err(err) = res {
+                error!("Failed to start control socket thread: {err}");
+            }
+        }
+        Err(err) => error!("Failed to create control socket: {err}"),
+    });
+}
+
+fn bind(socket: ControlSocket, application_name: Option<&str>) -> std::io::Result<UnixListener> {
+    match socket {
+        ControlSocket::Abstract => {
+            let name = format!(
+                "vk_video_record.{}",
+                application_name.unwrap_or("UnknownApp")
+            );
+            info!("Listening for commands on abstract socket @{name}");
+            UnixListener::bind_addr(&SocketAddr::from_abstract_name(name)?)
+        }
+        ControlSocket::Path => {
+            let path = dirs::runtime_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join(format!("vk_video_record.{}.sock", std::process::id()));
+            // a previous process with the same id didn't clean up
+            let _ = std::fs::remove_file(&path);
+            info!("Listening for commands on {path:?}");
+            UnixListener::bind(path)
+        }
+        ControlSocket::None => Err(std::io::Error::other("control socket is disabled")),
+    }
+}
+
+fn listen(listener: UnixListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = handle_client(stream) {
+                    warn!("Control socket connection failed: {err}");
+                }
+            }
+            Err(err) => error!("Failed to accept control socket connection: {err}"),
+        }
+    }
+}
+
+fn handle_client(stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        debug!("Control command {line:?}");
+        let reply = match Command::parse(&line) {
+            Ok(command) => command.apply(&get_state().control),
+            Err(err) => format!("error: {err}"),
+        };
+        writeln!(writer, "{reply}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command_test() {
+        assert_eq!(Command::parse("start").unwrap(), Command::Start);
+        assert_eq!(
+            Command::parse(" save-replay ").unwrap(),
+            Command::SaveReplay
+        );
+        assert_eq!(
+            Command::parse("set bitrate=4000000").unwrap(),
+            Command::SetBitrate(4_000_000)
+        );
+        assert_eq!(
+            Command::parse("set max_bitrate=8000000").unwrap(),
+            Command::SetMaxBitrate(8_000_000)
+        );
+        assert!(Command::parse("set max_bitrate=0").is_err());
+        assert!(Command::parse("set bitrate=fast").is_err());
+        assert_eq!(Command::parse("set qp=0").unwrap(), Command::SetQp(0));
+        assert_eq!(Command::parse("set qp=30").unwrap(), Command::SetQp(30));
+        assert!(Command::parse("set qp=52").is_err());
+        assert!(Command::parse("stop now").is_err());
+        assert!(Command::parse("").is_err());
+    }
+
+    #[test]
+    fn apply_command_test() {
+        let control = ControlState::default();
+        assert_eq!(Command::Pause.apply(&control), "ok");
+        assert_eq!(Command::Stop.apply(&control), "ok");
+        assert_eq!(Command::SetBitrate(1000).apply(&control), "ok");
+        assert_eq!(Command::SetQp(30).apply(&control), "ok");
+        assert_eq!(*control.qp.lock().unwrap(), Some(30));
+        assert_eq!(
+            Command::Status.apply(&control),
+            "recording=false paused=true frames=0 average_bitrate=1000 output="
+        );
+    }
+}
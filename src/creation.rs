@@ -1,16 +1,21 @@
 use ash::vk;
 use core::ptr::null_mut;
 
+use crate::control::start_control_socket;
+use crate::settings::Codec;
+use crate::signals::install_signal_handlers;
 use crate::state::get_state;
+use crate::vk_beta::{PhysicalDeviceVideoEncodeAV1FeaturesKHR, VK_KHR_VIDEO_ENCODE_AV1_NAME};
 use crate::vk_layer;
 use crate::vk_layer::VkLayerFunction;
 use crate::vulkan_utils::ptr_chain_get_next;
 #[cfg(debug_assertions)]
 use ash::ext;
 use ash::khr;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use std::collections::HashSet;
-use std::{ffi::CStr, mem::transmute};
+use std::ffi::{CStr, CString};
+use std::mem::transmute;
 
 #[no_mangle]
 pub extern "system" fn record_vk_create_instance(
@@ -86,6 +91,13 @@ pub extern "system" fn record_vk_create_instance(
                 // TODO: patch application info to support vk video
                 let res = real_create_instance(&create_info, p_allocator, p_instance);
                 if res == vk::Result::SUCCESS {
+                    start_control_socket(
+                        state.settings.control_socket,
+                        state.application_name.read().unwrap().as_deref(),
+                    );
+                    if state.settings.signal_handlers {
+                        install_signal_handlers();
+                    }
                     *state.instance.write().unwrap() = Some(ash::Instance::load(
                         &ash::StaticFn {
                             get_instance_proc_addr: transmute(get_instance_proc_addr),
@@ -164,10 +176,51 @@ pub extern "system" fn record_vk_create_device(
                     })
                     .collect();
                 info!("Enabled extensions: {:?}", extensions);
+                let supported_extensions: HashSet<CString> = instance
+                    .enumerate_device_extension_properties(physical_device)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|props| props.extension_name_as_c_str().ok())
+                    .map(CStr::to_owned)
+                    .collect();
                 // TODO check whether they are supported
                 for e in REQUIRED_EXTENSIONS.iter() {
                     extensions.insert(e);
                 }
+                // storage views of sRGB swapchains
+                let swapchain_mutable_format =
+                    supported_extensions.contains(khr::swapchain_mutable_format::NAME);
+                if swapchain_mutable_format {
+                    extensions.insert(khr::swapchain_mutable_format::NAME);
+                } else {
+                    warn!(
+                        "VK_KHR_swapchain_mutable_format is missing, not recording sRGB swapchains"
+                    );
+                }
+                *state.swapchain_mutable_format.write().unwrap() = swapchain_mutable_format;
+                let mut av1_features = PhysicalDeviceVideoEncodeAV1FeaturesKHR::default();
+                if state.settings.codec == Codec::AV1 {
+                    let av1_extensions =
+                        [VK_KHR_VIDEO_ENCODE_AV1_NAME, khr::video_decode_av1::NAME];
+                    if av1_extensions
+                        .iter()
+                        .all(|name| supported_extensions.contains(*name))
+                    {
+                        let mut features =
+                            vk::PhysicalDeviceFeatures2::default().push_next(&mut av1_features);
+                        instance.get_physical_device_features2(physical_device, &mut features);
+                    }
+                    if av1_features.video_encode_av1 != vk::TRUE {
+                        error!("The device doesn't support AV1 encoding, recording is disabled");
+                        return real_create_device(
+                            physical_device,
+                            p_create_info,
+                            p_allocator,
+                            p_device,
+                        );
+                    }
+                    extensions.extend(av1_extensions);
+                }
                 info!("Enabled extensions after layer: {:?}", extensions);
                 let extensions: Vec<_> = extensions.iter().map(|s| s.as_ptr()).collect();
 
@@ -311,6 +364,15 @@ pub extern "system" fn record_vk_create_device(
                 {
                     create_info = create_info.push_next(&mut features13);
                 }
+                if state.settings.codec == Codec::AV1
+                    && ptr_chain_get_next::<_, vk::BaseOutStructure>(&create_info, |c| {
+                        (*(*c)).s_type == av1_features.s_type
+                    })
+                    .is_none()
+                {
+                    av1_features = av1_features.video_encode_av1(true);
+                    create_info = create_info.push_next(&mut av1_features);
+                }
                 debug_assert!(!create_info.p_next.is_null());
                 debug_assert!(!(*p_create_info).p_next.is_null());
 
@@ -1,78 +1,124 @@
 #[cfg(debug_assertions)]
+use crate::nal::StreamValidator;
+#[cfg(debug_assertions)]
 use crate::vulkan_utils::name_object;
 use crate::{shader::ComputePipelineDescriptor, vulkan_utils::find_memorytype_index};
 
-#[cfg(feature = "nvpro_sample_gop")]
-use crate::gop_gen::{
-    VkVideoGopStructure, VkVideoGopStructure_GetFrameType, VkVideoGopStructure_destroy,
-    VkVideoGopStructure_new,
-};
 use anyhow::anyhow;
 use ash::{prelude::VkResult, vk};
-#[cfg(feature = "nvpro_sample_gop")]
-use core::slice;
-use itertools::Itertools;
-use log::{debug, error, trace};
-#[cfg(feature = "nvpro_sample_gop")]
-use std::ffi::c_void;
+use itertools::{izip, Itertools};
+use log::{debug, error, trace, warn};
 use std::{
     collections::HashMap,
-    io::Write,
-    marker::PhantomData,
-    mem::{transmute, zeroed, MaybeUninit},
+    mem::{zeroed, MaybeUninit},
     ptr::null,
+    time::Duration,
 };
 
 use crate::{
-    buffer_queue::{BitstreamBufferRing, BufferPair},
+    buffer_queue::{BitstreamBufferRing, BufferPair, FrameInfo},
     cmd_buffer_queue::{CommandBuffer, CommandBufferQueue},
+    color::{plane_view_formats, InputFormat, VIDEO_FORMAT_10BIT},
+    gop::GopStructure,
+    muxer::Muxer,
+    reorder::{FrameReorderer, ScheduledFrame, MAX_CONSECUTIVE_B_FRAMES},
+    scale::{ScaleOptions, Scaling},
+    session_parameters::H264_LOG2_MAX_FRAME_NUM,
     settings::Codec,
     shader::ShaderPipeline,
     state::Extensions,
     video_session::VideoSession,
+    vk_beta::{
+        StdVideoEncodeAV1PictureInfo, StdVideoEncodeAV1PictureInfoFlags,
+        StdVideoEncodeAV1ReferenceInfo, VideoEncodeAV1DpbSlotInfoKHR, VideoEncodeAV1PictureInfoKHR,
+        VideoEncodeAV1RateControlInfoKHR, VideoEncodeAV1RateControlLayerInfoKHR,
+        VIDEO_ENCODE_AV1_PREDICTION_MODE_INTRA_ONLY_KHR,
+        VIDEO_ENCODE_AV1_PREDICTION_MODE_SINGLE_REFERENCE_KHR,
+        VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_INTRA_KHR,
+        VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_PREDICTIVE_KHR,
+        VIDEO_ENCODE_AV1_RATE_CONTROL_REGULAR_GOP_BIT_KHR,
+        VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR,
+    },
+    vk_layer::{
+        STD_VIDEO_AV1_PRIMARY_REF_NONE, STD_VIDEO_H264_NO_REFERENCE_PICTURE,
+        STD_VIDEO_H265_NO_REFERENCE_PICTURE,
+    },
 };
 
-pub struct CbrOptions {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitrateOptions {
     pub max_bitrate: u64,
     pub average_bitrate: u64,
     pub frame_rate_numerator: u32,
     pub frame_rate_denominator: u32,
 }
 
+/// Constant QPs per picture type, quantizer indices for AV1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CqpOptions {
+    pub intra: u32,
+    pub predictive: u32,
+    pub bipredictive: u32,
+}
+
+impl CqpOptions {
+    fn qp(&self, picture_type: PictureType) -> u32 {
+        match picture_type {
+            PictureType::Idr | PictureType::I => self.intra,
+            PictureType::P => self.predictive,
+            PictureType::B => self.bipredictive,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum RateControlKind {
-    Cbr(CbrOptions),
+    Cbr(BitrateOptions),
+    Vbr(BitrateOptions),
+    Cqp(CqpOptions),
+    /// Leaves rate control to the implementation
+    Disabled,
 }
 
 impl RateControlKind {
-    pub fn as_cbr(&self) -> Option<&CbrOptions> {
-        #[allow(irrefutable_let_patterns)]
-        if let Self::Cbr(v) = self {
-            Some(v)
-        } else {
-            None
+    pub fn bitrate(&self) -> Option<&BitrateOptions> {
+        match self {
+            Self::Cbr(v) | Self::Vbr(v) => Some(v),
+            Self::Cqp(_) | Self::Disabled => None,
         }
     }
 
-    pub fn as_cbr_mut(&mut self) -> Option<&mut CbrOptions> {
-        #[allow(irrefutable_let_patterns)]
-        if let Self::Cbr(v) = self {
-            Some(v)
-        } else {
-            None
+    pub fn bitrate_mut(&mut self) -> Option<&mut BitrateOptions> {
+        match self {
+            Self::Cbr(v) | Self::Vbr(v) => Some(v),
+            Self::Cqp(_) | Self::Disabled => None,
         }
     }
 
-    /// Returns `true` if the rate control kind is [`Cbr`].
+    /// Rate control mode passed to the implementation. Constant QP is Vulkan's `DISABLED` mode,
+    /// while [`Disabled`] uses the implementation's default.
     ///
-    /// [`Cbr`]: RateControlKind::Cbr
-    #[must_use]
-    #[allow(dead_code)]
-    pub fn is_cbr(&self) -> bool {
-        matches!(self, Self::Cbr(..))
+    /// [`Disabled`]: RateControlKind::Disabled
+    pub fn mode(&self) -> vk::VideoEncodeRateControlModeFlagsKHR {
+        match self {
+            Self::Cbr(_) => vk::VideoEncodeRateControlModeFlagsKHR::CBR,
+            Self::Vbr(_) => vk::VideoEncodeRateControlModeFlagsKHR::VBR,
+            Self::Cqp(_) => vk::VideoEncodeRateControlModeFlagsKHR::DISABLED,
+            Self::Disabled => vk::VideoEncodeRateControlModeFlagsKHR::DEFAULT,
+        }
+    }
+
+    /// QP of the frame, 0 unless in constant QP mode
+    fn constant_qp(&self, picture_type: PictureType) -> u32 {
+        match self {
+            Self::Cqp(cqp) => cqp.qp(picture_type),
+            _ => 0,
+        }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct RateControlOptions {
     pub kind: RateControlKind,
     pub virtual_buffer_size_in_ms: u32,
@@ -80,7 +126,80 @@ pub struct RateControlOptions {
     pub quality_level: u32,
 }
 
-pub struct Dpb<'dpb> {
+impl RateControlOptions {
+    fn validate(&mut self) {
+        if let Some(bitrate) = self.kind.bitrate_mut() {
+            if bitrate.max_bitrate < bitrate.average_bitrate {
+                error!("Invalid settings detected! max_bitrate={} < average_bitrate={}. Setting max_bitrate=average_bitrate", bitrate.max_bitrate, bitrate.average_bitrate);
+                bitrate.max_bitrate = bitrate.average_bitrate;
+            }
+        }
+    }
+}
+
+/// Changes to the encoder state requested between frames
+struct EncodeControl {
+    rate_control_options: RateControlOptions,
+    /// The rate control options changed since they were last passed to the implementation
+    rate_control_changed: bool,
+    /// Encode the next frame as IDR frame without resetting the rate control
+    idr_requested: bool,
+}
+
+impl EncodeControl {
+    fn new(mut rate_control_options: RateControlOptions) -> Self {
+        rate_control_options.validate();
+        Self {
+            rate_control_options,
+            rate_control_changed: false,
+            idr_requested: false,
+        }
+    }
+
+    fn update_rate_control(&mut self, update: impl FnOnce(&mut RateControlOptions)) {
+        let quality_level = self.rate_control_options.quality_level;
+        update(&mut self.rate_control_options);
+        // the session parameters are created for the quality level
+        if self.rate_control_options.quality_level != quality_level {
+            warn!("The quality level can't change while recording, keeping {quality_level}");
+            self.rate_control_options.quality_level = quality_level;
+        }
+        self.rate_control_options.validate();
+        self.rate_control_changed = true;
+    }
+
+    /// Whether the next frame passed to the reorderer has to start a new GOP
+    fn take_force_idr(&mut self, needs_reset: bool) -> bool {
+        needs_reset || std::mem::take(&mut self.idr_requested)
+    }
+
+    /// Flags of the coding control command recorded before encoding a frame, if any. Resetting
+    /// drops the references, so only IDR frames reset the session, while rate control changes
+    /// alone keep the references and the current GOP.
+    fn take_control_flags(
+        &mut self,
+        needs_reset: bool,
+        is_idr: bool,
+    ) -> Option<vk::VideoCodingControlFlagsKHR> {
+        let flags = if needs_reset && is_idr {
+            vk::VideoCodingControlFlagsKHR::ENCODE_RATE_CONTROL
+                | vk::VideoCodingControlFlagsKHR::ENCODE_QUALITY_LEVEL
+                | vk::VideoCodingControlFlagsKHR::RESET
+        } else if self.rate_control_changed {
+            vk::VideoCodingControlFlagsKHR::ENCODE_RATE_CONTROL
+        } else {
+            return None;
+        };
+        self.rate_control_changed = false;
+        Some(flags)
+    }
+}
+
+pub struct Dpb {
+    /// Swapchain format and colour space the input images are converted from
+    input_format: InputFormat,
+    /// Placement of the swapchain images in the pictures of `extent`
+    scaling: Scaling,
     extent: vk::Extent2D,
     coded_extent: vk::Extent2D,
     dpb_images: Vec<vk::Image>,
@@ -105,22 +224,28 @@ pub struct Dpb<'dpb> {
     compute_semaphore: vk::Semaphore,
     encode_semaphore: vk::Semaphore,
     bitstream_buffers: VkResult<BitstreamBufferRing>,
+    /// Number of converted input images, also the last compute semaphore value
     frame_index: u64,
-    gop_size: u64,
-    #[cfg(feature = "nvpro_sample_gop")]
-    nvpro_gop: Option<&'dpb mut VkVideoGopStructure>,
-    #[cfg(not(feature = "nvpro_sample_gop"))]
-    nvpro_gop: Option<&'dpb PhantomData<i32>>,
-    rate_control_options: RateControlOptions,
+    /// Number of submitted encodes, also the last encode semaphore value
+    encode_index: u64,
+    /// Number of frames passed to the reorderer, including repeated ones
+    display_index: u64,
+    /// Whether an input image was taken over by the encode queue since its conversion
+    image_acquired: Vec<bool>,
+    /// Encode semaphore value signaled once the last encode reading each input image is done
+    image_encode_values: Vec<u64>,
+    reorderer: FrameReorderer<InputImage>,
+    control: EncodeControl,
+    /// Order hint and frame type held by each of the 8 AV1 reference frame slots
+    av1_ref_order_hints: [u8; 8],
+    av1_ref_frame_types: [vk::native::StdVideoAV1FrameType; 8],
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PictureType {
     Idr,
-    #[allow(dead_code)]
     I,
     P,
-    #[allow(dead_code)]
     B,
 }
 
@@ -162,11 +287,21 @@ impl PictureType {
             PictureType::B => vk::native::StdVideoH265PictureType_STD_VIDEO_H265_PICTURE_TYPE_B,
         }
     }
+    fn as_av1_frame_type(&self) -> vk::native::StdVideoAV1FrameType {
+        match self {
+            PictureType::Idr => vk::native::StdVideoAV1FrameType_STD_VIDEO_AV1_FRAME_TYPE_KEY,
+            PictureType::I => vk::native::StdVideoAV1FrameType_STD_VIDEO_AV1_FRAME_TYPE_INTRA_ONLY,
+            PictureType::P | PictureType::B => {
+                vk::native::StdVideoAV1FrameType_STD_VIDEO_AV1_FRAME_TYPE_INTER
+            }
+        }
+    }
+
     /// Returns `true` if the picture type is [`Idr`].
     ///
     /// [`Idr`]: PictureType::Idr
     #[must_use]
-    fn is_idr(&self) -> bool {
+    pub fn is_idr(&self) -> bool {
         matches!(self, Self::Idr)
     }
 
@@ -182,9 +317,8 @@ impl PictureType {
     /// Returns `true` if the picture type is [`P`].
     ///
     /// [`P`]: PictureType::P
-    #[allow(dead_code)]
     #[must_use]
-    fn is_p(&self) -> bool {
+    pub fn is_p(&self) -> bool {
         matches!(self, Self::P)
     }
 
@@ -192,34 +326,51 @@ impl PictureType {
     ///
     /// [`B`]: PictureType::B
     #[must_use]
-    #[allow(dead_code)]
-    fn is_b(&self) -> bool {
+    pub fn is_b(&self) -> bool {
         matches!(self, Self::B)
     }
 }
 
-#[allow(dead_code)]
+/// Converted input image of a frame waiting to be encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InputImage {
+    index: usize,
+    /// Compute semaphore value signaled once the conversion is done
+    compute_value: u64,
+    /// Position in display order, counting repeated frames
+    display_index: u64,
+}
+
 pub struct GopOptions {
-    pub use_nvpro: bool,
     pub gop_size: u64,
     pub idr_period: u64,
     pub max_consecutive_b_frames: u64,
     pub last_frame_type: PictureType,
+    /// Nominal time between two frames, presentation times are delayed by one run of B frames
+    pub frame_duration: Duration,
 }
 
-impl Dpb<'_> {
-    #[cfg(feature = "nvpro_sample_gop")]
-    fn display_order_to_dpb_idx(&self, idx: u64) -> u64 {
-        // we have a very "sophisticated" DPB management
-        idx % self.dpb_images.len() as u64
+/// SPIR-V of the compute shader converting `input_format` swapchain images, HDR formats are
+/// converted to P010 and need a matching storage image format declaration.
+fn conversion_shader(input_format: InputFormat) -> &'static [u8] {
+    match input_format.format {
+        vk::Format::R16G16B16A16_SFLOAT => {
+            include_bytes!("../shaders/rgba16f_to_p010_pq.hlsl.spirv")
+        }
+        vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => {
+            include_bytes!("../shaders/rgb10a2_to_p010_pq.hlsl.spirv")
+        }
+        _ => include_bytes!("../shaders/bgr_to_yuv.hlsl.spirv"),
     }
+}
 
+impl Dpb {
     pub fn new(
         // src_queue_family_index
         device: &ash::Device,
         extensions: &Extensions,
-        video_format: vk::Format,
-        extent: vk::Extent2D,
+        input_format: InputFormat,
+        input_extent: vk::Extent2D,
         num_dpb_images: u32,
         num_inflight_images: u32,
         max_input_image_views: u32,
@@ -230,15 +381,23 @@ impl Dpb<'_> {
         video_session: &VideoSession,
         physical_memory_props: &vk::PhysicalDeviceMemoryProperties,
         gop_options: GopOptions,
-        mut rate_control_options: RateControlOptions,
+        rate_control_options: RateControlOptions,
+        scale_options: ScaleOptions,
     ) -> VkResult<Self> {
         unsafe {
-            if let Some(cbr) = rate_control_options.kind.as_cbr_mut() {
-                if cbr.max_bitrate < cbr.average_bitrate {
-                    error!("Invalid settings detected! max_bitrate={} < average_bitrate={}. Setting max_bitrate=average_bitrate", cbr.max_bitrate, cbr.average_bitrate);
-                    cbr.max_bitrate = cbr.average_bitrate;
-                }
-            }
+            let gop = GopStructure::new(
+                gop_options.gop_size.try_into().unwrap_or(16),
+                gop_options.idr_period.try_into().unwrap_or(16),
+                gop_options
+                    .max_consecutive_b_frames
+                    .min(MAX_CONSECUTIVE_B_FRAMES as u64) as u8,
+                1,
+                gop_options.last_frame_type,
+            )
+            .map_err(|err| {
+                error!("Invalid GOP structure: {err}");
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
 
             let mut images = Vec::new();
             let mut dpb_images = Vec::new();
@@ -247,21 +406,9 @@ impl Dpb<'_> {
             let mut views = Vec::new();
             let mut y_views = Vec::new();
             let mut uv_views = Vec::new();
-            let vk::Extent2D {
-                mut width,
-                mut height,
-            } = extent;
-            match video_session.codec() {
-                Codec::H264 => {
-                    width = (width + 15) / 16 * 16;
-                    height = (height + 15) / 16 * 16;
-                }
-                Codec::H265 => {
-                    width = (width + 31) / 32 * 32;
-                    height = (height + 31) / 32 * 32;
-                }
-                Codec::AV1 => todo!(),
-            }
+            let vk::Extent2D { width, height } = video_session.config().coded_extent();
+            let video_format = video_session.config().format;
+            let [y_format, uv_format] = plane_view_formats(video_format);
             let mut res = vk::Result::SUCCESS;
             let indices = [
                 encode_family_index,
@@ -291,6 +438,14 @@ impl Dpb<'_> {
                 )
                 .tiling(vk::ImageTiling::OPTIMAL)
                 .initial_layout(vk::ImageLayout::UNDEFINED);
+            // the planes are written through views of a different format
+            let info = if video_format == VIDEO_FORMAT_10BIT {
+                info.flags(
+                    vk::ImageCreateFlags::MUTABLE_FORMAT | vk::ImageCreateFlags::EXTENDED_USAGE,
+                )
+            } else {
+                info
+            };
             let profiles = [*video_session.profile().profile()];
             let mut profile_list = vk::VideoProfileListInfoKHR::default().profiles(&profiles);
             let mut info = info.push_next(&mut profile_list);
@@ -307,7 +462,7 @@ impl Dpb<'_> {
                 });
             let mut y_view_info = vk::ImageViewCreateInfo::default()
                 .view_type(vk::ImageViewType::TYPE_2D)
-                .format(vk::Format::R8_UNORM)
+                .format(y_format)
                 .subresource_range(vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::PLANE_0,
                     base_mip_level: 0,
@@ -317,7 +472,7 @@ impl Dpb<'_> {
                 });
             let mut uv_view_info = vk::ImageViewCreateInfo::default()
                 .view_type(vk::ImageViewType::TYPE_2D)
-                .format(vk::Format::R8G8_UNORM)
+                .format(uv_format)
                 .subresource_range(vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::PLANE_1,
                     base_mip_level: 0,
@@ -464,14 +619,14 @@ impl Dpb<'_> {
                 .create_descriptor_pool(&info, allocator)
                 .map_err(|err| res = err)
                 .unwrap_or(vk::DescriptorPool::null());
-            let compute_shader = ShaderPipeline::new(
-                device,
-                &[include_bytes!("../shaders/bgr_to_yuv_rec709.hlsl.spirv")],
-            );
+            let conversion = video_session.config().conversion.specialization_constants();
+            let specialization_constants =
+                [conversion.as_slice(), &[scale_options.filter as u32]].concat();
+            let compute_shader = ShaderPipeline::new(device, &[conversion_shader(input_format)]);
 
             let compute_pipeline = if let Ok(shader) = compute_shader.as_ref() {
                 shader
-                    .make_compute_pipeline(device, "main", allocator)
+                    .make_compute_pipeline(device, "main", &specialization_constants, allocator)
                     .map_err(|e| {
                         error!("Failed to create compute pipeline: {e}");
                         e
@@ -519,44 +674,33 @@ impl Dpb<'_> {
                 &mut profiles[0].clone(),
                 allocator,
             );
+            #[cfg(debug_assertions)]
+            let bitstream_buffers = bitstream_buffers.map(|mut buffers| {
+                if let Codec::H264 | Codec::H265 = video_session.codec() {
+                    match StreamValidator::new(
+                        video_session.codec(),
+                        video_session.parameter_sets(),
+                    ) {
+                        Ok(validator) => buffers.set_validator(validator),
+                        Err(e) => error!("Not validating the encoded stream: {e:#}"),
+                    }
+                }
+                buffers
+            });
             let coded_extent = vk::Extent2D { width, height };
+            let extent = video_session.config().extent;
 
-            #[cfg(feature = "nvpro_sample_gop")]
-            let nvpro_gop = gop_options.use_nvpro.then(|| {
-                let rtn = VkVideoGopStructure_new(
-                    gop_options.gop_size.try_into().unwrap_or(16),
-                    gop_options.idr_period.try_into().unwrap_or(16),
-                    gop_options
-                        .max_consecutive_b_frames
-                        .try_into()
-                        .unwrap_or(16),
-                    1,
-                    match gop_options.last_frame_type {
-                        PictureType::Idr => {
-                            crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_IDR
-                        }
-                        PictureType::I => {
-                            crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_I
-                        }
-                        PictureType::P => {
-                            crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_P
-                        }
-                        PictureType::B => {
-                            crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_B
-                        }
-                    },
-                );
-
-                let rtn = rtn
-                    .as_mut()
-                    .expect("Failed to allocate VkVideoGopStructure");
-                rtn.Init();
-                rtn
-            });
-            let nvpro_gop = None;
+            let image_acquired = vec![false; images.len()];
+            let image_encode_values = vec![0; images.len()];
             let mut rtn = Self {
                 next_image: 0,
                 frame_index: 0,
+                encode_index: 0,
+                display_index: 0,
+                image_acquired,
+                image_encode_values,
+                input_format,
+                scaling: Scaling::new(input_extent, extent, scale_options),
                 extent,
                 coded_extent,
                 dpb_images,
@@ -579,10 +723,12 @@ impl Dpb<'_> {
                 compute_semaphore,
                 encode_semaphore,
                 bitstream_buffers,
-                gop_size: gop_options.gop_size,
                 sets: Default::default(),
-                nvpro_gop,
-                rate_control_options,
+                reorderer: FrameReorderer::new(gop, gop_options.frame_duration),
+                control: EncodeControl::new(rate_control_options),
+                av1_ref_order_hints: [0; 8],
+                av1_ref_frame_types: [vk::native::StdVideoAV1FrameType_STD_VIDEO_AV1_FRAME_TYPE_KEY;
+                    8],
             };
 
             if res == vk::Result::SUCCESS {
@@ -601,14 +747,16 @@ impl Dpb<'_> {
         device: &ash::Device,
         input_images: &[vk::Image],
         input_image_views: &[vk::ImageView],
-        input_format: vk::Format,
         src_queue_family_index: u32,
         dst_queue_family_index: u32,
     ) -> anyhow::Result<()> {
-        if input_format != vk::Format::B8G8R8A8_UNORM && input_format != vk::Format::B8G8R8A8_SRGB {
-            panic!("Conversion for input format {input_format:?} not implemented yet");
-        }
-
+        let push_constants = self
+            .scaling
+            .push_constants()
+            .into_iter()
+            .chain([self.input_format.transfer() as u32])
+            .flat_map(u32::to_ne_bytes)
+            .collect_vec();
         // TODO: transition encode images to shader write
         unsafe {
             let info = vk::CommandBufferAllocateInfo::default()
@@ -742,7 +890,7 @@ impl Dpb<'_> {
                         compute_pipeline.layout(),
                         vk::ShaderStageFlags::COMPUTE,
                         0,
-                        &transmute::<ash::vk::Extent2D, [u8; 8]>(self.extent),
+                        &push_constants,
                     );
                     let extent = self.coded_extent();
                     device.cmd_dispatch(cmd, (extent.width + 7) / 8, (extent.height + 7) / 8, 1);
@@ -764,7 +912,8 @@ impl Dpb<'_> {
         extensions: &Extensions,
         buffer: &BufferPair,
         video_session: &mut VideoSession,
-    ) -> anyhow::Result<(CommandBuffer, u64)> {
+        frame: &ScheduledFrame<InputImage>,
+    ) -> anyhow::Result<CommandBuffer> {
         let video_queue_fn = extensions.video_queue_fn();
         let video_encode_queue_fn = extensions.video_encode_queue_fn();
         let cmd = self
@@ -778,8 +927,10 @@ impl Dpb<'_> {
                 .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
             device.begin_command_buffer(cmd, &info)?;
 
-            let image = self.images[self.next_image as usize];
-            let image_view = self.views[self.next_image as usize];
+            let image_type = frame.picture_type;
+            let input_image = frame.input.index;
+            let image = self.images[input_image];
+            let image_view = self.views[input_image];
             let barriers = vec![vk::ImageMemoryBarrier2::default()
                 .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
                 .dst_stage_mask(vk::PipelineStageFlags2::VIDEO_ENCODE_KHR)
@@ -821,51 +972,12 @@ impl Dpb<'_> {
             //.image(self.dpb_images[0]),
             //)
             //}
-            let info = vk::DependencyInfo::default().image_memory_barriers(&barriers);
-            device.cmd_pipeline_barrier2(cmd, &info);
-
-            let image_type = if let Some(_gop) = self.nvpro_gop.as_mut() {
-                #[cfg(feature = "nvpro_sample_gop")]
-                {
-                    let gop = _gop;
-                    let first_frame = self.frame_index == 0;
-                    let last_frame = false;
-                    let pic_type = VkVideoGopStructure_GetFrameType(
-                        *gop as *mut VkVideoGopStructure as *mut c_void,
-                        self.frame_index,
-                        first_frame,
-                        last_frame,
-                    );
-                    match pic_type {
-                        crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_P => {
-                            PictureType::P
-                        }
-                        crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_B => {
-                            PictureType::B
-                        }
-                        crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_I => {
-                            PictureType::I
-                        }
-                        crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_IDR => {
-                            PictureType::Idr
-                        }
-                        crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_INTRA_REFRESH => {
-                            panic!("Intra refresh not yet supported");
-                        }
-                        crate::gop_gen::VkVideoGopStructure_FrameType::FRAME_TYPE_INVALID => {
-                            panic!("Obtained FRAME_TYPE_INVALID from NVPRO GOP structure");
-                        }
-                    }
-                }
-                #[cfg(not(feature = "nvpro_sample_gop"))]
-                unreachable!()
-            } else {
-                if video_session.needs_reset() || self.frame_index % self.gop_size == 0 {
-                    PictureType::Idr
-                } else {
-                    PictureType::P
-                }
-            };
+            // repeated frames encode an input image that was already taken over
+            if !self.image_acquired[input_image] {
+                let info = vk::DependencyInfo::default().image_memory_barriers(&barriers);
+                device.cmd_pipeline_barrier2(cmd, &info);
+                self.image_acquired[input_image] = true;
+            }
 
             let info = vk::VideoBeginCodingInfoKHR::default()
                 .video_session(video_session.session())
@@ -876,72 +988,53 @@ impl Dpb<'_> {
                 );
             (video_queue_fn.cmd_begin_video_coding_khr)(cmd, &info);
 
-            if video_session.needs_reset() {
-                let mut info = vk::VideoCodingControlInfoKHR::default().flags(
-                    vk::VideoCodingControlFlagsKHR::ENCODE_RATE_CONTROL
-                        | vk::VideoCodingControlFlagsKHR::ENCODE_QUALITY_LEVEL
-                        | vk::VideoCodingControlFlagsKHR::RESET,
-                );
-                #[cfg(feature = "nvpro_sample_gop")]
-                let consecutive_b_frame_count = self
-                    .nvpro_gop
-                    .as_ref()
-                    .map(|gop| gop.m_consecutiveBFrameCount as u32)
-                    .unwrap_or(0u32);
-                #[cfg(feature = "nvpro_sample_gop")]
-                debug_assert_eq!(
-                    self.nvpro_gop
-                        .as_ref()
-                        .map(|gop| gop.m_temporalLayerCount)
-                        .unwrap_or(1),
-                    1
-                );
-                #[cfg(not(feature = "nvpro_sample_gop"))]
-                let consecutive_b_frame_count = 0;
+            let control_flags = self
+                .control
+                .take_control_flags(video_session.needs_reset(), image_type.is_idr());
+            if let Some(flags) = control_flags {
+                let mut info = vk::VideoCodingControlInfoKHR::default().flags(flags);
+                let gop = self.reorderer.gop();
+                let consecutive_b_frame_count = gop.consecutive_b_frame_count() as u32;
+                let gop_frame_count = gop.gop_frame_count() as u32;
+                let idr_period = gop.idr_period() as u32;
+                let temporal_layer_count = gop.temporal_layer_count() as u32;
 
                 let mut encode_control_h264 = vk::VideoEncodeH264RateControlInfoKHR::default()
                     .flags(vk::VideoEncodeH264RateControlFlagsKHR::REGULAR_GOP)
                     .consecutive_b_frame_count(consecutive_b_frame_count)
-                    .temporal_layer_count(1)
-                    .gop_frame_count(self.gop_size as u32)
-                    .idr_period(self.gop_size as u32);
+                    .temporal_layer_count(temporal_layer_count)
+                    .gop_frame_count(gop_frame_count)
+                    .idr_period(idr_period);
                 let mut encode_control_h265 = vk::VideoEncodeH265RateControlInfoKHR::default()
                     .flags(vk::VideoEncodeH265RateControlFlagsKHR::REGULAR_GOP)
                     .consecutive_b_frame_count(consecutive_b_frame_count)
-                    .sub_layer_count(1)
-                    .gop_frame_count(self.gop_size as u32)
-                    .idr_period(self.gop_size as u32);
-                let average_bitrate = self
-                    .rate_control_options
-                    .kind
-                    .as_cbr()
-                    .map(|cbr| cbr.average_bitrate)
-                    .unwrap_or(0);
-                let max_bitrate = self
+                    .sub_layer_count(temporal_layer_count)
+                    .gop_frame_count(gop_frame_count)
+                    .idr_period(idr_period);
+                // only the bitrate based modes take layers
+                let layers: Vec<_> = self
+                    .control
                     .rate_control_options
                     .kind
-                    .as_cbr()
-                    .map(|cbr| cbr.max_bitrate)
-                    .unwrap_or(0);
-                let layers = [vk::VideoEncodeRateControlLayerInfoKHR::default()
-                    .average_bitrate(average_bitrate)
-                    .max_bitrate(max_bitrate)
-                    .frame_rate_numerator(
-                        self.rate_control_options
-                            .kind
-                            .as_cbr()
-                            .map(|cbr| cbr.frame_rate_numerator)
-                            .unwrap_or(60),
-                    )
-                    .frame_rate_denominator(
-                        self.rate_control_options
-                            .kind
-                            .as_cbr()
-                            .map(|cbr| cbr.frame_rate_denominator)
-                            .unwrap_or(1),
-                    )];
+                    .bitrate()
+                    .map(|bitrate| {
+                        vk::VideoEncodeRateControlLayerInfoKHR::default()
+                            .average_bitrate(bitrate.average_bitrate)
+                            .max_bitrate(bitrate.max_bitrate)
+                            .frame_rate_numerator(bitrate.frame_rate_numerator)
+                            .frame_rate_denominator(bitrate.frame_rate_denominator)
+                    })
+                    .into_iter()
+                    .collect();
                 let mut h264_layers = [vk::VideoEncodeH264RateControlLayerInfoKHR::default()];
+                let mut encode_control_av1 = VideoEncodeAV1RateControlInfoKHR::default()
+                    .flags(VIDEO_ENCODE_AV1_RATE_CONTROL_REGULAR_GOP_BIT_KHR)
+                    .consecutive_bipredictive_frame_count(consecutive_b_frame_count)
+                    .temporal_layer_count(temporal_layer_count)
+                    .gop_frame_count(gop_frame_count)
+                    .key_frame_period(idr_period);
                 let mut h265_layers = [vk::VideoEncodeH265RateControlLayerInfoKHR::default()];
+                let mut av1_layers = [VideoEncodeAV1RateControlLayerInfoKHR::default()];
 
                 let layers: Vec<_> = match video_session.codec() {
                     Codec::H264 => layers
@@ -954,27 +1047,37 @@ impl Dpb<'_> {
                         .zip(h265_layers.iter_mut())
                         .map(|(l, l2)| l.push_next(l2))
                         .collect(),
-                    Codec::AV1 => todo!(),
+                    Codec::AV1 => layers
+                        .iter()
+                        .zip(av1_layers.iter_mut())
+                        .map(|(l, l2)| l.push_next(l2))
+                        .collect(),
                 };
                 let mut encode_control = vk::VideoEncodeRateControlInfoKHR::default()
-                    .rate_control_mode(vk::VideoEncodeRateControlModeFlagsKHR::CBR)
-                    .virtual_buffer_size_in_ms(self.rate_control_options.virtual_buffer_size_in_ms)
+                    .rate_control_mode(self.control.rate_control_options.kind.mode())
+                    .virtual_buffer_size_in_ms(
+                        self.control.rate_control_options.virtual_buffer_size_in_ms,
+                    )
                     .initial_virtual_buffer_size_in_ms(
-                        self.rate_control_options.initial_virtual_buffer_size_in_ms,
+                        self.control
+                            .rate_control_options
+                            .initial_virtual_buffer_size_in_ms,
                     )
                     .layers(&layers);
 
                 let mut quality = vk::VideoEncodeQualityLevelInfoKHR::default()
-                    .quality_level(self.rate_control_options.quality_level);
+                    .quality_level(self.control.rate_control_options.quality_level);
                 info = info.push_next(&mut encode_control);
                 info = info.push_next(&mut quality);
                 info = match video_session.codec() {
                     Codec::H264 => info.push_next(&mut encode_control_h264),
                     Codec::H265 => info.push_next(&mut encode_control_h265),
-                    Codec::AV1 => todo!(),
+                    Codec::AV1 => info.push_next(&mut encode_control_av1),
                 };
                 (video_queue_fn.cmd_control_video_coding_khr)(cmd, &info);
-                video_session.set_needs_reset(false);
+                if flags.contains(vk::VideoCodingControlFlagsKHR::RESET) {
+                    video_session.set_needs_reset(false);
+                }
             }
 
             device.cmd_begin_query(
@@ -983,87 +1086,13 @@ impl Dpb<'_> {
                 buffer.slot,
                 vk::QueryControlFlags::default(),
             );
-            #[cfg(feature = "nvpro_sample_gop")]
-            const MAX_REFERENCES: usize = 8; // that's fine even for AV1
-            #[cfg(feature = "nvpro_sample_gop")]
-            let mut nvpro_references = MaybeUninit::<[i8; MAX_REFERENCES]>::zeroed();
-            #[cfg(feature = "nvpro_sample_gop")]
-            let mut num_nvpro_references = 0;
-            #[cfg(feature = "nvpro_sample_gop")]
-            let gop_idx = self.frame_index % self.gop_size;
-            #[cfg(feature = "nvpro_sample_gop")]
-            let gop_counter = self.frame_index / self.gop_size;
-            #[cfg(feature = "nvpro_sample_gop")]
-            if let Some(gop) = &self.nvpro_gop {
-                num_nvpro_references = gop.GetReferenceNumbers_c_signature(
-                    (self.frame_index % self.gop_size).try_into().unwrap_or(0),
-                    nvpro_references.as_mut_ptr() as *mut i8,
-                    MAX_REFERENCES,
-                    true,
-                    true,
-                );
-            }
-            #[cfg(feature = "nvpro_sample_gop")]
-            let nvpro_references = nvpro_references.assume_init();
-            #[cfg(feature = "nvpro_sample_gop")]
-            let nvpro_references =
-                slice::from_raw_parts(nvpro_references.as_ptr(), num_nvpro_references as usize);
-            #[cfg(feature = "nvpro_sample_gop")]
-            let num_back_refs = nvpro_references
-                .iter()
-                .filter(|&&i| (i as i64) < (gop_idx as i64))
-                .count();
-            #[cfg(feature = "nvpro_sample_gop")]
-            let num_forward_refs = nvpro_references
-                .iter()
-                .filter(|&&i| (i as i64) > (gop_idx as i64))
-                .count();
-            #[cfg(feature = "nvpro_sample_gop")]
-            let lowest_idx = nvpro_references.iter().copied().min().unwrap_or(0);
-            #[cfg(feature = "nvpro_sample_gop")]
-            let highest_idx = nvpro_references
+            let references = frame
+                .l0
                 .iter()
+                .chain(frame.l1.iter())
                 .copied()
-                .min()
-                .unwrap_or(gop_idx as i8);
-            #[cfg(feature = "nvpro_sample_gop")]
-            if self.nvpro_gop.is_some() {
-                debug!(
-                    "NVPRO suggested the following references: {:?} for frame {}",
-                    &nvpro_references, self.frame_index
-                );
-            }
-            #[cfg(feature = "nvpro_sample_gop")]
-            let dpb_indices: Vec<_> = nvpro_references
-                .iter()
-                .map(|gop_idx| self.display_order_to_dpb_idx(gop_counter + *gop_idx as u64))
-                .collect();
-            #[cfg(feature = "nvpro_sample_gop")]
-            let dpb_back_indices: Vec<_> = nvpro_references
-                .iter()
-                .copied()
-                .map(|ref_idx| ref_idx as u64)
-                .filter(|ref_idx| *ref_idx < gop_idx)
-                .map(|ref_idx| {
-                    (
-                        ref_idx,
-                        self.display_order_to_dpb_idx(gop_counter + ref_idx),
-                    )
-                })
-                .collect();
-            #[cfg(feature = "nvpro_sample_gop")]
-            let dpb_forward_indices: Vec<_> = nvpro_references
-                .iter()
-                .copied()
-                .map(|ref_idx| ref_idx as u64)
-                .filter(|ref_idx| *ref_idx > gop_idx)
-                .map(|ref_idx| {
-                    (
-                        ref_idx,
-                        self.display_order_to_dpb_idx(gop_counter + ref_idx),
-                    )
-                })
-                .collect();
+                .collect_vec();
+            let h264_frame_num = |frame_num: u32| frame_num % (1 << H264_LOG2_MAX_FRAME_NUM);
 
             let pic = vk::VideoPictureResourceInfoKHR::default()
                 .coded_extent(self.coded_extent())
@@ -1071,19 +1100,13 @@ impl Dpb<'_> {
             let flags = MaybeUninit::zeroed();
             let mut flags: vk::native::StdVideoEncodeH264PictureInfoFlags = flags.assume_init();
             flags.set_IdrPicFlag(image_type.is_idr() as u32);
-            flags.set_is_reference(1);
+            flags.set_is_reference(frame.is_reference() as u32);
             let mut ref_lists = vk::native::StdVideoEncodeH264ReferenceListsInfo {
                 flags: zeroed(), // set reorder flags
-                #[cfg(feature = "nvpro_sample_gop")]
-                num_ref_idx_l0_active_minus1: (gop_idx as i8 - lowest_idx as i8 - 1).max(0) as u8,
-                #[cfg(feature = "nvpro_sample_gop")]
-                num_ref_idx_l1_active_minus1: (highest_idx as i8 - gop_idx as i8 - 1).max(0) as u8,
-                #[cfg(not(feature = "nvpro_sample_gop"))]
                 num_ref_idx_l0_active_minus1: 0,
-                #[cfg(not(feature = "nvpro_sample_gop"))]
                 num_ref_idx_l1_active_minus1: 0,
-                RefPicList0: [0; 32],
-                RefPicList1: [0; 32],
+                RefPicList0: [STD_VIDEO_H264_NO_REFERENCE_PICTURE as u8; 32],
+                RefPicList1: [STD_VIDEO_H264_NO_REFERENCE_PICTURE as u8; 32],
                 refList0ModOpCount: 0,
                 refList1ModOpCount: 0,
                 refPicMarkingOpCount: 0,
@@ -1092,36 +1115,27 @@ impl Dpb<'_> {
                 pRefList1ModOperations: null(),
                 pRefPicMarkingOperations: null(),
             };
-            if self.nvpro_gop.is_some() {
-                #[cfg(feature = "nvpro_sample_gop")]
-                {
-                    for &(ref_idx, dpb_idx) in dpb_back_indices.iter() {
-                        ref_lists.RefPicList0[(gop_idx - ref_idx - 1) as usize] = dpb_idx as u8;
-                    }
-                    for &(ref_idx, dpb_idx) in dpb_forward_indices.iter() {
-                        ref_lists.RefPicList0[(ref_idx - gop_idx - 1) as usize] = dpb_idx as u8;
-                    }
-                }
-            } else if image_type.is_p() {
-                ref_lists.RefPicList0[0] =
-                    (self.frame_index as i32 - 1).rem_euclid(self.dpb_views.len() as i32) as u8;
-                ref_lists.RefPicList0[1] =
-                    (self.frame_index as i32 - 1).rem_euclid(self.dpb_views.len() as i32) as u8;
+            if let Some(l0) = frame.l0 {
+                ref_lists.RefPicList0[0] = l0.slot as u8;
+            }
+            if let Some(l1) = frame.l1 {
+                ref_lists.RefPicList1[0] = l1.slot as u8;
             }
             let h264_pic = vk::native::StdVideoEncodeH264PictureInfo {
                 flags,
                 seq_parameter_set_id: 0,
                 pic_parameter_set_id: 0,
                 reserved1: [0; 3],
-                frame_num: (self.frame_index % self.gop_size) as u32,
-                PicOrderCnt: 2 * (self.frame_index % self.gop_size) as i32,
+                frame_num: h264_frame_num(frame.frame_num),
+                PicOrderCnt: 2 * frame.poc as i32,
                 idr_pic_id: 0,
                 temporal_id: 0,
                 primary_pic_type: image_type.as_h264_picture_type(),
                 pRefLists: &ref_lists,
             };
             let flags = MaybeUninit::zeroed();
-            let flags = flags.assume_init();
+            let mut flags: vk::native::StdVideoEncodeH264SliceHeaderFlags = flags.assume_init();
+            flags.set_direct_spatial_mv_pred_flag(image_type.is_b() as u32);
             let h264_header = vk::native::StdVideoEncodeH264SliceHeader {
                 flags,
                 first_mb_in_slice: 0,
@@ -1134,8 +1148,14 @@ impl Dpb<'_> {
                 slice_qp_delta: 0,
                 pWeightTable: null(),
             };
-            let h264_nalus =
-                &[vk::VideoEncodeH264NaluSliceInfoKHR::default().std_slice_header(&h264_header)];
+            let constant_qp = self
+                .control
+                .rate_control_options
+                .kind
+                .constant_qp(image_type);
+            let h264_nalus = &[vk::VideoEncodeH264NaluSliceInfoKHR::default()
+                .constant_qp(constant_qp as i32)
+                .std_slice_header(&h264_header)];
             let mut h264_info = vk::VideoEncodeH264PictureInfoKHR::default()
                 .nalu_slice_entries(h264_nalus)
                 .std_picture_info(&h264_pic);
@@ -1143,48 +1163,31 @@ impl Dpb<'_> {
             let mut ref_lists = vk::native::StdVideoEncodeH265ReferenceListsInfo {
                 flags: zeroed(), // set reorder flags
 
-                #[cfg(feature = "nvpro_sample_gop")]
-                num_ref_idx_l0_active_minus1: (dpb_back_indices.len() as i64 - 1).max(0) as u8,
-                #[cfg(feature = "nvpro_sample_gop")]
-                num_ref_idx_l1_active_minus1: (dpb_forward_indices.len() as i64 - 1).max(0) as u8,
-                #[cfg(not(feature = "nvpro_sample_gop"))]
                 num_ref_idx_l0_active_minus1: 0,
-                #[cfg(not(feature = "nvpro_sample_gop"))]
                 num_ref_idx_l1_active_minus1: 0,
-                RefPicList0: [0; 15],
-                RefPicList1: [0; 15],
+                RefPicList0: [STD_VIDEO_H265_NO_REFERENCE_PICTURE as u8; 15],
+                RefPicList1: [STD_VIDEO_H265_NO_REFERENCE_PICTURE as u8; 15],
                 list_entry_l0: [0; 15],
                 list_entry_l1: [0; 15],
             };
-            if self.nvpro_gop.is_some() {
-                #[cfg(feature = "nvpro_sample_gop")]
-                {
-                    for (i, &(ref_idx, dpb_idx)) in dpb_back_indices.iter().enumerate() {
-                        // or vice-versa
-                        ref_lists.RefPicList0[i] = dpb_idx as u8;
-                        ref_lists.list_entry_l0[i] = (gop_idx - ref_idx - 1) as u8;
-                    }
-                    for (i, &(ref_idx, dpb_idx)) in dpb_forward_indices.iter().enumerate() {
-                        ref_lists.RefPicList1[i] = dpb_idx as u8;
-                        ref_lists.list_entry_l1[i] = (ref_idx - gop_idx - 1) as u8;
-                    }
-                }
-            } else if image_type.is_p() {
-                ref_lists.RefPicList0[0] =
-                    (self.frame_index as i32 - 1).rem_euclid(self.dpb_views.len() as i32) as u8;
-                ref_lists.RefPicList1[1] =
-                    (self.frame_index as i32 - 1).rem_euclid(self.dpb_views.len() as i32) as u8;
+            // the short term reference picture set keeps exactly the references of this frame
+            let mut short_term_ref_pic_set: vk::native::StdVideoH265ShortTermRefPicSet = zeroed();
+            if let Some(l0) = frame.l0 {
+                ref_lists.RefPicList0[0] = l0.slot as u8;
+                short_term_ref_pic_set.num_negative_pics = 1;
+                short_term_ref_pic_set.used_by_curr_pic_s0_flag = 1;
+                short_term_ref_pic_set.delta_poc_s0_minus1[0] = (frame.poc - l0.poc - 1) as u16;
+            }
+            if let Some(l1) = frame.l1 {
+                ref_lists.RefPicList1[0] = l1.slot as u8;
+                short_term_ref_pic_set.num_positive_pics = 1;
+                short_term_ref_pic_set.used_by_curr_pic_s1_flag = 1;
+                short_term_ref_pic_set.delta_poc_s1_minus1[0] = (l1.poc - frame.poc - 1) as u16;
             }
-            let mut flags: vk::native::StdVideoH265ShortTermRefPicSetFlags = zeroed();
-            flags.set_inter_ref_pic_set_prediction_flag(1);
             let flags = MaybeUninit::zeroed();
             let mut flags: vk::native::StdVideoEncodeH265PictureInfoFlags = flags.assume_init();
-            if image_type.is_p() {
-                flags.set_short_term_ref_pic_set_sps_flag(1);
-            }
-            if self.frame_index != self.gop_size - 1 {
-                flags.set_is_reference(1);
-            }
+            flags.set_is_reference(frame.is_reference() as u32);
+            flags.set_IrapPicFlag(image_type.is_idr() as u32);
             let h265_pic = vk::native::StdVideoEncodeH265PictureInfo {
                 flags,
                 reserved1: Default::default(),
@@ -1193,10 +1196,10 @@ impl Dpb<'_> {
                 sps_video_parameter_set_id: 0,
                 pps_seq_parameter_set_id: 0,
                 pps_pic_parameter_set_id: 0,
-                short_term_ref_pic_set_idx: 0, // which short term RPS to use (sps with short_term_ref_pic_set_sps_flag or set here if flag not set)
-                PicOrderCntVal: (self.frame_index % self.gop_size) as i32,
+                short_term_ref_pic_set_idx: 0,
+                PicOrderCntVal: frame.poc as i32,
                 TemporalId: 0,
-                pShortTermRefPicSet: null(),
+                pShortTermRefPicSet: &short_term_ref_pic_set,
                 pLongTermRefPics: null(),
             };
             let flags = MaybeUninit::zeroed();
@@ -1225,85 +1228,218 @@ impl Dpb<'_> {
                 slice_act_cr_qp_offset: 0,
             };
             let h265_nalus = &[vk::VideoEncodeH265NaluSliceSegmentInfoKHR::default()
+                .constant_qp(constant_qp as i32)
                 .std_slice_segment_header(&h265_header)];
             let mut h265_info = vk::VideoEncodeH265PictureInfoKHR::default()
                 .nalu_slice_segment_entries(h265_nalus)
                 .std_picture_info(&h265_pic);
 
-            let mut reference_slots = Vec::new();
-            let real_h264_setup_info = vk::native::StdVideoEncodeH264ReferenceInfo {
-                flags: zeroed(),
-                FrameNum: if self.frame_index % self.gop_size != 0 {
-                    (self.frame_index % self.gop_size) as u32 - 1
+            let is_key_frame = image_type.is_idr();
+            let is_intra = frame.l0.is_none();
+            let setup_slot = frame.setup_slot.unwrap_or_default();
+            let av1_reference_slot = frame.l0.map(|r| r.slot).unwrap_or(setup_slot);
+            let av1_order_hint = frame.poc as u8;
+            let mut flags = StdVideoEncodeAV1PictureInfoFlags::default();
+            if self.extent != self.coded_extent {
+                flags.set_render_and_frame_size_different(1);
+            }
+            let av1_quantization: vk::native::StdVideoAV1Quantization = zeroed();
+            let av1_loop_filter: vk::native::StdVideoAV1LoopFilter = zeroed();
+            let av1_cdef: vk::native::StdVideoAV1CDEF = zeroed();
+            let av1_loop_restoration: vk::native::StdVideoAV1LoopRestoration = zeroed();
+            let av1_global_motion: vk::native::StdVideoAV1GlobalMotion = zeroed();
+            let av1_pic = StdVideoEncodeAV1PictureInfo {
+                flags,
+                frame_type: image_type.as_av1_frame_type(),
+                frame_presentation_time: 0,
+                current_frame_id: 0,
+                order_hint: av1_order_hint,
+                primary_ref_frame: if is_intra {
+                    STD_VIDEO_AV1_PRIMARY_REF_NONE as u8
                 } else {
                     0
                 },
-                primary_pic_type: vk::native::StdVideoH264PictureType_STD_VIDEO_H264_PICTURE_TYPE_P, // not always correct
-                PicOrderCnt: 2 * if self.frame_index % self.gop_size != 0 {
-                    (self.frame_index % self.gop_size) as i32 - 1
+                refresh_frame_flags: if is_key_frame {
+                    0xff
                 } else {
-                    0
+                    1 << setup_slot
                 },
+                coded_denom: 0,
+                render_width_minus_1: (self.extent.width - 1) as u16,
+                render_height_minus_1: (self.extent.height - 1) as u16,
+                interpolation_filter:
+                    vk::native::StdVideoAV1InterpolationFilter_STD_VIDEO_AV1_INTERPOLATION_FILTER_EIGHTTAP,
+                TxMode: vk::native::StdVideoAV1TxMode_STD_VIDEO_AV1_TX_MODE_SELECT,
+                delta_q_res: 0,
+                delta_lf_res: 0,
+                ref_order_hint: self.av1_ref_order_hints,
+                // every reference name points to the previous anchor
+                ref_frame_idx: [av1_reference_slot as i8; VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR],
+                reserved1: [0; 3],
+                delta_frame_id_minus_1: [0; VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR],
+                pTileInfo: null(),
+                pQuantization: &av1_quantization,
+                pSegmentation: null(),
+                pLoopFilter: &av1_loop_filter,
+                pCDEF: &av1_cdef,
+                pLoopRestoration: &av1_loop_restoration,
+                pGlobalMotion: &av1_global_motion,
+                pExtensionHeader: null(),
+                pBufferRemovalTimes: null(),
+            };
+            let mut reference_name_slot_indices = [-1; VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR];
+            if !is_intra {
+                reference_name_slot_indices[0] = av1_reference_slot as i32;
+            }
+            let mut av1_info = VideoEncodeAV1PictureInfoKHR::default()
+                .prediction_mode(if is_intra {
+                    VIDEO_ENCODE_AV1_PREDICTION_MODE_INTRA_ONLY_KHR
+                } else {
+                    VIDEO_ENCODE_AV1_PREDICTION_MODE_SINGLE_REFERENCE_KHR
+                })
+                .rate_control_group(if is_intra {
+                    VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_INTRA_KHR
+                } else {
+                    VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_PREDICTIVE_KHR
+                })
+                .constant_q_index(constant_qp)
+                .std_picture_info(&av1_pic)
+                .reference_name_slot_indices(reference_name_slot_indices);
+
+            let h264_std_references = references
+                .iter()
+                .map(|r| vk::native::StdVideoEncodeH264ReferenceInfo {
+                    flags: zeroed(),
+                    primary_pic_type: r.picture_type.as_h264_picture_type(),
+                    FrameNum: h264_frame_num(r.frame_num),
+                    PicOrderCnt: 2 * r.poc as i32,
+                    long_term_pic_num: 0,
+                    long_term_frame_idx: 0,
+                    temporal_id: 0,
+                })
+                .collect_vec();
+            let h265_std_references = references
+                .iter()
+                .map(|r| vk::native::StdVideoEncodeH265ReferenceInfo {
+                    flags: zeroed(),
+                    pic_type: r.picture_type.as_h265_picture_type(),
+                    PicOrderCntVal: r.poc as i32,
+                    TemporalId: 0,
+                })
+                .collect_vec();
+            let av1_std_references = references
+                .iter()
+                .map(|r| StdVideoEncodeAV1ReferenceInfo {
+                    flags: Default::default(),
+                    RefFrameId: 0,
+                    frame_type: self.av1_ref_frame_types[r.slot],
+                    OrderHint: self.av1_ref_order_hints[r.slot],
+                    reserved1: [0; 3],
+                    pExtensionHeader: null(),
+                })
+                .collect_vec();
+            let mut h264_reference_infos = h264_std_references
+                .iter()
+                .map(|info| vk::VideoEncodeH264DpbSlotInfoKHR::default().std_reference_info(info))
+                .collect_vec();
+            let mut h265_reference_infos = h265_std_references
+                .iter()
+                .map(|info| vk::VideoEncodeH265DpbSlotInfoKHR::default().std_reference_info(info))
+                .collect_vec();
+            let mut av1_reference_infos = av1_std_references
+                .iter()
+                .map(|info| VideoEncodeAV1DpbSlotInfoKHR::default().std_reference_info(info))
+                .collect_vec();
+            let reference_resources = references
+                .iter()
+                .map(|r| {
+                    vk::VideoPictureResourceInfoKHR::default()
+                        .coded_extent(self.coded_extent())
+                        .image_view_binding(self.dpb_views[r.slot])
+                })
+                .collect_vec();
+            let mut reference_slots = Vec::new();
+            for (reference, resource, h264_info, h265_info, av1_info) in izip!(
+                &references,
+                &reference_resources,
+                &mut h264_reference_infos,
+                &mut h265_reference_infos,
+                &mut av1_reference_infos
+            ) {
+                let info = vk::VideoReferenceSlotInfoKHR::default()
+                    .slot_index(reference.slot as i32)
+                    .picture_resource(resource);
+                reference_slots.push(match video_session.codec() {
+                    Codec::H264 => info.push_next(h264_info),
+                    Codec::H265 => info.push_next(h265_info),
+                    Codec::AV1 => info.push_next(av1_info),
+                });
+            }
+
+            let h264_std_setup_info = vk::native::StdVideoEncodeH264ReferenceInfo {
+                flags: zeroed(),
+                primary_pic_type: image_type.as_h264_picture_type(),
+                FrameNum: h264_frame_num(frame.frame_num),
+                PicOrderCnt: 2 * frame.poc as i32,
                 long_term_pic_num: 0,
                 long_term_frame_idx: 0,
                 temporal_id: 0,
             };
-            let mut h264_reference_info = vk::VideoEncodeH264DpbSlotInfoKHR::default()
-                .std_reference_info(&real_h264_setup_info);
-            let flags: vk::native::StdVideoEncodeH265ReferenceInfoFlags = zeroed();
-            //flags.set_unused_for_reference(1);
-            let real_h265_setup_info = vk::native::StdVideoEncodeH265ReferenceInfo {
-                flags,
-                pic_type: vk::native::StdVideoH265PictureType_STD_VIDEO_H265_PICTURE_TYPE_P,
-                PicOrderCntVal: if self.frame_index % self.gop_size != 0 {
-                    (self.frame_index % self.gop_size) as i32 - 1
-                } else {
-                    0
-                },
+            let mut h264_setup_info = vk::VideoEncodeH264DpbSlotInfoKHR::default()
+                .std_reference_info(&h264_std_setup_info);
+            let h265_std_setup_info = vk::native::StdVideoEncodeH265ReferenceInfo {
+                flags: zeroed(),
+                pic_type: image_type.as_h265_picture_type(),
+                PicOrderCntVal: frame.poc as i32,
                 TemporalId: 0,
             };
-            let mut h265_reference_info = vk::VideoEncodeH265DpbSlotInfoKHR::default()
-                .std_reference_info(&real_h265_setup_info);
-            let ref_pic_res = vk::VideoPictureResourceInfoKHR::default()
-                .coded_extent(self.coded_extent())
-                .image_view_binding(
-                    self.dpb_views[((self.frame_index + self.dpb_views.len() as u64 - 1)
-                        % self.dpb_views.len() as u64) as usize],
-                );
-            if image_type.is_p() {
-                let slot_index =
-                    (self.frame_index as i32 - 1).rem_euclid(self.dpb_views.len() as i32);
-                let mut info = vk::VideoReferenceSlotInfoKHR::default()
-                    .slot_index(slot_index)
-                    .picture_resource(&ref_pic_res);
-                match video_session.codec() {
-                    Codec::H264 => info = info.push_next(&mut h264_reference_info),
-                    Codec::H265 => info = info.push_next(&mut h265_reference_info),
-                    Codec::AV1 => todo!(),
-                };
-                reference_slots.push(info);
-            }
+            let mut h265_setup_info = vk::VideoEncodeH265DpbSlotInfoKHR::default()
+                .std_reference_info(&h265_std_setup_info);
+            let av1_std_setup_info = StdVideoEncodeAV1ReferenceInfo {
+                flags: Default::default(),
+                RefFrameId: 0,
+                frame_type: image_type.as_av1_frame_type(),
+                OrderHint: av1_order_hint,
+                reserved1: [0; 3],
+                pExtensionHeader: null(),
+            };
+            let mut av1_setup_info =
+                VideoEncodeAV1DpbSlotInfoKHR::default().std_reference_info(&av1_std_setup_info);
             let setup_pic_res = vk::VideoPictureResourceInfoKHR::default()
                 .coded_extent(self.coded_extent())
-                .image_view_binding(
-                    self.dpb_views[self.frame_index as usize % self.dpb_views.len()],
-                );
-            let ref_slot_info = vk::VideoReferenceSlotInfoKHR::default()
-                .slot_index((self.frame_index as i32 - 1).rem_euclid(self.dpb_views.len() as i32))
+                .image_view_binding(self.dpb_views[setup_slot]);
+            let setup_slot_info = vk::VideoReferenceSlotInfoKHR::default()
+                .slot_index(setup_slot as i32)
                 .picture_resource(&setup_pic_res);
+            let setup_slot_info = match video_session.codec() {
+                Codec::H264 => setup_slot_info.push_next(&mut h264_setup_info),
+                Codec::H265 => setup_slot_info.push_next(&mut h265_setup_info),
+                Codec::AV1 => setup_slot_info.push_next(&mut av1_setup_info),
+            };
             let mut info = vk::VideoEncodeInfoKHR::default()
                 .dst_buffer(buffer.device.buffer())
                 .dst_buffer_range(buffer.device.size())
                 .src_picture_resource(pic)
-                .reference_slots(&reference_slots)
-                .setup_reference_slot(&ref_slot_info);
+                .reference_slots(&reference_slots);
+            // non-reference frames don't need to be reconstructed
+            if frame.is_reference() {
+                info = info.setup_reference_slot(&setup_slot_info);
+            }
 
             match video_session.codec() {
                 Codec::H264 => info = info.push_next(&mut h264_info),
                 Codec::H265 => info = info.push_next(&mut h265_info),
-                Codec::AV1 => todo!(),
+                Codec::AV1 => info = info.push_next(&mut av1_info),
             };
             (video_encode_queue_fn.cmd_encode_video_khr)(cmd, &info);
+
+            if is_key_frame {
+                self.av1_ref_order_hints = [av1_order_hint; 8];
+                self.av1_ref_frame_types = [image_type.as_av1_frame_type(); 8];
+            } else if frame.is_reference() {
+                self.av1_ref_order_hints[setup_slot] = av1_order_hint;
+                self.av1_ref_frame_types[setup_slot] = image_type.as_av1_frame_type();
+            }
             device.cmd_end_query(cmd, buffer.query_pool, buffer.slot);
 
             let info = vk::VideoEndCodingInfoKHR::default();
@@ -1329,17 +1465,8 @@ impl Dpb<'_> {
             device.end_command_buffer(cmd)?;
             debug!("ende cmd buffer");
         }
-
-        #[cfg(not(feature = "nvpro_sample_gop"))]
-        let decode_order_idx = self.frame_index;
-        #[cfg(feature = "nvpro_sample_gop")]
-        let decode_order_idx = if let Some(gop) = &mut self.nvpro_gop {
-            unsafe { gop.GetFrameInDecodeOrder(self.frame_index) }
-        } else {
-            self.frame_index
-        };
         trace!("Recorded encode command buffer");
-        Ok((cmd, decode_order_idx))
+        Ok(cmd)
     }
 
     pub fn encode_frame(
@@ -1352,29 +1479,32 @@ impl Dpb<'_> {
         encode_queue: vk::Queue,
         wait_semaphore_infos: &[vk::SemaphoreSubmitInfo],
         signal_semaphore_compute: &[vk::SemaphoreSubmitInfo],
-        output: Option<&mut impl Write>,
+        pts: Duration,
+        mut output: Option<&mut (impl Muxer + ?Sized)>,
     ) -> anyhow::Result<()> {
+        let input = InputImage {
+            index: self.next_image as usize,
+            compute_value: self.frame_index + 1,
+            display_index: self.display_index,
+        };
         unsafe {
             let cmd = self.compute_cmd_buffers[&(image_view, self.next_image)];
             debug!("encode_frame");
 
-            let buffer = self
-                .bitstream_buffers
-                .as_mut()
-                .map_err(|e| {
-                    error!("failed to acquire bitstream_buffers");
-                    *e
-                })?
-                .next(device, 100, output)
-                .map_err(|err| anyhow!("Failed to next: {err}"))?;
-
-            let (encode_cmd, decode_order_idx) =
-                self.record_encode_cmd_buffer(device, extensions, &buffer, video_session)?;
             // TODO: mutex around compute queue
             let cmd_infos = [vk::CommandBufferSubmitInfo::default().command_buffer(cmd)];
+            // the input image may still be read by the encode of an earlier frame
+            let wait_infos = [vk::SemaphoreSubmitInfo::default()
+                .semaphore(self.encode_semaphore)
+                .value(self.image_encode_values[input.index])
+                .stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)]
+            .iter()
+            .copied()
+            .chain(wait_semaphore_infos.iter().copied())
+            .collect_vec();
             let signal_infos = [vk::SemaphoreSubmitInfo::default()
                 .semaphore(self.compute_semaphore)
-                .value(self.frame_index + 1)
+                .value(input.compute_value)
                 .stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)]
             .iter()
             .copied()
@@ -1382,25 +1512,113 @@ impl Dpb<'_> {
             .collect_vec();
             let info = vk::SubmitInfo2::default()
                 .command_buffer_infos(&cmd_infos)
-                .wait_semaphore_infos(wait_semaphore_infos)
+                .wait_semaphore_infos(&wait_infos)
                 .signal_semaphore_infos(&signal_infos);
             device
                 .queue_submit2(compute_queue, &[info], vk::Fence::null())
                 .map_err(|err| anyhow!("Failed to submit to compute queue: {err}"))?;
+        }
+        self.image_acquired[input.index] = false;
+        self.next_image += 1;
+        if self.next_image as usize >= self.views.len() {
+            self.next_image = 0;
+        }
+        self.frame_index = self.frame_index.wrapping_add(1);
+        self.display_index += 1;
+
+        let force_idr = self.control.take_force_idr(video_session.needs_reset());
+        let frames = self.reorderer.push(input, pts, force_idr);
+        for frame in frames {
+            self.submit_encode(
+                device,
+                extensions,
+                video_session,
+                encode_queue,
+                &frame,
+                output.as_deref_mut(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the previous input image again without converting a new swapchain image.
+    pub fn repeat_frame(
+        &mut self,
+        device: &ash::Device,
+        extensions: &Extensions,
+        video_session: &mut VideoSession,
+        encode_queue: vk::Queue,
+        pts: Duration,
+        mut output: Option<&mut (impl Muxer + ?Sized)>,
+    ) -> anyhow::Result<()> {
+        if self.frame_index == 0 {
+            return Err(anyhow!("No previous frame to repeat"));
+        }
+        let input = InputImage {
+            index: (self.next_image as usize + self.views.len() - 1) % self.views.len(),
+            compute_value: self.frame_index,
+            display_index: self.display_index,
+        };
+        self.display_index += 1;
+        let force_idr = self.control.take_force_idr(video_session.needs_reset());
+        let frames = self.reorderer.push(input, pts, force_idr);
+        for frame in frames {
+            self.submit_encode(
+                device,
+                extensions,
+                video_session,
+                encode_queue,
+                &frame,
+                output.as_deref_mut(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Records and submits the encode of a frame once its input image is converted. Frames are
+    /// submitted in decode order, which is also the order of the bitstream buffers.
+    fn submit_encode(
+        &mut self,
+        device: &ash::Device,
+        extensions: &Extensions,
+        video_session: &mut VideoSession,
+        encode_queue: vk::Queue,
+        frame: &ScheduledFrame<InputImage>,
+        output: Option<&mut (impl Muxer + ?Sized)>,
+    ) -> anyhow::Result<()> {
+        unsafe {
+            let buffer = self
+                .bitstream_buffers
+                .as_mut()
+                .map_err(|e| {
+                    error!("failed to acquire bitstream_buffers");
+                    *e
+                })?
+                .next(device, 100, output)
+                .map_err(|err| anyhow!("Failed to next: {err}"))?;
+
+            let encode_cmd =
+                self.record_encode_cmd_buffer(device, extensions, &buffer, video_session, frame)?;
+            if let Ok(buffers) = self.bitstream_buffers.as_mut() {
+                buffers.set_frame_info(
+                    buffer.slot,
+                    FrameInfo::new(frame, frame.input.display_index, self.encode_index),
+                );
+            }
 
             let wait_infos = [
                 vk::SemaphoreSubmitInfo::default()
                     .semaphore(self.compute_semaphore)
-                    .value(self.frame_index + 1)
+                    .value(frame.input.compute_value)
                     .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS),
                 vk::SemaphoreSubmitInfo::default()
                     .semaphore(self.encode_semaphore)
-                    .value(decode_order_idx)
+                    .value(self.encode_index)
                     .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS),
             ];
             let signal_infos = [vk::SemaphoreSubmitInfo::default()
                 .semaphore(self.encode_semaphore)
-                .value(decode_order_idx + 1)
+                .value(self.encode_index + 1)
                 .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)];
             let cmd_infos = [vk::CommandBufferSubmitInfo::default().command_buffer(encode_cmd.cmd)];
             let info = vk::SubmitInfo2::default()
@@ -1411,11 +1629,43 @@ impl Dpb<'_> {
                 .queue_submit2(encode_queue, &[info], encode_cmd.fence)
                 .map_err(|err| anyhow!("Failed to submit to encode queue: {err}"))?;
         }
-        self.next_image += 1;
-        if self.next_image as usize >= self.views.len() {
-            self.next_image = 0;
+        self.encode_index += 1;
+        self.image_encode_values[frame.input.index] = self.encode_index;
+        Ok(())
+    }
+
+    /// Encodes the frames still waiting for reordering, waits for all submitted encodes and
+    /// writes their results to `output`.
+    pub fn flush(
+        &mut self,
+        device: &ash::Device,
+        extensions: &Extensions,
+        video_session: &mut VideoSession,
+        encode_queue: Option<vk::Queue>,
+        mut output: Option<&mut (impl Muxer + ?Sized)>,
+    ) -> anyhow::Result<()> {
+        const FLUSH_TIMEOUT: u64 = 1_000_000_000;
+        debug!(
+            "Flushing {} frames waiting for reordering",
+            self.reorderer.pending_count()
+        );
+        let frames = self.reorderer.flush();
+        if let Some(encode_queue) = encode_queue {
+            for frame in frames {
+                self.submit_encode(
+                    device,
+                    extensions,
+                    video_session,
+                    encode_queue,
+                    &frame,
+                    output.as_deref_mut(),
+                )?;
+            }
         }
-        self.frame_index = self.frame_index.wrapping_add(1);
+        self.bitstream_buffers
+            .as_mut()
+            .map_err(|e| *e)?
+            .flush(device, FLUSH_TIMEOUT, output)?;
         Ok(())
     }
 
@@ -1459,15 +1709,81 @@ impl Dpb<'_> {
             }
             device.destroy_semaphore(self.compute_semaphore, allocator);
             device.destroy_semaphore(self.encode_semaphore, allocator);
-            #[cfg(feature = "nvpro_sample_gop")]
-            if let Some(gop) = self.nvpro_gop.as_mut() {
-                VkVideoGopStructure_destroy(*gop as *mut VkVideoGopStructure);
-            }
         }
     }
     // TODO: DropBomb?
 
+    pub fn request_idr(&mut self) {
+        self.control.idr_requested = true;
+    }
+
+    pub fn rate_control_options(&self) -> &RateControlOptions {
+        &self.control.rate_control_options
+    }
+
+    /// Changes the rate control of the running session with the next frame. Unlike a reset, this
+    /// keeps the references and doesn't force an IDR frame.
+    pub fn update_rate_control(&mut self, update: impl FnOnce(&mut RateControlOptions)) {
+        self.control.update_rate_control(update);
+    }
+
     pub fn coded_extent(&self) -> vk::Extent2D {
         self.coded_extent
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cbr(bitrate: u64) -> RateControlKind {
+        RateControlKind::Cbr(BitrateOptions {
+            max_bitrate: bitrate,
+            average_bitrate: bitrate,
+            frame_rate_numerator: 60,
+            frame_rate_denominator: 1,
+        })
+    }
+
+    #[test]
+    fn encode_control_test() {
+        let mut control = EncodeControl::new(RateControlOptions {
+            kind: cbr(8_000_000),
+            virtual_buffer_size_in_ms: 1000,
+            initial_virtual_buffer_size_in_ms: 500,
+            quality_level: 0,
+        });
+        // the first IDR frame resets the new session
+        assert!(control.take_force_idr(true));
+        assert_eq!(
+            control.take_control_flags(true, true),
+            Some(
+                vk::VideoCodingControlFlagsKHR::ENCODE_RATE_CONTROL
+                    | vk::VideoCodingControlFlagsKHR::ENCODE_QUALITY_LEVEL
+                    | vk::VideoCodingControlFlagsKHR::RESET
+            )
+        );
+        assert!(!control.take_force_idr(false));
+        assert_eq!(control.take_control_flags(false, false), None);
+
+        // a bitrate change only updates the rate control of the next frame, whatever its type
+        control.update_rate_control(|options| {
+            options.kind = cbr(4_000_000);
+            options.quality_level = 2;
+        });
+        assert_eq!(control.rate_control_options.kind, cbr(4_000_000));
+        assert_eq!(control.rate_control_options.quality_level, 0);
+        assert!(!control.take_force_idr(false));
+        assert_eq!(
+            control.take_control_flags(false, false),
+            Some(vk::VideoCodingControlFlagsKHR::ENCODE_RATE_CONTROL)
+        );
+        assert_eq!(control.take_control_flags(false, false), None);
+
+        // requested IDR frames keep the rate control
+        control.idr_requested = true;
+        assert!(control.take_force_idr(false));
+        assert!(!control.take_force_idr(false));
+        assert_eq!(control.take_control_flags(false, true), None);
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Local, Utc};
+use log::warn;
+
+use crate::settings::Codec;
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{app}_{resolution}_{local}";
+
+/// Values available to the `filename_template` setting
+pub struct FilenameFields<'a> {
+    pub app: &'a str,
+    pub exe: &'a str,
+    pub pid: u32,
+    pub swapchain: u32,
+    pub width: u32,
+    pub height: u32,
+    pub codec: Codec,
+    pub time: DateTime<Utc>,
+    /// Index of the segment if the output is split
+    pub segment: Option<u32>,
+}
+
+impl FilenameFields<'_> {
+    fn value(&self, placeholder: &str) -> Option<String> {
+        Some(match placeholder {
+            "app" => self.app.to_owned(),
+            "exe" => self.exe.to_owned(),
+            "pid" => self.pid.to_string(),
+            "swapchain" => self.swapchain.to_string(),
+            "width" => self.width.to_string(),
+            "height" => self.height.to_string(),
+            "resolution" => format!("{}x{}", self.width, self.height),
+            "codec" => match self.codec {
+                Codec::H264 => "h264",
+                Codec::H265 => "h265",
+                Codec::AV1 => "av1",
+            }
+            .to_owned(),
+            // ISO 8601 basic format, which sorts and needs no colons
+            "local" => self
+                .time
+                .with_timezone(&Local)
+                .format("%Y%m%dT%H%M%S%z")
+                .to_string(),
+            "utc" => self.time.format("%Y%m%dT%H%M%SZ").to_string(),
+            "segment" => self
+                .segment
+                .map(|segment| format!("{segment:04}"))
+                .unwrap_or_default(),
+            _ => return None,
+        })
+    }
+}
+
+/// Expands the `{placeholder}`s in `template` without extension. Segmented outputs get the segment
+/// number appended if the template doesn't contain `{segment}`.
+pub fn expand_filename(template: &str, fields: &FilenameFields) -> String {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let placeholder = &rest[1..end];
+        match fields.value(placeholder) {
+            Some(value) => name.push_str(&sanitize(&value)),
+            None => {
+                warn!("Unknown placeholder {{{placeholder}}} in filename template");
+                name.push_str(&rest[..=end]);
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+    if let (Some(segment), false) = (fields.segment, template.contains("{segment}")) {
+        name.push_str(&format!("_{segment:04}"));
+    }
+    name
+}
+
+/// Replaces every run of characters that are unsafe in file names with a single `_`, similar to
+/// how git turns commit subjects into patch file names.
+fn sanitize(value: &str) -> String {
+    let mut sanitized = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_alphanumeric() || matches!(c, '-' | '+' | '.') {
+            sanitized.push(c);
+        } else if !sanitized.ends_with('_') {
+            sanitized.push('_');
+        }
+    }
+    // no hidden files or names that end up empty
+    let sanitized = sanitized.trim_matches(|c| c == '_' || c == '.');
+    if sanitized.is_empty() {
+        "_".into()
+    } else {
+        sanitized.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(segment: Option<u32>) -> FilenameFields<'static> {
+        FilenameFields {
+            app: "My Game: Remastered",
+            exe: "game.x86_64",
+            pid: 1234,
+            swapchain: 1,
+            width: 1920,
+            height: 1080,
+            codec: Codec::H265,
+            time: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            segment,
+        }
+    }
+
+    #[test]
+    fn expand_filename_test() {
+        assert_eq!(
+            expand_filename(
+                "{app}/{exe}_{pid}_{swapchain}_{resolution}_{codec}",
+                &fields(None)
+            ),
+            "My_Game_Remastered/game.x86_64_1234_1_1920x1080_h265"
+        );
+        assert_eq!(
+            expand_filename("{utc}-{segment}", &fields(Some(3))),
+            "20231114T221320Z-0003"
+        );
+        // the segment number is always part of segmented file names
+        assert_eq!(
+            expand_filename("{width}x{height}", &fields(Some(12))),
+            "1920x1080_0012"
+        );
+        assert_eq!(
+            expand_filename("{bogus}_{app", &fields(None)),
+            "{bogus}_{app"
+        );
+    }
+
+    #[test]
+    fn sanitize_test() {
+        assert_eq!(sanitize("a/b\\c:d*e?f\"g<h>i|j"), "a_b_c_d_e_f_g_h_i_j");
+        assert_eq!(sanitize("  ..hidden  "), "hidden");
+        assert_eq!(sanitize("Übung 1+1"), "Übung_1+1");
+        assert_eq!(sanitize("///"), "_");
+    }
+}
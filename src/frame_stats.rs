@@ -0,0 +1,180 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use ash::vk;
+use log::info;
+
+use crate::dpb::PictureType;
+use crate::muxer::{EncodedFrame, Muxer};
+use crate::settings::FrameStatsFormat;
+
+/// Encode feedback of a single frame
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    /// Position in display order since the encoder was created
+    pub display_index: u64,
+    /// Position in decode order since the encoder was created
+    pub decode_index: u64,
+    pub picture_type: PictureType,
+    /// Bitstream offset and bytes written as reported by the encode feedback query
+    pub offset: u32,
+    pub size: u32,
+    pub status: vk::QueryResultStatusKHR,
+    /// Capture time relative to the start of the recording
+    pub pts: Duration,
+    /// Time from submitting the encode until its result was read back, which includes waiting
+    /// for the following frames' captures, not only the encode itself
+    pub readback_latency: Duration,
+}
+
+/// Name of the query status as in the Vulkan spec, ash misspells the bitstream buffer one
+fn status_name(status: vk::QueryResultStatusKHR) -> String {
+    match status {
+        vk::QueryResultStatusKHR::ERROR => "ERROR".into(),
+        vk::QueryResultStatusKHR::NOT_READY => "NOT_READY".into(),
+        vk::QueryResultStatusKHR::COMPLETE => "COMPLETE".into(),
+        vk::QueryResultStatusKHR::INSUFFICIENTSTREAM_BUFFER_RANGE => {
+            "INSUFFICIENT_BITSTREAM_BUFFER_RANGE".into()
+        }
+        status => status.as_raw().to_string(),
+    }
+}
+
+/// Extension of the statistics file, `None` if they're disabled
+fn file_extension(format: FrameStatsFormat) -> Option<&'static str> {
+    match format {
+        FrameStatsFormat::None => None,
+        FrameStatsFormat::Csv => Some("frames.csv"),
+        FrameStatsFormat::Jsonl => Some("frames.jsonl"),
+    }
+}
+
+/// Wraps the output and writes one line of [`FrameStats`] per frame to a file next to it.
+pub struct FrameStatsMuxer<W: Write> {
+    output: Box<dyn Muxer>,
+    stats: W,
+    format: FrameStatsFormat,
+}
+
+impl FrameStatsMuxer<BufWriter<File>> {
+    /// `path` is the path of the output file, the stats file replaces its extension
+    pub fn create(
+        output: Box<dyn Muxer>,
+        path: &Path,
+        format: FrameStatsFormat,
+    ) -> std::io::Result<Self> {
+        let extension = file_extension(format).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "frame statistics are disabled",
+            )
+        })?;
+        let path = path.with_extension(extension);
+        info!("Writing frame statistics to {path:?}");
+        Self::new(output, BufWriter::new(File::create(path)?), format)
+    }
+}
+
+impl<W: Write> FrameStatsMuxer<W> {
+    fn new(
+        output: Box<dyn Muxer>,
+        mut stats: W,
+        format: FrameStatsFormat,
+    ) -> std::io::Result<Self> {
+        if format == FrameStatsFormat::Csv {
+            writeln!(
+                stats,
+                "frame,decode_index,picture_type,offset,bytes,status,pts_us,readback_latency_us"
+            )?;
+        }
+        Ok(Self {
+            output,
+            stats,
+            format,
+        })
+    }
+}
+
+impl<W: Write> Muxer for FrameStatsMuxer<W> {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        self.output.write_frame(frame)
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.stats.flush()?;
+        self.output.finish()
+    }
+
+    fn wants_keyframe(&mut self) -> bool {
+        self.output.wants_keyframe()
+    }
+
+    fn write_frame_stats(&mut self, stats: &FrameStats) -> std::io::Result<()> {
+        let FrameStats {
+            display_index,
+            decode_index,
+            picture_type,
+            offset,
+            size,
+            status,
+            pts,
+            readback_latency,
+        } = stats;
+        let status = status_name(*status);
+        let pts = pts.as_micros();
+        let latency = readback_latency.as_micros();
+        if self.format == FrameStatsFormat::Csv {
+            writeln!(
+                self.stats,
+                "{display_index},{decode_index},{picture_type:?},{offset},{size},{status},{pts},{latency}"
+            )
+        } else {
+            writeln!(
+                self.stats,
+                "{{\"frame\":{display_index},\"decode_index\":{decode_index},\"picture_type\":\"{picture_type:?}\",\"offset\":{offset},\"bytes\":{size},\"status\":\"{status}\",\"pts_us\":{pts},\"readback_latency_us\":{latency}}}"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muxer::RecordingMuxer;
+
+    fn write_stats(format: FrameStatsFormat) -> String {
+        let mut muxer =
+            FrameStatsMuxer::new(Box::new(RecordingMuxer::default()), Vec::new(), format).unwrap();
+        muxer
+            .write_frame_stats(&FrameStats {
+                display_index: 3,
+                decode_index: 2,
+                picture_type: PictureType::B,
+                offset: 0,
+                size: 1234,
+                status: vk::QueryResultStatusKHR::INSUFFICIENTSTREAM_BUFFER_RANGE,
+                pts: Duration::from_millis(50),
+                readback_latency: Duration::from_micros(1500),
+            })
+            .unwrap();
+        muxer.finish().unwrap();
+        String::from_utf8(muxer.stats).unwrap()
+    }
+
+    #[test]
+    fn frame_stats_test() {
+        assert_eq!(file_extension(FrameStatsFormat::None), None);
+        assert_eq!(
+            write_stats(FrameStatsFormat::Csv),
+            "frame,decode_index,picture_type,offset,bytes,status,pts_us,readback_latency_us\n\
+             3,2,B,0,1234,INSUFFICIENT_BITSTREAM_BUFFER_RANGE,50000,1500\n"
+        );
+        assert_eq!(
+            write_stats(FrameStatsFormat::Jsonl),
+            "{\"frame\":3,\"decode_index\":2,\"picture_type\":\"B\",\"offset\":0,\"bytes\":1234,\
+             \"status\":\"INSUFFICIENT_BITSTREAM_BUFFER_RANGE\",\"pts_us\":50000,\"readback_latency_us\":1500}\n"
+        );
+    }
+}
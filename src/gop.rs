@@ -0,0 +1,300 @@
+//! Frame types, decode order and references of a group of pictures (GOP).
+//!
+//! This is a port of `VkVideoGopStructure` from the NVIDIA Vulkan video samples. Frame types and
+//! references are identical. Decode order positions differ only for B frames at the end of a GOP
+//! whose anchor would be the next GOP's I frame: nvpro gives them the decode positions of the
+//! following GOP, here they are decoded in display order since they only reference backwards.
+use anyhow::bail;
+
+use crate::dpb::PictureType;
+
+#[derive(Debug, Clone, Copy)]
+struct GopEntry {
+    decode_order: u8,
+    is_reference: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GopStructure {
+    gop_frame_count: u8,
+    idr_period: u8,
+    consecutive_b_frame_count: u8,
+    temporal_layer_count: u8,
+    last_frame_type: PictureType,
+    decode_order_map: Vec<GopEntry>,
+}
+
+impl GopStructure {
+    /// Only a single temporal layer is supported, every frame references the anchors of the
+    /// base layer and other layer counts are rejected.
+    pub fn new(
+        gop_frame_count: u8,
+        idr_period: u8,
+        consecutive_b_frame_count: u8,
+        temporal_layer_count: u8,
+        last_frame_type: PictureType,
+    ) -> anyhow::Result<Self> {
+        if temporal_layer_count != 1 {
+            bail!("{temporal_layer_count} temporal layers are not supported, only 1");
+        }
+        let mut rtn = Self {
+            gop_frame_count: gop_frame_count.max(1),
+            idr_period: idr_period.max(1),
+            consecutive_b_frame_count,
+            temporal_layer_count,
+            last_frame_type,
+            decode_order_map: Vec::new(),
+        };
+        rtn.compute_decode_order_map();
+        Ok(rtn)
+    }
+
+    pub fn gop_frame_count(&self) -> u8 {
+        self.gop_frame_count
+    }
+
+    pub fn idr_period(&self) -> u8 {
+        self.idr_period
+    }
+
+    pub fn consecutive_b_frame_count(&self) -> u8 {
+        self.consecutive_b_frame_count
+    }
+
+    pub fn temporal_layer_count(&self) -> u8 {
+        self.temporal_layer_count
+    }
+
+    fn gop_frame_cycle(&self) -> u8 {
+        self.consecutive_b_frame_count + 1
+    }
+
+    fn compute_decode_order_map(&mut self) {
+        let gop_frame_count = self.gop_frame_count as usize;
+        let mut map = vec![
+            GopEntry {
+                decode_order: 0,
+                is_reference: true,
+            };
+            gop_frame_count
+        ];
+        let mut decode_index = 1;
+        for cycle_start in (1..gop_frame_count).step_by(self.gop_frame_cycle() as usize) {
+            let cycle =
+                cycle_start..(cycle_start + self.gop_frame_cycle() as usize).min(gop_frame_count);
+            // anchors (I and P frames) first, then the B frames referencing them
+            for is_anchor in [true, false] {
+                for gop_num in cycle.clone() {
+                    let frame_type = self.frame_type(gop_num as u64, false, false);
+                    if frame_type.is_b() != is_anchor {
+                        map[gop_num] = GopEntry {
+                            decode_order: decode_index,
+                            is_reference: is_anchor,
+                        };
+                        decode_index += 1;
+                    }
+                }
+            }
+        }
+        self.decode_order_map = map;
+    }
+
+    pub fn frame_type(&self, display_idx: u64, first_frame: bool, last_frame: bool) -> PictureType {
+        if first_frame {
+            return PictureType::Idr;
+        }
+        if last_frame {
+            return self.last_frame_type;
+        }
+        if display_idx.is_multiple_of(self.idr_period as u64) {
+            return PictureType::Idr;
+        }
+        let position_in_gop = display_idx % self.gop_frame_count as u64;
+        if position_in_gop == 0 {
+            PictureType::I
+        } else if position_in_gop.is_multiple_of(self.gop_frame_cycle() as u64) {
+            PictureType::P
+        } else {
+            PictureType::B
+        }
+    }
+
+    /// Position of a frame in decode order relative to the start of its GOP
+    #[allow(dead_code)]
+    pub fn decode_order_position(&self, display_idx: u64) -> u8 {
+        self.decode_order_map[(display_idx % self.gop_frame_count as u64) as usize].decode_order
+    }
+
+    #[allow(dead_code)]
+    pub fn frame_in_decode_order(&self, display_idx: u64) -> u64 {
+        let gop_start = display_idx - display_idx % self.gop_frame_count as u64;
+        gop_start + self.decode_order_position(display_idx) as u64
+    }
+
+    #[allow(dead_code)]
+    pub fn is_reference(&self, display_idx: u64) -> bool {
+        self.decode_order_map[(display_idx % self.gop_frame_count as u64) as usize].is_reference
+    }
+
+    /// Display order positions within the GOP that the frame at `gop_num` references, backward
+    /// references first starting with the closest one.
+    pub fn reference_numbers(
+        &self,
+        gop_num: u8,
+        search_backward: bool,
+        search_forward: bool,
+    ) -> Vec<u8> {
+        let mut rtn = Vec::new();
+        self.visit_gop_frames(
+            gop_num as i16,
+            search_backward,
+            search_forward,
+            &mut |visited, frame_type| {
+                if !frame_type.is_b() && visited != gop_num {
+                    rtn.push(visited);
+                }
+            },
+        );
+        rtn
+    }
+
+    fn visit_gop_frames(
+        &self,
+        gop_num: i16,
+        search_backward: bool,
+        search_forward: bool,
+        callback: &mut impl FnMut(u8, PictureType),
+    ) {
+        if gop_num < 0 || gop_num >= self.gop_frame_count as i16 {
+            return;
+        }
+        let frame_type = self.frame_type(gop_num as u64, false, false);
+        callback(gop_num as u8, frame_type);
+        match frame_type {
+            PictureType::Idr | PictureType::I => {}
+            PictureType::P => {
+                if search_backward {
+                    self.visit_gop_frames(gop_num - 1, true, false, callback);
+                }
+            }
+            PictureType::B => {
+                if search_backward {
+                    self.visit_gop_frames(gop_num - 1, true, false, callback);
+                }
+                if search_forward {
+                    self.visit_gop_frames(gop_num + 1, false, true, callback);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use PictureType::{Idr, B, I, P};
+
+    // Tables below were printed by VkVideoGopStructure of the NVIDIA Vulkan video samples.
+
+    #[test]
+    fn ip_only_test() {
+        assert!(GopStructure::new(16, 16, 0, 2, P).is_err());
+        let gop = GopStructure::new(16, 16, 0, 1, P).unwrap();
+        let frame_types: Vec<_> = (0..32).map(|i| gop.frame_type(i, false, false)).collect();
+        assert_eq!(
+            frame_types,
+            [&[Idr][..], &[P; 15], &[Idr], &[P; 15]].concat()
+        );
+        let decode_order: Vec<_> = (0..32).map(|i| gop.frame_in_decode_order(i)).collect();
+        assert_eq!(decode_order, (0..32).collect::<Vec<_>>());
+        assert!((0..16).all(|i| gop.is_reference(i)));
+        assert_eq!(gop.reference_numbers(0, true, true), []);
+        assert_eq!(gop.reference_numbers(1, true, true), [0]);
+        assert_eq!(gop.reference_numbers(4, true, true), [3, 2, 1, 0]);
+        assert_eq!(gop.reference_numbers(4, false, true), []);
+    }
+
+    #[test]
+    fn b_frames_test() {
+        let gop = GopStructure::new(16, 16, 2, 1, P).unwrap();
+        let frame_types: Vec<_> = (0..16).map(|i| gop.frame_type(i, false, false)).collect();
+        assert_eq!(
+            frame_types,
+            [Idr, B, B, P, B, B, P, B, B, P, B, B, P, B, B, P]
+        );
+        let decode_order: Vec<_> = (0..16).map(|i| gop.decode_order_position(i)).collect();
+        assert_eq!(
+            decode_order,
+            [0, 2, 3, 1, 5, 6, 4, 8, 9, 7, 11, 12, 10, 14, 15, 13]
+        );
+        let is_reference: Vec<_> = (0..16).map(|i| gop.is_reference(i)).collect();
+        assert_eq!(
+            is_reference,
+            [
+                true, false, false, true, false, false, true, false, false, true, false, false,
+                true, false, false, true
+            ]
+        );
+        assert_eq!(gop.frame_in_decode_order(17), 18);
+        assert_eq!(gop.frame_in_decode_order(19), 17);
+
+        let references = [
+            vec![],
+            vec![0, 3],
+            vec![0, 3],
+            vec![0],
+            vec![3, 0, 6],
+            vec![3, 0, 6],
+            vec![3, 0],
+            vec![6, 3, 0, 9],
+            vec![6, 3, 0, 9],
+            vec![6, 3, 0],
+            vec![9, 6, 3, 0, 12],
+            vec![9, 6, 3, 0, 12],
+            vec![9, 6, 3, 0],
+            vec![12, 9, 6, 3, 0, 15],
+            vec![12, 9, 6, 3, 0, 15],
+            vec![12, 9, 6, 3, 0],
+        ];
+        for (gop_num, references) in references.iter().enumerate() {
+            assert_eq!(
+                &gop.reference_numbers(gop_num as u8, true, true),
+                references
+            );
+        }
+        assert_eq!(gop.reference_numbers(4, true, false), [3, 0]);
+        assert_eq!(gop.reference_numbers(4, false, true), [6]);
+    }
+
+    #[test]
+    fn open_gop_end_test() {
+        let gop = GopStructure::new(8, 16, 3, 1, P).unwrap();
+        let frame_types: Vec<_> = (0..24).map(|i| gop.frame_type(i, false, false)).collect();
+        assert_eq!(
+            frame_types,
+            [Idr, B, B, B, P, B, B, B, I, B, B, B, P, B, B, B, Idr, B, B, B, P, B, B, B]
+        );
+        // nvpro: [0, 2, 3, 4, 1, 6, 7, 8], which collides with the next GOP's I frame
+        let decode_order: Vec<_> = (0..8).map(|i| gop.decode_order_position(i)).collect();
+        assert_eq!(decode_order, [0, 2, 3, 4, 1, 5, 6, 7]);
+        let references = [
+            vec![],
+            vec![0, 4],
+            vec![0, 4],
+            vec![0, 4],
+            vec![0],
+            vec![4, 0],
+            vec![4, 0],
+            vec![4, 0],
+        ];
+        for (gop_num, references) in references.iter().enumerate() {
+            assert_eq!(
+                &gop.reference_numbers(gop_num as u8, true, true),
+                references
+            );
+        }
+        assert_eq!(gop.frame_type(5, false, true), P);
+        assert_eq!(gop.frame_type(5, true, false), Idr);
+    }
+}
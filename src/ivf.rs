@@ -0,0 +1,139 @@
+//! IVF writer, the usual raw container for AV1 elementary streams.
+//!
+//! Every frame starts with a temporal delimiter and key frames repeat the sequence header, so
+//! the stream can be decoded from any key frame on. The frame count in the file header is
+//! patched when the file is finished.
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::muxer::{
+    sample_data, EncodedFrame, Muxer, MuxerConfig, ParameterSets, OBU_TYPE_TEMPORAL_DELIMITER,
+};
+use crate::settings::Codec;
+
+const TIMESCALE: u32 = 90_000;
+const HEADER_SIZE: u16 = 32;
+const FRAME_COUNT_OFFSET: u64 = 24;
+const TEMPORAL_DELIMITER: [u8; 2] = [OBU_TYPE_TEMPORAL_DELIMITER << 3 | 0x02, 0x00];
+
+pub struct IvfMuxer<W: Write + Seek> {
+    writer: W,
+    start: u64,
+    sequence_header: Vec<u8>,
+    frame_count: u32,
+}
+
+impl<W: Write + Seek> IvfMuxer<W> {
+    pub fn new(mut writer: W, config: MuxerConfig, parameter_sets: &[u8]) -> std::io::Result<Self> {
+        let start = writer.stream_position()?;
+        // key frames repeat the HDR metadata OBUs along with the sequence header
+        let parameter_sets = ParameterSets::from_annex_b(Codec::AV1, parameter_sets);
+        let sequence_header = [parameter_sets.sps, parameter_sets.sei].concat().concat();
+
+        let mut buf = Vec::with_capacity(HEADER_SIZE as usize);
+        buf.extend_from_slice(b"DKIF");
+        buf.extend_from_slice(&0u16.to_le_bytes()); // version
+        buf.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+        buf.extend_from_slice(b"AV01");
+        buf.extend_from_slice(&(config.width as u16).to_le_bytes());
+        buf.extend_from_slice(&(config.height as u16).to_le_bytes());
+        // time base 1/90000
+        buf.extend_from_slice(&TIMESCALE.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes()); // frame count
+        buf.extend_from_slice(&0u32.to_le_bytes()); // unused
+        writer.write_all(&buf)?;
+        writer.flush()?;
+
+        Ok(Self {
+            writer,
+            start,
+            sequence_header,
+            frame_count: 0,
+        })
+    }
+}
+
+impl<W: Write + Seek> Muxer for IvfMuxer<W> {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        let mut data = TEMPORAL_DELIMITER.to_vec();
+        if frame.is_keyframe() {
+            data.extend_from_slice(&self.sequence_header);
+        }
+        data.extend_from_slice(&sample_data(Codec::AV1, frame.data));
+
+        let pts = (frame.pts.as_nanos() * TIMESCALE as u128 / 1_000_000_000) as u64;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&pts.to_le_bytes())?;
+        self.writer.write_all(&data)?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        let end = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.start + FRAME_COUNT_OFFSET))?;
+        self.writer.write_all(&self.frame_count.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::dpb::PictureType;
+
+    #[test]
+    fn frames_test() {
+        let sequence_header = [0x0a, 0x05, 0x00, 0x00, 0x00, 0x68, 0x40];
+        let config = MuxerConfig {
+            codec: Codec::AV1,
+            width: 64,
+            height: 48,
+            bit_depth: 8,
+            frame_rate_numerator: 60,
+            frame_rate_denominator: 1,
+        };
+        let mut muxer = IvfMuxer::new(Cursor::new(Vec::new()), config, &sequence_header).unwrap();
+        let frame = [0x32, 0x01, 0xaa];
+        muxer
+            .write_frame(&EncodedFrame {
+                data: &frame,
+                picture_type: PictureType::Idr,
+                pts: Duration::ZERO,
+                dts: Duration::ZERO,
+            })
+            .unwrap();
+        muxer
+            .write_frame(&EncodedFrame {
+                data: &[TEMPORAL_DELIMITER.as_slice(), &frame].concat(),
+                picture_type: PictureType::P,
+                pts: Duration::from_millis(10),
+                dts: Duration::from_millis(10),
+            })
+            .unwrap();
+        muxer.finish().unwrap();
+        let data = muxer.writer.into_inner();
+
+        assert_eq!(&data[..4], b"DKIF");
+        assert_eq!(&data[8..12], b"AV01");
+        assert_eq!(data[24..28], 2u32.to_le_bytes());
+
+        let first_size = 2 + sequence_header.len() + frame.len();
+        assert_eq!(data[32..36], (first_size as u32).to_le_bytes());
+        let first = &data[44..44 + first_size];
+        assert_eq!(
+            first,
+            [&TEMPORAL_DELIMITER[..], &sequence_header, &frame].concat()
+        );
+
+        let second = &data[44 + first_size..];
+        assert_eq!(second[..4], 5u32.to_le_bytes());
+        assert_eq!(second[4..12], 900u64.to_le_bytes());
+        assert_eq!(&second[12..], [&TEMPORAL_DELIMITER[..], &frame].concat());
+    }
+}
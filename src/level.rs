@@ -0,0 +1,526 @@
+use ash::vk;
+use ash::vk::native::{StdVideoH264LevelIdc, StdVideoH264ProfileIdc};
+use ash::vk::native::{StdVideoH265LevelIdc, StdVideoH265ProfileIdc};
+
+/// Properties of a stream that determine its level
+#[derive(Debug, Clone, Copy)]
+pub struct LevelParameters {
+    pub coded_extent: vk::Extent2D,
+    pub frame_rate_numerator: u32,
+    pub frame_rate_denominator: u32,
+    /// In bits per second, 0 if not limited by rate control
+    pub max_bitrate: u64,
+    /// Pictures the decoder keeps, `max_dec_frame_buffering` for H.264 and
+    /// `sps_max_dec_pic_buffering` for H.265
+    pub dpb_size: u32,
+}
+
+impl LevelParameters {
+    /// Whether `units` per frame at the frame rate stay within `max_rate` units per second
+    fn rate_within(&self, units: u64, max_rate: u64) -> bool {
+        units * self.frame_rate_numerator as u64
+            <= max_rate * self.frame_rate_denominator.max(1) as u64
+    }
+}
+
+struct H264Level {
+    level_idc: StdVideoH264LevelIdc,
+    name: &'static str,
+    max_mbps: u64,
+    max_fs: u64,
+    max_dpb_mbs: u64,
+    /// In units of the profile's `cpbBrVclFactor` bits per second
+    max_br: u64,
+}
+
+/// Table A-1 of the H.264 spec without level 1b
+const H264_LEVELS: [H264Level; 19] = {
+    use ash::vk::native::*;
+    const fn level(
+        level_idc: StdVideoH264LevelIdc,
+        name: &'static str,
+        max_mbps: u64,
+        max_fs: u64,
+        max_dpb_mbs: u64,
+        max_br: u64,
+    ) -> H264Level {
+        H264Level {
+            level_idc,
+            name,
+            max_mbps,
+            max_fs,
+            max_dpb_mbs,
+            max_br,
+        }
+    }
+    [
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_1_0,
+            "1.0",
+            1485,
+            99,
+            396,
+            64,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_1_1,
+            "1.1",
+            3000,
+            396,
+            900,
+            192,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_1_2,
+            "1.2",
+            6000,
+            396,
+            2376,
+            384,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_1_3,
+            "1.3",
+            11880,
+            396,
+            2376,
+            768,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_2_0,
+            "2.0",
+            11880,
+            396,
+            2376,
+            2000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_2_1,
+            "2.1",
+            19800,
+            792,
+            4752,
+            4000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_2_2,
+            "2.2",
+            20250,
+            1620,
+            8100,
+            4000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_0,
+            "3.0",
+            40500,
+            1620,
+            8100,
+            10000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_1,
+            "3.1",
+            108000,
+            3600,
+            18000,
+            14000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_2,
+            "3.2",
+            216000,
+            5120,
+            20480,
+            20000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_0,
+            "4.0",
+            245760,
+            8192,
+            32768,
+            20000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_1,
+            "4.1",
+            245760,
+            8192,
+            32768,
+            50000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_2,
+            "4.2",
+            522240,
+            8704,
+            34816,
+            50000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_5_0,
+            "5.0",
+            589824,
+            22080,
+            110400,
+            135000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_5_1,
+            "5.1",
+            983040,
+            36864,
+            184320,
+            240000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_5_2,
+            "5.2",
+            2073600,
+            36864,
+            184320,
+            240000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_6_0,
+            "6.0",
+            4177920,
+            139264,
+            696320,
+            240000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_6_1,
+            "6.1",
+            8355840,
+            139264,
+            696320,
+            480000,
+        ),
+        level(
+            StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_6_2,
+            "6.2",
+            16711680,
+            139264,
+            696320,
+            800000,
+        ),
+    ]
+};
+
+pub fn h264_level_name(level_idc: StdVideoH264LevelIdc) -> &'static str {
+    H264_LEVELS
+        .iter()
+        .find(|level| level.level_idc == level_idc)
+        .map_or("unknown", |level| level.name)
+}
+
+/// `cpbBrVclFactor` of Table A-2
+fn h264_cpb_br_vcl_factor(profile: StdVideoH264ProfileIdc) -> u64 {
+    match profile {
+        vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_HIGH => 1250,
+        vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_HIGH_444_PREDICTIVE => 4000,
+        _ => 1000,
+    }
+}
+
+/// Returns the lowest level the stream conforms to, `None` if it exceeds the highest level.
+pub fn h264_level(
+    parameters: &LevelParameters,
+    profile: StdVideoH264ProfileIdc,
+) -> Option<StdVideoH264LevelIdc> {
+    let width_in_mbs = parameters.coded_extent.width.div_ceil(16) as u64;
+    let height_in_mbs = parameters.coded_extent.height.div_ceil(16) as u64;
+    let frame_size = width_in_mbs * height_in_mbs;
+    let cpb_br_vcl_factor = h264_cpb_br_vcl_factor(profile);
+    H264_LEVELS
+        .iter()
+        .find(|level| {
+            // A.3.1 limits the aspect ratio through the width and height
+            frame_size <= level.max_fs
+                && width_in_mbs * width_in_mbs <= 8 * level.max_fs
+                && height_in_mbs * height_in_mbs <= 8 * level.max_fs
+                && parameters.rate_within(frame_size, level.max_mbps)
+                && parameters.dpb_size as u64 * frame_size <= level.max_dpb_mbs
+                && parameters.max_bitrate <= level.max_br * cpb_br_vcl_factor
+        })
+        .map(|level| level.level_idc)
+}
+
+struct H265Level {
+    level_idc: StdVideoH265LevelIdc,
+    name: &'static str,
+    max_luma_ps: u64,
+    max_luma_sr: u64,
+    /// Main tier, in units of the profile's `CpbBrVclFactor` bits per second
+    max_br: u64,
+}
+
+/// Tables A.8 and A.9 of the H.265 spec
+const H265_LEVELS: [H265Level; 13] = {
+    use ash::vk::native::*;
+    const fn level(
+        level_idc: StdVideoH265LevelIdc,
+        name: &'static str,
+        max_luma_ps: u64,
+        max_luma_sr: u64,
+        max_br: u64,
+    ) -> H265Level {
+        H265Level {
+            level_idc,
+            name,
+            max_luma_ps,
+            max_luma_sr,
+            max_br,
+        }
+    }
+    [
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_1_0,
+            "1.0",
+            36864,
+            552960,
+            128,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_2_0,
+            "2.0",
+            122880,
+            3686400,
+            1500,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_2_1,
+            "2.1",
+            245760,
+            7372800,
+            3000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_3_0,
+            "3.0",
+            552960,
+            16588800,
+            6000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_3_1,
+            "3.1",
+            983040,
+            33177600,
+            10000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_4_0,
+            "4.0",
+            2228224,
+            66846720,
+            12000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_4_1,
+            "4.1",
+            2228224,
+            133693440,
+            20000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_0,
+            "5.0",
+            8912896,
+            267386880,
+            25000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_1,
+            "5.1",
+            8912896,
+            534773760,
+            40000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_2,
+            "5.2",
+            8912896,
+            1069547520,
+            60000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_0,
+            "6.0",
+            35651584,
+            1069547520,
+            60000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_1,
+            "6.1",
+            35651584,
+            2139095040,
+            120000,
+        ),
+        level(
+            StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_2,
+            "6.2",
+            35651584,
+            4278190080,
+            240000,
+        ),
+    ]
+};
+
+pub fn h265_level_name(level_idc: StdVideoH265LevelIdc) -> &'static str {
+    H265_LEVELS
+        .iter()
+        .find(|level| level.level_idc == level_idc)
+        .map_or("unknown", |level| level.name)
+}
+
+/// `CpbBrVclFactor` of Table A.10 for the main tier
+fn h265_cpb_br_vcl_factor(profile: StdVideoH265ProfileIdc) -> u64 {
+    match profile {
+        vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_FORMAT_RANGE_EXTENSIONS
+        | vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_SCC_EXTENSIONS => 1500,
+        _ => 1000,
+    }
+}
+
+/// Returns the lowest main tier level the stream conforms to, `None` if it exceeds the highest
+/// level.
+pub fn h265_level(
+    parameters: &LevelParameters,
+    profile: StdVideoH265ProfileIdc,
+) -> Option<StdVideoH265LevelIdc> {
+    let width = parameters.coded_extent.width as u64;
+    let height = parameters.coded_extent.height as u64;
+    let picture_size = width * height;
+    let cpb_br_vcl_factor = h265_cpb_br_vcl_factor(profile);
+    H265_LEVELS
+        .iter()
+        .find(|level| {
+            picture_size <= level.max_luma_ps
+                && width * width <= 8 * level.max_luma_ps
+                && height * height <= 8 * level.max_luma_ps
+                && parameters.rate_within(picture_size, level.max_luma_sr)
+                && parameters.dpb_size <= h265_max_dpb_size(picture_size, level.max_luma_ps)
+                && parameters.max_bitrate <= level.max_br * cpb_br_vcl_factor
+        })
+        .map(|level| level.level_idc)
+}
+
+/// `maxDpbSize` of A.4.2, smaller pictures allow more of them
+fn h265_max_dpb_size(picture_size: u64, max_luma_ps: u64) -> u32 {
+    const MAX_DPB_PIC_BUF: u32 = 6;
+    if picture_size <= max_luma_ps >> 2 {
+        (4 * MAX_DPB_PIC_BUF).min(16)
+    } else if picture_size <= max_luma_ps >> 1 {
+        (2 * MAX_DPB_PIC_BUF).min(16)
+    } else if picture_size <= (3 * max_luma_ps) >> 2 {
+        (4 * MAX_DPB_PIC_BUF / 3).min(16)
+    } else {
+        MAX_DPB_PIC_BUF
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::native::*;
+
+    fn parameters(width: u32, height: u32, fps: u32, max_bitrate: u64) -> LevelParameters {
+        LevelParameters {
+            coded_extent: vk::Extent2D { width, height },
+            frame_rate_numerator: fps,
+            frame_rate_denominator: 1,
+            max_bitrate,
+            dpb_size: 2,
+        }
+    }
+
+    #[test]
+    fn h264_level_test() {
+        let main = StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_MAIN;
+        let level = |parameters: LevelParameters| h264_level(&parameters, main);
+        assert_eq!(
+            level(parameters(1280, 720, 30, 5_000_000)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_1)
+        );
+        assert_eq!(
+            level(parameters(1280, 720, 60, 10_000_000)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_2)
+        );
+        assert_eq!(
+            level(parameters(1920, 1080, 30, 10_000_000)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_0)
+        );
+        // the bitrate alone needs a higher level
+        assert_eq!(
+            level(parameters(1920, 1080, 30, 30_000_000)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_1)
+        );
+        assert_eq!(
+            level(parameters(1920, 1080, 60, 20_000_000)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_2)
+        );
+        assert_eq!(
+            level(parameters(3840, 2160, 60, 40_000_000)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_5_2)
+        );
+        // 59.94 fps
+        let mut ntsc = parameters(1280, 720, 60000, 0);
+        ntsc.frame_rate_denominator = 1001;
+        assert_eq!(
+            level(ntsc),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_2)
+        );
+        // too wide for level 3.0 despite the frame size
+        assert_eq!(
+            level(parameters(2560, 160, 25, 0)),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_3_1)
+        );
+        assert_eq!(level(parameters(8192, 8192, 120, 0)), None);
+        // High profile allows more bitrate
+        assert_eq!(
+            h264_level(
+                &parameters(1920, 1080, 30, 24_000_000),
+                StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_HIGH
+            ),
+            Some(StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_4_0)
+        );
+    }
+
+    #[test]
+    fn h265_level_test() {
+        let main = StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN;
+        let level = |parameters: LevelParameters| h265_level(&parameters, main);
+        assert_eq!(
+            level(parameters(1280, 720, 30, 5_000_000)),
+            Some(StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_3_1)
+        );
+        assert_eq!(
+            level(parameters(1920, 1080, 30, 10_000_000)),
+            Some(StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_4_0)
+        );
+        assert_eq!(
+            level(parameters(1920, 1080, 60, 20_000_000)),
+            Some(StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_4_1)
+        );
+        assert_eq!(
+            level(parameters(3840, 2160, 60, 40_000_000)),
+            Some(StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_5_1)
+        );
+        assert_eq!(
+            level(parameters(7680, 4320, 60, 0)),
+            Some(StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_1)
+        );
+        assert_eq!(level(parameters(16384, 8192, 60, 0)), None);
+    }
+
+    #[test]
+    fn h265_max_dpb_size_test() {
+        assert_eq!(h265_max_dpb_size(1920 * 1080, 2228224), 6);
+        assert_eq!(h265_max_dpb_size(1280 * 720, 2228224), 12);
+        assert_eq!(h265_max_dpb_size(640 * 360, 2228224), 16);
+    }
+}
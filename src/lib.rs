@@ -1,16 +1,35 @@
 mod bitstream;
 mod buffer_queue;
+mod capabilities;
 mod cmd_buffer_queue;
+mod color;
+mod control;
 mod creation;
 mod dpb;
-#[cfg(feature = "nvpro_sample_gop")]
-mod gop_gen;
+mod filename;
+mod frame_stats;
+mod gop;
+mod ivf;
+mod level;
+mod mkv;
+mod mp4;
+mod muxer;
+// only used to validate the encoder output in debug builds and tests
+#[cfg(debug_assertions)]
+mod nal;
 mod profile;
+mod reorder;
+mod replay;
+mod scale;
+mod segment;
 mod session_parameters;
 mod settings;
 mod shader;
+mod sidecar;
+mod signals;
 mod state;
 mod video_session;
+mod vk_beta;
 mod vk_layer;
 mod vulkan_utils;
 
@@ -70,12 +89,22 @@ pub unsafe extern "system" fn record_vk_get_device_proc_addr(
         let device: vk::Device = vk::Handle::from_raw(device as u64);
         let str_fn_name = CStr::from_ptr(fn_name).to_str().unwrap();
         trace!("{device:?} {str_fn_name:?}");
+        // the device was created without the layer's changes when recording isn't supported
+        let recording = get_state().device.read().unwrap().is_some();
         match str_fn_name {
-            "vkCreateSwapchainKHR" => Some(transmute(record_vk_create_swapchain as *mut c_void)),
+            "vkCreateSwapchainKHR" if recording => {
+                Some(transmute(record_vk_create_swapchain as *mut c_void))
+            }
             //"vkAcquireNextImageKHR" => Some(transmute(record_vk_aquire_next_image as *mut c_void)),
-            "vkDestroySwapchainKHR" => Some(transmute(record_vk_destroy_swapchain as *mut c_void)),
-            "vkDestroyDevice" => Some(transmute(record_vk_destroy_device as *mut c_void)),
-            "vkQueuePresentKHR" => Some(transmute(record_vk_queue_present as *mut c_void)),
+            "vkDestroySwapchainKHR" if recording => {
+                Some(transmute(record_vk_destroy_swapchain as *mut c_void))
+            }
+            "vkDestroyDevice" if recording => {
+                Some(transmute(record_vk_destroy_device as *mut c_void))
+            }
+            "vkQueuePresentKHR" if recording => {
+                Some(transmute(record_vk_queue_present as *mut c_void))
+            }
             _ => {
                 let state = get_state();
                 let get_fn = state.device_get_fn.read().unwrap();
@@ -0,0 +1,438 @@
+//! Matroska writer.
+//!
+//! Blocks are written as soon as they arrive in clusters of unknown size. Cluster sizes, the
+//! segment size, the duration and the seek head are patched when the file is finished, so an
+//! interrupted recording is still readable by most demuxers.
+use std::io::{Seek, SeekFrom, Write};
+use std::time::Duration;
+
+use log::debug;
+
+use crate::muxer::{
+    av1_codec_configuration_record, avc_decoder_configuration_record,
+    hevc_decoder_configuration_record, sample_data, EncodedFrame, Muxer, MuxerConfig,
+    ParameterSets,
+};
+use crate::settings::Codec;
+
+const EBML: u32 = 0x1A45_DFA3;
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const VOID: u32 = 0xEC;
+
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+
+const INFO: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+const DURATION: u32 = 0x4489;
+const MUXING_APP: u32 = 0x4D80;
+const WRITING_APP: u32 = 0x5741;
+
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_UID: u32 = 0x73C5;
+const TRACK_TYPE: u32 = 0x83;
+const FLAG_LACING: u32 = 0x9C;
+const DEFAULT_DURATION: u32 = 0x23_E383;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+
+const CUES: u32 = 0x1C53_BB6B;
+const CUE_POINT: u32 = 0xBB;
+const CUE_TIME: u32 = 0xB3;
+const CUE_TRACK_POSITIONS: u32 = 0xB7;
+const CUE_TRACK: u32 = 0xF7;
+const CUE_CLUSTER_POSITION: u32 = 0xF1;
+
+const TRACK: u64 = 1;
+/// Timestamps are in milliseconds
+const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+const UNKNOWN_SIZE: u64 = 0x00FF_FFFF_FFFF_FFFF;
+/// Space reserved for the seek head which can only be written once the cues are known
+const SEEK_HEAD_RESERVED_SIZE: u64 = 96;
+/// Block timestamps are relative to the cluster and stored as i16
+const MAX_CLUSTER_DURATION_MS: u64 = 30_000;
+
+fn put_id(buf: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    let leading_zero_bytes = (id.leading_zeros() / 8) as usize;
+    buf.extend_from_slice(&bytes[leading_zero_bytes.min(3)..]);
+}
+
+fn put_size(buf: &mut Vec<u8>, size: u64) {
+    if size == UNKNOWN_SIZE {
+        buf.extend_from_slice(&[0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        return;
+    }
+    let len = (1..=8).find(|len| size < (1 << (7 * len)) - 1).unwrap_or(8);
+    put_size_with_len(buf, size, len);
+}
+
+fn put_size_with_len(buf: &mut Vec<u8>, size: u64, len: usize) {
+    let marked = size | (1 << (7 * len));
+    buf.extend_from_slice(&marked.to_be_bytes()[8 - len..]);
+}
+
+fn put_element(buf: &mut Vec<u8>, id: u32, data: &[u8]) {
+    put_id(buf, id);
+    put_size(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+fn put_uint(buf: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let leading_zero_bytes = ((value.leading_zeros() / 8) as usize).min(7);
+    put_element(buf, id, &bytes[leading_zero_bytes..]);
+}
+
+fn put_string(buf: &mut Vec<u8>, id: u32, value: &str) {
+    put_element(buf, id, value.as_bytes());
+}
+
+fn put_master(buf: &mut Vec<u8>, id: u32, content: impl FnOnce(&mut Vec<u8>)) {
+    let mut inner = Vec::new();
+    content(&mut inner);
+    put_element(buf, id, &inner);
+}
+
+fn put_void(buf: &mut Vec<u8>, total_size: u64) {
+    // 1 byte ID + 8 byte size
+    put_id(buf, VOID);
+    put_size_with_len(buf, total_size - 9, 8);
+    buf.resize(buf.len() + (total_size - 9) as usize, 0);
+}
+
+struct CuePoint {
+    time: u64,
+    cluster_position: u64,
+}
+
+pub struct MkvMuxer<W: Write + Seek> {
+    writer: W,
+    codec: Codec,
+    /// HDR metadata in the sample format, prepended to every keyframe
+    keyframe_metadata: Vec<u8>,
+    frame_duration: Duration,
+    segment_data_start: u64,
+    seek_head_position: u64,
+    info_position: u64,
+    tracks_position: u64,
+    duration_position: u64,
+    cluster: Option<(u64, u64)>,
+    cues: Vec<CuePoint>,
+    last_timestamp: u64,
+}
+
+impl<W: Write + Seek> MkvMuxer<W> {
+    pub fn new(mut writer: W, config: MuxerConfig, parameter_sets: &[u8]) -> std::io::Result<Self> {
+        let parameter_sets = ParameterSets::from_annex_b(config.codec, parameter_sets);
+        let start = writer.stream_position()?;
+
+        let mut buf = Vec::new();
+        put_master(&mut buf, EBML, |buf| {
+            put_uint(buf, EBML_VERSION, 1);
+            put_uint(buf, EBML_READ_VERSION, 1);
+            put_uint(buf, EBML_MAX_ID_LENGTH, 4);
+            put_uint(buf, EBML_MAX_SIZE_LENGTH, 8);
+            put_string(buf, DOC_TYPE, "matroska");
+            put_uint(buf, DOC_TYPE_VERSION, 4);
+            put_uint(buf, DOC_TYPE_READ_VERSION, 2);
+        });
+        put_id(&mut buf, SEGMENT);
+        put_size(&mut buf, UNKNOWN_SIZE);
+        let segment_data_start = start + buf.len() as u64;
+
+        let seek_head_position = segment_data_start;
+        put_void(&mut buf, SEEK_HEAD_RESERVED_SIZE);
+
+        let info_position = start + buf.len() as u64;
+        let mut info = Vec::new();
+        put_uint(&mut info, TIMESTAMP_SCALE, TIMESTAMP_SCALE_NS);
+        put_string(&mut info, MUXING_APP, env!("CARGO_PKG_NAME"));
+        put_string(&mut info, WRITING_APP, env!("CARGO_PKG_NAME"));
+        put_id(&mut info, DURATION);
+        put_size(&mut info, 8);
+        let duration_offset = info.len();
+        info.extend_from_slice(&0f64.to_be_bytes());
+        put_id(&mut buf, INFO);
+        put_size(&mut buf, info.len() as u64);
+        let duration_position = start + (buf.len() + duration_offset) as u64;
+        buf.extend_from_slice(&info);
+
+        let tracks_position = start + buf.len() as u64;
+        let (codec_id, codec_private) = match config.codec {
+            Codec::H264 => (
+                "V_MPEG4/ISO/AVC",
+                avc_decoder_configuration_record(&parameter_sets, config.bit_depth),
+            ),
+            Codec::H265 => (
+                "V_MPEGH/ISO/HEVC",
+                hevc_decoder_configuration_record(&parameter_sets, config.bit_depth),
+            ),
+            Codec::AV1 => (
+                "V_AV1",
+                av1_codec_configuration_record(&parameter_sets, config.bit_depth),
+            ),
+        };
+        put_master(&mut buf, TRACKS, |buf| {
+            put_master(buf, TRACK_ENTRY, |buf| {
+                put_uint(buf, TRACK_NUMBER, TRACK);
+                put_uint(buf, TRACK_UID, TRACK);
+                put_uint(buf, TRACK_TYPE, 1); // video
+                put_uint(buf, FLAG_LACING, 0);
+                put_uint(
+                    buf,
+                    DEFAULT_DURATION,
+                    config.frame_duration().as_nanos() as u64,
+                );
+                put_string(buf, CODEC_ID, codec_id);
+                put_element(buf, CODEC_PRIVATE, &codec_private);
+                put_master(buf, VIDEO, |buf| {
+                    put_uint(buf, PIXEL_WIDTH, config.width.into());
+                    put_uint(buf, PIXEL_HEIGHT, config.height.into());
+                });
+            });
+        });
+        writer.write_all(&buf)?;
+        writer.flush()?;
+
+        Ok(Self {
+            writer,
+            codec: config.codec,
+            keyframe_metadata: sample_data(
+                config.codec,
+                &parameter_sets.keyframe_metadata(config.codec),
+            ),
+            frame_duration: config.frame_duration(),
+            segment_data_start,
+            seek_head_position,
+            info_position,
+            tracks_position,
+            duration_position,
+            cluster: None,
+            cues: Vec::new(),
+            last_timestamp: 0,
+        })
+    }
+
+    /// Writes `data` at `position` and returns to the end of the file
+    fn patch(&mut self, position: u64, data: &[u8]) -> std::io::Result<()> {
+        self.writer.seek(SeekFrom::Start(position))?;
+        self.writer.write_all(data)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    fn close_cluster(&mut self) -> std::io::Result<()> {
+        if let Some((position, _)) = self.cluster.take() {
+            let end = self.writer.stream_position()?;
+            // 4 byte ID + 8 byte size
+            let size = end - position - 12;
+            let mut buf = Vec::new();
+            put_size_with_len(&mut buf, size, 8);
+            self.patch(position + 4, &buf)?;
+        }
+        Ok(())
+    }
+
+    fn open_cluster(&mut self, timestamp: u64, is_keyframe: bool) -> std::io::Result<()> {
+        let position = self.writer.stream_position()?;
+        let mut buf = Vec::new();
+        put_id(&mut buf, CLUSTER);
+        put_size(&mut buf, UNKNOWN_SIZE);
+        put_uint(&mut buf, TIMESTAMP, timestamp);
+        self.writer.write_all(&buf)?;
+        if is_keyframe {
+            self.cues.push(CuePoint {
+                time: timestamp,
+                cluster_position: position - self.segment_data_start,
+            });
+        }
+        self.cluster = Some((position, timestamp));
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Muxer for MkvMuxer<W> {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        let timestamp = frame.pts.as_millis() as u64;
+        // blocks are in decode order, with B frames the presentation time jumps back and forth
+        let decode_timestamp = frame.dts.as_millis() as u64;
+        let needs_new_cluster = match self.cluster {
+            Some((_, cluster_timestamp)) => {
+                frame.is_keyframe()
+                    || decode_timestamp < cluster_timestamp
+                    || decode_timestamp - cluster_timestamp > MAX_CLUSTER_DURATION_MS
+            }
+            None => true,
+        };
+        if needs_new_cluster {
+            self.close_cluster()?;
+            self.open_cluster(decode_timestamp.min(timestamp), frame.is_keyframe())?;
+            self.writer.flush()?;
+        }
+        let cluster_timestamp = self.cluster.map(|(_, t)| t).unwrap_or(0);
+
+        let mut data = Vec::new();
+        if frame.is_keyframe() {
+            data.extend_from_slice(&self.keyframe_metadata);
+        }
+        data.extend_from_slice(&sample_data(self.codec, frame.data));
+        let mut buf = Vec::with_capacity(data.len() + 16);
+        put_id(&mut buf, SIMPLE_BLOCK);
+        put_size(&mut buf, data.len() as u64 + 4);
+        put_size_with_len(&mut buf, TRACK, 1);
+        buf.extend_from_slice(
+            &((timestamp as i64 - cluster_timestamp as i64) as i16).to_be_bytes(),
+        );
+        buf.push(if frame.is_keyframe() { 0x80 } else { 0x00 });
+        buf.extend_from_slice(&data);
+        self.writer.write_all(&buf)?;
+        self.last_timestamp = self.last_timestamp.max(timestamp);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.close_cluster()?;
+
+        let cues_position = self.writer.stream_position()?;
+        let mut buf = Vec::new();
+        put_master(&mut buf, CUES, |buf| {
+            for cue in self.cues.iter() {
+                put_master(buf, CUE_POINT, |buf| {
+                    put_uint(buf, CUE_TIME, cue.time);
+                    put_master(buf, CUE_TRACK_POSITIONS, |buf| {
+                        put_uint(buf, CUE_TRACK, TRACK);
+                        put_uint(buf, CUE_CLUSTER_POSITION, cue.cluster_position);
+                    });
+                });
+            }
+        });
+        self.writer.write_all(&buf)?;
+        let end = self.writer.stream_position()?;
+
+        let mut seek_head = Vec::new();
+        put_master(&mut seek_head, SEEK_HEAD, |buf| {
+            for (id, position) in [
+                (INFO, self.info_position),
+                (TRACKS, self.tracks_position),
+                (CUES, cues_position),
+            ] {
+                put_master(buf, SEEK, |buf| {
+                    put_element(buf, SEEK_ID, &id.to_be_bytes());
+                    put_uint(buf, SEEK_POSITION, position - self.segment_data_start);
+                });
+            }
+        });
+        let void_size = SEEK_HEAD_RESERVED_SIZE - seek_head.len() as u64;
+        put_void(&mut seek_head, void_size);
+        self.patch(self.seek_head_position, &seek_head)?;
+
+        let duration_ms = self.last_timestamp + self.frame_duration.as_millis() as u64;
+        self.patch(self.duration_position, &(duration_ms as f64).to_be_bytes())?;
+
+        let mut segment_size = Vec::new();
+        put_size_with_len(&mut segment_size, end - self.segment_data_start, 8);
+        // segment size follows the 4 byte segment ID
+        self.patch(self.segment_data_start - 8, &segment_size)?;
+        debug!("Finished Matroska file with {} cue points", self.cues.len());
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::dpb::PictureType;
+    use crate::muxer::{test_h264_config, TEST_H264_PARAMETER_SETS};
+
+    fn read_vint(data: &[u8]) -> (u64, usize) {
+        let len = data[0].leading_zeros() as usize + 1;
+        let mut value = (data[0] & 0xffu8.checked_shr(len as u32).unwrap_or(0)) as u64;
+        for byte in &data[1..len] {
+            value = (value << 8) | *byte as u64;
+        }
+        (value, len)
+    }
+
+    fn read_element(data: &[u8]) -> (u32, &[u8], &[u8]) {
+        let id_len = data[0].leading_zeros() as usize + 1;
+        let id = data[..id_len]
+            .iter()
+            .fold(0u32, |id, byte| (id << 8) | *byte as u32);
+        let (size, size_len) = read_vint(&data[id_len..]);
+        let start = id_len + size_len;
+        let end = start + size as usize;
+        (id, &data[start..end], &data[end..])
+    }
+
+    fn children(mut data: &[u8]) -> Vec<(u32, &[u8])> {
+        let mut elements = Vec::new();
+        while !data.is_empty() {
+            let (id, content, rest) = read_element(data);
+            elements.push((id, content));
+            data = rest;
+        }
+        elements
+    }
+
+    #[test]
+    fn clusters_and_cues_test() {
+        let config = test_h264_config(60);
+        let mut buffer = Cursor::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut buffer, config, &TEST_H264_PARAMETER_SETS).unwrap();
+        for (i, picture_type) in [PictureType::Idr, PictureType::P, PictureType::Idr]
+            .into_iter()
+            .enumerate()
+        {
+            muxer
+                .write_frame(&EncodedFrame {
+                    data: &[0x00, 0x00, 0x00, 0x01, 0x65, 0x88, 0x84],
+                    picture_type,
+                    pts: Duration::from_millis(20 * i as u64),
+                    dts: Duration::from_millis(20 * i as u64),
+                })
+                .unwrap();
+        }
+        muxer.finish().unwrap();
+
+        let buffer = buffer.into_inner();
+        let top_level = children(&buffer);
+        let ids: Vec<_> = top_level.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [EBML, SEGMENT]);
+
+        let segment = children(top_level[1].1);
+        let ids: Vec<_> = segment.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [SEEK_HEAD, VOID, INFO, TRACKS, CLUSTER, CLUSTER, CUES]);
+
+        let first_cluster = children(segment[4].1);
+        let flags: Vec<_> = first_cluster
+            .iter()
+            .filter(|(id, _)| *id == SIMPLE_BLOCK)
+            .map(|(_, block)| block[3])
+            .collect();
+        assert_eq!(flags, [0x80, 0x00]);
+
+        let cues = children(segment[6].1);
+        assert_eq!(cues.len(), 2);
+    }
+}
@@ -0,0 +1,430 @@
+//! Fragmented MP4 (ISO/IEC 14496-12) writer.
+//!
+//! The `moov` box only describes the track, all samples go into `moof`/`mdat` pairs. Every
+//! fragment is flushed to disk once it is complete, so a file stays playable up to the last
+//! complete fragment even when the application crashes.
+use std::io::Write;
+
+use log::debug;
+
+use crate::muxer::{
+    av1_codec_configuration_record, avc_decoder_configuration_record,
+    hevc_decoder_configuration_record, sample_data, EncodedFrame, Muxer, MuxerConfig,
+    ParameterSets,
+};
+use crate::settings::Codec;
+
+const TIMESCALE: u32 = 90_000;
+const TRACK_ID: u32 = 1;
+const MAX_FRAGMENT_DURATION: u64 = TIMESCALE as u64;
+
+const SAMPLE_FLAGS_SYNC: u32 = 0x0200_0000;
+const SAMPLE_FLAGS_NON_SYNC: u32 = 0x0101_0000;
+
+const TRUN_DATA_OFFSET_PRESENT: u32 = 0x1;
+const TRUN_SAMPLE_DURATION_PRESENT: u32 = 0x100;
+const TRUN_SAMPLE_SIZE_PRESENT: u32 = 0x200;
+const TRUN_SAMPLE_FLAGS_PRESENT: u32 = 0x400;
+const TRUN_SAMPLE_COMPOSITION_TIME_OFFSET_PRESENT: u32 = 0x800;
+const TFHD_DEFAULT_BASE_IS_MOOF: u32 = 0x02_0000;
+
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+struct Sample {
+    data: Vec<u8>,
+    decode_time: u64,
+    /// Presentation minus decode time, negative for B frames
+    composition_offset: i32,
+    is_sync: bool,
+}
+
+pub struct Mp4Muxer<W: Write> {
+    writer: W,
+    codec: Codec,
+    /// HDR metadata in the sample format, prepended to every keyframe
+    keyframe_metadata: Vec<u8>,
+    samples: Vec<Sample>,
+    sequence_number: u32,
+    last_duration: u64,
+}
+
+fn to_timescale(duration: std::time::Duration) -> u64 {
+    (duration.as_nanos() * TIMESCALE as u128 / 1_000_000_000) as u64
+}
+
+fn write_box(buf: &mut Vec<u8>, fourcc: &[u8; 4], content: impl FnOnce(&mut Vec<u8>)) {
+    let start = buf.len();
+    buf.extend_from_slice(&0u32.to_be_bytes());
+    buf.extend_from_slice(fourcc);
+    content(buf);
+    let size = (buf.len() - start) as u32;
+    buf[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+fn write_full_box(
+    buf: &mut Vec<u8>,
+    fourcc: &[u8; 4],
+    version: u8,
+    flags: u32,
+    content: impl FnOnce(&mut Vec<u8>),
+) {
+    write_box(buf, fourcc, |buf| {
+        buf.extend_from_slice(&((version as u32) << 24 | (flags & 0xff_ffff)).to_be_bytes());
+        content(buf);
+    })
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+impl<W: Write> Mp4Muxer<W> {
+    pub fn new(mut writer: W, config: MuxerConfig, parameter_sets: &[u8]) -> std::io::Result<Self> {
+        let parameter_sets = ParameterSets::from_annex_b(config.codec, parameter_sets);
+        let init_segment = Self::init_segment(&config, &parameter_sets);
+        writer.write_all(&init_segment)?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            codec: config.codec,
+            keyframe_metadata: sample_data(
+                config.codec,
+                &parameter_sets.keyframe_metadata(config.codec),
+            ),
+            last_duration: to_timescale(config.frame_duration()),
+            samples: Vec::new(),
+            sequence_number: 1,
+        })
+    }
+
+    fn init_segment(config: &MuxerConfig, parameter_sets: &ParameterSets) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_box(&mut buf, b"ftyp", |buf| {
+            buf.extend_from_slice(b"iso5");
+            put_u32(buf, 512);
+            for brand in [b"iso5", b"iso6", b"mp41"] {
+                buf.extend_from_slice(brand);
+            }
+        });
+        write_box(&mut buf, b"moov", |buf| {
+            write_full_box(buf, b"mvhd", 0, 0, |buf| {
+                put_u32(buf, 0); // creation_time
+                put_u32(buf, 0); // modification_time
+                put_u32(buf, TIMESCALE);
+                put_u32(buf, 0); // duration: unknown, given by fragments
+                put_u32(buf, 0x0001_0000); // rate 1.0
+                put_u16(buf, 0x0100); // volume 1.0
+                buf.extend_from_slice(&[0; 10]);
+                UNITY_MATRIX.iter().for_each(|&m| put_u32(buf, m));
+                buf.extend_from_slice(&[0; 24]);
+                put_u32(buf, TRACK_ID + 1); // next_track_ID
+            });
+            write_box(buf, b"trak", |buf| {
+                write_full_box(buf, b"tkhd", 0, 0x3, |buf| {
+                    put_u32(buf, 0); // creation_time
+                    put_u32(buf, 0); // modification_time
+                    put_u32(buf, TRACK_ID);
+                    put_u32(buf, 0);
+                    put_u32(buf, 0); // duration
+                    buf.extend_from_slice(&[0; 8]);
+                    put_u16(buf, 0); // layer
+                    put_u16(buf, 0); // alternate_group
+                    put_u16(buf, 0); // volume
+                    put_u16(buf, 0);
+                    UNITY_MATRIX.iter().for_each(|&m| put_u32(buf, m));
+                    put_u32(buf, config.width << 16);
+                    put_u32(buf, config.height << 16);
+                });
+                write_box(buf, b"mdia", |buf| {
+                    write_full_box(buf, b"mdhd", 0, 0, |buf| {
+                        put_u32(buf, 0); // creation_time
+                        put_u32(buf, 0); // modification_time
+                        put_u32(buf, TIMESCALE);
+                        put_u32(buf, 0); // duration
+                        put_u16(buf, 0x55c4); // language "und"
+                        put_u16(buf, 0);
+                    });
+                    write_full_box(buf, b"hdlr", 0, 0, |buf| {
+                        put_u32(buf, 0);
+                        buf.extend_from_slice(b"vide");
+                        buf.extend_from_slice(&[0; 12]);
+                        buf.extend_from_slice(b"VideoHandler\0");
+                    });
+                    write_box(buf, b"minf", |buf| {
+                        write_full_box(buf, b"vmhd", 0, 1, |buf| {
+                            buf.extend_from_slice(&[0; 8]); // graphicsmode, opcolor
+                        });
+                        write_box(buf, b"dinf", |buf| {
+                            write_full_box(buf, b"dref", 0, 0, |buf| {
+                                put_u32(buf, 1);
+                                write_full_box(buf, b"url ", 0, 1, |_| {});
+                            });
+                        });
+                        write_box(buf, b"stbl", |buf| {
+                            write_full_box(buf, b"stsd", 0, 0, |buf| {
+                                put_u32(buf, 1);
+                                Self::write_sample_entry(buf, config, parameter_sets);
+                            });
+                            write_full_box(buf, b"stts", 0, 0, |buf| put_u32(buf, 0));
+                            write_full_box(buf, b"stsc", 0, 0, |buf| put_u32(buf, 0));
+                            write_full_box(buf, b"stsz", 0, 0, |buf| {
+                                put_u32(buf, 0);
+                                put_u32(buf, 0);
+                            });
+                            write_full_box(buf, b"stco", 0, 0, |buf| put_u32(buf, 0));
+                        });
+                    });
+                });
+            });
+            write_box(buf, b"mvex", |buf| {
+                write_full_box(buf, b"trex", 0, 0, |buf| {
+                    put_u32(buf, TRACK_ID);
+                    put_u32(buf, 1); // default_sample_description_index
+                    put_u32(buf, 0); // default_sample_duration
+                    put_u32(buf, 0); // default_sample_size
+                    put_u32(buf, 0); // default_sample_flags
+                });
+            });
+        });
+        buf
+    }
+
+    fn write_sample_entry(buf: &mut Vec<u8>, config: &MuxerConfig, parameter_sets: &ParameterSets) {
+        let (fourcc, config_fourcc, config_record) = match config.codec {
+            Codec::H264 => (
+                b"avc1",
+                b"avcC",
+                avc_decoder_configuration_record(parameter_sets, config.bit_depth),
+            ),
+            Codec::H265 => (
+                b"hvc1",
+                b"hvcC",
+                hevc_decoder_configuration_record(parameter_sets, config.bit_depth),
+            ),
+            Codec::AV1 => (
+                b"av01",
+                b"av1C",
+                av1_codec_configuration_record(parameter_sets, config.bit_depth),
+            ),
+        };
+        write_box(buf, fourcc, |buf| {
+            buf.extend_from_slice(&[0; 6]);
+            put_u16(buf, 1); // data_reference_index
+            buf.extend_from_slice(&[0; 16]);
+            put_u16(buf, config.width as u16);
+            put_u16(buf, config.height as u16);
+            put_u32(buf, 0x0048_0000); // horizresolution 72 dpi
+            put_u32(buf, 0x0048_0000); // vertresolution 72 dpi
+            put_u32(buf, 0);
+            put_u16(buf, 1); // frame_count
+            buf.extend_from_slice(&[0; 32]); // compressorname
+            put_u16(buf, 0x0018); // depth
+            put_u16(buf, 0xffff); // pre_defined = -1
+            write_box(buf, config_fourcc, |buf| {
+                buf.extend_from_slice(&config_record)
+            });
+        });
+    }
+
+    /// Writes all buffered samples as one `moof`/`mdat` pair. The duration of the last sample
+    /// is taken from `next_decode_time` if known.
+    fn write_fragment(&mut self, next_decode_time: Option<u64>) -> std::io::Result<()> {
+        if self.samples.is_empty() {
+            return Ok(());
+        }
+        let durations: Vec<u64> = self
+            .samples
+            .windows(2)
+            .map(|w| w[1].decode_time.saturating_sub(w[0].decode_time))
+            .chain(std::iter::once(
+                next_decode_time
+                    .map(|t| t.saturating_sub(self.samples.last().unwrap().decode_time))
+                    .unwrap_or(self.last_duration),
+            ))
+            .collect();
+        self.last_duration = *durations.last().unwrap();
+        let has_composition_offsets = self.samples.iter().any(|s| s.composition_offset != 0);
+        let mut trun_flags = TRUN_DATA_OFFSET_PRESENT
+            | TRUN_SAMPLE_DURATION_PRESENT
+            | TRUN_SAMPLE_SIZE_PRESENT
+            | TRUN_SAMPLE_FLAGS_PRESENT;
+        if has_composition_offsets {
+            trun_flags |= TRUN_SAMPLE_COMPOSITION_TIME_OFFSET_PRESENT;
+        }
+
+        let mut moof = Vec::new();
+        let mut data_offset_position = 0;
+        write_box(&mut moof, b"moof", |buf| {
+            write_full_box(buf, b"mfhd", 0, 0, |buf| put_u32(buf, self.sequence_number));
+            write_box(buf, b"traf", |buf| {
+                write_full_box(buf, b"tfhd", 0, TFHD_DEFAULT_BASE_IS_MOOF, |buf| {
+                    put_u32(buf, TRACK_ID)
+                });
+                write_full_box(buf, b"tfdt", 1, 0, |buf| {
+                    put_u64(buf, self.samples[0].decode_time)
+                });
+                // version 1 for signed composition offsets
+                write_full_box(
+                    buf,
+                    b"trun",
+                    has_composition_offsets as u8,
+                    trun_flags,
+                    |buf| {
+                        put_u32(buf, self.samples.len() as u32);
+                        data_offset_position = buf.len();
+                        put_u32(buf, 0); // data_offset, patched below
+                        for (sample, &duration) in self.samples.iter().zip(durations.iter()) {
+                            put_u32(buf, duration as u32);
+                            put_u32(buf, sample.data.len() as u32);
+                            put_u32(
+                                buf,
+                                if sample.is_sync {
+                                    SAMPLE_FLAGS_SYNC
+                                } else {
+                                    SAMPLE_FLAGS_NON_SYNC
+                                },
+                            );
+                            if has_composition_offsets {
+                                buf.extend_from_slice(&sample.composition_offset.to_be_bytes());
+                            }
+                        }
+                    },
+                );
+            });
+        });
+        let data_offset = (moof.len() + 8) as u32;
+        moof[data_offset_position..data_offset_position + 4]
+            .copy_from_slice(&data_offset.to_be_bytes());
+
+        let mdat_size: usize = 8 + self.samples.iter().map(|s| s.data.len()).sum::<usize>();
+        self.writer.write_all(&moof)?;
+        self.writer.write_all(&(mdat_size as u32).to_be_bytes())?;
+        self.writer.write_all(b"mdat")?;
+        for sample in self.samples.drain(..) {
+            self.writer.write_all(&sample.data)?;
+        }
+        self.writer.flush()?;
+        debug!("Wrote MP4 fragment {} ({mdat_size}B)", self.sequence_number);
+        self.sequence_number += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Muxer for Mp4Muxer<W> {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        let decode_time = to_timescale(frame.dts);
+        let fragment_duration = self
+            .samples
+            .first()
+            .map(|first| decode_time.saturating_sub(first.decode_time))
+            .unwrap_or(0);
+        if frame.is_keyframe() || fragment_duration >= MAX_FRAGMENT_DURATION {
+            self.write_fragment(Some(decode_time))?;
+        }
+        let mut data = Vec::new();
+        if frame.is_keyframe() {
+            data.extend_from_slice(&self.keyframe_metadata);
+        }
+        data.extend_from_slice(&sample_data(self.codec, frame.data));
+        self.samples.push(Sample {
+            data,
+            decode_time,
+            composition_offset: (to_timescale(frame.pts) as i64 - decode_time as i64) as i32,
+            is_sync: frame.is_keyframe(),
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.write_fragment(None)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::dpb::PictureType;
+    use crate::muxer::{test_h264_config, TEST_H264_PARAMETER_SETS};
+
+    #[test]
+    fn fragments_test() {
+        let config = test_h264_config(60);
+        let mut buffer = Vec::new();
+        let mut muxer = Mp4Muxer::new(&mut buffer, config, &TEST_H264_PARAMETER_SETS).unwrap();
+        for (i, picture_type) in [PictureType::Idr, PictureType::P, PictureType::Idr]
+            .into_iter()
+            .enumerate()
+        {
+            muxer
+                .write_frame(&EncodedFrame {
+                    data: &[0x00, 0x00, 0x00, 0x01, 0x65, 0x88, 0x84],
+                    picture_type,
+                    pts: Duration::from_millis(20 * i as u64),
+                    dts: Duration::from_millis(20 * i as u64),
+                })
+                .unwrap();
+        }
+        muxer.finish().unwrap();
+
+        let mut boxes = Vec::new();
+        let mut rest = buffer.as_slice();
+        while !rest.is_empty() {
+            let size = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+            boxes.push(String::from_utf8_lossy(&rest[4..8]).to_string());
+            rest = &rest[size..];
+        }
+        assert_eq!(boxes, ["ftyp", "moov", "moof", "mdat", "moof", "mdat"]);
+    }
+
+    #[test]
+    fn composition_offsets_test() {
+        let config = test_h264_config(50);
+        let mut buffer = Vec::new();
+        let mut muxer = Mp4Muxer::new(&mut buffer, config, &TEST_H264_PARAMETER_SETS).unwrap();
+        // I P B in decode order, displayed as I B P one frame after decoding
+        for (picture_type, pts, dts) in [
+            (PictureType::Idr, 20, 0),
+            (PictureType::P, 60, 20),
+            (PictureType::B, 40, 40),
+        ] {
+            muxer
+                .write_frame(&EncodedFrame {
+                    data: &[0x00, 0x00, 0x00, 0x01, 0x41, 0x9a, 0x02],
+                    picture_type,
+                    pts: Duration::from_millis(pts),
+                    dts: Duration::from_millis(dts),
+                })
+                .unwrap();
+        }
+        muxer.finish().unwrap();
+
+        let trun = buffer.windows(4).position(|w| w == b"trun").unwrap() + 4;
+        assert_eq!(buffer[trun], 1);
+        assert_eq!(buffer[trun + 4..trun + 8], 3u32.to_be_bytes());
+        let offsets: Vec<_> = (0..3)
+            .map(|i| {
+                let offset = trun + 12 + i * 16 + 12;
+                i32::from_be_bytes(buffer[offset..offset + 4].try_into().unwrap())
+            })
+            .collect();
+        assert_eq!(offsets, [1800, 3600, 0]);
+        let durations: Vec<_> = (0..2)
+            .map(|i| {
+                let offset = trun + 12 + i * 16;
+                u32::from_be_bytes(buffer[offset..offset + 4].try_into().unwrap())
+            })
+            .collect();
+        assert_eq!(durations, [1800, 1800]);
+    }
+}
@@ -0,0 +1,639 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use bitstream_io::{BigEndian, BitRead, BitReader};
+use log::warn;
+
+use crate::dpb::PictureType;
+use crate::frame_stats::FrameStats;
+use crate::ivf::IvfMuxer;
+use crate::mkv::MkvMuxer;
+use crate::mp4::Mp4Muxer;
+use crate::settings::{Codec, Container};
+
+const NAL_UNIT_TYPE_H264_SPS: u8 = 7;
+const NAL_UNIT_TYPE_H264_PPS: u8 = 8;
+const NAL_UNIT_TYPE_H265_VPS: u8 = 32;
+const NAL_UNIT_TYPE_H265_SPS: u8 = 33;
+const NAL_UNIT_TYPE_H265_PPS: u8 = 34;
+const NAL_UNIT_TYPE_H265_PREFIX_SEI: u8 = 39;
+pub const OBU_TYPE_SEQUENCE_HEADER: u8 = 1;
+pub const OBU_TYPE_TEMPORAL_DELIMITER: u8 = 2;
+const OBU_TYPE_METADATA: u8 = 5;
+
+/// Everything a container needs to know about the video track besides the parameter sets.
+#[derive(Debug, Clone)]
+pub struct MuxerConfig {
+    pub codec: Codec,
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u32,
+    pub frame_rate_numerator: u32,
+    pub frame_rate_denominator: u32,
+}
+
+impl MuxerConfig {
+    /// Nominal frame duration, used when a container needs a duration that can't be derived
+    /// from neighbouring timestamps (e.g. for the very last frame).
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_nanos(
+            1_000_000_000 * self.frame_rate_denominator.max(1) as u64
+                / self.frame_rate_numerator.max(1) as u64,
+        )
+    }
+}
+
+/// One encoded access unit as written by the encoder (Annex-B byte stream).
+pub struct EncodedFrame<'a> {
+    pub data: &'a [u8],
+    pub picture_type: PictureType,
+    /// Presentation time relative to the start of the recording
+    pub pts: Duration,
+    /// Decode time, frames are written in decode order
+    pub dts: Duration,
+}
+
+impl EncodedFrame<'_> {
+    pub fn is_keyframe(&self) -> bool {
+        matches!(self.picture_type, PictureType::Idr)
+    }
+}
+
+pub trait Muxer {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()>;
+
+    /// Writes everything still buffered. No frames may be written afterwards.
+    fn finish(&mut self) -> std::io::Result<()>;
+
+    /// Whether the encoder should insert an IDR frame as soon as possible, e.g. to start a new
+    /// segment. Returns `true` only once per request.
+    fn wants_keyframe(&mut self) -> bool {
+        false
+    }
+
+    /// Records the encode feedback of a frame, ignored unless per-frame statistics are enabled
+    fn write_frame_stats(&mut self, _stats: &FrameStats) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the raw elementary stream, i.e. parameter sets followed by all access units.
+/// Keyframes after the first repeat the HDR metadata SEI.
+pub struct AnnexBMuxer<W: Write> {
+    writer: W,
+    keyframe_metadata: Vec<u8>,
+    /// The first frame directly follows the parameter sets and their metadata
+    first_frame: bool,
+}
+
+impl<W: Write> AnnexBMuxer<W> {
+    pub fn new(mut writer: W, codec: Codec, parameter_sets: &[u8]) -> std::io::Result<Self> {
+        writer.write_all(parameter_sets)?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            keyframe_metadata: ParameterSets::from_annex_b(codec, parameter_sets)
+                .keyframe_metadata(codec),
+            first_frame: true,
+        })
+    }
+}
+
+impl<W: Write> Muxer for AnnexBMuxer<W> {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        if frame.is_keyframe() && !self.first_frame {
+            self.writer.write_all(&self.keyframe_metadata)?;
+        }
+        self.first_frame = false;
+        self.writer.write_all(frame.data)
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Container {
+    pub fn file_extension(&self, codec: Codec) -> &'static str {
+        match (self, codec) {
+            (Container::AnnexB, Codec::H264) => "h264",
+            (Container::AnnexB, Codec::H265) => "h265",
+            (Container::AnnexB, Codec::AV1) => "ivf",
+            (Container::Mp4, _) => "mp4",
+            (Container::Mkv, _) => "mkv",
+        }
+    }
+}
+
+pub fn create_muxer(
+    container: Container,
+    path: &Path,
+    config: MuxerConfig,
+    parameter_sets: &[u8],
+) -> std::io::Result<Box<dyn Muxer>> {
+    // the file name template may contain subdirectories
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = BufWriter::new(File::create(path)?);
+    Ok(match container {
+        Container::AnnexB if config.codec == Codec::AV1 => {
+            Box::new(IvfMuxer::new(file, config, parameter_sets)?)
+        }
+        Container::AnnexB => Box::new(AnnexBMuxer::new(file, config.codec, parameter_sets)?),
+        Container::Mp4 => Box::new(Mp4Muxer::new(file, config, parameter_sets)?),
+        Container::Mkv => Box::new(MkvMuxer::new(file, config, parameter_sets)?),
+    })
+}
+
+/// Splits an Annex-B byte stream into NAL units (without start codes).
+pub fn split_annex_b(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    let ends: Vec<usize> = starts
+        .iter()
+        .skip(1)
+        .map(|&next_start| {
+            // a 4-byte start code contributes a leading zero byte that belongs to no NAL
+            let mut end = next_start - 3;
+            while end > 0 && data[end - 1] == 0 {
+                end -= 1;
+            }
+            end
+        })
+        .chain(std::iter::once(data.len()))
+        .collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(move |(start, end)| &data[start..end.max(start)])
+        .filter(|nal| !nal.is_empty())
+}
+
+/// Removes emulation prevention bytes (0x000003 → 0x0000) from a NAL unit.
+pub fn nal_to_rbsp(nal: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
+
+/// Splits a sequence of AV1 OBUs (low overhead bitstream format) into OBUs including their headers.
+pub fn split_obus(mut data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    std::iter::from_fn(move || {
+        let header = *data.first()?;
+        let has_extension = header & 0x04 != 0;
+        let has_size_field = header & 0x02 != 0;
+        let mut len = 1 + has_extension as usize;
+        let obu_len = if has_size_field {
+            let mut size = 0u64;
+            for i in 0..8 {
+                let byte = *data.get(len)?;
+                len += 1;
+                size |= ((byte & 0x7f) as u64) << (7 * i);
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            (len + size as usize).min(data.len())
+        } else {
+            data.len()
+        };
+        let (obu, rest) = data.split_at(obu_len);
+        data = rest;
+        Some(obu)
+    })
+}
+
+pub fn obu_type(obu: &[u8]) -> u8 {
+    (obu[0] >> 3) & 0xf
+}
+
+/// Parameter set NAL units (without start codes) extracted from an Annex-B header.
+/// For AV1 the header is a sequence of OBUs and the sequence header OBU is stored as `sps`.
+#[derive(Default, Debug, Clone)]
+pub struct ParameterSets {
+    pub vps: Vec<Vec<u8>>,
+    pub sps: Vec<Vec<u8>>,
+    pub pps: Vec<Vec<u8>>,
+    /// Prefix SEI NAL units for H.265 and metadata OBUs for AV1, i.e. the HDR metadata
+    pub sei: Vec<Vec<u8>>,
+}
+
+impl ParameterSets {
+    pub fn from_annex_b(codec: Codec, data: &[u8]) -> Self {
+        let mut rtn = Self::default();
+        if codec == Codec::AV1 {
+            for obu in split_obus(data) {
+                match obu_type(obu) {
+                    OBU_TYPE_SEQUENCE_HEADER => rtn.sps.push(obu.to_vec()),
+                    OBU_TYPE_METADATA => rtn.sei.push(obu.to_vec()),
+                    _ => {}
+                }
+            }
+            return rtn;
+        }
+        for nal in split_annex_b(data) {
+            match codec {
+                Codec::H264 => match nal[0] & 0x1f {
+                    NAL_UNIT_TYPE_H264_SPS => rtn.sps.push(nal.to_vec()),
+                    NAL_UNIT_TYPE_H264_PPS => rtn.pps.push(nal.to_vec()),
+                    _ => {}
+                },
+                Codec::H265 => match (nal[0] >> 1) & 0x3f {
+                    NAL_UNIT_TYPE_H265_VPS => rtn.vps.push(nal.to_vec()),
+                    NAL_UNIT_TYPE_H265_SPS => rtn.sps.push(nal.to_vec()),
+                    NAL_UNIT_TYPE_H265_PPS => rtn.pps.push(nal.to_vec()),
+                    NAL_UNIT_TYPE_H265_PREFIX_SEI => rtn.sei.push(nal.to_vec()),
+                    _ => {}
+                },
+                Codec::AV1 => unreachable!(),
+            }
+        }
+        rtn
+    }
+
+    /// The HDR metadata repeated with every keyframe, so decoding can start at any of them:
+    /// prefix SEI NAL units with start codes for H.265 and metadata OBUs for AV1.
+    pub fn keyframe_metadata(&self, codec: Codec) -> Vec<u8> {
+        match codec {
+            Codec::H264 | Codec::H265 => self
+                .sei
+                .iter()
+                .flat_map(|nal| [&[0, 0, 0, 1][..], nal])
+                .flatten()
+                .copied()
+                .collect(),
+            Codec::AV1 => self.sei.concat(),
+        }
+    }
+}
+
+/// Converts an Annex-B access unit into 4-byte length prefixed NAL units as used by MP4/MKV.
+pub fn annex_b_to_length_prefixed(data: &[u8]) -> Vec<u8> {
+    let mut rtn = Vec::with_capacity(data.len() + 16);
+    for nal in split_annex_b(data) {
+        rtn.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+        rtn.extend_from_slice(nal);
+    }
+    rtn
+}
+
+/// Converts an encoded frame into the sample format of MP4/MKV: length prefixed NAL units for
+/// H.264/H.265 and OBUs without temporal delimiters for AV1.
+pub fn sample_data(codec: Codec, data: &[u8]) -> Vec<u8> {
+    match codec {
+        Codec::H264 | Codec::H265 => annex_b_to_length_prefixed(data),
+        Codec::AV1 => split_obus(data)
+            .filter(|obu| obu_type(obu) != OBU_TYPE_TEMPORAL_DELIMITER)
+            .flatten()
+            .copied()
+            .collect(),
+    }
+}
+
+/// AVCDecoderConfigurationRecord (ISO/IEC 14496-15, 5.3.3.1)
+pub fn avc_decoder_configuration_record(parameter_sets: &ParameterSets, bit_depth: u32) -> Vec<u8> {
+    let Some(sps) = parameter_sets.sps.first().map(|sps| nal_to_rbsp(sps)) else {
+        warn!("Writing avcC without SPS");
+        return Vec::new();
+    };
+    let profile_idc = sps.get(1).copied().unwrap_or(0);
+    let mut rtn = vec![
+        1, // configurationVersion
+        profile_idc,
+        sps.get(2).copied().unwrap_or(0), // profile_compatibility
+        sps.get(3).copied().unwrap_or(0), // AVCLevelIndication
+        0xfc | 3,                         // lengthSizeMinusOne
+        0xe0 | parameter_sets.sps.len() as u8,
+    ];
+    for sps in parameter_sets.sps.iter() {
+        rtn.extend_from_slice(&(sps.len() as u16).to_be_bytes());
+        rtn.extend_from_slice(sps);
+    }
+    rtn.push(parameter_sets.pps.len() as u8);
+    for pps in parameter_sets.pps.iter() {
+        rtn.extend_from_slice(&(pps.len() as u16).to_be_bytes());
+        rtn.extend_from_slice(pps);
+    }
+    if matches!(profile_idc, 100 | 110 | 122 | 144) {
+        rtn.extend_from_slice(&[
+            0xfc | 1, // chroma_format_idc 4:2:0
+            0xf8 | (bit_depth.saturating_sub(8) as u8),
+            0xf8 | (bit_depth.saturating_sub(8) as u8),
+            0, // numOfSequenceParameterSetExt
+        ]);
+    }
+    rtn
+}
+
+/// HEVCDecoderConfigurationRecord (ISO/IEC 14496-15, 8.3.3.1)
+pub fn hevc_decoder_configuration_record(
+    parameter_sets: &ParameterSets,
+    bit_depth: u32,
+) -> Vec<u8> {
+    let Some(sps) = parameter_sets.sps.first().map(|sps| nal_to_rbsp(sps)) else {
+        warn!("Writing hvcC without SPS");
+        return Vec::new();
+    };
+    // 2 bytes NAL header, 1 byte sps_video_parameter_set_id/sps_max_sub_layers_minus1/
+    // sps_temporal_id_nesting_flag followed by 12 bytes general_profile_tier_level
+    let mut general_profile_tier_level = [0u8; 12];
+    if sps.len() >= 15 {
+        general_profile_tier_level.copy_from_slice(&sps[3..15]);
+    } else {
+        warn!("SPS too short to contain profile_tier_level");
+    }
+    let temporal_id_nested = sps.get(2).map(|b| b & 1).unwrap_or(1);
+
+    let mut rtn = vec![1]; // configurationVersion
+    rtn.extend_from_slice(&general_profile_tier_level);
+    rtn.extend_from_slice(&[
+        0xf0,
+        0x00,     // min_spatial_segmentation_idc
+        0xfc,     // parallelismType
+        0xfc | 1, // chromaFormat 4:2:0
+        0xf8 | (bit_depth.saturating_sub(8) as u8),
+        0xf8 | (bit_depth.saturating_sub(8) as u8),
+        0x00,
+        0x00, // avgFrameRate
+        // constantFrameRate 0, numTemporalLayers 1, temporalIdNested, lengthSizeMinusOne 3
+        (1 << 3) | (temporal_id_nested << 2) | 3,
+    ]);
+    let arrays = [
+        (NAL_UNIT_TYPE_H265_VPS, &parameter_sets.vps),
+        (NAL_UNIT_TYPE_H265_SPS, &parameter_sets.sps),
+        (NAL_UNIT_TYPE_H265_PPS, &parameter_sets.pps),
+        (NAL_UNIT_TYPE_H265_PREFIX_SEI, &parameter_sets.sei),
+    ];
+    rtn.push(arrays.iter().filter(|(_, nals)| !nals.is_empty()).count() as u8);
+    for (nal_unit_type, nals) in arrays.iter().filter(|(_, nals)| !nals.is_empty()) {
+        rtn.push(0x80 | nal_unit_type); // array_completeness
+        rtn.extend_from_slice(&(nals.len() as u16).to_be_bytes());
+        for nal in nals.iter() {
+            rtn.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+            rtn.extend_from_slice(nal);
+        }
+    }
+    rtn
+}
+
+/// AV1CodecConfigurationRecord (AV1 Codec ISO Media File Format Binding, 2.3.3)
+pub fn av1_codec_configuration_record(parameter_sets: &ParameterSets, bit_depth: u32) -> Vec<u8> {
+    let Some(sequence_header) = parameter_sets.sps.first() else {
+        warn!("Writing av1C without sequence header");
+        return Vec::new();
+    };
+    let payload_start = split_obus(sequence_header)
+        .next()
+        .map(|obu| {
+            let header_len = 1 + ((obu[0] & 0x04) != 0) as usize;
+            let size_len = if obu[0] & 0x02 != 0 {
+                obu[header_len..]
+                    .iter()
+                    .take_while(|b| *b & 0x80 != 0)
+                    .count()
+                    + 1
+            } else {
+                0
+            };
+            header_len + size_len
+        })
+        .unwrap_or(1);
+    let payload = &sequence_header[payload_start.min(sequence_header.len())..];
+    let parse = || -> std::io::Result<(u8, u8, u8)> {
+        let mut reader = BitReader::endian(payload, BigEndian);
+        let seq_profile = reader.read::<u8>(3)?;
+        let _still_picture = reader.read_bit()?;
+        if reader.read_bit()? {
+            // reduced_still_picture_header
+            return Ok((seq_profile, reader.read::<u8>(5)?, 0));
+        }
+        if reader.read_bit()? {
+            return Err(std::io::Error::other(
+                "timing_info_present_flag is not supported",
+            ));
+        }
+        let _initial_display_delay_present = reader.read_bit()?;
+        let _operating_points_cnt_minus_1 = reader.read::<u8>(5)?;
+        let _operating_point_idc = reader.read::<u16>(12)?;
+        let seq_level_idx = reader.read::<u8>(5)?;
+        let seq_tier = if seq_level_idx > 7 {
+            reader.read::<u8>(1)?
+        } else {
+            0
+        };
+        Ok((seq_profile, seq_level_idx, seq_tier))
+    };
+    let (seq_profile, seq_level_idx, seq_tier) = parse().unwrap_or_else(|err| {
+        warn!("Failed to parse AV1 sequence header ({err}), assuming main profile level 5.1");
+        (0, 13, 0)
+    });
+    let mut rtn = vec![
+        0x81, // marker, version 1
+        (seq_profile << 5) | seq_level_idx,
+        // high_bitdepth, twelve_bit, monochrome 0, chroma_subsampling_x/y 4:2:0
+        (seq_tier << 7) | (((bit_depth > 8) as u8) << 6) | (((bit_depth > 10) as u8) << 5) | 0x0c,
+        0, // initial_presentation_delay_present 0
+    ];
+    rtn.extend_from_slice(sequence_header);
+    // configOBUs may carry the HDR metadata OBUs after the sequence header
+    for metadata in parameter_sets.sei.iter() {
+        rtn.extend_from_slice(metadata);
+    }
+    rtn
+}
+
+/// Annex-B SPS and PPS of a 64x32 H.264 stream, the header of the muxer tests
+#[cfg(test)]
+pub const TEST_H264_PARAMETER_SETS: [u8; 17] = [
+    0x00, 0x00, 0x00, 0x01, 0x67, 0x4d, 0x40, 0x33, 0x9a, 0x00, 0x00, 0x00, 0x01, 0x68, 0xee, 0x3c,
+    0x80,
+];
+
+/// Config matching [`TEST_H264_PARAMETER_SETS`]
+#[cfg(test)]
+pub fn test_h264_config(frame_rate: u32) -> MuxerConfig {
+    MuxerConfig {
+        codec: Codec::H264,
+        width: 64,
+        height: 32,
+        bit_depth: 8,
+        frame_rate_numerator: frame_rate,
+        frame_rate_denominator: 1,
+    }
+}
+
+/// A frame passed to [`RecordingMuxer`]
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    pub data: Vec<u8>,
+    pub picture_type: PictureType,
+    pub pts: Duration,
+    pub dts: Duration,
+}
+
+/// Keeps everything written to it, for tests of the muxers wrapping other muxers
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingMuxer {
+    pub frames: Vec<RecordedFrame>,
+    pub stats: Vec<FrameStats>,
+    pub finished: bool,
+}
+
+#[cfg(test)]
+impl Muxer for RecordingMuxer {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        self.frames.push(RecordedFrame {
+            data: frame.data.to_vec(),
+            picture_type: frame.picture_type,
+            pts: frame.pts,
+            dts: frame.dts,
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.finished = true;
+        Ok(())
+    }
+
+    fn write_frame_stats(&mut self, stats: &FrameStats) -> std::io::Result<()> {
+        self.stats.push(*stats);
+        Ok(())
+    }
+}
+
+/// Lets tests inspect a muxer after handing it over as `Box<dyn Muxer>`
+#[cfg(test)]
+impl<M: Muxer> Muxer for std::rc::Rc<std::cell::RefCell<M>> {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        self.borrow_mut().write_frame(frame)
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.borrow_mut().finish()
+    }
+
+    fn wants_keyframe(&mut self) -> bool {
+        self.borrow_mut().wants_keyframe()
+    }
+
+    fn write_frame_stats(&mut self, stats: &FrameStats) -> std::io::Result<()> {
+        self.borrow_mut().write_frame_stats(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_annex_b_test() {
+        let data = [
+            0x00, 0x00, 0x00, 0x01, 0x67, 0x4d, 0x00, 0x00, 0x00, 0x01, 0x68, 0xee, 0x00, 0x00,
+            0x01, 0x65, 0x88, 0x00,
+        ];
+        let nals: Vec<&[u8]> = split_annex_b(&data).collect();
+        assert_eq!(
+            nals,
+            [
+                &[0x67, 0x4d][..],
+                &[0x68, 0xee][..],
+                &[0x65, 0x88, 0x00][..]
+            ]
+        );
+    }
+
+    #[test]
+    fn nal_to_rbsp_test() {
+        assert_eq!(
+            nal_to_rbsp(&[0x67, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x03]),
+            [0x67, 0x00, 0x00, 0x01, 0x00, 0x00]
+        );
+        assert_eq!(nal_to_rbsp(&[0x00, 0x03, 0x00]), [0x00, 0x03, 0x00]);
+    }
+
+    #[test]
+    fn avc_decoder_configuration_record_test() {
+        let parameter_sets = ParameterSets::from_annex_b(Codec::H264, &TEST_H264_PARAMETER_SETS);
+        assert_eq!(
+            avc_decoder_configuration_record(&parameter_sets, 8),
+            [
+                0x01, 0x4d, 0x40, 0x33, 0xff, 0xe1, 0x00, 0x05, 0x67, 0x4d, 0x40, 0x33, 0x9a, 0x01,
+                0x00, 0x04, 0x68, 0xee, 0x3c, 0x80
+            ]
+        );
+    }
+
+    #[test]
+    fn av1_codec_configuration_record_test() {
+        // temporal delimiter followed by a sequence header with seq_level_idx 13 (level 5.1)
+        let header = [0x12, 0x00, 0x0a, 0x05, 0x00, 0x00, 0x00, 0x68, 0x40];
+        let parameter_sets = ParameterSets::from_annex_b(Codec::AV1, &header);
+        assert_eq!(parameter_sets.sps, [&header[2..]]);
+        assert_eq!(
+            av1_codec_configuration_record(&parameter_sets, 8),
+            [0x81, 0x0d, 0x0c, 0x00, 0x0a, 0x05, 0x00, 0x00, 0x00, 0x68, 0x40]
+        );
+        assert_eq!(sample_data(Codec::AV1, &header), &header[2..]);
+    }
+
+    #[test]
+    fn keyframe_metadata_test() {
+        let sps = [0x00, 0x00, 0x00, 0x01, 0x42, 0x01, 0x01];
+        let sei = [0x00, 0x00, 0x00, 0x01, 0x4e, 0x01, 0x89, 0x01, 0x00, 0x80];
+        let idr = [0x00, 0x00, 0x00, 0x01, 0x26, 0x01, 0xaf];
+        let p = [0x00, 0x00, 0x00, 0x01, 0x02, 0x01, 0xd0];
+        let header = [&sps[..], &sei].concat();
+        assert_eq!(
+            ParameterSets::from_annex_b(Codec::H265, &header).keyframe_metadata(Codec::H265),
+            sei
+        );
+        assert!(ParameterSets::from_annex_b(Codec::H265, &sps)
+            .keyframe_metadata(Codec::H265)
+            .is_empty());
+
+        let mut muxer = AnnexBMuxer::new(Vec::new(), Codec::H265, &header).unwrap();
+        for (i, (data, picture_type)) in [
+            (&idr, PictureType::Idr),
+            (&p, PictureType::P),
+            (&idr, PictureType::Idr),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let time = Duration::from_millis(i as u64 * 10);
+            muxer
+                .write_frame(&EncodedFrame {
+                    data,
+                    picture_type,
+                    pts: time,
+                    dts: time,
+                })
+                .unwrap();
+        }
+        muxer.finish().unwrap();
+        // the parameter sets already carry the metadata of the first keyframe
+        assert_eq!(muxer.writer, [&header[..], &idr, &p, &sei, &idr].concat());
+    }
+}
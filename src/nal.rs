@@ -0,0 +1,1416 @@
+//! Reads back the Annex-B streams written by the encoder: NAL units, H.264/H.265 parameter sets
+//! and slice headers. Debug builds use it to check every encoded access unit against what was
+//! requested from the driver.
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::dpb::PictureType;
+use crate::muxer::{nal_to_rbsp, split_annex_b};
+use crate::settings::Codec;
+
+const H264_NAL_UNIT_TYPE_NON_IDR: u8 = 1;
+const H264_NAL_UNIT_TYPE_IDR: u8 = 5;
+const H264_NAL_UNIT_TYPE_SPS: u8 = 7;
+const H264_NAL_UNIT_TYPE_PPS: u8 = 8;
+const H265_NAL_UNIT_TYPE_BLA_W_LP: u8 = 16;
+const H265_NAL_UNIT_TYPE_IDR_W_RADL: u8 = 19;
+const H265_NAL_UNIT_TYPE_IDR_N_LP: u8 = 20;
+const H265_NAL_UNIT_TYPE_RSV_IRAP_23: u8 = 23;
+const H265_NAL_UNIT_TYPE_VPS: u8 = 32;
+const H265_NAL_UNIT_TYPE_SPS: u8 = 33;
+const H265_NAL_UNIT_TYPE_PPS: u8 = 34;
+
+const H264_HIGH_PROFILE_IDCS: [u8; 13] =
+    [100, 110, 122, 244, 44, 83, 86, 118, 128, 138, 139, 134, 135];
+const EXTENDED_SAR: u32 = 255;
+
+/// Bit reader over a RBSP, i.e. a NAL unit payload without emulation prevention bytes
+struct RbspReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> RbspReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn u(&mut self, bits: u32) -> anyhow::Result<u32> {
+        let mut rtn = 0u32;
+        for _ in 0..bits {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| anyhow!("unexpected end of RBSP"))?;
+            rtn = (rtn << 1) | ((byte >> (7 - self.position % 8)) & 1) as u32;
+            self.position += 1;
+        }
+        Ok(rtn)
+    }
+
+    fn flag(&mut self) -> anyhow::Result<bool> {
+        Ok(self.u(1)? == 1)
+    }
+
+    fn skip(&mut self, bits: u32) -> anyhow::Result<()> {
+        if self.position + bits as usize > self.data.len() * 8 {
+            bail!("unexpected end of RBSP");
+        }
+        self.position += bits as usize;
+        Ok(())
+    }
+
+    fn ue(&mut self) -> anyhow::Result<u32> {
+        let mut leading_zeros = 0;
+        while !self.flag()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                bail!("exp-Golomb code exceeds 32 bits");
+            }
+        }
+        Ok(((1u64 << leading_zeros) - 1 + self.u(leading_zeros)? as u64) as u32)
+    }
+
+    fn se(&mut self) -> anyhow::Result<i32> {
+        let k = self.ue()? as i64;
+        Ok(if k % 2 == 1 { (k + 1) / 2 } else { -(k / 2) } as i32)
+    }
+
+    /// Whether there is data left before the RBSP stop bit
+    fn more_rbsp_data(&self) -> bool {
+        let Some(last) = self.data.iter().rposition(|byte| *byte != 0) else {
+            return false;
+        };
+        let stop_bit = last * 8 + 7 - self.data[last].trailing_zeros() as usize;
+        self.position < stop_bit
+    }
+}
+
+/// Returns the payload of a RBSP after its `header_len` byte NAL unit header
+fn rbsp_payload(rbsp: &[u8], header_len: usize) -> anyhow::Result<&[u8]> {
+    match rbsp.get(header_len..) {
+        Some(payload) => Ok(payload),
+        None => bail!("truncated NAL unit header"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceType {
+    P,
+    B,
+    I,
+    Sp,
+    Si,
+}
+
+impl SliceType {
+    fn from_h264(slice_type: u32) -> Self {
+        // values 5-9 additionally signal that all slices of the picture have the same type
+        match slice_type % 5 {
+            0 => Self::P,
+            1 => Self::B,
+            2 => Self::I,
+            3 => Self::Sp,
+            _ => Self::Si,
+        }
+    }
+
+    fn from_h265(slice_type: u32) -> anyhow::Result<Self> {
+        Ok(match slice_type {
+            0 => Self::B,
+            1 => Self::P,
+            2 => Self::I,
+            _ => bail!("invalid H.265 slice_type {slice_type}"),
+        })
+    }
+
+    fn matches(self, picture_type: PictureType) -> bool {
+        matches!(
+            (self, picture_type),
+            (Self::I, PictureType::Idr | PictureType::I)
+                | (Self::P, PictureType::P)
+                | (Self::B, PictureType::B)
+        )
+    }
+}
+
+/// Video usability information shared by H.264 and H.265
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vui {
+    pub aspect_ratio_idc: Option<u32>,
+    pub video_full_range_flag: bool,
+    /// colour_primaries, transfer_characteristics and matrix_coefficients
+    pub colour_description: Option<(u8, u8, u8)>,
+    pub chroma_sample_loc_type: Option<(u32, u32)>,
+    /// num_units_in_tick and time_scale
+    pub timing_info: Option<(u32, u32)>,
+    pub max_num_reorder_frames: Option<u32>,
+    pub max_dec_frame_buffering: Option<u32>,
+}
+
+impl Vui {
+    /// Parses the part up to the chroma location, which is identical for both codecs
+    fn parse_common(reader: &mut RbspReader) -> anyhow::Result<Self> {
+        let mut vui = Self::default();
+        if reader.flag()? {
+            let aspect_ratio_idc = reader.u(8)?;
+            if aspect_ratio_idc == EXTENDED_SAR {
+                reader.skip(32)?; // sar_width, sar_height
+            }
+            vui.aspect_ratio_idc = Some(aspect_ratio_idc);
+        }
+        if reader.flag()? {
+            reader.skip(1)?; // overscan_appropriate_flag
+        }
+        if reader.flag()? {
+            reader.skip(3)?; // video_format
+            vui.video_full_range_flag = reader.flag()?;
+            if reader.flag()? {
+                vui.colour_description =
+                    Some((reader.u(8)? as u8, reader.u(8)? as u8, reader.u(8)? as u8));
+            }
+        }
+        if reader.flag()? {
+            vui.chroma_sample_loc_type = Some((reader.ue()?, reader.ue()?));
+        }
+        Ok(vui)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H264Sps {
+    pub profile_idc: u8,
+    pub constraint_set_flags: u8,
+    pub level_idc: u8,
+    pub seq_parameter_set_id: u32,
+    pub chroma_format_idc: u32,
+    pub separate_colour_plane_flag: bool,
+    pub bit_depth_luma: u32,
+    pub bit_depth_chroma: u32,
+    pub log2_max_frame_num: u32,
+    pub pic_order_cnt_type: u32,
+    pub log2_max_pic_order_cnt_lsb: u32,
+    pub delta_pic_order_always_zero_flag: bool,
+    pub max_num_ref_frames: u32,
+    pub pic_width_in_mbs: u32,
+    pub pic_height_in_map_units: u32,
+    pub frame_mbs_only_flag: bool,
+    /// left, right, top and bottom offset
+    pub frame_crop: [u32; 4],
+    pub vui: Option<Vui>,
+}
+
+impl H264Sps {
+    pub fn parse(nal: &[u8]) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 1)?);
+        let profile_idc = reader.u(8)? as u8;
+        let constraint_set_flags = reader.u(8)? as u8;
+        let level_idc = reader.u(8)? as u8;
+        let seq_parameter_set_id = reader.ue()?;
+        let mut chroma_format_idc = 1;
+        let mut separate_colour_plane_flag = false;
+        let mut bit_depth_luma = 8;
+        let mut bit_depth_chroma = 8;
+        if H264_HIGH_PROFILE_IDCS.contains(&profile_idc) {
+            chroma_format_idc = reader.ue()?;
+            if chroma_format_idc == 3 {
+                separate_colour_plane_flag = reader.flag()?;
+            }
+            bit_depth_luma = reader.ue()? + 8;
+            bit_depth_chroma = reader.ue()? + 8;
+            reader.skip(1)?; // qpprime_y_zero_transform_bypass_flag
+            if reader.flag()? {
+                let count = if chroma_format_idc == 3 { 12 } else { 8 };
+                skip_h264_scaling_lists(&mut reader, count)?;
+            }
+        }
+        let log2_max_frame_num = reader.ue()? + 4;
+        let pic_order_cnt_type = reader.ue()?;
+        let mut log2_max_pic_order_cnt_lsb = 0;
+        let mut delta_pic_order_always_zero_flag = false;
+        match pic_order_cnt_type {
+            0 => log2_max_pic_order_cnt_lsb = reader.ue()? + 4,
+            1 => {
+                delta_pic_order_always_zero_flag = reader.flag()?;
+                reader.se()?; // offset_for_non_ref_pic
+                reader.se()?; // offset_for_top_to_bottom_field
+                for _ in 0..reader.ue()? {
+                    reader.se()?; // offset_for_ref_frame
+                }
+            }
+            _ => {}
+        }
+        let max_num_ref_frames = reader.ue()?;
+        reader.skip(1)?; // gaps_in_frame_num_value_allowed_flag
+        let pic_width_in_mbs = reader.ue()? + 1;
+        let pic_height_in_map_units = reader.ue()? + 1;
+        let frame_mbs_only_flag = reader.flag()?;
+        if !frame_mbs_only_flag {
+            reader.skip(1)?; // mb_adaptive_frame_field_flag
+        }
+        reader.skip(1)?; // direct_8x8_inference_flag
+        let mut frame_crop = [0; 4];
+        if reader.flag()? {
+            for offset in frame_crop.iter_mut() {
+                *offset = reader.ue()?;
+            }
+        }
+        let vui = if reader.flag()? {
+            Some(parse_h264_vui(&mut reader)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            profile_idc,
+            constraint_set_flags,
+            level_idc,
+            seq_parameter_set_id,
+            chroma_format_idc,
+            separate_colour_plane_flag,
+            bit_depth_luma,
+            bit_depth_chroma,
+            log2_max_frame_num,
+            pic_order_cnt_type,
+            log2_max_pic_order_cnt_lsb,
+            delta_pic_order_always_zero_flag,
+            max_num_ref_frames,
+            pic_width_in_mbs,
+            pic_height_in_map_units,
+            frame_mbs_only_flag,
+            frame_crop,
+            vui,
+        })
+    }
+
+    #[cfg(test)]
+    fn crop_units(&self) -> (u32, u32) {
+        let field_factor = 2 - self.frame_mbs_only_flag as u32;
+        match (self.chroma_format_idc, self.separate_colour_plane_flag) {
+            (1, false) => (2, 2 * field_factor),
+            (2, false) => (2, field_factor),
+            _ => (1, field_factor),
+        }
+    }
+
+    /// Width in luma samples after cropping
+    #[cfg(test)]
+    pub fn width(&self) -> u32 {
+        let (crop_unit_x, _) = self.crop_units();
+        self.pic_width_in_mbs * 16 - crop_unit_x * (self.frame_crop[0] + self.frame_crop[1])
+    }
+
+    /// Height in luma samples after cropping
+    #[cfg(test)]
+    pub fn height(&self) -> u32 {
+        let (_, crop_unit_y) = self.crop_units();
+        let map_unit_height = 16 * (2 - self.frame_mbs_only_flag as u32);
+        self.pic_height_in_map_units * map_unit_height
+            - crop_unit_y * (self.frame_crop[2] + self.frame_crop[3])
+    }
+}
+
+fn skip_h264_scaling_lists(reader: &mut RbspReader, count: usize) -> anyhow::Result<()> {
+    for i in 0..count {
+        if !reader.flag()? {
+            continue;
+        }
+        let size = if i < 6 { 16 } else { 64 };
+        let mut next_scale = 8;
+        for _ in 0..size {
+            if next_scale == 0 {
+                break;
+            }
+            next_scale = (next_scale + reader.se()? + 256) % 256;
+        }
+    }
+    Ok(())
+}
+
+fn parse_h264_vui(reader: &mut RbspReader) -> anyhow::Result<Vui> {
+    let mut vui = Vui::parse_common(reader)?;
+    if reader.flag()? {
+        vui.timing_info = Some((reader.u(32)?, reader.u(32)?));
+        reader.skip(1)?; // fixed_frame_rate_flag
+    }
+    let nal_hrd = reader.flag()?;
+    if nal_hrd {
+        skip_h264_hrd(reader)?;
+    }
+    let vcl_hrd = reader.flag()?;
+    if vcl_hrd {
+        skip_h264_hrd(reader)?;
+    }
+    if nal_hrd || vcl_hrd {
+        reader.skip(1)?; // low_delay_hrd_flag
+    }
+    reader.skip(1)?; // pic_struct_present_flag
+    if reader.flag()? {
+        reader.skip(1)?; // motion_vectors_over_pic_boundaries_flag
+        for _ in 0..4 {
+            // max_bytes_per_pic_denom, max_bits_per_mb_denom and the maximum mv lengths
+            reader.ue()?;
+        }
+        vui.max_num_reorder_frames = Some(reader.ue()?);
+        vui.max_dec_frame_buffering = Some(reader.ue()?);
+    }
+    Ok(vui)
+}
+
+fn skip_h264_hrd(reader: &mut RbspReader) -> anyhow::Result<()> {
+    let cpb_cnt = reader.ue()? + 1;
+    reader.skip(8)?; // bit_rate_scale, cpb_size_scale
+    for _ in 0..cpb_cnt {
+        reader.ue()?;
+        reader.ue()?;
+        reader.skip(1)?;
+    }
+    reader.skip(20)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H264Pps {
+    pub pic_parameter_set_id: u32,
+    pub seq_parameter_set_id: u32,
+    pub entropy_coding_mode_flag: bool,
+    pub bottom_field_pic_order_in_frame_present_flag: bool,
+    pub num_ref_idx_l0_default_active: u32,
+    pub num_ref_idx_l1_default_active: u32,
+    pub weighted_pred_flag: bool,
+    pub weighted_bipred_idc: u32,
+    pub pic_init_qp: i32,
+    pub chroma_qp_index_offset: i32,
+    pub deblocking_filter_control_present_flag: bool,
+    pub constrained_intra_pred_flag: bool,
+    pub redundant_pic_cnt_present_flag: bool,
+    pub transform_8x8_mode_flag: bool,
+    pub second_chroma_qp_index_offset: i32,
+}
+
+impl H264Pps {
+    pub fn parse(nal: &[u8]) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 1)?);
+        let pic_parameter_set_id = reader.ue()?;
+        let seq_parameter_set_id = reader.ue()?;
+        let entropy_coding_mode_flag = reader.flag()?;
+        let bottom_field_pic_order_in_frame_present_flag = reader.flag()?;
+        if reader.ue()? != 0 {
+            bail!("slice groups are not supported");
+        }
+        let num_ref_idx_l0_default_active = reader.ue()? + 1;
+        let num_ref_idx_l1_default_active = reader.ue()? + 1;
+        let weighted_pred_flag = reader.flag()?;
+        let weighted_bipred_idc = reader.u(2)?;
+        let pic_init_qp = reader.se()? + 26;
+        reader.se()?; // pic_init_qs_minus26
+        let chroma_qp_index_offset = reader.se()?;
+        let deblocking_filter_control_present_flag = reader.flag()?;
+        let constrained_intra_pred_flag = reader.flag()?;
+        let redundant_pic_cnt_present_flag = reader.flag()?;
+        let mut transform_8x8_mode_flag = false;
+        let mut second_chroma_qp_index_offset = chroma_qp_index_offset;
+        if reader.more_rbsp_data() {
+            transform_8x8_mode_flag = reader.flag()?;
+            if reader.flag()? {
+                // the number of chroma lists depends on the SPS, assume 4:2:0
+                skip_h264_scaling_lists(&mut reader, 6 + 2 * transform_8x8_mode_flag as usize)?;
+            }
+            second_chroma_qp_index_offset = reader.se()?;
+        }
+        Ok(Self {
+            pic_parameter_set_id,
+            seq_parameter_set_id,
+            entropy_coding_mode_flag,
+            bottom_field_pic_order_in_frame_present_flag,
+            num_ref_idx_l0_default_active,
+            num_ref_idx_l1_default_active,
+            weighted_pred_flag,
+            weighted_bipred_idc,
+            pic_init_qp,
+            chroma_qp_index_offset,
+            deblocking_filter_control_present_flag,
+            constrained_intra_pred_flag,
+            redundant_pic_cnt_present_flag,
+            transform_8x8_mode_flag,
+            second_chroma_qp_index_offset,
+        })
+    }
+}
+
+/// The start of a H.264 slice header, up to the picture order count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H264SliceHeader {
+    pub nal_unit_type: u8,
+    pub nal_ref_idc: u8,
+    pub first_mb_in_slice: u32,
+    pub slice_type: SliceType,
+    pub pic_parameter_set_id: u32,
+    pub frame_num: u32,
+    pub idr_pic_id: Option<u32>,
+    pub pic_order_cnt_lsb: Option<u32>,
+}
+
+impl H264SliceHeader {
+    pub fn parse(
+        nal: &[u8],
+        sps: &HashMap<u32, H264Sps>,
+        pps: &HashMap<u32, H264Pps>,
+    ) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 1)?);
+        let nal_unit_type = rbsp[0] & 0x1f;
+        let nal_ref_idc = (rbsp[0] >> 5) & 0x3;
+        let first_mb_in_slice = reader.ue()?;
+        let slice_type = SliceType::from_h264(reader.ue()?);
+        let pic_parameter_set_id = reader.ue()?;
+        let pps = pps
+            .get(&pic_parameter_set_id)
+            .ok_or_else(|| anyhow!("slice references unknown PPS {pic_parameter_set_id}"))?;
+        let sps = sps
+            .get(&pps.seq_parameter_set_id)
+            .ok_or_else(|| anyhow!("PPS references unknown SPS {}", pps.seq_parameter_set_id))?;
+        if sps.separate_colour_plane_flag {
+            reader.skip(2)?; // colour_plane_id
+        }
+        let frame_num = reader.u(sps.log2_max_frame_num)?;
+        if !sps.frame_mbs_only_flag && reader.flag()? {
+            reader.skip(1)?; // bottom_field_flag
+        }
+        let idr_pic_id = if nal_unit_type == H264_NAL_UNIT_TYPE_IDR {
+            Some(reader.ue()?)
+        } else {
+            None
+        };
+        let pic_order_cnt_lsb = if sps.pic_order_cnt_type == 0 {
+            Some(reader.u(sps.log2_max_pic_order_cnt_lsb)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            nal_unit_type,
+            nal_ref_idc,
+            first_mb_in_slice,
+            slice_type,
+            pic_parameter_set_id,
+            frame_num,
+            idr_pic_id,
+            pic_order_cnt_lsb,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H265Vps {
+    pub vps_video_parameter_set_id: u32,
+    pub max_sub_layers_minus1: u32,
+    pub temporal_id_nesting_flag: bool,
+    pub profile_tier_level: H265ProfileTierLevel,
+}
+
+impl H265Vps {
+    pub fn parse(nal: &[u8]) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 2)?);
+        let vps_video_parameter_set_id = reader.u(4)?;
+        reader.skip(8)?; // base layer flags and vps_max_layers_minus1
+        let max_sub_layers_minus1 = reader.u(3)?;
+        let temporal_id_nesting_flag = reader.flag()?;
+        reader.skip(16)?; // vps_reserved_0xffff_16bits
+        let profile_tier_level = H265ProfileTierLevel::parse(&mut reader, max_sub_layers_minus1)?;
+        Ok(Self {
+            vps_video_parameter_set_id,
+            max_sub_layers_minus1,
+            temporal_id_nesting_flag,
+            profile_tier_level,
+        })
+    }
+}
+
+/// The general part of profile_tier_level, sub-layer information is skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H265ProfileTierLevel {
+    pub tier_flag: bool,
+    pub profile_idc: u32,
+    pub profile_compatibility_flags: u32,
+    pub level_idc: u32,
+}
+
+impl H265ProfileTierLevel {
+    fn parse(reader: &mut RbspReader, max_sub_layers_minus1: u32) -> anyhow::Result<Self> {
+        reader.skip(2)?; // general_profile_space
+        let tier_flag = reader.flag()?;
+        let profile_idc = reader.u(5)?;
+        let profile_compatibility_flags = reader.u(32)?;
+        reader.skip(48)?; // source and constraint flags
+        let level_idc = reader.u(8)?;
+        let mut sub_layers = Vec::new();
+        for _ in 0..max_sub_layers_minus1 {
+            sub_layers.push((reader.flag()?, reader.flag()?));
+        }
+        if max_sub_layers_minus1 > 0 {
+            reader.skip(2 * (8 - max_sub_layers_minus1))?;
+        }
+        for (profile_present, level_present) in sub_layers {
+            if profile_present {
+                reader.skip(88)?;
+            }
+            if level_present {
+                reader.skip(8)?;
+            }
+        }
+        Ok(Self {
+            tier_flag,
+            profile_idc,
+            profile_compatibility_flags,
+            level_idc,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H265Sps {
+    pub sps_video_parameter_set_id: u32,
+    pub max_sub_layers_minus1: u32,
+    pub profile_tier_level: H265ProfileTierLevel,
+    pub sps_seq_parameter_set_id: u32,
+    pub chroma_format_idc: u32,
+    pub separate_colour_plane_flag: bool,
+    pub pic_width_in_luma_samples: u32,
+    pub pic_height_in_luma_samples: u32,
+    /// left, right, top and bottom offset in chroma samples
+    pub conformance_window: [u32; 4],
+    pub bit_depth_luma: u32,
+    pub bit_depth_chroma: u32,
+    pub log2_max_pic_order_cnt_lsb: u32,
+    /// max_dec_pic_buffering_minus1 of the highest sub-layer
+    pub max_dec_pic_buffering_minus1: u32,
+    /// max_num_reorder_pics of the highest sub-layer
+    pub max_num_reorder_pics: u32,
+    pub log2_min_luma_coding_block_size: u32,
+    pub log2_ctb_size: u32,
+    pub num_short_term_ref_pic_sets: u32,
+    pub vui: Option<Vui>,
+}
+
+impl H265Sps {
+    pub fn parse(nal: &[u8]) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 2)?);
+        let sps_video_parameter_set_id = reader.u(4)?;
+        let max_sub_layers_minus1 = reader.u(3)?;
+        reader.skip(1)?; // sps_temporal_id_nesting_flag
+        let profile_tier_level = H265ProfileTierLevel::parse(&mut reader, max_sub_layers_minus1)?;
+        let sps_seq_parameter_set_id = reader.ue()?;
+        let chroma_format_idc = reader.ue()?;
+        let separate_colour_plane_flag = chroma_format_idc == 3 && reader.flag()?;
+        let pic_width_in_luma_samples = reader.ue()?;
+        let pic_height_in_luma_samples = reader.ue()?;
+        let mut conformance_window = [0; 4];
+        if reader.flag()? {
+            for offset in conformance_window.iter_mut() {
+                *offset = reader.ue()?;
+            }
+        }
+        let bit_depth_luma = reader.ue()? + 8;
+        let bit_depth_chroma = reader.ue()? + 8;
+        let log2_max_pic_order_cnt_lsb = reader.ue()? + 4;
+        let sub_layer_ordering_info_present = reader.flag()?;
+        let mut max_dec_pic_buffering_minus1 = 0;
+        let mut max_num_reorder_pics = 0;
+        let first = if sub_layer_ordering_info_present {
+            0
+        } else {
+            max_sub_layers_minus1
+        };
+        for _ in first..=max_sub_layers_minus1 {
+            max_dec_pic_buffering_minus1 = reader.ue()?;
+            max_num_reorder_pics = reader.ue()?;
+            reader.ue()?; // max_latency_increase_plus1
+        }
+        let log2_min_luma_coding_block_size = reader.ue()? + 3;
+        let log2_ctb_size = log2_min_luma_coding_block_size + reader.ue()?;
+        for _ in 0..4 {
+            // transform block sizes and hierarchy depths
+            reader.ue()?;
+        }
+        if reader.flag()? && reader.flag()? {
+            skip_h265_scaling_list_data(&mut reader)?;
+        }
+        reader.skip(2)?; // amp_enabled_flag, sample_adaptive_offset_enabled_flag
+        if reader.flag()? {
+            reader.skip(8)?; // pcm sample bit depths
+            reader.ue()?;
+            reader.ue()?;
+            reader.skip(1)?; // pcm_loop_filter_disabled_flag
+        }
+        let num_short_term_ref_pic_sets = reader.ue()?;
+        let mut num_delta_pocs = Vec::with_capacity(num_short_term_ref_pic_sets as usize);
+        for idx in 0..num_short_term_ref_pic_sets as usize {
+            let count = parse_h265_st_ref_pic_set(&mut reader, idx, &num_delta_pocs)?;
+            num_delta_pocs.push(count);
+        }
+        if reader.flag()? {
+            for _ in 0..reader.ue()? {
+                reader.skip(log2_max_pic_order_cnt_lsb + 1)?;
+            }
+        }
+        reader.skip(2)?; // sps_temporal_mvp_enabled_flag, strong_intra_smoothing_enabled_flag
+        let vui = if reader.flag()? {
+            Some(parse_h265_vui(&mut reader, max_sub_layers_minus1)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            sps_video_parameter_set_id,
+            max_sub_layers_minus1,
+            profile_tier_level,
+            sps_seq_parameter_set_id,
+            chroma_format_idc,
+            separate_colour_plane_flag,
+            pic_width_in_luma_samples,
+            pic_height_in_luma_samples,
+            conformance_window,
+            bit_depth_luma,
+            bit_depth_chroma,
+            log2_max_pic_order_cnt_lsb,
+            max_dec_pic_buffering_minus1,
+            max_num_reorder_pics,
+            log2_min_luma_coding_block_size,
+            log2_ctb_size,
+            num_short_term_ref_pic_sets,
+            vui,
+        })
+    }
+
+    #[cfg(test)]
+    fn sub_sampling(&self) -> (u32, u32) {
+        match (self.chroma_format_idc, self.separate_colour_plane_flag) {
+            (1, false) => (2, 2),
+            (2, false) => (2, 1),
+            _ => (1, 1),
+        }
+    }
+
+    /// Width in luma samples after cropping to the conformance window
+    #[cfg(test)]
+    pub fn width(&self) -> u32 {
+        let (sub_width, _) = self.sub_sampling();
+        self.pic_width_in_luma_samples
+            - sub_width * (self.conformance_window[0] + self.conformance_window[1])
+    }
+
+    /// Height in luma samples after cropping to the conformance window
+    #[cfg(test)]
+    pub fn height(&self) -> u32 {
+        let (_, sub_height) = self.sub_sampling();
+        self.pic_height_in_luma_samples
+            - sub_height * (self.conformance_window[2] + self.conformance_window[3])
+    }
+
+    fn pic_size_in_ctbs(&self) -> u32 {
+        let ctb_size = 1 << self.log2_ctb_size;
+        self.pic_width_in_luma_samples.div_ceil(ctb_size)
+            * self.pic_height_in_luma_samples.div_ceil(ctb_size)
+    }
+}
+
+fn skip_h265_scaling_list_data(reader: &mut RbspReader) -> anyhow::Result<()> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for _ in (0..6).step_by(step) {
+            if !reader.flag()? {
+                reader.ue()?; // scaling_list_pred_matrix_id_delta
+                continue;
+            }
+            if size_id > 1 {
+                reader.se()?; // scaling_list_dc_coef_minus8
+            }
+            for _ in 0..64.min(1 << (4 + (size_id << 1))) {
+                reader.se()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Skips a short term reference picture set of the SPS and returns its number of pictures
+fn parse_h265_st_ref_pic_set(
+    reader: &mut RbspReader,
+    idx: usize,
+    num_delta_pocs: &[u32],
+) -> anyhow::Result<u32> {
+    if idx != 0 && reader.flag()? {
+        // inter_ref_pic_set_prediction_flag, predicted from the previous set
+        reader.skip(1)?; // delta_rps_sign
+        reader.ue()?; // abs_delta_rps_minus1
+        let mut count = 0;
+        for _ in 0..=num_delta_pocs[idx - 1] {
+            let used_by_curr_pic = reader.flag()?;
+            if used_by_curr_pic || reader.flag()? {
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+    let num_negative_pics = reader.ue()?;
+    let num_positive_pics = reader.ue()?;
+    for _ in 0..num_negative_pics + num_positive_pics {
+        reader.ue()?;
+        reader.skip(1)?;
+    }
+    Ok(num_negative_pics + num_positive_pics)
+}
+
+fn parse_h265_vui(reader: &mut RbspReader, max_sub_layers_minus1: u32) -> anyhow::Result<Vui> {
+    let mut vui = Vui::parse_common(reader)?;
+    // neutral_chroma_indication_flag, field_seq_flag, frame_field_info_present_flag
+    reader.skip(3)?;
+    if reader.flag()? {
+        for _ in 0..4 {
+            reader.ue()?; // default display window
+        }
+    }
+    if reader.flag()? {
+        vui.timing_info = Some((reader.u(32)?, reader.u(32)?));
+        if reader.flag()? {
+            reader.ue()?; // vui_num_ticks_poc_diff_one_minus1
+        }
+        if reader.flag()? {
+            skip_h265_hrd(reader, max_sub_layers_minus1)?;
+        }
+    }
+    if reader.flag()? {
+        reader.skip(3)?;
+        for _ in 0..5 {
+            reader.ue()?;
+        }
+    }
+    Ok(vui)
+}
+
+fn skip_h265_hrd(reader: &mut RbspReader, max_sub_layers_minus1: u32) -> anyhow::Result<()> {
+    let nal_hrd = reader.flag()?;
+    let vcl_hrd = reader.flag()?;
+    let mut sub_pic_hrd = false;
+    if nal_hrd || vcl_hrd {
+        sub_pic_hrd = reader.flag()?;
+        if sub_pic_hrd {
+            reader.skip(19)?;
+        }
+        reader.skip(8)?; // bit_rate_scale, cpb_size_scale
+        if sub_pic_hrd {
+            reader.skip(4)?; // cpb_size_du_scale
+        }
+        reader.skip(15)?;
+    }
+    for _ in 0..=max_sub_layers_minus1 {
+        let fixed_pic_rate_within_cvs = reader.flag()? || reader.flag()?;
+        let low_delay_hrd = if fixed_pic_rate_within_cvs {
+            reader.ue()?; // elemental_duration_in_tc_minus1
+            false
+        } else {
+            reader.flag()?
+        };
+        let cpb_cnt = if low_delay_hrd { 1 } else { reader.ue()? + 1 };
+        for _ in 0..(nal_hrd as u32 + vcl_hrd as u32) * cpb_cnt {
+            reader.ue()?;
+            reader.ue()?;
+            if sub_pic_hrd {
+                reader.ue()?;
+                reader.ue()?;
+            }
+            reader.skip(1)?; // cbr_flag
+        }
+    }
+    Ok(())
+}
+
+/// The start of a H.265 PPS, up to the fields needed for slice headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H265Pps {
+    pub pps_pic_parameter_set_id: u32,
+    pub pps_seq_parameter_set_id: u32,
+    pub dependent_slice_segments_enabled_flag: bool,
+    pub output_flag_present_flag: bool,
+    pub num_extra_slice_header_bits: u32,
+    pub sign_data_hiding_enabled_flag: bool,
+    pub cabac_init_present_flag: bool,
+    pub num_ref_idx_l0_default_active: u32,
+    pub num_ref_idx_l1_default_active: u32,
+    pub init_qp: i32,
+}
+
+impl H265Pps {
+    pub fn parse(nal: &[u8]) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 2)?);
+        Ok(Self {
+            pps_pic_parameter_set_id: reader.ue()?,
+            pps_seq_parameter_set_id: reader.ue()?,
+            dependent_slice_segments_enabled_flag: reader.flag()?,
+            output_flag_present_flag: reader.flag()?,
+            num_extra_slice_header_bits: reader.u(3)?,
+            sign_data_hiding_enabled_flag: reader.flag()?,
+            cabac_init_present_flag: reader.flag()?,
+            num_ref_idx_l0_default_active: reader.ue()? + 1,
+            num_ref_idx_l1_default_active: reader.ue()? + 1,
+            init_qp: reader.se()? + 26,
+        })
+    }
+}
+
+/// The start of a H.265 slice segment header, up to the picture order count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H265SliceHeader {
+    pub nal_unit_type: u8,
+    pub temporal_id: u8,
+    pub first_slice_segment_in_pic_flag: bool,
+    pub slice_pic_parameter_set_id: u32,
+    pub slice_segment_address: u32,
+    /// `None` for dependent slice segments, they share the header of the previous segment
+    pub slice_type: Option<SliceType>,
+    /// `None` for IDR pictures, their POC is 0
+    pub pic_order_cnt_lsb: Option<u32>,
+}
+
+impl H265SliceHeader {
+    pub fn parse(
+        nal: &[u8],
+        sps: &HashMap<u32, H265Sps>,
+        pps: &HashMap<u32, H265Pps>,
+    ) -> anyhow::Result<Self> {
+        let rbsp = nal_to_rbsp(nal);
+        let mut reader = RbspReader::new(rbsp_payload(&rbsp, 2)?);
+        let nal_unit_type = (rbsp[0] >> 1) & 0x3f;
+        let temporal_id = (rbsp[1] & 0x7).saturating_sub(1);
+        let first_slice_segment_in_pic_flag = reader.flag()?;
+        if (H265_NAL_UNIT_TYPE_BLA_W_LP..=H265_NAL_UNIT_TYPE_RSV_IRAP_23).contains(&nal_unit_type) {
+            reader.skip(1)?; // no_output_of_prior_pics_flag
+        }
+        let slice_pic_parameter_set_id = reader.ue()?;
+        let pps = pps
+            .get(&slice_pic_parameter_set_id)
+            .ok_or_else(|| anyhow!("slice references unknown PPS {slice_pic_parameter_set_id}"))?;
+        let sps = sps.get(&pps.pps_seq_parameter_set_id).ok_or_else(|| {
+            anyhow!(
+                "PPS references unknown SPS {}",
+                pps.pps_seq_parameter_set_id
+            )
+        })?;
+        let mut dependent_slice_segment_flag = false;
+        let mut slice_segment_address = 0;
+        if !first_slice_segment_in_pic_flag {
+            if pps.dependent_slice_segments_enabled_flag {
+                dependent_slice_segment_flag = reader.flag()?;
+            }
+            let bits = u32::BITS - (sps.pic_size_in_ctbs() - 1).leading_zeros();
+            slice_segment_address = reader.u(bits)?;
+        }
+        let mut slice_type = None;
+        let mut pic_order_cnt_lsb = None;
+        if !dependent_slice_segment_flag {
+            reader.skip(pps.num_extra_slice_header_bits)?;
+            slice_type = Some(SliceType::from_h265(reader.ue()?)?);
+            if pps.output_flag_present_flag {
+                reader.skip(1)?; // pic_output_flag
+            }
+            if sps.separate_colour_plane_flag {
+                reader.skip(2)?; // colour_plane_id
+            }
+            if !is_h265_idr(nal_unit_type) {
+                pic_order_cnt_lsb = Some(reader.u(sps.log2_max_pic_order_cnt_lsb)?);
+            }
+        }
+        Ok(Self {
+            nal_unit_type,
+            temporal_id,
+            first_slice_segment_in_pic_flag,
+            slice_pic_parameter_set_id,
+            slice_segment_address,
+            slice_type,
+            pic_order_cnt_lsb,
+        })
+    }
+}
+
+fn is_h265_idr(nal_unit_type: u8) -> bool {
+    matches!(
+        nal_unit_type,
+        H265_NAL_UNIT_TYPE_IDR_W_RADL | H265_NAL_UNIT_TYPE_IDR_N_LP
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nal {
+    H264Sps(H264Sps),
+    H264Pps(H264Pps),
+    H264Slice(H264SliceHeader),
+    H265Vps(H265Vps),
+    H265Sps(H265Sps),
+    H265Pps(H265Pps),
+    H265Slice(H265SliceHeader),
+    /// Any other NAL unit, e.g. SEI or access unit delimiters
+    Other {
+        nal_unit_type: u8,
+    },
+}
+
+/// Parses NAL units while keeping track of the parameter sets slices refer to.
+#[derive(Default)]
+pub struct StreamParser {
+    h264_sps: HashMap<u32, H264Sps>,
+    h264_pps: HashMap<u32, H264Pps>,
+    h265_sps: HashMap<u32, H265Sps>,
+    h265_pps: HashMap<u32, H265Pps>,
+}
+
+impl StreamParser {
+    pub fn parse_nal(&mut self, codec: Codec, nal: &[u8]) -> anyhow::Result<Nal> {
+        let Some(header) = nal.first() else {
+            bail!("empty NAL unit");
+        };
+        Ok(match codec {
+            Codec::H264 => match header & 0x1f {
+                H264_NAL_UNIT_TYPE_SPS => {
+                    let sps = H264Sps::parse(nal).context("failed to parse SPS")?;
+                    self.h264_sps.insert(sps.seq_parameter_set_id, sps.clone());
+                    Nal::H264Sps(sps)
+                }
+                H264_NAL_UNIT_TYPE_PPS => {
+                    let pps = H264Pps::parse(nal).context("failed to parse PPS")?;
+                    self.h264_pps.insert(pps.pic_parameter_set_id, pps.clone());
+                    Nal::H264Pps(pps)
+                }
+                H264_NAL_UNIT_TYPE_NON_IDR | H264_NAL_UNIT_TYPE_IDR => Nal::H264Slice(
+                    H264SliceHeader::parse(nal, &self.h264_sps, &self.h264_pps)
+                        .context("failed to parse slice header")?,
+                ),
+                nal_unit_type => Nal::Other { nal_unit_type },
+            },
+            Codec::H265 => match (header >> 1) & 0x3f {
+                H265_NAL_UNIT_TYPE_VPS => {
+                    Nal::H265Vps(H265Vps::parse(nal).context("failed to parse VPS")?)
+                }
+                H265_NAL_UNIT_TYPE_SPS => {
+                    let sps = H265Sps::parse(nal).context("failed to parse SPS")?;
+                    self.h265_sps
+                        .insert(sps.sps_seq_parameter_set_id, sps.clone());
+                    Nal::H265Sps(sps)
+                }
+                H265_NAL_UNIT_TYPE_PPS => {
+                    let pps = H265Pps::parse(nal).context("failed to parse PPS")?;
+                    self.h265_pps
+                        .insert(pps.pps_pic_parameter_set_id, pps.clone());
+                    Nal::H265Pps(pps)
+                }
+                // VCL NAL units
+                0..=H265_NAL_UNIT_TYPE_RSV_IRAP_23 => Nal::H265Slice(
+                    H265SliceHeader::parse(nal, &self.h265_sps, &self.h265_pps)
+                        .context("failed to parse slice segment header")?,
+                ),
+                nal_unit_type => Nal::Other { nal_unit_type },
+            },
+            Codec::AV1 => bail!("AV1 streams consist of OBUs, not NAL units"),
+        })
+    }
+
+    /// Parses all NAL units of an Annex-B byte stream
+    pub fn parse_annex_b(&mut self, codec: Codec, data: &[u8]) -> anyhow::Result<Vec<Nal>> {
+        split_annex_b(data)
+            .map(|nal| self.parse_nal(codec, nal))
+            .collect()
+    }
+
+    fn h264_sps_of(&self, header: &H264SliceHeader) -> Option<&H264Sps> {
+        let pps = self.h264_pps.get(&header.pic_parameter_set_id)?;
+        self.h264_sps.get(&pps.seq_parameter_set_id)
+    }
+
+    fn h265_sps_of(&self, header: &H265SliceHeader) -> Option<&H265Sps> {
+        let pps = self.h265_pps.get(&header.slice_pic_parameter_set_id)?;
+        self.h265_sps.get(&pps.pps_seq_parameter_set_id)
+    }
+}
+
+/// Checks encoded access units against the picture type, POC and frame_num they were encoded
+/// with, and that frame_num only advances after reference pictures.
+pub struct StreamValidator {
+    codec: Codec,
+    parser: StreamParser,
+    /// frame_num and nal_ref_idc of the previous H.264 picture
+    previous_h264_picture: Option<(u32, u8)>,
+}
+
+impl StreamValidator {
+    /// `parameter_sets` is the Annex-B header written before the first frame
+    pub fn new(codec: Codec, parameter_sets: &[u8]) -> anyhow::Result<Self> {
+        if codec == Codec::AV1 {
+            bail!("only H.264 and H.265 streams can be validated");
+        }
+        let mut parser = StreamParser::default();
+        parser.parse_annex_b(codec, parameter_sets)?;
+        Ok(Self {
+            codec,
+            parser,
+            previous_h264_picture: None,
+        })
+    }
+
+    pub fn check_access_unit(
+        &mut self,
+        data: &[u8],
+        picture_type: PictureType,
+        poc: u32,
+        frame_num: u32,
+    ) -> anyhow::Result<()> {
+        let nals = self.parser.parse_annex_b(self.codec, data)?;
+        let mut slices = 0;
+        for nal in nals {
+            match nal {
+                Nal::H264Slice(header) => {
+                    self.check_h264_slice(&header, picture_type, poc, frame_num)?;
+                    slices += 1;
+                }
+                Nal::H265Slice(header) => {
+                    self.check_h265_slice(&header, picture_type, poc)?;
+                    slices += 1;
+                }
+                _ => {}
+            }
+        }
+        if slices == 0 {
+            bail!("access unit contains no slices");
+        }
+        Ok(())
+    }
+
+    fn check_h264_slice(
+        &mut self,
+        header: &H264SliceHeader,
+        picture_type: PictureType,
+        poc: u32,
+        frame_num: u32,
+    ) -> anyhow::Result<()> {
+        let sps = self
+            .parser
+            .h264_sps_of(header)
+            .ok_or_else(|| anyhow!("slice without SPS"))?;
+        let is_idr = header.nal_unit_type == H264_NAL_UNIT_TYPE_IDR;
+        if !header.slice_type.matches(picture_type) || is_idr != picture_type.is_idr() {
+            bail!(
+                "requested {picture_type:?} but got a {:?} slice in NAL unit type {}",
+                header.slice_type,
+                header.nal_unit_type
+            );
+        }
+        let max_frame_num = 1 << sps.log2_max_frame_num;
+        if header.frame_num != frame_num % max_frame_num {
+            bail!(
+                "frame_num {} does not match the requested {frame_num}",
+                header.frame_num
+            );
+        }
+        if let Some(lsb) = header.pic_order_cnt_lsb {
+            // a frame covers two fields, the POC advances by 2 per frame
+            let expected = (2 * poc) % (1 << sps.log2_max_pic_order_cnt_lsb);
+            if lsb != expected {
+                bail!("pic_order_cnt_lsb {lsb} does not match the requested POC {poc}");
+            }
+        }
+        if header.first_mb_in_slice == 0 {
+            if let (false, Some((previous, previous_ref_idc))) =
+                (is_idr, self.previous_h264_picture)
+            {
+                let expected = if previous_ref_idc != 0 {
+                    (previous + 1) % max_frame_num
+                } else {
+                    previous
+                };
+                if header.frame_num != expected {
+                    bail!(
+                        "frame_num jumped from {previous} to {} (expected {expected})",
+                        header.frame_num
+                    );
+                }
+            }
+            self.previous_h264_picture = Some((header.frame_num, header.nal_ref_idc));
+        }
+        Ok(())
+    }
+
+    fn check_h265_slice(
+        &self,
+        header: &H265SliceHeader,
+        picture_type: PictureType,
+        poc: u32,
+    ) -> anyhow::Result<()> {
+        let sps = self
+            .parser
+            .h265_sps_of(header)
+            .ok_or_else(|| anyhow!("slice without SPS"))?;
+        if is_h265_idr(header.nal_unit_type) != picture_type.is_idr() {
+            bail!(
+                "requested {picture_type:?} but got NAL unit type {}",
+                header.nal_unit_type
+            );
+        }
+        if let Some(slice_type) = header.slice_type {
+            if !slice_type.matches(picture_type) {
+                bail!("requested {picture_type:?} but got a {slice_type:?} slice");
+            }
+        }
+        let expected = if picture_type.is_idr() {
+            None
+        } else {
+            Some(poc % (1 << sps.log2_max_pic_order_cnt_lsb))
+        };
+        if header.slice_type.is_some() && header.pic_order_cnt_lsb != expected {
+            bail!(
+                "slice_pic_order_cnt_lsb {:?} does not match the requested POC {poc}",
+                header.pic_order_cnt_lsb
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitstream_io::{BigEndian, BitWriter};
+
+    use super::*;
+    use crate::bitstream::{rbsp_trailing_bits, u, ue};
+
+    /// x264 1280x720 High@3.1 SPS and PPS, as written by the bitstream tests
+    const H264_PARAMETER_SETS: [u8; 41] = [
+        0x00, 0x00, 0x00, 0x01, 0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x50, 0x05, 0xbb, 0x01,
+        0x10, 0x00, 0x00, 0x03, 0x00, 0x10, 0x00, 0x00, 0x03, 0x03, 0xc0, 0xda, 0x08, 0x84, 0x59,
+        0x60, 0x00, 0x00, 0x00, 0x01, 0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0,
+    ];
+
+    /// x265 1280x720 Main@3.1 VPS, SPS and PPS, as written by the bitstream tests
+    const H265_PARAMETER_SETS: [u8; 84] = [
+        0x00, 0x00, 0x00, 0x01, 0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03,
+        0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0x95, 0x98, 0x09, 0x00, 0x00,
+        0x00, 0x01, 0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03,
+        0x00, 0x00, 0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93,
+        0x2b, 0xc0, 0x5a, 0x70, 0x80, 0x00, 0x01, 0xf4, 0x80, 0x00, 0x3a, 0x98, 0x04, 0x00, 0x00,
+        0x00, 0x01, 0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40,
+    ];
+
+    /// A decodable 16x16 Main profile CAVLC stream of an IBP GOP in decode order, as the layer
+    /// writes it: SPS and PPS, an I_PCM IDR picture, then P and non-reference B pictures whose
+    /// single macroblock is skipped. Display order is I0 B1 P2 B3 P4.
+    const H264_IPBPB_STREAM: &[u8] = include_bytes!("../testdata/ipbpb_16x16.h264");
+
+    /// Writes a NAL unit with the given header whose slice data is empty
+    fn slice_nal(
+        header: &[u8],
+        write: impl FnOnce(&mut BitWriter<&mut Vec<u8>, BigEndian>) -> std::io::Result<()>,
+    ) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 1];
+        data.extend_from_slice(header);
+        let mut writer = BitWriter::endian(&mut data, BigEndian);
+        write(&mut writer).unwrap();
+        rbsp_trailing_bits(&mut writer).unwrap();
+        data
+    }
+
+    fn h264_slice(nal_header: u8, slice_type: u64, frame_num: u32, poc_lsb: u32) -> Vec<u8> {
+        slice_nal(&[nal_header], |writer| {
+            ue(writer, 0)?; // first_mb_in_slice
+            ue(writer, slice_type)?;
+            ue(writer, 0)?; // pic_parameter_set_id
+            u(4, writer, frame_num)?;
+            if nal_header & 0x1f == H264_NAL_UNIT_TYPE_IDR {
+                ue(writer, 0)?; // idr_pic_id
+            }
+            u(6, writer, poc_lsb)
+        })
+    }
+
+    fn h265_slice(nal_unit_type: u8, slice_type: u64, poc_lsb: u32) -> Vec<u8> {
+        slice_nal(&[nal_unit_type << 1, 1], |writer| {
+            u(1, writer, 1)?; // first_slice_segment_in_pic_flag
+            if is_h265_idr(nal_unit_type) {
+                u(1, writer, 0)?; // no_output_of_prior_pics_flag
+            }
+            ue(writer, 0)?; // slice_pic_parameter_set_id
+            ue(writer, slice_type)?;
+            if !is_h265_idr(nal_unit_type) {
+                u(8, writer, poc_lsb)?;
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn h264_parameter_sets_test() {
+        let nals = StreamParser::default()
+            .parse_annex_b(Codec::H264, &H264_PARAMETER_SETS)
+            .unwrap();
+        let [Nal::H264Sps(sps), Nal::H264Pps(pps)] = nals.as_slice() else {
+            panic!("unexpected NAL units {nals:?}");
+        };
+        assert_eq!((sps.profile_idc, sps.level_idc), (100, 31));
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+        assert_eq!(sps.log2_max_frame_num, 4);
+        assert_eq!(sps.log2_max_pic_order_cnt_lsb, 6);
+        assert_eq!(sps.max_num_ref_frames, 4);
+        let vui = sps.vui.as_ref().unwrap();
+        assert_eq!(vui.timing_info, Some((1, 60)));
+        assert_eq!(vui.max_num_reorder_frames, Some(2));
+        assert_eq!(vui.max_dec_frame_buffering, Some(4));
+        assert!(pps.entropy_coding_mode_flag);
+        assert!(pps.transform_8x8_mode_flag);
+        assert_eq!(pps.chroma_qp_index_offset, -2);
+    }
+
+    #[test]
+    fn h265_parameter_sets_test() {
+        let nals = StreamParser::default()
+            .parse_annex_b(Codec::H265, &H265_PARAMETER_SETS)
+            .unwrap();
+        let [Nal::H265Vps(vps), Nal::H265Sps(sps), Nal::H265Pps(pps)] = nals.as_slice() else {
+            panic!("unexpected NAL units {nals:?}");
+        };
+        assert_eq!(vps.profile_tier_level.profile_idc, 1);
+        assert_eq!(sps.profile_tier_level.level_idc, 93);
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+        assert_eq!(sps.log2_ctb_size, 6);
+        assert_eq!(sps.log2_max_pic_order_cnt_lsb, 8);
+        let vui = sps.vui.as_ref().unwrap();
+        assert_eq!(vui.timing_info, Some((1001, 30000)));
+        assert_eq!(vui.chroma_sample_loc_type, Some((0, 0)));
+        assert_eq!(pps.pps_seq_parameter_set_id, 0);
+    }
+
+    #[test]
+    fn h264_validator_test() {
+        let mut validator = StreamValidator::new(Codec::H264, &H264_PARAMETER_SETS).unwrap();
+        let idr = h264_slice(0x65, 7, 0, 0);
+        validator
+            .check_access_unit(&idr, PictureType::Idr, 0, 0)
+            .unwrap();
+        let p = h264_slice(0x41, 5, 1, 4);
+        assert!(validator
+            .check_access_unit(&p, PictureType::B, 2, 1)
+            .is_err());
+        assert!(validator
+            .check_access_unit(&p, PictureType::P, 1, 1)
+            .is_err());
+        validator
+            .check_access_unit(&p, PictureType::P, 2, 1)
+            .unwrap();
+        // frame_num wraps at 16 but may only advance by one after a reference picture
+        let p = h264_slice(0x41, 5, 3, 8);
+        assert!(validator
+            .check_access_unit(&p, PictureType::P, 4, 3)
+            .is_err());
+    }
+
+    #[test]
+    fn h265_validator_test() {
+        let mut validator = StreamValidator::new(Codec::H265, &H265_PARAMETER_SETS).unwrap();
+        let idr = h265_slice(H265_NAL_UNIT_TYPE_IDR_W_RADL, 2, 0);
+        validator
+            .check_access_unit(&idr, PictureType::Idr, 0, 0)
+            .unwrap();
+        assert!(validator
+            .check_access_unit(&idr, PictureType::I, 0, 0)
+            .is_err());
+        // the POC is only signalled modulo 256
+        let b = h265_slice(1, 0, 1);
+        validator
+            .check_access_unit(&b, PictureType::B, 257, 1)
+            .unwrap();
+        assert!(validator
+            .check_access_unit(&b, PictureType::P, 257, 1)
+            .is_err());
+        assert!(validator
+            .check_access_unit(&b, PictureType::B, 2, 1)
+            .is_err());
+    }
+
+    /// (picture type, POC, frame_num) of the access units of [`H264_IPBPB_STREAM`]
+    const H264_IPBPB_PICTURES: [(PictureType, u32, u32); 5] = [
+        (PictureType::Idr, 0, 0),
+        (PictureType::P, 2, 1),
+        (PictureType::B, 1, 2),
+        (PictureType::P, 4, 2),
+        (PictureType::B, 3, 3),
+    ];
+
+    #[test]
+    fn h264_stream_test() {
+        let nals = StreamParser::default()
+            .parse_annex_b(Codec::H264, H264_IPBPB_STREAM)
+            .unwrap();
+        let [Nal::H264Sps(sps), Nal::H264Pps(pps), slices @ ..] = nals.as_slice() else {
+            panic!("unexpected NAL units {nals:?}");
+        };
+        assert_eq!((sps.profile_idc, sps.level_idc), (77, 10));
+        assert_eq!((sps.width(), sps.height()), (16, 16));
+        assert_eq!(sps.log2_max_pic_order_cnt_lsb, 6);
+        assert_eq!(sps.max_num_ref_frames, 2);
+        assert!(!pps.entropy_coding_mode_flag);
+        let slices: Vec<_> = slices
+            .iter()
+            .map(|nal| match nal {
+                Nal::H264Slice(header) => (
+                    header.nal_ref_idc,
+                    header.slice_type,
+                    header.frame_num,
+                    header.pic_order_cnt_lsb,
+                ),
+                nal => panic!("unexpected NAL unit {nal:?}"),
+            })
+            .collect();
+        assert_eq!(
+            slices,
+            [
+                (3, SliceType::I, 0, Some(0)),
+                (2, SliceType::P, 1, Some(4)),
+                (0, SliceType::B, 2, Some(2)),
+                (2, SliceType::P, 2, Some(8)),
+                (0, SliceType::B, 3, Some(6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn h264_stream_validator_test() {
+        let mut access_units =
+            split_annex_b(H264_IPBPB_STREAM).map(|nal| [&[0, 0, 0, 1], nal].concat());
+        let parameter_sets = [access_units.next().unwrap(), access_units.next().unwrap()].concat();
+        let mut validator = StreamValidator::new(Codec::H264, &parameter_sets).unwrap();
+        let access_units: Vec<_> = access_units.collect();
+        assert_eq!(access_units.len(), H264_IPBPB_PICTURES.len());
+        for (data, (picture_type, poc, frame_num)) in access_units.iter().zip(H264_IPBPB_PICTURES) {
+            validator
+                .check_access_unit(data, picture_type, poc, frame_num)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn truncated_nal_test() {
+        let (sps, pps) = (HashMap::new(), HashMap::new());
+        assert!(H264SliceHeader::parse(&[], &sps, &pps).is_err());
+        assert!(H264Sps::parse(&[]).is_err());
+        assert!(H264Pps::parse(&[]).is_err());
+        let (sps, pps) = (HashMap::new(), HashMap::new());
+        assert!(H265SliceHeader::parse(&[0x26], &sps, &pps).is_err());
+        assert!(H265Vps::parse(&[0x40]).is_err());
+        assert!(H265Sps::parse(&[]).is_err());
+        assert!(H265Pps::parse(&[0x44]).is_err());
+        // a slice whose header ends after the NAL unit header
+        let mut parser = StreamParser::default();
+        parser
+            .parse_annex_b(Codec::H264, &H264_PARAMETER_SETS)
+            .unwrap();
+        assert!(parser.parse_nal(Codec::H264, &[0x65]).is_err());
+    }
+}
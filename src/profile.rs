@@ -1,7 +1,9 @@
 use std::marker::PhantomPinned;
 use std::mem::transmute;
 
+use crate::color::{bit_depth, VIDEO_FORMAT_10BIT, VIDEO_FORMAT_8BIT};
 use crate::settings::Codec;
+use crate::vk_beta::{VideoEncodeAV1ProfileInfoKHR, VIDEO_CODEC_OPERATION_ENCODE_AV1};
 use ash::vk;
 
 use ash::prelude::VkResult;
@@ -15,25 +17,35 @@ pub struct VideoProfile<'a> {
     h264_decode_profile: vk::VideoDecodeH264ProfileInfoKHR<'a>,
     h265_encode_profile: vk::VideoEncodeH265ProfileInfoKHR<'a>,
     h265_decode_profile: vk::VideoDecodeH265ProfileInfoKHR<'a>,
+    av1_encode_profile: VideoEncodeAV1ProfileInfoKHR<'a>,
+    av1_decode_profile: vk::VideoDecodeAV1ProfileInfoKHR<'a>,
     _marker: PhantomPinned, // self-referential pointers in this struct. Don't move in memory!
 }
 
 impl VideoProfile<'_> {
     pub fn new(video_format: vk::Format, codec: Codec, is_encode: bool) -> VkResult<Box<Self>> {
-        assert_eq!(video_format, vk::Format::G8_B8R8_2PLANE_420_UNORM);
+        assert!(matches!(
+            video_format,
+            VIDEO_FORMAT_8BIT | VIDEO_FORMAT_10BIT
+        ));
+        let bit_depth = if bit_depth(video_format) == 10 {
+            vk::VideoComponentBitDepthFlagsKHR::TYPE_10
+        } else {
+            vk::VideoComponentBitDepthFlagsKHR::TYPE_8
+        };
         let mut rtn: Box<Self> = Default::default();
 
         rtn.profile = vk::VideoProfileInfoKHR::default()
             .video_codec_operation(match (is_encode, codec) {
                 (true, Codec::H264) => vk::VideoCodecOperationFlagsKHR::ENCODE_H264,
                 (true, Codec::H265) => vk::VideoCodecOperationFlagsKHR::ENCODE_H265,
-                (true, Codec::AV1) => todo!(),
+                (true, Codec::AV1) => VIDEO_CODEC_OPERATION_ENCODE_AV1,
                 (false, Codec::H264) => vk::VideoCodecOperationFlagsKHR::DECODE_H264,
                 (false, Codec::H265) => vk::VideoCodecOperationFlagsKHR::DECODE_H265,
-                (false, Codec::AV1) => todo!(),
+                (false, Codec::AV1) => vk::VideoCodecOperationFlagsKHR::DECODE_AV1,
             })
-            .luma_bit_depth(vk::VideoComponentBitDepthFlagsKHR::TYPE_8)
-            .chroma_bit_depth(vk::VideoComponentBitDepthFlagsKHR::TYPE_8)
+            .luma_bit_depth(bit_depth)
+            .chroma_bit_depth(bit_depth)
             .chroma_subsampling(vk::VideoChromaSubsamplingFlagsKHR::TYPE_420);
 
         rtn.encode_usage = vk::VideoEncodeUsageInfoKHR::default()
@@ -45,9 +57,18 @@ impl VideoProfile<'_> {
         rtn.h264_encode_profile = vk::VideoEncodeH264ProfileInfoKHR::default()
             .std_profile_idc(vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_MAIN);
         rtn.h265_encode_profile = vk::VideoEncodeH265ProfileInfoKHR::default();
+        if bit_depth == vk::VideoComponentBitDepthFlagsKHR::TYPE_10 {
+            rtn.h265_encode_profile = rtn.h265_encode_profile.std_profile_idc(
+                vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN_10,
+            );
+        }
         rtn.h264_decode_profile = vk::VideoDecodeH264ProfileInfoKHR::default()
             .std_profile_idc(vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_MAIN);
         rtn.h265_decode_profile = vk::VideoDecodeH265ProfileInfoKHR::default();
+        rtn.av1_encode_profile = VideoEncodeAV1ProfileInfoKHR::default()
+            .std_profile(vk::native::StdVideoAV1Profile_STD_VIDEO_AV1_PROFILE_MAIN);
+        rtn.av1_decode_profile = vk::VideoDecodeAV1ProfileInfoKHR::default()
+            .std_profile(vk::native::StdVideoAV1Profile_STD_VIDEO_AV1_PROFILE_MAIN);
         unsafe {
             if is_encode {
                 match codec {
@@ -57,7 +78,9 @@ impl VideoProfile<'_> {
                     Codec::H265 => {
                         rtn.profile.p_next = transmute(&rtn.h265_encode_profile);
                     }
-                    Codec::AV1 => todo!(),
+                    Codec::AV1 => {
+                        rtn.profile.p_next = transmute(&rtn.av1_encode_profile);
+                    }
                 };
             } else {
                 match codec {
@@ -67,7 +90,9 @@ impl VideoProfile<'_> {
                     Codec::H265 => {
                         rtn.profile.p_next = transmute(&rtn.h265_decode_profile);
                     }
-                    Codec::AV1 => todo!(),
+                    Codec::AV1 => {
+                        rtn.profile.p_next = transmute(&rtn.av1_decode_profile);
+                    }
                 };
             }
         }
@@ -0,0 +1,341 @@
+//! Reordering of captured frames into decode order.
+//!
+//! B frames are held back until the next anchor (I or P frame) is captured. The anchor is
+//! encoded first, followed by the held back B frames which reference it and the anchor before.
+//! B frames are never used as references, so two DPB slots holding the two most recent anchors
+//! are enough.
+//!
+//! Anchors are decoded up to one run of B frames before they are presented. Presentation times are
+//! delayed by that run so the decode times, which follow the capture times, never exceed them.
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{dpb::PictureType, gop::GopStructure};
+
+/// Number of DPB slots used for reference pictures
+pub const DPB_SLOT_COUNT: usize = 2;
+/// Upper bound for B frames between two anchors, every held back frame occupies an input image
+pub const MAX_CONSECUTIVE_B_FRAMES: u8 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub slot: usize,
+    pub picture_type: PictureType,
+    pub poc: u32,
+    pub frame_num: u32,
+}
+
+/// A frame ready to be encoded, in decode order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledFrame<T> {
+    pub input: T,
+    pub picture_type: PictureType,
+    /// Display position since the last IDR frame
+    pub poc: u32,
+    /// H.264 frame_num: reference pictures since the last IDR frame
+    pub frame_num: u32,
+    /// DPB slot of the reconstructed picture, `None` for non-reference pictures
+    pub setup_slot: Option<usize>,
+    pub l0: Option<Reference>,
+    pub l1: Option<Reference>,
+    /// Presentation time, the capture time delayed by the reorder delay
+    pub pts: Duration,
+    /// Decode time, the presentation time of the frame one run of B frames earlier in display
+    /// order, so it never exceeds the presentation time
+    pub dts: Duration,
+}
+
+impl<T> ScheduledFrame<T> {
+    pub fn is_reference(&self) -> bool {
+        self.setup_slot.is_some()
+    }
+}
+
+struct PendingFrame<T> {
+    input: T,
+    pts: Duration,
+    display_idx: u64,
+}
+
+pub struct FrameReorderer<T> {
+    gop: GopStructure,
+    display_idx: u64,
+    idr_display_idx: u64,
+    /// Display index the GOP structure is counted from, moved by forced IDR frames
+    gop_origin: u64,
+    next_frame_num: u32,
+    /// The two most recent anchors, oldest first
+    anchors: VecDeque<Reference>,
+    pending: Vec<PendingFrame<T>>,
+    capture_times: VecDeque<Duration>,
+    /// Nominal time between two captured frames
+    frame_duration: Duration,
+    first_capture_time: Option<Duration>,
+    /// Frames scheduled so far, the position of the next frame in decode order
+    decode_idx: u64,
+}
+
+impl<T> FrameReorderer<T> {
+    pub fn new(gop: GopStructure, frame_duration: Duration) -> Self {
+        Self {
+            gop,
+            display_idx: 0,
+            idr_display_idx: 0,
+            gop_origin: 0,
+            next_frame_num: 0,
+            anchors: VecDeque::with_capacity(DPB_SLOT_COUNT),
+            pending: Vec::new(),
+            capture_times: VecDeque::new(),
+            frame_duration,
+            first_capture_time: None,
+            decode_idx: 0,
+        }
+    }
+
+    pub fn gop(&self) -> &GopStructure {
+        &self.gop
+    }
+
+    /// Frames held back until their forward reference is captured
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Number of frames an anchor can be decoded ahead of its display position
+    fn reorder_delay(&self) -> u64 {
+        self.gop.consecutive_b_frame_count() as u64
+    }
+
+    fn pts_offset(&self) -> Duration {
+        self.frame_duration * self.reorder_delay() as u32
+    }
+
+    /// Adds a captured frame and returns the frames that can be encoded now, in decode order.
+    pub fn push(&mut self, input: T, pts: Duration, force_idr: bool) -> Vec<ScheduledFrame<T>> {
+        let display_idx = self.display_idx;
+        self.display_idx += 1;
+        self.capture_times.push_back(pts);
+        self.first_capture_time.get_or_insert(pts);
+
+        if force_idr {
+            self.gop_origin = display_idx;
+        }
+        let gop_position = display_idx - self.gop_origin;
+        let mut picture_type = if force_idr {
+            PictureType::Idr
+        } else {
+            self.gop.frame_type(gop_position, display_idx == 0, false)
+        };
+        if picture_type.is_b() {
+            let gop_num = (gop_position % self.gop.gop_frame_count() as u64) as u8;
+            if !self.gop.reference_numbers(gop_num, false, true).is_empty() {
+                self.pending.push(PendingFrame {
+                    input,
+                    pts,
+                    display_idx,
+                });
+                return Vec::new();
+            }
+            // The GOP ends before the next anchor, only backward prediction is possible
+            picture_type = PictureType::P;
+        }
+
+        let mut rtn = Vec::new();
+        if picture_type.is_idr() {
+            // nothing may reference across an IDR frame
+            self.flush_into(&mut rtn);
+        }
+        rtn.push(self.schedule_anchor(input, pts, display_idx, picture_type));
+
+        let l1 = self.anchors.back().copied();
+        let l0 = (self.anchors.len() == DPB_SLOT_COUNT).then(|| self.anchors[0]);
+        for pending in std::mem::take(&mut self.pending) {
+            rtn.push(ScheduledFrame {
+                input: pending.input,
+                picture_type: PictureType::B,
+                poc: (pending.display_idx - self.idr_display_idx) as u32,
+                frame_num: self.next_frame_num,
+                setup_slot: None,
+                l0,
+                l1,
+                pts: pending.pts + self.pts_offset(),
+                dts: self.next_dts(),
+            });
+        }
+        rtn
+    }
+
+    /// Returns all held back frames, encoded as P frames since their anchor never arrived.
+    pub fn flush(&mut self) -> Vec<ScheduledFrame<T>> {
+        let mut rtn = Vec::new();
+        self.flush_into(&mut rtn);
+        rtn
+    }
+
+    fn flush_into(&mut self, frames: &mut Vec<ScheduledFrame<T>>) {
+        for pending in std::mem::take(&mut self.pending) {
+            frames.push(self.schedule_anchor(
+                pending.input,
+                pending.pts,
+                pending.display_idx,
+                PictureType::P,
+            ));
+        }
+    }
+
+    fn schedule_anchor(
+        &mut self,
+        input: T,
+        pts: Duration,
+        display_idx: u64,
+        picture_type: PictureType,
+    ) -> ScheduledFrame<T> {
+        if picture_type.is_idr() {
+            self.anchors.clear();
+            self.idr_display_idx = display_idx;
+            self.next_frame_num = 0;
+        }
+        let l0 = if picture_type.is_p() {
+            self.anchors.back().copied()
+        } else {
+            None
+        };
+        // overwrite the oldest anchor, its B frames are already encoded
+        let slot = if self.anchors.len() < DPB_SLOT_COUNT {
+            (0..DPB_SLOT_COUNT)
+                .find(|slot| self.anchors.iter().all(|anchor| anchor.slot != *slot))
+                .unwrap_or_default()
+        } else {
+            self.anchors.pop_front().map(|a| a.slot).unwrap_or_default()
+        };
+        let reference = Reference {
+            slot,
+            picture_type,
+            poc: (display_idx - self.idr_display_idx) as u32,
+            frame_num: self.next_frame_num,
+        };
+        self.next_frame_num = self.next_frame_num.wrapping_add(1);
+        self.anchors.push_back(reference);
+
+        ScheduledFrame {
+            input,
+            picture_type,
+            poc: reference.poc,
+            frame_num: reference.frame_num,
+            setup_slot: Some(slot),
+            l0,
+            l1: None,
+            pts: pts + self.pts_offset(),
+            dts: self.next_dts(),
+        }
+    }
+
+    /// Decode time of the next scheduled frame. The first frames of the stream are decoded one
+    /// frame duration apart, starting one run of B frames before the first presentation time.
+    fn next_dts(&mut self) -> Duration {
+        let decode_idx = self.decode_idx;
+        self.decode_idx += 1;
+        if decode_idx < self.reorder_delay() {
+            return self.first_capture_time.unwrap_or_default()
+                + self.frame_duration * decode_idx as u32;
+        }
+        self.capture_times.pop_front().unwrap_or_default() + self.pts_offset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use PictureType::{Idr, B, I, P};
+
+    fn pts(i: u64) -> Duration {
+        Duration::from_millis(10 * i)
+    }
+
+    fn reorderer(gop: GopStructure) -> FrameReorderer<u64> {
+        FrameReorderer::new(gop, pts(1))
+    }
+
+    #[test]
+    fn b_frames_test() {
+        let mut reorderer = reorderer(GopStructure::new(8, 16, 2, 1, P).unwrap());
+        let mut frames: Vec<_> = (0..10)
+            .flat_map(|i| reorderer.push(i, pts(i), false))
+            .collect();
+        frames.extend(reorderer.flush());
+
+        let order: Vec<_> = frames
+            .iter()
+            .map(|f| (f.input, f.picture_type, f.poc, f.frame_num, f.setup_slot))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (0, Idr, 0, 0, Some(0)),
+                (3, P, 3, 1, Some(1)),
+                (1, B, 1, 2, None),
+                (2, B, 2, 2, None),
+                (6, P, 6, 2, Some(0)),
+                (4, B, 4, 3, None),
+                (5, B, 5, 3, None),
+                (7, P, 7, 3, Some(1)),
+                (8, I, 8, 4, Some(0)),
+                (9, P, 9, 5, Some(1)),
+            ]
+        );
+        let slots = |f: &ScheduledFrame<u64>| (f.l0.map(|r| r.slot), f.l1.map(|r| r.slot));
+        assert_eq!(slots(&frames[1]), (Some(0), None));
+        assert_eq!(slots(&frames[2]), (Some(0), Some(1)));
+        assert_eq!(frames[5].l0.unwrap().poc, 3);
+        assert_eq!(frames[5].l1.unwrap().poc, 6);
+        assert_eq!(slots(&frames[8]), (None, None));
+
+        // presented two frames late, decoded in capture order from the first capture time
+        let times: Vec<_> = frames.iter().map(|f| (f.pts, f.dts)).collect();
+        assert_eq!(
+            times,
+            [0, 3, 1, 2, 6, 4, 5, 7, 8, 9]
+                .into_iter()
+                .zip(0..10)
+                .map(|(input, i)| (pts(input + 2), pts(i)))
+                .collect::<Vec<_>>()
+        );
+        assert!(frames.iter().all(|f| f.dts <= f.pts));
+    }
+
+    #[test]
+    fn forced_idr_test() {
+        let mut reorderer = reorderer(GopStructure::new(16, 16, 3, 1, P).unwrap());
+        assert_eq!(reorderer.push(0, pts(0), false).len(), 1);
+        assert!(reorderer.push(1, pts(1), false).is_empty());
+        assert!(reorderer.push(2, pts(2), false).is_empty());
+        assert_eq!(reorderer.pending_count(), 2);
+
+        let frames = reorderer.push(3, pts(3), true);
+        let order: Vec<_> = frames
+            .iter()
+            .map(|f| (f.input, f.picture_type, f.poc, f.setup_slot))
+            .collect();
+        assert_eq!(
+            order,
+            [(1, P, 1, Some(1)), (2, P, 2, Some(0)), (3, Idr, 0, Some(0))]
+        );
+        assert_eq!(frames[1].l0.unwrap().slot, 1);
+        assert_eq!(reorderer.pending_count(), 0);
+
+        // the GOP restarts at the forced IDR frame
+        let frames: Vec<_> = (4..=19)
+            .flat_map(|i| reorderer.push(i, pts(i), false))
+            .collect();
+        let intra: Vec<_> = frames
+            .iter()
+            .filter(|f| !f.picture_type.is_p() && !f.picture_type.is_b())
+            .map(|f| (f.input, f.picture_type, f.poc))
+            .collect();
+        let gop_frame_count = reorderer.gop().gop_frame_count() as u64;
+        assert_eq!(intra, [(3 + gop_frame_count, Idr, 0)]);
+        assert_eq!(frames[0].input, 7);
+        assert_eq!(frames[0].picture_type, P);
+        assert!(frames.iter().all(|f| f.dts <= f.pts));
+    }
+}
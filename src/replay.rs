@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::dpb::PictureType;
+use crate::muxer::{EncodedFrame, Muxer};
+
+/// An encoded frame held in memory by the [`ReplayBuffer`]
+#[derive(Clone)]
+pub struct BufferedFrame {
+    data: Arc<[u8]>,
+    picture_type: PictureType,
+    pts: Duration,
+    dts: Duration,
+}
+
+/// Keeps the encoded frames of the last `duration` in memory instead of writing them to a file.
+///
+/// Frames are grouped into GOPs that each start with an IDR frame, so that whatever is saved
+/// decodes on its own. Whole GOPs are dropped once the next GOP alone covers `duration`, or while
+/// the buffer holds more than `max_size` bytes. The GOP being filled is never dropped.
+pub struct ReplayBuffer {
+    duration: Duration,
+    max_size: Option<usize>,
+    gops: VecDeque<Vec<BufferedFrame>>,
+    size: usize,
+}
+
+impl ReplayBuffer {
+    pub fn new(duration: Duration, max_size: Option<usize>) -> Self {
+        Self {
+            duration,
+            max_size,
+            gops: VecDeque::new(),
+            size: 0,
+        }
+    }
+
+    /// Buffered data in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Cheap copy of all buffered frames in decode order, starting at an IDR frame
+    pub fn snapshot(&self) -> Vec<BufferedFrame> {
+        self.gops.iter().flatten().cloned().collect()
+    }
+
+    /// Writes `frames` to `output` with timestamps relative to the first frame and finishes it.
+    pub fn save(frames: &[BufferedFrame], output: &mut dyn Muxer) -> std::io::Result<()> {
+        let start = frames.first().map(|frame| frame.dts).unwrap_or_default();
+        for frame in frames {
+            output.write_frame(&EncodedFrame {
+                data: &frame.data,
+                picture_type: frame.picture_type,
+                pts: frame.pts.saturating_sub(start),
+                dts: frame.dts.saturating_sub(start),
+            })?;
+        }
+        output.finish()
+    }
+}
+
+impl Muxer for ReplayBuffer {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        if frame.is_keyframe() {
+            self.gops.push_back(Vec::new());
+        }
+        // frames before the first IDR can't be decoded
+        let Some(gop) = self.gops.back_mut() else {
+            return Ok(());
+        };
+        gop.push(BufferedFrame {
+            data: frame.data.into(),
+            picture_type: frame.picture_type,
+            pts: frame.pts,
+            dts: frame.dts,
+        });
+        self.size += frame.data.len();
+
+        while self.gops.len() > 1
+            && (self.gops[1][0].pts + self.duration <= frame.pts
+                || self.max_size.is_some_and(|max_size| self.size > max_size))
+        {
+            let gop = self.gops.pop_front().unwrap();
+            self.size -= gop.iter().map(|frame| frame.data.len()).sum::<usize>();
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muxer::RecordingMuxer;
+
+    use PictureType::{Idr, P};
+
+    fn write(buffer: &mut ReplayBuffer, idx: u8, picture_type: PictureType) {
+        let time = Duration::from_secs(idx as u64);
+        buffer
+            .write_frame(&EncodedFrame {
+                data: &[idx; 4],
+                picture_type,
+                pts: time,
+                dts: time,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn replay_buffer_test() {
+        let mut buffer = ReplayBuffer::new(Duration::from_secs(3), None);
+        // one frame per second with an IDR every 2 frames, the first frame is not decodable
+        write(&mut buffer, 0, P);
+        for idx in 1..8 {
+            write(&mut buffer, idx, if idx % 2 == 1 { Idr } else { P });
+        }
+        assert_eq!(buffer.size(), 5 * 4);
+
+        let mut output = RecordingMuxer::default();
+        ReplayBuffer::save(&buffer.snapshot(), &mut output).unwrap();
+        assert!(output.finished);
+        // frames 4..=7 are needed to cover 3 seconds, the replay starts at the IDR before them
+        let frames: Vec<_> = output.frames.iter().map(|frame| frame.data[0]).collect();
+        assert_eq!(frames, [3, 4, 5, 6, 7]);
+        assert_eq!(output.frames[0].picture_type, Idr);
+        assert_eq!(output.frames[0].pts, Duration::ZERO);
+        assert_eq!(output.frames[4].dts, Duration::from_secs(4));
+    }
+
+    #[test]
+    fn replay_buffer_size_test() {
+        let mut buffer = ReplayBuffer::new(Duration::from_secs(60), Some(10));
+        for idx in 0..8 {
+            write(&mut buffer, idx, if idx % 2 == 0 { Idr } else { P });
+        }
+        // each GOP holds 8 bytes, only the last one fits
+        assert_eq!(buffer.size(), 8);
+        let frames: Vec<_> = buffer
+            .snapshot()
+            .iter()
+            .map(|frame| frame.data[0])
+            .collect();
+        assert_eq!(frames, [6, 7]);
+
+        // a GOP larger than the limit is kept until the next IDR
+        let mut buffer = ReplayBuffer::new(Duration::from_secs(60), Some(10));
+        for idx in 0..4 {
+            write(&mut buffer, idx, if idx == 0 { Idr } else { P });
+        }
+        assert_eq!(buffer.size(), 16);
+        write(&mut buffer, 4, Idr);
+        assert_eq!(buffer.size(), 4);
+    }
+}
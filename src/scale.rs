@@ -0,0 +1,197 @@
+use ash::vk;
+
+use crate::settings::{ScaleFilter, Settings};
+
+/// Scaling settings of [`crate::dpb::Dpb`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleOptions {
+    pub filter: ScaleFilter,
+    pub letterbox: bool,
+}
+
+impl ScaleOptions {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            filter: settings.scale_filter,
+            letterbox: settings.letterbox,
+        }
+    }
+}
+
+/// Where the conversion shader places the swapchain images in the recorded pictures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scaling {
+    pub input_extent: vk::Extent2D,
+    /// Part of the picture the input is scaled to, the rest is black
+    pub rect: vk::Rect2D,
+    pub filter: ScaleFilter,
+}
+
+impl Scaling {
+    pub fn new(
+        input_extent: vk::Extent2D,
+        output_extent: vk::Extent2D,
+        options: ScaleOptions,
+    ) -> Self {
+        let (input, output) = (input_extent, output_extent);
+        let rect = if !options.letterbox {
+            vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent: output,
+            }
+        } else if input.width as u64 * output.height as u64
+            > output.width as u64 * input.height as u64
+        {
+            // wider than the output, bars at the top and bottom
+            let height = scale_even(input.height, output.width, input.width).min(output.height);
+            vk::Rect2D {
+                offset: vk::Offset2D {
+                    x: 0,
+                    y: (((output.height - height) / 2) & !1) as i32,
+                },
+                extent: vk::Extent2D {
+                    width: output.width,
+                    height,
+                },
+            }
+        } else {
+            let width = scale_even(input.width, output.height, input.height).min(output.width);
+            vk::Rect2D {
+                offset: vk::Offset2D {
+                    x: (((output.width - width) / 2) & !1) as i32,
+                    y: 0,
+                },
+                extent: vk::Extent2D {
+                    width,
+                    height: output.height,
+                },
+            }
+        };
+        Self {
+            input_extent,
+            rect,
+            filter: options.filter,
+        }
+    }
+
+    /// Push constants of the conversion shaders up to the input transfer, see `scale.hlsli`
+    pub fn push_constants(&self) -> [u32; 6] {
+        let vk::Rect2D { offset, extent } = self.rect;
+        [
+            self.input_extent.width,
+            self.input_extent.height,
+            offset.x as u32,
+            offset.y as u32,
+            extent.width,
+            extent.height,
+        ]
+    }
+}
+
+/// `value * numerator / denominator` rounded to an even number, chroma is subsampled by 2
+fn scale_even(value: u32, numerator: u32, denominator: u32) -> u32 {
+    let scaled = value as u64 * numerator as u64 / denominator.max(1) as u64;
+    ((scaled as u32 + 1) & !1).max(2)
+}
+
+/// Size of the recorded pictures of a swapchain, `output_width` or `output_height` of 0 keeps
+/// the aspect ratio of the swapchain.
+pub fn output_extent(settings: &Settings, input_extent: vk::Extent2D) -> vk::Extent2D {
+    let vk::Extent2D { width, height } = input_extent;
+    match (settings.output_width, settings.output_height) {
+        (0, 0) => input_extent,
+        (0, output_height) => vk::Extent2D {
+            width: scale_even(width, output_height, height),
+            height: output_height,
+        },
+        (output_width, 0) => vk::Extent2D {
+            width: output_width,
+            height: scale_even(height, output_width, width),
+        },
+        (width, height) => vk::Extent2D { width, height },
+    }
+}
+
+/// Largest extent with the aspect ratio of `extent` that is no larger than `max`
+pub fn fit_extent(extent: vk::Extent2D, max: vk::Extent2D) -> vk::Extent2D {
+    if extent.width <= max.width && extent.height <= max.height {
+        return extent;
+    }
+    let (width, height) =
+        if extent.width as u64 * max.height as u64 > max.width as u64 * extent.height as u64 {
+            let height = extent.height as u64 * max.width as u64 / extent.width as u64;
+            (max.width, height as u32)
+        } else {
+            let width = extent.width as u64 * max.height as u64 / extent.height as u64;
+            (width as u32, max.height)
+        };
+    vk::Extent2D {
+        width: (width & !1).max(2),
+        height: (height & !1).max(2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    #[test]
+    fn output_extent_test() {
+        let uhd = extent(3840, 2160);
+        let settings = |output_width, output_height| Settings {
+            output_width,
+            output_height,
+            ..Default::default()
+        };
+        assert_eq!(output_extent(&settings(0, 0), uhd), uhd);
+        assert_eq!(output_extent(&settings(0, 1080), uhd), extent(1920, 1080));
+        assert_eq!(output_extent(&settings(1280, 0), uhd), extent(1280, 720));
+        assert_eq!(output_extent(&settings(1000, 0), uhd), extent(1000, 562));
+        assert_eq!(output_extent(&settings(640, 480), uhd), extent(640, 480));
+
+        assert_eq!(fit_extent(uhd, extent(4096, 4096)), uhd);
+        assert_eq!(fit_extent(uhd, extent(1920, 4096)), extent(1920, 1080));
+        assert_eq!(
+            fit_extent(extent(1080, 1920), extent(4096, 1080)),
+            extent(606, 1080)
+        );
+    }
+
+    #[test]
+    fn letterbox_test() {
+        let options = ScaleOptions {
+            filter: ScaleFilter::Lanczos,
+            letterbox: true,
+        };
+        let rect = |x, y, width, height| vk::Rect2D {
+            offset: vk::Offset2D { x, y },
+            extent: extent(width, height),
+        };
+
+        let scaling = Scaling::new(extent(3840, 2160), extent(1920, 1080), options);
+        assert_eq!(scaling.rect, rect(0, 0, 1920, 1080));
+        assert_eq!(scaling.push_constants(), [3840, 2160, 0, 0, 1920, 1080]);
+
+        // 4:3 pillarboxed in 16:9
+        let scaling = Scaling::new(extent(1024, 768), extent(1280, 720), options);
+        assert_eq!(scaling.rect, rect(160, 0, 960, 720));
+
+        // 21:9 letterboxed in 16:9
+        let scaling = Scaling::new(extent(2560, 1080), extent(1920, 1080), options);
+        assert_eq!(scaling.rect, rect(0, 134, 1920, 810));
+
+        let stretched = Scaling::new(
+            extent(1024, 768),
+            extent(1280, 720),
+            ScaleOptions {
+                letterbox: false,
+                ..options
+            },
+        );
+        assert_eq!(stretched.rect, rect(0, 0, 1280, 720));
+    }
+}
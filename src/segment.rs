@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use log::info;
+
+use crate::frame_stats::FrameStats;
+use crate::muxer::{EncodedFrame, Muxer};
+
+/// When to start a new segment, `None` disables a limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SegmentLimits {
+    pub duration: Option<Duration>,
+    pub size: Option<u64>,
+}
+
+impl SegmentLimits {
+    pub fn is_enabled(&self) -> bool {
+        self.duration.is_some() || self.size.is_some()
+    }
+}
+
+type CreateSegment = Box<dyn FnMut(u32) -> std::io::Result<Box<dyn Muxer>>>;
+
+/// Splits the output into segments that each start with an IDR frame. Every segment is a
+/// complete file with its own parameter sets and decode times starting at 0.
+pub struct SegmentedMuxer {
+    create_segment: CreateSegment,
+    current: Box<dyn Muxer>,
+    index: u32,
+    limits: SegmentLimits,
+    /// Decode time of the keyframe starting the current segment
+    start: Option<Duration>,
+    /// Bytes written to the current segment
+    size: u64,
+    limit_reached: bool,
+    keyframe_requested: bool,
+}
+
+impl SegmentedMuxer {
+    /// `create_segment` opens the output for the segment with the given index
+    pub fn new(mut create_segment: CreateSegment, limits: SegmentLimits) -> std::io::Result<Self> {
+        let current = create_segment(0)?;
+        Ok(Self {
+            create_segment,
+            current,
+            index: 0,
+            limits,
+            start: None,
+            size: 0,
+            limit_reached: false,
+            keyframe_requested: false,
+        })
+    }
+
+    fn next_segment(&mut self) -> std::io::Result<()> {
+        self.current.finish()?;
+        self.index += 1;
+        info!("Starting segment {}", self.index);
+        self.current = (self.create_segment)(self.index)?;
+        self.start = None;
+        self.size = 0;
+        self.limit_reached = false;
+        self.keyframe_requested = false;
+        Ok(())
+    }
+}
+
+impl Muxer for SegmentedMuxer {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        if self.limit_reached && frame.is_keyframe() {
+            self.next_segment()?;
+        }
+        // segments start with a keyframe and the reorderer keeps presentation times at or after
+        // decode times, so timestamps relative to the keyframe's decode time are never negative
+        let start = *self.start.get_or_insert(frame.dts);
+        debug_assert!(
+            frame.pts >= start && frame.dts >= start,
+            "frame at {:?}/{:?} before the segment start {start:?}",
+            frame.pts,
+            frame.dts
+        );
+        self.current.write_frame(&EncodedFrame {
+            data: frame.data,
+            picture_type: frame.picture_type,
+            pts: frame.pts.saturating_sub(start),
+            dts: frame.dts.saturating_sub(start),
+        })?;
+        self.size += frame.data.len() as u64;
+        self.limit_reached |= self
+            .limits
+            .duration
+            .is_some_and(|duration| frame.dts.saturating_sub(start) >= duration)
+            || self.limits.size.is_some_and(|size| self.size >= size);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.current.finish()
+    }
+
+    fn wants_keyframe(&mut self) -> bool {
+        let request = self.limit_reached && !self.keyframe_requested;
+        self.keyframe_requested |= request;
+        request
+    }
+
+    fn write_frame_stats(&mut self, stats: &FrameStats) -> std::io::Result<()> {
+        self.current.write_frame_stats(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::dpb::PictureType::{self, Idr, P};
+    use crate::muxer::RecordingMuxer;
+
+    type Segments = Rc<RefCell<Vec<Rc<RefCell<RecordingMuxer>>>>>;
+
+    /// Writes a frame presented one frame after its decode time, like with one B frame
+    fn write(muxer: &mut SegmentedMuxer, idx: u64, picture_type: PictureType) {
+        let time = Duration::from_secs(idx);
+        muxer
+            .write_frame(&EncodedFrame {
+                data: &[0; 100],
+                picture_type,
+                pts: time + Duration::from_secs(1),
+                dts: time,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn segmented_muxer_test() {
+        let segments = Segments::default();
+        let create_segment = {
+            let segments = segments.clone();
+            Box::new(move |index| {
+                assert_eq!(segments.borrow().len(), index as usize);
+                let segment = Rc::new(RefCell::new(RecordingMuxer::default()));
+                segments.borrow_mut().push(segment.clone());
+                Ok(Box::new(segment) as Box<dyn Muxer>)
+            })
+        };
+        let limits = SegmentLimits {
+            duration: Some(Duration::from_secs(2)),
+            size: Some(1000),
+        };
+        let mut muxer = SegmentedMuxer::new(create_segment, limits).unwrap();
+        write(&mut muxer, 0, Idr);
+        write(&mut muxer, 1, P);
+        assert!(!muxer.wants_keyframe());
+        write(&mut muxer, 2, P);
+        // the keyframe is only requested once
+        assert!(muxer.wants_keyframe());
+        assert!(!muxer.wants_keyframe());
+        write(&mut muxer, 3, P);
+        write(&mut muxer, 4, Idr);
+        for idx in 5..15 {
+            write(&mut muxer, idx, P);
+        }
+        write(&mut muxer, 15, Idr);
+
+        // every segment but the last one is finished when the next one starts
+        let finished: Vec<_> = segments
+            .borrow()
+            .iter()
+            .map(|s| s.borrow().finished)
+            .collect();
+        assert_eq!(finished, [true, true, false]);
+        let segments: Vec<Vec<_>> = segments
+            .borrow()
+            .iter()
+            .map(|segment| {
+                let segment = segment.borrow();
+                segment
+                    .frames
+                    .iter()
+                    .map(|frame| (frame.picture_type, frame.pts))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].len(), 4);
+        assert_eq!(segments[1][0], (Idr, Duration::from_secs(1)));
+        // the size limit is reached after 10 frames
+        assert_eq!(segments[1].len(), 11);
+        assert_eq!(segments[2], [(Idr, Duration::from_secs(1))]);
+    }
+}
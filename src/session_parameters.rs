@@ -1,5 +1,13 @@
 use crate::bitstream::write_h264_pps;
 use crate::bitstream::write_h264_sps;
+use crate::bitstream::{write_av1_hdr_metadata, write_h265_hdr_sei};
+use crate::bitstream::{write_h265_pps, write_h265_sps, write_h265_vps};
+use crate::capabilities::EncoderConfig;
+use crate::settings::ChromaSiting;
+use crate::vk_beta::{
+    StdVideoEncodeAV1OperatingPointInfo, VideoEncodeAV1SessionParametersCreateInfoKHR,
+};
+use crate::vk_layer::{STD_VIDEO_AV1_SELECT_INTEGER_MV, STD_VIDEO_AV1_SELECT_SCREEN_CONTENT_TOOLS};
 use ash::khr;
 use ash::prelude::VkResult;
 use ash::vk;
@@ -9,34 +17,70 @@ use std::io::Write;
 use std::mem::{transmute, MaybeUninit};
 use std::ptr::{null, null_mut};
 
+pub const H264_LOG2_MAX_FRAME_NUM: u8 = 10;
+/// `video_format` of the VUI, the source of the video isn't any of the listed formats
+const VIDEO_FORMAT_UNSPECIFIED: u8 = 5;
+
+/// Which of the requested parameters the driver changed when creating the session parameters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParameterOverrides {
+    /// Whether the driver reported any override at all
+    pub any: bool,
+    pub vps: bool,
+    pub sps: bool,
+    pub pps: bool,
+}
+
+/// Reference frames the decoder has to keep, two anchors once B frames reference both.
+fn num_reference_frames(consecutive_b_frames: u8) -> u8 {
+    if consecutive_b_frames > 0 {
+        2
+    } else {
+        1
+    }
+}
+
 pub fn make_h264_video_session_parameters(
     device: &ash::Device,
     video_queue_fn: &khr::video_queue::DeviceFn,
     encode_queue_fn: &khr::video_encode_queue::DeviceFn,
     video_session: vk::VideoSessionKHR,
     format: vk::Format,
-    extent: vk::Extent2D,
+    config: &EncoderConfig,
     output_file: Option<impl Write>,
     allocator: Option<&vk::AllocationCallbacks>,
-) -> VkResult<vk::VideoSessionParametersKHR> {
+) -> VkResult<(vk::VideoSessionParametersKHR, ParameterOverrides)> {
+    let extent = config.extent;
+    let coded_extent = config.coded_extent();
+    let consecutive_b_frames = config.consecutive_b_frames;
     let bitdepth = 8;
-    let flags = unsafe { MaybeUninit::zeroed().assume_init() };
-    let _vui = vk::native::StdVideoH264SequenceParameterSetVui {
+    let num_reference_frames = num_reference_frames(consecutive_b_frames);
+    let mut flags: vk::native::StdVideoH264SpsVuiFlags =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    // lets decoders output frames without waiting for a full DPB
+    flags.set_bitstream_restriction_flag(1);
+    let colour_description = config.colour_description();
+    let chroma_sample_loc_type = config.conversion.chroma_sample_loc_type();
+    flags.set_video_signal_type_present_flag(1);
+    flags.set_video_full_range_flag(colour_description.full_range as u32);
+    flags.set_color_description_present_flag(1);
+    flags.set_chroma_loc_info_present_flag(1);
+    let vui = vk::native::StdVideoH264SequenceParameterSetVui {
         flags,
         aspect_ratio_idc:
             ash::vk::native::StdVideoH265AspectRatioIdc_STD_VIDEO_H265_ASPECT_RATIO_IDC_SQUARE,
         sar_width: 0,
         sar_height: 0,
-        video_format: 0,
-        colour_primaries: 0,
-        transfer_characteristics: 0,
-        matrix_coefficients: 0,
+        video_format: VIDEO_FORMAT_UNSPECIFIED,
+        colour_primaries: colour_description.colour_primaries,
+        transfer_characteristics: colour_description.transfer_characteristics,
+        matrix_coefficients: colour_description.matrix_coefficients,
         num_units_in_tick: 1000,
         time_scale: 0,
-        max_num_reorder_frames: 0,
-        max_dec_frame_buffering: 0,
-        chroma_sample_loc_type_top_field: 0,
-        chroma_sample_loc_type_bottom_field: 0,
+        max_num_reorder_frames: (consecutive_b_frames > 0) as u8,
+        max_dec_frame_buffering: num_reference_frames,
+        chroma_sample_loc_type_top_field: chroma_sample_loc_type,
+        chroma_sample_loc_type_bottom_field: chroma_sample_loc_type,
         reserved1: 0,
         pHrdParameters: null(),
     };
@@ -51,31 +95,33 @@ pub fn make_h264_video_session_parameters(
     let mut sps = vec![vk::native::StdVideoH264SequenceParameterSet {
         flags,
         profile_idc: vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_MAIN,
-        level_idc: vk::native::StdVideoH264LevelIdc_STD_VIDEO_H264_LEVEL_IDC_5_1,
+        level_idc: config.level,
         chroma_format_idc:
             vk::native::StdVideoH264ChromaFormatIdc_STD_VIDEO_H264_CHROMA_FORMAT_IDC_420,
         seq_parameter_set_id: 0,
         bit_depth_luma_minus8: bitdepth - 8,
         bit_depth_chroma_minus8: bitdepth - 8,
-        log2_max_frame_num_minus4: 10 - 4,
+        log2_max_frame_num_minus4: H264_LOG2_MAX_FRAME_NUM - 4,
         pic_order_cnt_type: 0,
         offset_for_non_ref_pic: 0,
         offset_for_top_to_bottom_field: 0,
         log2_max_pic_order_cnt_lsb_minus4: 8 - 4, // pic order count 0-255
         num_ref_frames_in_pic_order_cnt_cycle: 0,
-        max_num_ref_frames: 1,
+        max_num_ref_frames: num_reference_frames,
         reserved1: 0,
-        pic_width_in_mbs_minus1: (extent.width + 15) / 16 - 1, //extent.width.div_ceil(16) - 1, // with unstable feature int_roundings
-        pic_height_in_map_units_minus1: (extent.height + 15) / 16 - 1,
+        pic_width_in_mbs_minus1: coded_extent.width / 16 - 1,
+        pic_height_in_map_units_minus1: coded_extent.height / 16 - 1,
+        // in chroma samples
         frame_crop_left_offset: 0,
-        frame_crop_right_offset: (16 - extent.width % 16) / 2,
+        frame_crop_right_offset: (coded_extent.width - extent.width) / 2,
         frame_crop_top_offset: 0,
-        frame_crop_bottom_offset: (16 - extent.height % 16) / 2,
+        frame_crop_bottom_offset: (coded_extent.height - extent.height) / 2,
         reserved2: 0,
         pOffsetForRefFrame: null(),
         pScalingLists: null(),
-        pSequenceParameterSetVui: null(), //&vui (requires vui_is_present_flag)
+        pSequenceParameterSetVui: &vui,
     }];
+    sps[0].flags.set_vui_parameters_present_flag(1);
     if sps[0].frame_crop_right_offset != 0 || sps[0].frame_crop_bottom_offset != 0 {
         sps[0].flags.set_frame_cropping_flag(1);
     }
@@ -109,6 +155,10 @@ pub fn make_h264_video_session_parameters(
         let mut info =
             vk::VideoSessionParametersCreateInfoKHR::default().video_session(video_session);
         info = info.push_next(&mut codec_info);
+        // the parameters are optimized for the quality level the session uses
+        let mut quality =
+            vk::VideoEncodeQualityLevelInfoKHR::default().quality_level(config.quality_level);
+        info = info.push_next(&mut quality);
         let mut parameters = MaybeUninit::zeroed();
         let res = (video_queue_fn.create_video_session_parameters_khr)(
             device.handle(),
@@ -121,6 +171,7 @@ pub fn make_h264_video_session_parameters(
         }
         res.result_with_success(parameters.assume_init())
     };
+    let mut overrides = ParameterOverrides::default();
     if let (Some(mut output_file), Ok(video_session_parameters)) =
         (output_file, video_session_parameters)
     {
@@ -164,6 +215,12 @@ pub fn make_h264_video_session_parameters(
         };
         if res == vk::Result::SUCCESS {
             info!("Received driver feedback: {size} bytes, {feedback:?} {h264_feedback:?}");
+            overrides = ParameterOverrides {
+                any: feedback.has_overrides == vk::TRUE,
+                vps: false,
+                sps: h264_feedback.is_some_and(|f| f.has_std_sps_overrides == vk::TRUE),
+                pps: h264_feedback.is_some_and(|f| f.has_std_pps_overrides == vk::TRUE),
+            };
             output_file.write(&data).map_err(|e| {
                 error!("Failed to write to file: {e}");
                 unsafe {
@@ -195,7 +252,7 @@ pub fn make_h264_video_session_parameters(
         })?;
     }
 
-    video_session_parameters
+    video_session_parameters.map(|parameters| (parameters, overrides))
 }
 
 pub fn make_h265_video_session_parameters(
@@ -204,24 +261,36 @@ pub fn make_h265_video_session_parameters(
     encode_queue_fn: &khr::video_encode_queue::DeviceFn,
     video_session: vk::VideoSessionKHR,
     format: vk::Format,
-    coded_extent: vk::Extent2D,
+    config: &EncoderConfig,
     output_file: Option<impl Write>,
     allocator: Option<&vk::AllocationCallbacks>,
-) -> VkResult<vk::VideoSessionParametersKHR> {
-    let flags = unsafe { MaybeUninit::zeroed().assume_init() };
-    let _vui = vk::native::StdVideoH265SequenceParameterSetVui {
+) -> VkResult<(vk::VideoSessionParametersKHR, ParameterOverrides)> {
+    let extent = config.extent;
+    let coded_extent = config.coded_extent();
+    let log2_ctb_size = config.h265_ctb_size.ilog2() as u8;
+    let consecutive_b_frames = config.consecutive_b_frames;
+    let bitdepth = config.bit_depth() as u8;
+    let colour_description = config.colour_description();
+    let chroma_sample_loc_type = config.conversion.chroma_sample_loc_type();
+    let mut flags: vk::native::StdVideoH265SpsVuiFlags =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    flags.set_video_signal_type_present_flag(1);
+    flags.set_video_full_range_flag(colour_description.full_range as u32);
+    flags.set_colour_description_present_flag(1);
+    flags.set_chroma_loc_info_present_flag(1);
+    let vui = vk::native::StdVideoH265SequenceParameterSetVui {
         flags,
         aspect_ratio_idc:
             ash::vk::native::StdVideoH265AspectRatioIdc_STD_VIDEO_H265_ASPECT_RATIO_IDC_SQUARE,
         sar_width: 0,
         sar_height: 0,
-        video_format: 0,
-        colour_primaries: 0,
-        transfer_characteristics: 0,
-        matrix_coeffs: 0,
+        video_format: VIDEO_FORMAT_UNSPECIFIED,
+        colour_primaries: colour_description.colour_primaries,
+        transfer_characteristics: colour_description.transfer_characteristics,
+        matrix_coeffs: colour_description.matrix_coefficients,
         vui_num_units_in_tick: 0,
-        chroma_sample_loc_type_top_field: 0,
-        chroma_sample_loc_type_bottom_field: 0,
+        chroma_sample_loc_type_top_field: chroma_sample_loc_type,
+        chroma_sample_loc_type_bottom_field: chroma_sample_loc_type,
         reserved1: 0,
         pHrdParameters: null(),
         reserved2: Default::default(),
@@ -238,7 +307,7 @@ pub fn make_h265_video_session_parameters(
         log2_max_mv_length_horizontal: 0,
         log2_max_mv_length_vertical: 0,
     };
-    assert_eq!(format, vk::Format::G8_B8R8_2PLANE_420_UNORM);
+    assert_eq!(format, config.format);
 
     let mut flags = unsafe {
         MaybeUninit::<vk::native::StdVideoH265ProfileTierLevelFlags>::zeroed().assume_init()
@@ -248,8 +317,8 @@ pub fn make_h265_video_session_parameters(
 
     let profile_tier_level = vk::native::StdVideoH265ProfileTierLevel {
         flags,
-        general_profile_idc: vk::native::StdVideoH265ProfileIdc_STD_VIDEO_H265_PROFILE_IDC_MAIN,
-        general_level_idc: vk::native::StdVideoH265LevelIdc_STD_VIDEO_H265_LEVEL_IDC_6_1,
+        general_profile_idc: config.h265_profile_idc(),
+        general_level_idc: config.level,
     };
 
     let flags = MaybeUninit::zeroed();
@@ -260,7 +329,9 @@ pub fn make_h265_video_session_parameters(
         max_dec_pic_buffering_minus1: Default::default(),
         max_num_reorder_pics: Default::default(),
     };
-    dec_pic_buf_mgr.max_dec_pic_buffering_minus1[0] = 1;
+    // the references plus the current picture
+    dec_pic_buf_mgr.max_dec_pic_buffering_minus1[0] = num_reference_frames(consecutive_b_frames);
+    dec_pic_buf_mgr.max_num_reorder_pics[0] = (consecutive_b_frames > 0) as u8;
     let sub_layer_hdr_parameters = vk::native::StdVideoH265SubLayerHrdParameters {
         bit_rate_value_minus1: Default::default(),
         cpb_size_value_minus1: Default::default(),
@@ -338,7 +409,7 @@ pub fn make_h265_video_session_parameters(
     };
     flags.set_amp_enabled_flag(1);
     flags.set_sample_adaptive_offset_enabled_flag(1);
-    let sps = vec![vk::native::StdVideoH265SequenceParameterSet {
+    let mut sps = vec![vk::native::StdVideoH265SequenceParameterSet {
         flags,
         chroma_format_idc:
             vk::native::StdVideoH265ChromaFormatIdc_STD_VIDEO_H265_CHROMA_FORMAT_IDC_420,
@@ -347,13 +418,14 @@ pub fn make_h265_video_session_parameters(
         sps_video_parameter_set_id: 0,
         sps_max_sub_layers_minus1: 0,
         sps_seq_parameter_set_id: 0,
-        bit_depth_luma_minus8: 0,
-        bit_depth_chroma_minus8: 0,
+        bit_depth_luma_minus8: bitdepth - 8,
+        bit_depth_chroma_minus8: bitdepth - 8,
         log2_max_pic_order_cnt_lsb_minus4: 8 - 4, // pic order count 0-255
         log2_min_luma_coding_block_size_minus3: 1, // 16
-        log2_diff_max_min_luma_coding_block_size: 1, // 32
+        log2_diff_max_min_luma_coding_block_size: log2_ctb_size - 4,
         log2_min_luma_transform_block_size_minus2: 0,
-        log2_diff_max_min_luma_transform_block_size: 3,
+        // transform blocks of up to 32x32 but at most the CTB size
+        log2_diff_max_min_luma_transform_block_size: log2_ctb_size.min(5) - 2,
         max_transform_hierarchy_depth_inter: 3,
         max_transform_hierarchy_depth_intra: 3,
         num_short_term_ref_pic_sets: 1,
@@ -368,18 +440,23 @@ pub fn make_h265_video_session_parameters(
         delta_palette_max_predictor_size: 0,
         motion_vector_resolution_control_idc: 0,
         sps_num_palette_predictor_initializers_minus1: 0,
+        // in chroma samples
         conf_win_left_offset: 0,
-        conf_win_right_offset: (32 - coded_extent.width % 32) / 2,
+        conf_win_right_offset: (coded_extent.width - extent.width) / 2,
         conf_win_top_offset: 0,
-        conf_win_bottom_offset: (32 - coded_extent.height % 32) / 2,
+        conf_win_bottom_offset: (coded_extent.height - extent.height) / 2,
         pProfileTierLevel: &profile_tier_level,
         pDecPicBufMgr: &dec_pic_buf_mgr,
         pScalingLists: &scaling_lists,
         pShortTermRefPicSet: &short_term_ref_pics_set,
         pLongTermRefPicsSps: &long_term_ref_pics_sps,
-        pSequenceParameterSetVui: null(),
+        pSequenceParameterSetVui: &vui,
         pPredictorPaletteEntries: null(),
     }];
+    sps[0].flags.set_vui_parameters_present_flag(1);
+    if sps[0].conf_win_right_offset != 0 || sps[0].conf_win_bottom_offset != 0 {
+        sps[0].flags.set_conformance_window_flag(1);
+    }
     let flags = MaybeUninit::zeroed();
     let mut flags: vk::native::StdVideoH265PpsFlags = unsafe { flags.assume_init() };
     flags.set_transform_skip_enabled_flag(1);
@@ -440,6 +517,10 @@ pub fn make_h265_video_session_parameters(
         let mut info =
             vk::VideoSessionParametersCreateInfoKHR::default().video_session(video_session);
         info = info.push_next(&mut codec_info);
+        // the parameters are optimized for the quality level the session uses
+        let mut quality =
+            vk::VideoEncodeQualityLevelInfoKHR::default().quality_level(config.quality_level);
+        info = info.push_next(&mut quality);
         let mut parameters = MaybeUninit::zeroed();
         let res = (video_queue_fn.create_video_session_parameters_khr)(
             device.handle(),
@@ -452,6 +533,7 @@ pub fn make_h265_video_session_parameters(
         }
         res.result_with_success(parameters.assume_init())
     };
+    let mut overrides = ParameterOverrides::default();
     if let (Some(mut output_file), Ok(video_session_parameters)) =
         (output_file, video_session_parameters)
     {
@@ -497,6 +579,12 @@ pub fn make_h265_video_session_parameters(
         };
         if res == vk::Result::SUCCESS {
             info!("Received driver feedback: {size} bytes, {feedback:?} {h265_feedback:?}");
+            overrides = ParameterOverrides {
+                any: feedback.has_overrides == vk::TRUE,
+                vps: h265_feedback.is_some_and(|f| f.has_std_vps_overrides == vk::TRUE),
+                sps: h265_feedback.is_some_and(|f| f.has_std_sps_overrides == vk::TRUE),
+                pps: h265_feedback.is_some_and(|f| f.has_std_pps_overrides == vk::TRUE),
+            };
             output_file.write(&data).map_err(|e| {
                 error!("Failed to write to file: {e}");
                 unsafe {
@@ -511,17 +599,26 @@ pub fn make_h265_video_session_parameters(
                 vk::Result::ERROR_INITIALIZATION_FAILED
             })?;
         } else {
-            //unsafe {
-            //(video_queue_fn.destroy_video_session_parameters_khr)(
-            //device.handle(),
-            //video_session_parameters,
-            //allocator
-            //.map(|e| e as *const vk::AllocationCallbacks)
-            //.unwrap_or(null()),
-            //)
-            //};
-            error!("Failed to retrieve encode video session parameters: {res}.");
-            //return Err(vk::Result::ERROR_INITIALIZATION_FAILED);
+            warn!("Failed to retrieve encode video session parameters: {res}. Falling back to own bitstream writer logic. Might not use driver applied overwrites");
+            // Own logic to write vps/sps/pps
+            write_h265_vps(&mut output_file, &vps[0]).map_err(|e| {
+                error!("Error writing vps: {e}!");
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
+            write_h265_sps(&mut output_file, &sps[0]).map_err(|e| {
+                error!("Error writing sps: {e}!");
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
+            write_h265_pps(&mut output_file, &pps[0]).map_err(|e| {
+                error!("Error writing pps: {e}!");
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
+        }
+        if let Some(metadata) = &config.hdr_metadata {
+            write_h265_hdr_sei(&mut output_file, metadata).map_err(|e| {
+                error!("Error writing HDR metadata SEI: {e}!");
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
         }
         output_file.flush().map_err(|e| {
             error!("Failed flushing output file: {e}!");
@@ -538,5 +635,160 @@ pub fn make_h265_video_session_parameters(
         })?;
     }
 
-    video_session_parameters
+    video_session_parameters.map(|parameters| (parameters, overrides))
+}
+
+pub fn make_av1_video_session_parameters(
+    device: &ash::Device,
+    video_queue_fn: &khr::video_queue::DeviceFn,
+    encode_queue_fn: &khr::video_encode_queue::DeviceFn,
+    video_session: vk::VideoSessionKHR,
+    config: &EncoderConfig,
+    output_file: Option<impl Write>,
+    allocator: Option<&vk::AllocationCallbacks>,
+) -> VkResult<(vk::VideoSessionParametersKHR, ParameterOverrides)> {
+    // frame size as encoded by the DPB, the real extent is signaled as render size
+    let vk::Extent2D { width, height } = config.coded_extent();
+
+    let colour_description = config.colour_description();
+    let mut flags: vk::native::StdVideoAV1ColorConfigFlags =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    flags.set_color_description_present_flag(1);
+    flags.set_color_range(colour_description.full_range as u32);
+    let color_config = vk::native::StdVideoAV1ColorConfig {
+        flags,
+        BitDepth: config.bit_depth() as u8,
+        subsampling_x: 1,
+        subsampling_y: 1,
+        reserved1: 0,
+        // the H.273 code points of AV1 are the same as in the VUI
+        color_primaries: colour_description.colour_primaries.into(),
+        transfer_characteristics: colour_description.transfer_characteristics.into(),
+        matrix_coefficients: colour_description.matrix_coefficients.into(),
+        chroma_sample_position: match config.conversion.chroma_siting {
+            ChromaSiting::Left => {
+                vk::native::StdVideoAV1ChromaSamplePosition_STD_VIDEO_AV1_CHROMA_SAMPLE_POSITION_VERTICAL
+            }
+            ChromaSiting::TopLeft => {
+                vk::native::StdVideoAV1ChromaSamplePosition_STD_VIDEO_AV1_CHROMA_SAMPLE_POSITION_COLOCATED
+            }
+            // AV1 can't signal centered chroma
+            ChromaSiting::Center => {
+                vk::native::StdVideoAV1ChromaSamplePosition_STD_VIDEO_AV1_CHROMA_SAMPLE_POSITION_UNKNOWN
+            }
+        },
+    };
+
+    let mut flags: vk::native::StdVideoAV1SequenceHeaderFlags =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    flags.set_enable_order_hint(1);
+    flags.set_enable_cdef(1);
+    let sequence_header = vk::native::StdVideoAV1SequenceHeader {
+        flags,
+        seq_profile: vk::native::StdVideoAV1Profile_STD_VIDEO_AV1_PROFILE_MAIN,
+        frame_width_bits_minus_1: 15,
+        frame_height_bits_minus_1: 15,
+        max_frame_width_minus_1: (width - 1) as u16,
+        max_frame_height_minus_1: (height - 1) as u16,
+        delta_frame_id_length_minus_2: 0,
+        additional_frame_id_length_minus_1: 0,
+        order_hint_bits_minus_1: 8 - 1, // order hint 0-255
+        seq_force_integer_mv: STD_VIDEO_AV1_SELECT_INTEGER_MV as u8,
+        seq_force_screen_content_tools: STD_VIDEO_AV1_SELECT_SCREEN_CONTENT_TOOLS as u8,
+        reserved1: Default::default(),
+        pColorConfig: &color_config,
+        pTimingInfo: null(),
+    };
+    let operating_points = [StdVideoEncodeAV1OperatingPointInfo {
+        seq_level_idx: config.level as u8,
+        ..Default::default()
+    }];
+    let mut codec_info = VideoEncodeAV1SessionParametersCreateInfoKHR::default()
+        .std_sequence_header(&sequence_header)
+        .std_operating_points(&operating_points);
+
+    let video_session_parameters = unsafe {
+        let mut info =
+            vk::VideoSessionParametersCreateInfoKHR::default().video_session(video_session);
+        info = info.push_next(&mut codec_info);
+        // the parameters are optimized for the quality level the session uses
+        let mut quality =
+            vk::VideoEncodeQualityLevelInfoKHR::default().quality_level(config.quality_level);
+        info = info.push_next(&mut quality);
+        let mut parameters = MaybeUninit::zeroed();
+        let res = (video_queue_fn.create_video_session_parameters_khr)(
+            device.handle(),
+            &info,
+            allocator.map_or(null(), |allocator| allocator as *const _),
+            parameters.as_mut_ptr(),
+        );
+        if res != vk::Result::SUCCESS {
+            error!("Failed to create AV1 session parameters: {res}");
+        }
+        res.result_with_success(parameters.assume_init())
+    };
+    let mut overrides = ParameterOverrides::default();
+    if let (Some(mut output_file), Ok(video_session_parameters)) =
+        (output_file, video_session_parameters)
+    {
+        // AV1 has no codec specific get info, the result is the sequence header OBU
+        let info = vk::VideoEncodeSessionParametersGetInfoKHR::default()
+            .video_session_parameters(video_session_parameters);
+        let mut feedback = vk::VideoEncodeSessionParametersFeedbackInfoKHR::default();
+        let mut size = 0usize;
+        let mut data = Vec::new();
+        let mut res = unsafe {
+            (encode_queue_fn.get_encoded_video_session_parameters_khr)(
+                device.handle(),
+                &info,
+                &mut feedback,
+                &mut size,
+                null_mut(),
+            )
+        };
+        if res == vk::Result::SUCCESS {
+            info!("Resizing array for feedback: {size} bytes");
+            data.resize(size, 0);
+            res = unsafe {
+                (encode_queue_fn.get_encoded_video_session_parameters_khr)(
+                    device.handle(),
+                    &info,
+                    &mut feedback,
+                    &mut size,
+                    data.as_mut_ptr() as *mut c_void,
+                )
+            };
+        }
+        if res == vk::Result::SUCCESS {
+            info!("Received driver feedback: {size} bytes, {feedback:?}");
+            overrides.any = feedback.has_overrides == vk::TRUE;
+            output_file.write_all(&data).map_err(|e| {
+                error!("Failed to write to file: {e}");
+                unsafe {
+                    (video_queue_fn.destroy_video_session_parameters_khr)(
+                        device.handle(),
+                        video_session_parameters,
+                        allocator
+                            .map(|e| e as *const vk::AllocationCallbacks)
+                            .unwrap_or(null()),
+                    )
+                };
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
+        } else {
+            error!("Failed to retrieve encode video session parameters: {res}.");
+        }
+        if let Some(metadata) = &config.hdr_metadata {
+            write_av1_hdr_metadata(&mut output_file, metadata).map_err(|e| {
+                error!("Error writing HDR metadata OBUs: {e}!");
+                vk::Result::ERROR_INITIALIZATION_FAILED
+            })?;
+        }
+        output_file.flush().map_err(|e| {
+            error!("Failed flushing output file: {e}!");
+            vk::Result::ERROR_INITIALIZATION_FAILED
+        })?;
+    }
+
+    video_session_parameters.map(|parameters| (parameters, overrides))
 }
@@ -1,9 +1,12 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{fmt::Display, path::PathBuf, time::Duration};
 
 use log::{debug, error, info};
 use regex::Regex;
 
-use crate::dpb::PictureType;
+use crate::{
+    dpb::PictureType, filename::DEFAULT_FILENAME_TEMPLATE, reorder::MAX_CONSECUTIVE_B_FRAMES,
+    segment::SegmentLimits,
+};
 
 #[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
 pub enum Codec {
@@ -13,17 +16,93 @@ pub enum Codec {
     AV1,
 }
 
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum Container {
+    #[default]
+    AnnexB,
+    Mp4,
+    Mkv,
+}
+
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum ControlSocket {
+    #[default]
+    None,
+    /// Per-process socket file in the runtime directory
+    Path,
+    /// Abstract socket named after the application
+    Abstract,
+}
+
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum FrameStatsFormat {
+    #[default]
+    None,
+    Csv,
+    /// JSON Lines, one object per frame
+    Jsonl,
+}
+
 #[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
 pub enum RateControlMode {
     #[default]
     Cbr,
+    /// Variable bitrate between the average and maximum bitrate
+    Vbr,
+    /// Constant QP per picture type
+    Cqp,
+    /// Rate control is left to the implementation
+    Disabled,
+}
+
+/// Filter downsampling the swapchain images, the `SCALE_FILTER` constant of `scale.hlsli`
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum ScaleFilter {
+    #[default]
+    Bilinear = 0,
+    /// Lanczos with 3 lobes, sharper but slower
+    Lanczos = 1,
+    /// Area average, for integer downscaling factors
+    Box = 2,
+}
+
+/// YCbCr matrix of SDR swapchains, HDR swapchains always use BT.2020
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum ColorMatrix {
+    Bt601,
+    #[default]
+    Bt709,
+    Bt2020,
+}
+
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum ColorRange {
+    /// 16-235 luma and 16-240 chroma for 8 bit
+    #[default]
+    Limited,
+    Full,
+}
+
+/// Position of the chroma samples relative to the luma samples, the values are the
+/// `chroma_sample_loc_type` of the VUI
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone)]
+pub enum ChromaSiting {
+    /// Horizontally co-sited with the left luma sample, vertically between the rows (MPEG-2)
+    #[default]
+    Left = 0,
+    /// Centered between the four luma samples (MPEG-1, JPEG)
+    Center = 1,
+    /// Co-sited with the top left luma sample (BT.2020 UHD)
+    TopLeft = 2,
 }
 
 #[derive(Debug, Clone)]
 pub struct Settings {
     pub codec: Codec,
+    pub container: Container,
     pub output_folder: PathBuf,
-    pub use_nvpro: bool,
+    /// Name of output files without extension, see [`crate::filename::expand_filename`]
+    pub filename_template: String,
     pub gop_size: u64,
     pub idr_period: u64,
     pub max_consecutive_b_frames: u64,
@@ -33,17 +112,54 @@ pub struct Settings {
     pub rate_control_mode: RateControlMode,
     pub frame_rate_numerator: u32,
     pub frame_rate_denominator: u32,
+    pub fixed_frame_rate: bool,
     pub vbv_size_in_ms: u32,
     pub initial_vbv_size_in_ms: u32,
     pub quality_level: u32,
+    /// QPs of I, P and B frames in [`RateControlMode::Cqp`]
+    pub qp_i: u32,
+    pub qp_p: u32,
+    pub qp_b: u32,
+    /// Size of the recorded video, 0 keeps the swapchain size or its aspect ratio if only the
+    /// other one is set
+    pub output_width: u32,
+    pub output_height: u32,
+    pub scale_filter: ScaleFilter,
+    /// Keep the aspect ratio of the swapchain with black bars instead of stretching it
+    pub letterbox: bool,
+    pub color_matrix: ColorMatrix,
+    pub color_range: ColorRange,
+    pub chroma_siting: ChromaSiting,
+    /// Peak luminance of the mastering display in cd/m², signalled for HDR swapchains
+    pub hdr_max_luminance: u32,
+    /// Minimum luminance of the mastering display in 0.0001 cd/m²
+    pub hdr_min_luminance: u32,
+    /// Maximum content and frame-average light level in cd/m²
+    pub hdr_max_cll: u32,
+    pub hdr_max_fall: u32,
+    /// Keep only this many seconds in memory until a replay is saved, 0 records to a file
+    pub replay_buffer_seconds: u32,
+    /// Drop the oldest GOPs once the replay buffer holds more than this many MiB, 0 disables the
+    /// limit
+    pub replay_buffer_mb: u64,
+    pub control_socket: ControlSocket,
+    /// Toggle recording on SIGUSR1, save the replay or split the file on SIGUSR2
+    pub signal_handlers: bool,
+    /// Start a new file after this many seconds, 0 disables the limit
+    pub segment_duration_s: u64,
+    /// Start a new file after this many MiB, 0 disables the limit
+    pub segment_size_mb: u64,
+    /// Write per-frame encode statistics next to every output file
+    pub frame_stats: FrameStatsFormat,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             codec: Codec::default(),
+            container: Container::default(),
             output_folder: "".into(),
-            use_nvpro: false,
+            filename_template: DEFAULT_FILENAME_TEMPLATE.into(),
             gop_size: 16,
             idr_period: 16,
             max_consecutive_b_frames: 0,
@@ -55,7 +171,29 @@ impl Default for Settings {
             rate_control_mode: RateControlMode::Cbr,
             frame_rate_numerator: 60,
             frame_rate_denominator: 1,
+            fixed_frame_rate: false,
             quality_level: 1,
+            qp_i: 22,
+            qp_p: 24,
+            qp_b: 26,
+            output_width: 0,
+            output_height: 0,
+            scale_filter: ScaleFilter::default(),
+            letterbox: true,
+            color_matrix: ColorMatrix::default(),
+            color_range: ColorRange::default(),
+            chroma_siting: ChromaSiting::default(),
+            hdr_max_luminance: 1000,
+            hdr_min_luminance: 50,
+            hdr_max_cll: 1000,
+            hdr_max_fall: 400,
+            replay_buffer_seconds: 0,
+            replay_buffer_mb: 0,
+            control_socket: ControlSocket::default(),
+            signal_handlers: false,
+            segment_duration_s: 0,
+            segment_size_mb: 0,
+            frame_stats: FrameStatsFormat::default(),
         }
     }
 }
@@ -78,14 +216,15 @@ impl Settings {
                         );
                         match &cap[1] {
                             "video_output_folder" => settings.output_folder = cap[2].into(),
+                            "filename_template" => settings.filename_template = cap[2].into(),
                             "codec" => settings.codec = cap[2].into(),
+                            "container" => settings.container = cap[2].into(),
                             "rate_control_mode" => settings.rate_control_mode = cap[2].into(),
-                            "use_nvpro" => settings.use_nvpro = cap[2].parse().unwrap_or(false),
                             "gop_size" => settings.gop_size = cap[2].parse().unwrap_or(16),
-                            "idr_period" => settings.gop_size = cap[2].parse().unwrap_or(16),
+                            "idr_period" => settings.idr_period = cap[2].parse().unwrap_or(16),
                             "last_frame_type" => settings.last_frame_type = cap[2].into(),
                             "max_consecutive_b_frames" => {
-                                settings.max_consecutive_b_frames = cap[2].parse().unwrap_or(16)
+                                settings.max_consecutive_b_frames = cap[2].parse().unwrap_or(0)
                             }
                             "frame_rate_numerator" => {
                                 settings.frame_rate_numerator = cap[2].parse().unwrap_or(60)
@@ -93,6 +232,9 @@ impl Settings {
                             "frame_rate_denominator" => {
                                 settings.frame_rate_denominator = cap[2].parse().unwrap_or(1)
                             }
+                            "fixed_frame_rate" => {
+                                settings.fixed_frame_rate = cap[2].parse().unwrap_or(false)
+                            }
                             "average_bitrate" => {
                                 settings.average_bitrate = cap[2].parse().unwrap_or(8 * 1024 * 1024)
                             }
@@ -107,6 +249,41 @@ impl Settings {
                                     cap[2].parse().unwrap_or(8 * 1024 * 1024)
                             }
                             "quality_level" => settings.quality_level = cap[2].parse().unwrap_or(1),
+                            "qp_i" => settings.qp_i = cap[2].parse().unwrap_or(22),
+                            "qp_p" => settings.qp_p = cap[2].parse().unwrap_or(24),
+                            "qp_b" => settings.qp_b = cap[2].parse().unwrap_or(26),
+                            "output_width" => settings.output_width = cap[2].parse().unwrap_or(0),
+                            "output_height" => settings.output_height = cap[2].parse().unwrap_or(0),
+                            "scale_filter" => settings.scale_filter = cap[2].into(),
+                            "letterbox" => settings.letterbox = cap[2].parse().unwrap_or(true),
+                            "color_matrix" => settings.color_matrix = cap[2].into(),
+                            "color_range" => settings.color_range = cap[2].into(),
+                            "chroma_siting" => settings.chroma_siting = cap[2].into(),
+                            "hdr_max_luminance" => {
+                                settings.hdr_max_luminance = cap[2].parse().unwrap_or(1000)
+                            }
+                            "hdr_min_luminance" => {
+                                settings.hdr_min_luminance = cap[2].parse().unwrap_or(50)
+                            }
+                            "hdr_max_cll" => settings.hdr_max_cll = cap[2].parse().unwrap_or(1000),
+                            "hdr_max_fall" => settings.hdr_max_fall = cap[2].parse().unwrap_or(400),
+                            "replay_buffer_seconds" => {
+                                settings.replay_buffer_seconds = cap[2].parse().unwrap_or(0)
+                            }
+                            "replay_buffer_mb" => {
+                                settings.replay_buffer_mb = cap[2].parse().unwrap_or(0)
+                            }
+                            "control_socket" => settings.control_socket = cap[2].into(),
+                            "signal_handlers" => {
+                                settings.signal_handlers = cap[2].parse().unwrap_or(false)
+                            }
+                            "segment_duration_s" => {
+                                settings.segment_duration_s = cap[2].parse().unwrap_or(0)
+                            }
+                            "segment_size_mb" => {
+                                settings.segment_size_mb = cap[2].parse().unwrap_or(0)
+                            }
+                            "frame_stats" => settings.frame_stats = cap[2].into(),
                             _ => error!("Could not parse unknown key {}", &cap[1]),
                         }
                     }
@@ -118,12 +295,68 @@ impl Settings {
         if let Ok(output_file) = std::env::var("VK_VIDEO_RECORD_OUTPUT_FOLDER") {
             settings.output_folder = output_file.into();
         }
+        if let Ok(filename_template) = std::env::var("VK_VIDEO_RECORD_FILENAME_TEMPLATE") {
+            settings.filename_template = filename_template;
+        }
         if let Ok(codec) = std::env::var("VK_VIDEO_RECORD_CODEC") {
             settings.codec = codec.into();
         }
+        if let Ok(container) = std::env::var("VK_VIDEO_RECORD_CONTAINER") {
+            settings.container = container.into();
+        }
+        if let Ok(rate_control_mode) = std::env::var("VK_VIDEO_RECORD_RATE_CONTROL_MODE") {
+            settings.rate_control_mode = rate_control_mode.into();
+        }
+        if let Ok(fixed_frame_rate) = std::env::var("VK_VIDEO_RECORD_FIXED_FRAME_RATE") {
+            settings.fixed_frame_rate = fixed_frame_rate.parse().unwrap_or(false);
+        }
+        if let Ok(replay_buffer_seconds) = std::env::var("VK_VIDEO_RECORD_REPLAY_BUFFER_SECONDS") {
+            settings.replay_buffer_seconds = replay_buffer_seconds.parse().unwrap_or(0);
+        }
+        if let Ok(replay_buffer_mb) = std::env::var("VK_VIDEO_RECORD_REPLAY_BUFFER_MB") {
+            settings.replay_buffer_mb = replay_buffer_mb.parse().unwrap_or(0);
+        }
+        if let Ok(control_socket) = std::env::var("VK_VIDEO_RECORD_CONTROL_SOCKET") {
+            settings.control_socket = control_socket.into();
+        }
+        if let Ok(signal_handlers) = std::env::var("VK_VIDEO_RECORD_SIGNAL_HANDLERS") {
+            settings.signal_handlers = signal_handlers.parse().unwrap_or(false);
+        }
+        if let Ok(segment_duration_s) = std::env::var("VK_VIDEO_RECORD_SEGMENT_DURATION_S") {
+            settings.segment_duration_s = segment_duration_s.parse().unwrap_or(0);
+        }
+        if let Ok(segment_size_mb) = std::env::var("VK_VIDEO_RECORD_SEGMENT_SIZE_MB") {
+            settings.segment_size_mb = segment_size_mb.parse().unwrap_or(0);
+        }
+        if let Ok(frame_stats) = std::env::var("VK_VIDEO_RECORD_FRAME_STATS") {
+            settings.frame_stats = frame_stats.into();
+        }
         info!("{:?}", settings);
         settings
     }
+
+    pub fn segment_limits(&self) -> SegmentLimits {
+        SegmentLimits {
+            duration: (self.segment_duration_s > 0)
+                .then(|| Duration::from_secs(self.segment_duration_s)),
+            size: (self.segment_size_mb > 0).then(|| self.segment_size_mb * 1024 * 1024),
+        }
+    }
+
+    /// Size limit of the replay buffer in bytes
+    pub fn replay_buffer_size(&self) -> Option<usize> {
+        (self.replay_buffer_mb > 0).then(|| self.replay_buffer_mb as usize * 1024 * 1024)
+    }
+
+    /// Number of B frames between two anchors the encoder actually uses. AV1 is encoded without
+    /// B frames.
+    pub fn consecutive_b_frames(&self) -> u8 {
+        if self.codec == Codec::AV1 {
+            return 0;
+        }
+        self.max_consecutive_b_frames
+            .min(MAX_CONSECUTIVE_B_FRAMES as u64) as u8
+    }
 }
 
 impl<T> From<T> for Codec
@@ -147,13 +380,79 @@ where
     }
 }
 
+impl<T> From<T> for Container
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "ANNEXB" => Container::AnnexB,
+            "MP4" => Container::Mp4,
+            "MKV" => Container::Mkv,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for VK_VIDEO_RECORD_CONTAINER! Falling back to {:?}",
+                    value,
+                    Container::default()
+                );
+                Container::default()
+            }
+        }
+    }
+}
+
+impl<T> From<T> for FrameStatsFormat
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "NONE" => FrameStatsFormat::None,
+            "CSV" => FrameStatsFormat::Csv,
+            "JSONL" => FrameStatsFormat::Jsonl,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for VK_VIDEO_RECORD_FRAME_STATS! Falling back to {:?}",
+                    value,
+                    FrameStatsFormat::default()
+                );
+                FrameStatsFormat::default()
+            }
+        }
+    }
+}
+
+impl<T> From<T> for ControlSocket
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "NONE" => ControlSocket::None,
+            "PATH" => ControlSocket::Path,
+            "ABSTRACT" => ControlSocket::Abstract,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for VK_VIDEO_RECORD_CONTROL_SOCKET! Falling back to {:?}",
+                    value,
+                    ControlSocket::default()
+                );
+                ControlSocket::default()
+            }
+        }
+    }
+}
+
 impl<T> From<T> for RateControlMode
 where
     T: AsRef<str> + Display,
 {
     fn from(value: T) -> Self {
-        match value.as_ref() {
+        match value.as_ref().to_ascii_uppercase().as_str() {
             "CBR" => RateControlMode::Cbr,
+            "VBR" => RateControlMode::Vbr,
+            "CQP" => RateControlMode::Cqp,
+            "DISABLED" => RateControlMode::Disabled,
             _ => {
                 error!(
                     "Could not parse value \"{}\" for rate control mode! Falling back to {:?}",
@@ -166,6 +465,89 @@ where
     }
 }
 
+impl<T> From<T> for ScaleFilter
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "BILINEAR" => ScaleFilter::Bilinear,
+            "LANCZOS" => ScaleFilter::Lanczos,
+            "BOX" => ScaleFilter::Box,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for scale filter! Falling back to {:?}",
+                    value,
+                    ScaleFilter::default()
+                );
+                ScaleFilter::default()
+            }
+        }
+    }
+}
+
+impl<T> From<T> for ColorMatrix
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "BT601" => ColorMatrix::Bt601,
+            "BT709" => ColorMatrix::Bt709,
+            "BT2020" => ColorMatrix::Bt2020,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for color matrix! Falling back to {:?}",
+                    value,
+                    ColorMatrix::default()
+                );
+                ColorMatrix::default()
+            }
+        }
+    }
+}
+
+impl<T> From<T> for ColorRange
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "LIMITED" => ColorRange::Limited,
+            "FULL" => ColorRange::Full,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for color range! Falling back to {:?}",
+                    value,
+                    ColorRange::default()
+                );
+                ColorRange::default()
+            }
+        }
+    }
+}
+
+impl<T> From<T> for ChromaSiting
+where
+    T: AsRef<str> + Display,
+{
+    fn from(value: T) -> Self {
+        match value.as_ref().to_ascii_uppercase().as_str() {
+            "LEFT" => ChromaSiting::Left,
+            "CENTER" => ChromaSiting::Center,
+            "TOPLEFT" => ChromaSiting::TopLeft,
+            _ => {
+                error!(
+                    "Could not parse value \"{}\" for chroma siting! Falling back to {:?}",
+                    value,
+                    ChromaSiting::default()
+                );
+                ChromaSiting::default()
+            }
+        }
+    }
+}
+
 impl<T> From<T> for PictureType
 where
     T: AsRef<str> + Display,
@@ -80,10 +80,12 @@ impl ShaderPipeline {
         Ok(Self { shaders })
     }
 
+    /// `specialization_constants` are the 32 bit values of the constant ids 0, 1, ...
     pub fn make_compute_pipeline(
         &self,
         device: &ash::Device,
         entry_point: &str,
+        specialization_constants: &[u32],
         allocator: Option<&vk::AllocationCallbacks>,
     ) -> anyhow::Result<ComputePipelineDescriptor> {
         let mut bindings: HashMap<u32, Vec<vk::DescriptorSetLayoutBinding>> = Default::default();
@@ -155,10 +157,26 @@ impl ShaderPipeline {
         let shader = &self.shaders[0];
         let entry_point =
             CString::new(entry_point).expect("Could not convert entry point to CStr!");
+        let map_entries = (0..specialization_constants.len() as u32)
+            .map(|id| {
+                vk::SpecializationMapEntry::default()
+                    .constant_id(id)
+                    .offset(id * 4)
+                    .size(4)
+            })
+            .collect_vec();
+        let data = specialization_constants
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect_vec();
+        let specialization_info = vk::SpecializationInfo::default()
+            .map_entries(&map_entries)
+            .data(&data);
         let shader_stage_create_info = vk::PipelineShaderStageCreateInfo::default()
             .name(&entry_point)
             .module(shader.module)
-            .stage(vk::ShaderStageFlags::COMPUTE);
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .specialization_info(&specialization_info);
 
         let pipeline = unsafe {
             device.create_compute_pipelines(
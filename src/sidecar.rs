@@ -0,0 +1,549 @@
+use std::fmt::{Display, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+use chrono::{DateTime, Local};
+use log::{error, info};
+
+use crate::dpb::{RateControlKind, RateControlOptions};
+use crate::frame_stats::FrameStats;
+use crate::muxer::{EncodedFrame, Muxer};
+use crate::session_parameters::ParameterOverrides;
+use crate::settings::Settings;
+use crate::state::get_state;
+
+const VENDOR_ID_NVIDIA: u32 = 0x10de;
+
+/// Minimal JSON document, just enough for the sidecar files
+enum Json {
+    Bool(bool),
+    Number(String),
+    String(String),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn number(value: impl Display) -> Self {
+        Self::Number(value.to_string())
+    }
+
+    fn string(value: impl Display) -> Self {
+        Self::String(value.to_string())
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Self::Bool(value) => write!(out, "{value}").unwrap(),
+            Self::Number(value) => out.push_str(value),
+            Self::String(value) => write_string(out, value),
+            Self::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Self::Object(fields) => {
+                out.push('{');
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    write!(out, "\n{:1$}", "", (indent + 1) * 2).unwrap();
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                write!(out, "\n{:1$}}}", "", indent * 2).unwrap();
+            }
+        }
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Capture metadata that is the same for every file recorded from a swapchain
+#[derive(Debug, Clone)]
+pub struct CaptureInfo {
+    pub device_name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_version: u32,
+    pub api_version: u32,
+    /// Graphics, compute, encode and decode queue family
+    pub queue_families: [u32; 4],
+    pub swapchain_format: vk::Format,
+    pub swapchain_extent: vk::Extent2D,
+    pub parameter_overrides: ParameterOverrides,
+}
+
+impl CaptureInfo {
+    pub fn new(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        swapchain_format: vk::Format,
+        swapchain_extent: vk::Extent2D,
+        parameter_overrides: ParameterOverrides,
+    ) -> Self {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let state = get_state();
+        Self {
+            device_name: properties
+                .device_name_as_c_str()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            driver_version: properties.driver_version,
+            api_version: properties.api_version,
+            queue_families: [
+                *state.graphics_queue_family_idx.read().unwrap(),
+                *state.compute_queue_family_idx.read().unwrap(),
+                *state.encode_queue_family_idx.read().unwrap(),
+                *state.decode_queue_family_idx.read().unwrap(),
+            ],
+            swapchain_format,
+            swapchain_extent,
+            parameter_overrides,
+        }
+    }
+
+    /// The driver version is vendor specific, everyone but NVIDIA uses the Vulkan version scheme
+    fn driver_version_string(&self) -> String {
+        let version = self.driver_version;
+        if self.vendor_id == VENDOR_ID_NVIDIA {
+            format!(
+                "{}.{}.{}.{}",
+                version >> 22,
+                (version >> 14) & 0xff,
+                (version >> 6) & 0xff,
+                version & 0x3f
+            )
+        } else {
+            version_string(version)
+        }
+    }
+}
+
+fn version_string(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        vk::api_version_major(version),
+        vk::api_version_minor(version),
+        vk::api_version_patch(version)
+    )
+}
+
+fn settings_json(settings: &Settings) -> Json {
+    Json::Object(vec![
+        ("codec", Json::string(format!("{:?}", settings.codec))),
+        (
+            "container",
+            Json::string(format!("{:?}", settings.container)),
+        ),
+        (
+            "output_folder",
+            Json::string(settings.output_folder.display()),
+        ),
+        (
+            "filename_template",
+            Json::string(&settings.filename_template),
+        ),
+        ("gop_size", Json::number(settings.gop_size)),
+        ("idr_period", Json::number(settings.idr_period)),
+        (
+            "consecutive_b_frames",
+            Json::number(settings.consecutive_b_frames()),
+        ),
+        (
+            "last_frame_type",
+            Json::string(format!("{:?}", settings.last_frame_type)),
+        ),
+        (
+            "rate_control_mode",
+            Json::string(format!("{:?}", settings.rate_control_mode)),
+        ),
+        ("average_bitrate", Json::number(settings.average_bitrate)),
+        ("max_bitrate", Json::number(settings.max_bitrate)),
+        ("vbv_size_in_ms", Json::number(settings.vbv_size_in_ms)),
+        (
+            "initial_vbv_size_in_ms",
+            Json::number(settings.initial_vbv_size_in_ms),
+        ),
+        ("quality_level", Json::number(settings.quality_level)),
+        ("qp_i", Json::number(settings.qp_i)),
+        ("qp_p", Json::number(settings.qp_p)),
+        ("qp_b", Json::number(settings.qp_b)),
+        ("output_width", Json::number(settings.output_width)),
+        ("output_height", Json::number(settings.output_height)),
+        (
+            "scale_filter",
+            Json::string(format!("{:?}", settings.scale_filter)),
+        ),
+        ("letterbox", Json::Bool(settings.letterbox)),
+        (
+            "hdr_max_luminance",
+            Json::number(settings.hdr_max_luminance),
+        ),
+        (
+            "hdr_min_luminance",
+            Json::number(settings.hdr_min_luminance),
+        ),
+        ("hdr_max_cll", Json::number(settings.hdr_max_cll)),
+        ("hdr_max_fall", Json::number(settings.hdr_max_fall)),
+        (
+            "color_matrix",
+            Json::string(format!("{:?}", settings.color_matrix)),
+        ),
+        (
+            "color_range",
+            Json::string(format!("{:?}", settings.color_range)),
+        ),
+        (
+            "chroma_siting",
+            Json::string(format!("{:?}", settings.chroma_siting)),
+        ),
+        (
+            "frame_rate_numerator",
+            Json::number(settings.frame_rate_numerator),
+        ),
+        (
+            "frame_rate_denominator",
+            Json::number(settings.frame_rate_denominator),
+        ),
+        ("fixed_frame_rate", Json::Bool(settings.fixed_frame_rate)),
+        (
+            "replay_buffer_seconds",
+            Json::number(settings.replay_buffer_seconds),
+        ),
+        ("replay_buffer_mb", Json::number(settings.replay_buffer_mb)),
+        (
+            "segment_duration_s",
+            Json::number(settings.segment_duration_s),
+        ),
+        ("segment_size_mb", Json::number(settings.segment_size_mb)),
+    ])
+}
+
+/// What happened while one file was recorded
+struct RecordingStats {
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+    frames: u64,
+    dropped_frames: u64,
+}
+
+/// The rate control the encoder ended up using, which differs from the settings after they were
+/// clamped to the capabilities or changed through the control socket
+fn rate_control_json(options: &RateControlOptions) -> Json {
+    let mut fields = match &options.kind {
+        RateControlKind::Cbr(bitrate) | RateControlKind::Vbr(bitrate) => vec![
+            ("average_bitrate", Json::number(bitrate.average_bitrate)),
+            ("max_bitrate", Json::number(bitrate.max_bitrate)),
+        ],
+        RateControlKind::Cqp(cqp) => vec![
+            ("qp_i", Json::number(cqp.intra)),
+            ("qp_p", Json::number(cqp.predictive)),
+            ("qp_b", Json::number(cqp.bipredictive)),
+        ],
+        RateControlKind::Disabled => vec![],
+    };
+    let mode = match options.kind {
+        RateControlKind::Cbr(_) => "Cbr",
+        RateControlKind::Vbr(_) => "Vbr",
+        RateControlKind::Cqp(_) => "Cqp",
+        RateControlKind::Disabled => "Disabled",
+    };
+    fields.insert(0, ("mode", Json::string(mode)));
+    fields.push(("quality_level", Json::number(options.quality_level)));
+    Json::Object(fields)
+}
+
+fn sidecar_json(
+    settings: &Settings,
+    info: &CaptureInfo,
+    rate_control: Option<&RateControlOptions>,
+    stats: &RecordingStats,
+) -> Json {
+    let overrides = &info.parameter_overrides;
+    let [graphics, compute, encode, decode] = info.queue_families;
+    let mut fields = vec![("settings", settings_json(settings))];
+    if let Some(rate_control) = rate_control {
+        fields.push(("rate_control", rate_control_json(rate_control)));
+    }
+    fields.extend([
+        (
+            "parameter_overrides",
+            Json::Object(vec![
+                ("any", Json::Bool(overrides.any)),
+                ("vps", Json::Bool(overrides.vps)),
+                ("sps", Json::Bool(overrides.sps)),
+                ("pps", Json::Bool(overrides.pps)),
+            ]),
+        ),
+        (
+            "swapchain",
+            Json::Object(vec![
+                (
+                    "format",
+                    Json::string(format!("{:?}", info.swapchain_format)),
+                ),
+                ("width", Json::number(info.swapchain_extent.width)),
+                ("height", Json::number(info.swapchain_extent.height)),
+            ]),
+        ),
+        (
+            "queue_families",
+            Json::Object(vec![
+                ("graphics", Json::number(graphics)),
+                ("compute", Json::number(compute)),
+                ("encode", Json::number(encode)),
+                ("decode", Json::number(decode)),
+            ]),
+        ),
+        (
+            "device",
+            Json::Object(vec![
+                ("name", Json::string(&info.device_name)),
+                (
+                    "vendor_id",
+                    Json::string(format!("{:#06x}", info.vendor_id)),
+                ),
+                (
+                    "device_id",
+                    Json::string(format!("{:#06x}", info.device_id)),
+                ),
+                ("driver_version", Json::string(info.driver_version_string())),
+                ("driver_version_raw", Json::number(info.driver_version)),
+                (
+                    "api_version",
+                    Json::string(version_string(info.api_version)),
+                ),
+            ]),
+        ),
+        ("start_time", Json::string(stats.start_time.to_rfc3339())),
+        ("end_time", Json::string(stats.end_time.to_rfc3339())),
+        ("frames", Json::number(stats.frames)),
+        ("dropped_frames", Json::number(stats.dropped_frames)),
+    ]);
+    Json::Object(fields)
+}
+
+/// Rate control of a swapchain's encoder, updated when it changes while recording
+pub type SharedRateControl = Arc<Mutex<Option<RateControlOptions>>>;
+
+/// Counts the frames written to `output` and writes a `.json` file with the capture metadata next
+/// to it once it is finished.
+pub struct SidecarMuxer {
+    output: Box<dyn Muxer>,
+    path: PathBuf,
+    info: Arc<CaptureInfo>,
+    /// Frames dropped by the swapchain since it was created, shared by all of its outputs
+    dropped_frames: Arc<AtomicU64>,
+    /// Value of `dropped_frames` when this output was opened
+    dropped_frames_at_start: u64,
+    /// Reported as it is when the output is finished
+    rate_control: SharedRateControl,
+    start_time: DateTime<Local>,
+    frames: u64,
+}
+
+impl SidecarMuxer {
+    /// `path` is the path of the output file, the sidecar replaces its extension
+    pub fn new(
+        output: Box<dyn Muxer>,
+        path: &Path,
+        info: Arc<CaptureInfo>,
+        dropped_frames: Arc<AtomicU64>,
+        rate_control: SharedRateControl,
+    ) -> Self {
+        Self {
+            output,
+            path: path.with_extension("json"),
+            info,
+            dropped_frames_at_start: dropped_frames.load(Ordering::Relaxed),
+            dropped_frames,
+            rate_control,
+            start_time: Local::now(),
+            frames: 0,
+        }
+    }
+
+    /// Frames dropped since this output was opened
+    fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed) - self.dropped_frames_at_start
+    }
+}
+
+impl Muxer for SidecarMuxer {
+    fn write_frame(&mut self, frame: &EncodedFrame) -> std::io::Result<()> {
+        self.output.write_frame(frame)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        let res = self.output.finish();
+        let stats = RecordingStats {
+            start_time: self.start_time,
+            end_time: Local::now(),
+            frames: self.frames,
+            dropped_frames: self.dropped_frames(),
+        };
+        let rate_control = self.rate_control.lock().unwrap();
+        let mut json = String::new();
+        sidecar_json(
+            &get_state().settings,
+            &self.info,
+            rate_control.as_ref(),
+            &stats,
+        )
+        .write(&mut json, 0);
+        json.push('\n');
+        info!("Writing sidecar {:?}", self.path);
+        if let Err(err) = std::fs::write(&self.path, json) {
+            error!("Failed to write sidecar {:?}: {err}", self.path);
+        }
+        res
+    }
+
+    fn wants_keyframe(&mut self) -> bool {
+        self.output.wants_keyframe()
+    }
+
+    fn write_frame_stats(&mut self, stats: &FrameStats) -> std::io::Result<()> {
+        self.output.write_frame_stats(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpb::BitrateOptions;
+    use crate::muxer::RecordingMuxer;
+
+    #[test]
+    fn json_test() {
+        let json = Json::Object(vec![
+            ("name", Json::string("a \"quoted\"\\path\n\u{1}")),
+            ("count", Json::number(3)),
+            (
+                "nested",
+                Json::Object(vec![
+                    ("flag", Json::Bool(true)),
+                    ("empty", Json::Object(vec![])),
+                ]),
+            ),
+        ]);
+        let mut out = String::new();
+        json.write(&mut out, 0);
+        assert_eq!(
+            out,
+            r#"{
+  "name": "a \"quoted\"\\path\n\u0001",
+  "count": 3,
+  "nested": {
+    "flag": true,
+    "empty": {}
+  }
+}"#
+        );
+    }
+
+    fn capture_info() -> CaptureInfo {
+        CaptureInfo {
+            device_name: "GPU".into(),
+            vendor_id: VENDOR_ID_NVIDIA,
+            device_id: 0x2684,
+            driver_version: (550 << 22) | (54 << 14) | (14 << 6),
+            api_version: vk::make_api_version(0, 1, 3, 277),
+            queue_families: [0, 1, 2, 3],
+            swapchain_format: vk::Format::B8G8R8A8_SRGB,
+            swapchain_extent: vk::Extent2D {
+                width: 1920,
+                height: 1080,
+            },
+            parameter_overrides: ParameterOverrides {
+                any: true,
+                sps: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn sidecar_json_test() {
+        let info = capture_info();
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap().into();
+        let stats = RecordingStats {
+            start_time: time,
+            end_time: time,
+            frames: 60,
+            dropped_frames: 2,
+        };
+        let rate_control = RateControlOptions {
+            kind: RateControlKind::Cbr(BitrateOptions {
+                max_bitrate: 8_000_000,
+                average_bitrate: 8_000_000,
+                frame_rate_numerator: 60,
+                frame_rate_denominator: 1,
+            }),
+            virtual_buffer_size_in_ms: 1000,
+            initial_virtual_buffer_size_in_ms: 500,
+            quality_level: 0,
+        };
+        let mut out = String::new();
+        sidecar_json(&Settings::default(), &info, Some(&rate_control), &stats).write(&mut out, 0);
+        assert!(out.contains("\n    \"codec\": \"H264\",\n"));
+        assert!(out.contains("\n    \"sps\": true,\n"));
+        // the bitrate changed through the control socket, not the configured one
+        assert!(out.contains(
+            r#"
+  "rate_control": {
+    "mode": "Cbr",
+    "average_bitrate": 8000000,
+    "max_bitrate": 8000000,
+    "quality_level": 0
+  },
+"#
+        ));
+        assert!(out.contains("\n    \"format\": \"B8G8R8A8_SRGB\",\n"));
+        assert!(out.contains("\n    \"vendor_id\": \"0x10de\",\n"));
+        assert!(out.contains("\n    \"driver_version\": \"550.54.14.0\",\n"));
+        assert!(out.contains("\n    \"api_version\": \"1.3.277\"\n"));
+        assert!(out.ends_with("\n  \"frames\": 60,\n  \"dropped_frames\": 2\n}"));
+    }
+
+    #[test]
+    fn dropped_frames_test() {
+        let info = Arc::new(capture_info());
+        let dropped_frames = Arc::new(AtomicU64::new(3));
+        let sidecar = |path: &str| {
+            SidecarMuxer::new(
+                Box::new(RecordingMuxer::default()),
+                Path::new(path),
+                info.clone(),
+                dropped_frames.clone(),
+                SharedRateControl::default(),
+            )
+        };
+        let first = sidecar("first.mp4");
+        assert_eq!(first.dropped_frames(), 0);
+        dropped_frames.fetch_add(2, Ordering::Relaxed);
+        let second = sidecar("second.mp4");
+        assert_eq!(second.dropped_frames(), 0);
+        dropped_frames.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(first.dropped_frames(), 3);
+        assert_eq!(second.dropped_frames(), 1);
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{error, info, warn};
+
+/// Set by SIGUSR1 to start or stop recording
+static TOGGLE_RECORDING: AtomicBool = AtomicBool::new(false);
+/// Set by SIGUSR2 to save the replay buffer or start a new output file
+static SPLIT_RECORDING: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    // only async-signal-safe operations are allowed here
+    match signal {
+        libc::SIGUSR1 => TOGGLE_RECORDING.store(true, Ordering::Relaxed),
+        libc::SIGUSR2 => SPLIT_RECORDING.store(true, Ordering::Relaxed),
+        _ => {}
+    }
+}
+
+/// Installs the SIGUSR1/SIGUSR2 handlers unless the application already handles the signals.
+pub fn install_signal_handlers() {
+    for signal in [libc::SIGUSR1, libc::SIGUSR2] {
+        unsafe {
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(signal, std::ptr::null(), &mut previous) != 0 {
+                error!("Failed to query handler of signal {signal}");
+                continue;
+            }
+            if previous.sa_sigaction != libc::SIG_DFL {
+                warn!("Not installing a handler for signal {signal}, the application handles it");
+                continue;
+            }
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(signal, &action, std::ptr::null_mut()) != 0 {
+                error!("Failed to install handler for signal {signal}");
+            } else {
+                info!("Installed handler for signal {signal}");
+            }
+        }
+    }
+}
+
+/// Whether SIGUSR1 was received since the last call
+pub fn take_toggle_recording() -> bool {
+    TOGGLE_RECORDING.swap(false, Ordering::Relaxed)
+}
+
+/// Whether SIGUSR2 was received since the last call
+pub fn take_split_recording() -> bool {
+    SPLIT_RECORDING.swap(false, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_flags_test() {
+        handle_signal(libc::SIGUSR2);
+        assert!(!take_toggle_recording());
+        assert!(take_split_recording());
+        assert!(!take_split_recording());
+    }
+}
@@ -6,6 +6,7 @@ use ash::khr;
 use ash::vk;
 use once_cell::sync::Lazy;
 
+use crate::control::ControlState;
 use crate::settings::Settings;
 
 #[derive(Default)]
@@ -92,6 +93,9 @@ pub struct State {
     pub decode_queue: RwLock<Option<vk::Queue>>,
     pub decode_queue_family_idx: RwLock<u32>,
     pub private_slot: RwLock<vk::PrivateDataSlot>,
+    /// `VK_KHR_swapchain_mutable_format` is enabled, sRGB swapchains can only be recorded with it
+    pub swapchain_mutable_format: RwLock<bool>,
+    pub control: ControlState,
 }
 
 pub fn get_state() -> &'static State {
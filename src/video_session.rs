@@ -1,28 +1,52 @@
 use ash::khr;
-use chrono::offset::Utc;
-use chrono::DateTime;
-use std::ffi::CStr;
-use std::fs::File;
+use std::ffi::{c_void, CStr};
 use std::mem::transmute;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::ptr::null_mut;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use ash::prelude::VkResult;
 use ash::vk;
 use log::{debug, error, info, trace, warn};
 
-use crate::dpb::{CbrOptions, Dpb, GopOptions, RateControlKind, RateControlOptions};
+use crate::capabilities::{EncodeCapabilities, EncoderConfig};
+use crate::color::{bit_depth, InputFormat};
+use crate::control::ControlState;
+use crate::dpb::{
+    BitrateOptions, CqpOptions, Dpb, GopOptions, RateControlKind, RateControlOptions,
+};
+use crate::filename::{expand_filename, FilenameFields};
+use crate::frame_stats::FrameStatsMuxer;
+use crate::muxer::{create_muxer, Muxer, MuxerConfig};
 use crate::profile::VideoProfile;
+use crate::replay::ReplayBuffer;
+use crate::scale::{output_extent, ScaleOptions};
+use crate::segment::SegmentedMuxer;
 use crate::session_parameters::{
-    make_h264_video_session_parameters, make_h265_video_session_parameters,
+    make_av1_video_session_parameters, make_h264_video_session_parameters,
+    make_h265_video_session_parameters, ParameterOverrides,
 };
-use crate::settings::Codec;
+use crate::settings::{Codec, FrameStatsFormat, RateControlMode, Settings};
+use crate::sidecar::{CaptureInfo, SharedRateControl, SidecarMuxer};
+use crate::signals::{take_split_recording, take_toggle_recording};
 
 use crate::state::{get_state, Extensions};
 
 #[cfg(debug_assertions)]
 use crate::vulkan_utils::name_object;
 
+/// Upper bound of repeated frames per present, so that a long stall does not flood the encoder.
+/// The ticks of longer stalls are counted as dropped and leave a gap in the timestamps.
+const MAX_REPEATED_FRAMES: u64 = 8;
+
+/// Input images being converted or encoded besides the ones held for reordering and repeating
+const INFLIGHT_INPUT_IMAGES: u32 = 2;
+
+static SWAPCHAIN_COUNT: AtomicU32 = AtomicU32::new(0);
+
 pub struct VideoSession<'a> {
     session: vk::VideoSessionKHR,
     profile: Box<VideoProfile<'a>>,
@@ -30,6 +54,10 @@ pub struct VideoSession<'a> {
     parameters: Option<vk::VideoSessionParametersKHR>,
     codec: Codec,
     needs_reset: bool,
+    parameter_sets: Vec<u8>,
+    parameter_overrides: ParameterOverrides,
+    config: EncoderConfig,
+    capabilities: Option<EncodeCapabilities>,
 }
 
 impl VideoSession<'_> {
@@ -49,6 +77,15 @@ impl VideoSession<'_> {
         &self.profile
     }
 
+    pub fn config(&self) -> &EncoderConfig {
+        &self.config
+    }
+
+    /// Only known for encode sessions
+    pub fn capabilities(&self) -> Option<&EncodeCapabilities> {
+        self.capabilities.as_ref()
+    }
+
     fn destroy(
         &mut self,
         device: &ash::Device,
@@ -81,10 +118,19 @@ impl VideoSession<'_> {
     pub fn set_needs_reset(&mut self, needs_reset: bool) {
         self.needs_reset = needs_reset;
     }
+
+    /// Encoded parameter sets (Annex-B) that need to precede the first frame in the output
+    pub fn parameter_sets(&self) -> &[u8] {
+        &self.parameter_sets
+    }
+
+    pub fn parameter_overrides(&self) -> ParameterOverrides {
+        self.parameter_overrides
+    }
 }
 
 struct SwapChainData<'a> {
-    dpb: VkResult<Dpb<'a>>,
+    dpb: VkResult<Dpb>,
     _video_max_extent: vk::Extent2D,
     _swapchain_format: vk::Format,
     //swapchain_color_space: vk::ColorSpace,
@@ -94,14 +140,32 @@ struct SwapChainData<'a> {
     image_views: VkResult<Vec<vk::ImageView>>,
     semaphores: Vec<VkResult<vk::Semaphore>>,
     frame_index: u64,
-    output_file: Option<File>,
+    /// Present time of the first recorded frame
+    start_time: Option<Instant>,
+    /// Next capture interval to encode when sampling at a fixed frame rate
+    next_tick: u64,
+    /// Whether frames are encoded, see [`crate::control::ControlState::recording`]
+    recording: bool,
+    /// Present time at which the recording was paused
+    paused_at: Option<Instant>,
+    output: Option<Box<dyn Muxer>>,
+    muxer_config: MuxerConfig,
+    /// Number of swapchains created before this one, used in file names
+    swapchain_index: u32,
+    capture_info: Arc<CaptureInfo>,
+    /// Presented images that were not encoded
+    dropped_frames: Arc<AtomicU64>,
+    /// Copy of the DPB's rate control for the sidecars
+    rate_control: SharedRateControl,
+    /// Takes the place of `output` in replay buffer mode
+    replay: Option<ReplayBuffer>,
 }
 
 impl SwapChainData<'_> {
     pub fn destroy(
         &mut self,
         device: &ash::Device,
-        video_queue_fn: &khr::video_queue::DeviceFn,
+        extensions: &Extensions,
         allocator: Option<&vk::AllocationCallbacks>,
     ) {
         if let Ok(views) = self.image_views.as_mut() {
@@ -111,6 +175,7 @@ impl SwapChainData<'_> {
                 }
             }
         }
+        self.stop_recording(device, extensions);
         if let Ok(dpb) = self.dpb.as_mut() {
             dpb.destroy(device, allocator);
         }
@@ -121,23 +186,157 @@ impl SwapChainData<'_> {
 
         for session in [&mut self.encode_session, &mut self.decode_session].iter_mut() {
             if let Ok(session) = session {
-                session.destroy(device, video_queue_fn, allocator);
+                session.destroy(device, extensions.video_queue_fn(), allocator);
+            }
+        }
+    }
+
+    /// Applies the state requested through the control socket or signals.
+    fn apply_control(&mut self, device: &ash::Device, extensions: &Extensions, now: Instant) {
+        let control = &get_state().control;
+        if take_toggle_recording() {
+            control.recording.fetch_xor(true, Ordering::Relaxed);
+        }
+        if take_split_recording() {
+            if self.replay.is_some() {
+                control.save_replay.store(true, Ordering::Relaxed);
+            } else if self.recording {
+                info!("Splitting output file");
+                self.stop_recording(device, extensions);
+                self.start_recording();
+            }
+        }
+        if control.save_replay.swap(false, Ordering::Relaxed) {
+            self.save_replay();
+        }
+        let recording = control.recording.load(Ordering::Relaxed);
+        if recording != self.recording {
+            self.recording = recording;
+            if recording {
+                self.start_recording();
+            } else {
+                self.stop_recording(device, extensions);
+            }
+        }
+        match (control.paused.load(Ordering::Relaxed), self.paused_at) {
+            (true, None) => {
+                info!("Pausing recording");
+                self.paused_at = Some(now);
+            }
+            (false, Some(paused_at)) => {
+                info!("Resuming recording");
+                // continue the timeline where it was paused
+                if let Some(start_time) = self.start_time.as_mut() {
+                    *start_time += now - paused_at;
+                }
+                self.paused_at = None;
+            }
+            _ => {}
+        }
+        if let Ok(dpb) = self.dpb.as_mut() {
+            let current = &dpb.rate_control_options().kind;
+            if let Some(kind) = requested_rate_control(current, control, get_state().settings.codec)
+            {
+                info!("Changing rate control to {kind:?}");
+                dpb.update_rate_control(|options| options.kind = kind);
+                *self.rate_control.lock().unwrap() = Some(dpb.rate_control_options().clone());
+            }
+        }
+        if let (Ok(dpb), Some(output)) = (self.dpb.as_mut(), self.output.as_mut()) {
+            if output.wants_keyframe() {
+                dpb.request_idr();
             }
         }
     }
 
+    /// Starts a new output file, or resumes filling the replay buffer.
+    fn start_recording(&mut self) {
+        let Ok(encode_session) = self.encode_session.as_mut() else {
+            return;
+        };
+        // the new file has to start with an IDR frame
+        encode_session.set_needs_reset(true);
+        if self.replay.is_none() {
+            self.output = self.create_output();
+            self.start_time = None;
+            self.next_tick = 0;
+        }
+    }
+
+    /// Writes all frames still in flight and finalizes the output file.
+    fn stop_recording(&mut self, device: &ash::Device, extensions: &Extensions) {
+        if let (Ok(dpb), Ok(encode_session)) = (self.dpb.as_mut(), self.encode_session.as_mut()) {
+            let encode_queue = *get_state().encode_queue.read().unwrap();
+            if let Err(err) = dpb.flush(
+                device,
+                extensions,
+                encode_session,
+                encode_queue,
+                frame_sink(&mut self.output, &mut self.replay),
+            ) {
+                error!("Failed to write remaining frames: {err:?}");
+            }
+        }
+        if let Some(mut output) = self.output.take() {
+            info!("Finishing output file");
+            if let Err(err) = output.finish() {
+                error!("Failed to finish output file: {err}");
+            }
+            *get_state().control.output_file.lock().unwrap() = None;
+        }
+    }
+
     pub fn encode_image(
         &mut self,
         device: &ash::Device,
         extensions: &Extensions,
-        swapchain_index: usize,
         compute_queue: vk::Queue,
         encode_queue: vk::Queue,
         present_info: &vk::PresentInfoKHR,
-    ) {
+        present_time: Instant,
+    ) -> bool {
+        if !self.recording || self.paused_at.is_some() {
+            return false;
+        }
         if let (Ok(views), Ok(dpb), Ok(encode_session)) =
             (&self.image_views, &mut self.dpb, &mut self.encode_session)
         {
+            let settings = &get_state().settings;
+            let elapsed = present_time - *self.start_time.get_or_insert(present_time);
+            let pts = if settings.fixed_frame_rate {
+                let FramePacing::Encode {
+                    tick,
+                    repeated,
+                    skipped,
+                } = pace_frame(elapsed, self.next_tick, settings)
+                else {
+                    trace!("Dropping frame presented at {elapsed:?}");
+                    self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                };
+                if skipped > 0 {
+                    debug!("Skipping {skipped} frames after a stall at {elapsed:?}");
+                    self.dropped_frames.fetch_add(skipped, Ordering::Relaxed);
+                }
+                for repeated_tick in repeated {
+                    if let Err(err) = dpb.repeat_frame(
+                        device,
+                        extensions,
+                        encode_session,
+                        encode_queue,
+                        capture_tick_time(repeated_tick, settings),
+                        frame_sink(&mut self.output, &mut self.replay),
+                    ) {
+                        error!("Failed to repeat frame: {err:?}");
+                        break;
+                    }
+                }
+                self.next_tick = tick + 1;
+                capture_tick_time(tick, settings)
+            } else {
+                elapsed
+            };
+
             if present_info.wait_semaphore_count != 1 {
                 warn!(
                     "Queue present has not a single wait semaphore, but instead {}",
@@ -153,6 +352,7 @@ impl SwapChainData<'_> {
                 })
                 .value(1)
                 .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)];
+            let swapchain_index = unsafe { *present_info.p_image_indices } as usize;
             let semaphore = self.semaphores[swapchain_index];
             if let Ok(semaphore) = semaphore {
                 let signal_semaphore_compute = [vk::SemaphoreSubmitInfo::default()
@@ -169,17 +369,316 @@ impl SwapChainData<'_> {
                     encode_queue,
                     &wait_semaphore_infos,
                     &signal_semaphore_compute,
-                    self.output_file.as_mut(),
+                    pts,
+                    frame_sink(&mut self.output, &mut self.replay),
                 );
                 if let Err(err) = err {
                     error!("Failed to encode frame {}: {err:?}", self.frame_index);
+                    self.dropped_frames.fetch_add(1, Ordering::Relaxed);
                 } else {
                     self.frame_index += 1;
+                    get_state()
+                        .control
+                        .encoded_frames
+                        .fetch_add(1, Ordering::Relaxed);
                 }
+                return true;
             } else {
                 error!("Something is terribly wrong: a semaphore is missing!");
+                self.dropped_frames.fetch_add(1, Ordering::Relaxed);
             }
         }
+        false
+    }
+
+    /// Opens a new output file, split into segments if configured. All segments are named after
+    /// the time the recording started.
+    fn create_output(&self) -> Option<Box<dyn Muxer>> {
+        let settings = &get_state().settings;
+        let parameter_sets = self.encode_session.as_ref().ok()?.parameter_sets();
+        let limits = settings.segment_limits();
+        let start_time = SystemTime::now();
+        let output_file = output_file_path(
+            settings,
+            &self.muxer_config,
+            self.swapchain_index,
+            start_time,
+            limits.is_enabled().then_some(0),
+            "",
+        );
+        let output = if limits.is_enabled() {
+            let config = self.muxer_config.clone();
+            let swapchain_index = self.swapchain_index;
+            let parameter_sets = parameter_sets.to_vec();
+            let capture_info = self.capture_info.clone();
+            let dropped_frames = self.dropped_frames.clone();
+            let rate_control = self.rate_control.clone();
+            let create_segment = Box::new(move |index| {
+                let settings = &get_state().settings;
+                let path = output_file_path(
+                    settings,
+                    &config,
+                    swapchain_index,
+                    start_time,
+                    Some(index),
+                    "",
+                );
+                let output = open_output(
+                    &path,
+                    &config,
+                    &parameter_sets,
+                    capture_info.clone(),
+                    dropped_frames.clone(),
+                    rate_control.clone(),
+                )?;
+                *get_state().control.output_file.lock().unwrap() = Some(path);
+                Ok(output)
+            });
+            SegmentedMuxer::new(create_segment, limits)
+                .map(|output| Box::new(output) as Box<dyn Muxer>)
+        } else {
+            open_output(
+                &output_file,
+                &self.muxer_config,
+                parameter_sets,
+                self.capture_info.clone(),
+                self.dropped_frames.clone(),
+                self.rate_control.clone(),
+            )
+            .inspect(|_| {
+                *get_state().control.output_file.lock().unwrap() = Some(output_file.clone())
+            })
+        };
+        output
+            .inspect_err(|err| error!("Failed to create output file {output_file:?}: {err}"))
+            .ok()
+    }
+
+    /// Writes the content of the replay buffer to a new file in the background.
+    fn save_replay(&self) {
+        let (Some(replay), Ok(encode_session)) = (&self.replay, &self.encode_session) else {
+            warn!("Cannot save a replay without replay buffer, set replay_buffer_seconds");
+            return;
+        };
+        let settings = &get_state().settings;
+        let path = output_file_path(
+            settings,
+            &self.muxer_config,
+            self.swapchain_index,
+            SystemTime::now(),
+            None,
+            "_replay",
+        );
+        info!("Saving {}B replay to {path:?}", replay.size());
+        let frames = replay.snapshot();
+        let container = settings.container;
+        let config = self.muxer_config.clone();
+        let parameter_sets = encode_session.parameter_sets().to_vec();
+        let capture_info = self.capture_info.clone();
+        let dropped_frames = self.dropped_frames.clone();
+        let rate_control = self.rate_control.clone();
+        std::thread::spawn(move || {
+            let res = create_muxer(container, &path, config, &parameter_sets)
+                .map(|output| {
+                    SidecarMuxer::new(output, &path, capture_info, dropped_frames, rate_control)
+                })
+                .and_then(|mut output| ReplayBuffer::save(&frames, &mut output));
+            if let Err(err) = res {
+                error!("Failed to save replay {path:?}: {err}");
+            }
+        });
+    }
+}
+
+/// Encoded frames go to the replay buffer if there is one, otherwise to the output file
+fn frame_sink<'a>(
+    output: &'a mut Option<Box<dyn Muxer>>,
+    replay: &'a mut Option<ReplayBuffer>,
+) -> Option<&'a mut dyn Muxer> {
+    match replay {
+        Some(replay) => Some(replay),
+        None => output.as_deref_mut().map(|output| output as &mut dyn Muxer),
+    }
+}
+
+/// The settings use the H.264/H.265 QP range, AV1 has quantizer indices from 0 to 255
+fn codec_qp(qp: u32, codec: Codec) -> u32 {
+    match codec {
+        Codec::H264 | Codec::H265 => qp,
+        Codec::AV1 => qp * 255 / 51,
+    }
+}
+
+/// Rate control with the changes requested through the control socket applied, `None` if they
+/// don't change `current`. Constant bitrate keeps the maximum at the average bitrate.
+fn requested_rate_control(
+    current: &RateControlKind,
+    control: &ControlState,
+    codec: Codec,
+) -> Option<RateControlKind> {
+    let requested = |bitrate: &AtomicU64, current: u64| match bitrate.load(Ordering::Relaxed) {
+        0 => current,
+        requested => requested,
+    };
+    let requested = match current {
+        RateControlKind::Cbr(bitrate) => {
+            let average_bitrate = requested(&control.average_bitrate, bitrate.average_bitrate);
+            RateControlKind::Cbr(BitrateOptions {
+                average_bitrate,
+                max_bitrate: average_bitrate,
+                ..bitrate.clone()
+            })
+        }
+        RateControlKind::Vbr(bitrate) => {
+            let average_bitrate = requested(&control.average_bitrate, bitrate.average_bitrate);
+            // the maximum can't be below the average
+            let max_bitrate =
+                requested(&control.max_bitrate, bitrate.max_bitrate).max(average_bitrate);
+            RateControlKind::Vbr(BitrateOptions {
+                average_bitrate,
+                max_bitrate,
+                ..bitrate.clone()
+            })
+        }
+        RateControlKind::Cqp(cqp) => match *control.qp.lock().unwrap() {
+            Some(qp) => {
+                let qp = codec_qp(qp, codec);
+                RateControlKind::Cqp(CqpOptions {
+                    intra: qp,
+                    predictive: qp,
+                    bipredictive: qp,
+                })
+            }
+            None => RateControlKind::Cqp(*cqp),
+        },
+        RateControlKind::Disabled => RateControlKind::Disabled,
+    };
+    (requested != *current).then_some(requested)
+}
+
+fn rate_control_kind(settings: &Settings) -> RateControlKind {
+    let bitrate = BitrateOptions {
+        max_bitrate: settings.max_bitrate,
+        average_bitrate: settings.average_bitrate,
+        frame_rate_numerator: settings.frame_rate_numerator,
+        frame_rate_denominator: settings.frame_rate_denominator,
+    };
+    let qp = |qp: u32| codec_qp(qp, settings.codec);
+    match settings.rate_control_mode {
+        // a constant bitrate has no separate maximum
+        RateControlMode::Cbr => RateControlKind::Cbr(BitrateOptions {
+            max_bitrate: settings.average_bitrate,
+            ..bitrate
+        }),
+        RateControlMode::Vbr => RateControlKind::Vbr(bitrate),
+        RateControlMode::Cqp => RateControlKind::Cqp(CqpOptions {
+            intra: qp(settings.qp_i),
+            predictive: qp(settings.qp_p),
+            bipredictive: qp(settings.qp_b),
+        }),
+        RateControlMode::Disabled => RateControlKind::Disabled,
+    }
+}
+
+/// Creates the output file at `path` together with its sidecar and frame statistics files
+fn open_output(
+    path: &Path,
+    config: &MuxerConfig,
+    parameter_sets: &[u8],
+    capture_info: Arc<CaptureInfo>,
+    dropped_frames: Arc<AtomicU64>,
+    rate_control: SharedRateControl,
+) -> std::io::Result<Box<dyn Muxer>> {
+    let settings = &get_state().settings;
+    info!("Starting output file: {path:?}");
+    let mut output = create_muxer(settings.container, path, config.clone(), parameter_sets)?;
+    if settings.frame_stats != FrameStatsFormat::None {
+        output = Box::new(FrameStatsMuxer::create(output, path, settings.frame_stats)?);
+    }
+    Ok(Box::new(SidecarMuxer::new(
+        output,
+        path,
+        capture_info,
+        dropped_frames,
+        rate_control,
+    )))
+}
+
+/// Path of a new output file named after the `filename_template` setting, `suffix` is appended
+/// to its name
+fn output_file_path(
+    settings: &Settings,
+    config: &MuxerConfig,
+    swapchain_index: u32,
+    time: SystemTime,
+    segment: Option<u32>,
+    suffix: &str,
+) -> PathBuf {
+    let lock = get_state().application_name.read().unwrap();
+    let application_name = lock.as_ref().map(|s| s.as_str()).unwrap_or("UnknownApp");
+    let exe = std::env::current_exe().ok();
+    let exe_name = exe
+        .as_ref()
+        .and_then(|exe| exe.file_name())
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let fields = FilenameFields {
+        app: application_name,
+        exe: &exe_name,
+        pid: std::process::id(),
+        swapchain: swapchain_index,
+        width: config.width,
+        height: config.height,
+        codec: config.codec,
+        time: time.into(),
+        segment,
+    };
+    let file_name = expand_filename(&settings.filename_template, &fields);
+    let file_ext = settings.container.file_extension(settings.codec);
+    settings
+        .output_folder
+        .join(format!("{file_name}{suffix}.{file_ext}"))
+}
+
+/// Index of the capture interval `elapsed` falls into when sampling at the configured frame rate
+fn capture_tick(elapsed: Duration, settings: &Settings) -> u64 {
+    (elapsed.as_nanos() * settings.frame_rate_numerator as u128
+        / (1_000_000_000 * settings.frame_rate_denominator.max(1) as u128)) as u64
+}
+
+fn capture_tick_time(tick: u64, settings: &Settings) -> Duration {
+    Duration::from_nanos(
+        (tick as u128 * 1_000_000_000 * settings.frame_rate_denominator as u128
+            / settings.frame_rate_numerator.max(1) as u128) as u64,
+    )
+}
+
+/// What to do with a frame presented in fixed frame rate mode
+#[derive(Debug, PartialEq, Eq)]
+enum FramePacing {
+    /// The tick of the frame was already encoded
+    Drop,
+    Encode {
+        tick: u64,
+        /// Ticks the previous frame was still on screen for and is encoded again
+        repeated: Range<u64>,
+        /// Ticks after a long stall that are neither repeated nor encoded
+        skipped: u64,
+    },
+}
+
+/// Paces a frame presented `elapsed` after the start of the recording when `next_tick` is the
+/// first tick not encoded yet.
+fn pace_frame(elapsed: Duration, next_tick: u64, settings: &Settings) -> FramePacing {
+    let tick = capture_tick(elapsed, settings);
+    if tick < next_tick {
+        return FramePacing::Drop;
+    }
+    let first_repeat = tick.saturating_sub(MAX_REPEATED_FRAMES).max(next_tick);
+    FramePacing::Encode {
+        tick,
+        repeated: first_repeat..tick,
+        skipped: first_repeat - next_tick,
     }
 }
 
@@ -192,9 +691,30 @@ pub unsafe fn record_vk_create_swapchain(
     let allocator = p_allocator.as_ref();
     let extensions = get_state().extensions.read().unwrap();
     let swapchain_fn = extensions.swapchain_fn();
-    let create_info = p_create_info.as_ref().unwrap();
-    let create_info =
-        create_info.image_usage(create_info.image_usage | vk::ImageUsageFlags::STORAGE);
+    let mut create_info = *p_create_info.as_ref().unwrap();
+    let input_format = InputFormat {
+        format: create_info.image_format,
+        color_space: create_info.image_color_space,
+    };
+    let view_formats = [input_format.format, input_format.storage_view_format()];
+    // the swapchain is left untouched if the layer can't read its images
+    let readable =
+        view_formats[0] == view_formats[1] || *get_state().swapchain_mutable_format.read().unwrap();
+    if readable {
+        create_info.image_usage |= vk::ImageUsageFlags::STORAGE;
+    }
+    let mut format_list = vk::ImageFormatListCreateInfo::default().view_formats(&view_formats);
+    if readable
+        && view_formats[0] != view_formats[1]
+        && !create_info
+            .flags
+            .contains(vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT)
+    {
+        // the application's views keep the sRGB format, the layer's storage view is UNORM
+        create_info.flags |= vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT;
+        format_list.p_next = create_info.p_next;
+        create_info.p_next = &format_list as *const _ as *const c_void;
+    }
     let result =
         (swapchain_fn.create_swapchain_khr)(device, &create_info, p_allocator, p_swapchain);
 
@@ -216,7 +736,7 @@ pub unsafe fn record_vk_create_swapchain(
 
             let mut view_info = vk::ImageViewCreateInfo::default()
                 .view_type(vk::ImageViewType::TYPE_2D)
-                .format(create_info.image_format)
+                .format(input_format.storage_view_format())
                 .subresource_range(vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::COLOR,
                     base_mip_level: 0,
@@ -226,7 +746,7 @@ pub unsafe fn record_vk_create_swapchain(
                 });
 
             let image_views: VkResult<Vec<vk::ImageView>> = {
-                if let Ok(images) = &images {
+                if let (Ok(images), true) = (&images, readable) {
                     images
                         .iter()
                         .enumerate()
@@ -258,58 +778,98 @@ pub unsafe fn record_vk_create_swapchain(
                 }
             };
 
-            let video_format = vk::Format::G8_B8R8_2PLANE_420_UNORM;
-
-            let output_folder = &get_state().settings.output_folder;
-            let codec = &get_state().settings.codec;
-            let lock = get_state().application_name.read().unwrap();
-            let application_name = lock.as_ref().map(|s| s.as_str()).unwrap_or("UnknownApp");
-            let time = SystemTime::now();
-            let datetime: DateTime<Utc> = time.into();
-            let vk::Extent2D { width, height } = create_info.image_extent;
-            let codec_file_ext = match codec {
-                Codec::H264 => "h264",
-                Codec::H265 => "h265",
-                Codec::AV1 => "av1",
+            let codec = get_state().settings.codec;
+            let video_format = if readable {
+                input_format.video_format(codec).ok_or_else(|| {
+                    error!("Recording {input_format:?} swapchains with {codec:?} is not supported");
+                    vk::Result::ERROR_FORMAT_NOT_SUPPORTED
+                })
+            } else {
+                error!(
+                    "Recording {input_format:?} swapchains needs VK_KHR_swapchain_mutable_format"
+                );
+                Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT)
             };
-            let output_file = output_folder.join(format!(
-                "{application_name}_{width}x{height}_{}.{codec_file_ext}",
-                datetime.format("%d.%m.%Y_%H_%M_%S")
-            ));
-            info!("Starting output file: {output_file:?}");
-            let output_file_handle = File::create(&output_file);
 
+            let settings = &get_state().settings;
+            let output_extent = output_extent(settings, create_info.image_extent);
+            if output_extent != create_info.image_extent {
+                info!(
+                    "Scaling {:?} swapchain images to {output_extent:?}",
+                    create_info.image_extent
+                );
+            }
             debug!("Create encode session");
-            let encode_session = create_video_session(
-                *get_state().encode_queue_family_idx.read().unwrap(),
-                create_info.image_extent,
+            let encode_session = video_format.and_then(|video_format| {
+                create_video_session(
+                    *get_state().encode_queue_family_idx.read().unwrap(),
+                    output_extent,
+                    video_format,
+                    true,
+                    p_allocator,
+                )
+            });
+
+            // reduced to the maximum coded extent of the device
+            let output_extent = encode_session
+                .as_ref()
+                .map_or(output_extent, |session| session.config().extent);
+            let vk::Extent2D { width, height } = output_extent;
+            let muxer_config = MuxerConfig {
+                codec: settings.codec,
+                width,
+                height,
+                bit_depth: video_format.map(bit_depth).unwrap_or(8),
+                frame_rate_numerator: settings.frame_rate_numerator,
+                frame_rate_denominator: settings.frame_rate_denominator,
+            };
+            let capture_info = CaptureInfo::new(
+                instance,
+                *physical_device,
+                create_info.image_format,
                 create_info.image_extent,
-                video_format,
-                true,
-                output_file_handle.as_ref().ok(),
-                p_allocator,
+                encode_session
+                    .as_ref()
+                    .map(|session| session.parameter_overrides())
+                    .unwrap_or_default(),
             );
+            let replay = (settings.replay_buffer_seconds > 0).then(|| {
+                info!(
+                    "Keeping the last {}s in the replay buffer",
+                    settings.replay_buffer_seconds
+                );
+                ReplayBuffer::new(
+                    Duration::from_secs(settings.replay_buffer_seconds.into()),
+                    settings.replay_buffer_size(),
+                )
+            });
 
             debug!("Create decode session");
-            let decode_session = create_video_session(
-                *get_state().decode_queue_family_idx.read().unwrap(),
-                create_info.image_extent,
-                create_info.image_extent,
-                video_format,
-                false,
-                None,
-                p_allocator,
-            );
-            let swapchain_format = create_info.image_format;
-            let num_dpb_images = 2;
-            let num_inflight_images = 10;
+            let decode_session = video_format.and_then(|video_format| {
+                create_video_session(
+                    *get_state().decode_queue_family_idx.read().unwrap(),
+                    output_extent,
+                    video_format,
+                    false,
+                    p_allocator,
+                )
+            });
             let mut dpb = encode_session.as_ref().map_err(|e| *e).and_then(|s| {
+                let config = s.config();
+                let num_inflight_images = config.consecutive_b_frames as u32
+                    + MAX_REPEATED_FRAMES as u32
+                    + INFLIGHT_INPUT_IMAGES;
+                let rate_control = rate_control_kind(settings);
+                let rate_control = match s.capabilities() {
+                    Some(capabilities) => capabilities.validate_rate_control(rate_control),
+                    None => rate_control,
+                };
                 Dpb::new(
                     device,
                     &extensions,
-                    video_format,
+                    input_format,
                     create_info.image_extent,
-                    num_dpb_images,
+                    config.dpb_slots,
                     num_inflight_images,
                     create_info.min_image_count,
                     p_allocator.as_ref(),
@@ -319,28 +879,21 @@ pub unsafe fn record_vk_create_swapchain(
                     s,
                     &physical_memory_props,
                     GopOptions {
-                        #[cfg(feature = "nvpro_sample_gop")]
-                        use_nvpro: get_state().settings.use_nvpro,
-                        #[cfg(not(feature = "nvpro_sample_gop"))]
-                        use_nvpro: false,
                         gop_size: get_state().settings.gop_size,
                         idr_period: get_state().settings.idr_period,
-                        max_consecutive_b_frames: get_state().settings.max_consecutive_b_frames,
+                        max_consecutive_b_frames: config.consecutive_b_frames as u64,
                         last_frame_type: get_state().settings.last_frame_type,
+                        frame_duration: capture_tick_time(1, settings),
                     },
                     RateControlOptions {
-                        kind: RateControlKind::Cbr(CbrOptions {
-                            max_bitrate: get_state().settings.max_bitrate,
-                            average_bitrate: get_state().settings.average_bitrate,
-                            frame_rate_numerator: get_state().settings.frame_rate_numerator,
-                            frame_rate_denominator: get_state().settings.frame_rate_denominator,
-                        }),
+                        kind: rate_control,
                         virtual_buffer_size_in_ms: get_state().settings.vbv_size_in_ms,
                         initial_virtual_buffer_size_in_ms: get_state()
                             .settings
                             .initial_vbv_size_in_ms,
-                        quality_level: get_state().settings.quality_level,
+                        quality_level: config.quality_level,
                     },
+                    ScaleOptions::new(settings),
                 )
             });
             let present_family_idx = *get_state().graphics_queue_family_idx.read().unwrap();
@@ -351,7 +904,6 @@ pub unsafe fn record_vk_create_swapchain(
                     device,
                     images,
                     image_views,
-                    swapchain_format,
                     present_family_idx,
                     present_family_idx,
                 ) {
@@ -362,12 +914,18 @@ pub unsafe fn record_vk_create_swapchain(
             let info = vk::SemaphoreCreateInfo::default();
             let semaphores = (0..create_info.min_image_count) // TODO: image count might be higher
                 .map(|_| {
-                    device.create_semaphore(&info, allocator).inspect_err(|err| {
-                        error!("Failed to create present semaphore: {err}");
-                    })
+                    device
+                        .create_semaphore(&info, allocator)
+                        .inspect_err(|err| {
+                            error!("Failed to create present semaphore: {err}");
+                        })
                 })
                 .collect();
 
+            let rate_control = dpb
+                .as_ref()
+                .ok()
+                .map(|dpb| dpb.rate_control_options().clone());
             SwapChainData {
                 _video_max_extent: create_info.image_extent,
                 _swapchain_format: create_info.image_format,
@@ -378,7 +936,18 @@ pub unsafe fn record_vk_create_swapchain(
                 _images: images,
                 image_views,
                 frame_index: 0,
-                output_file: output_file_handle.ok(),
+                start_time: None,
+                next_tick: 0,
+                // the output file is started with the first present
+                recording: false,
+                paused_at: None,
+                output: None,
+                muxer_config,
+                swapchain_index: SWAPCHAIN_COUNT.fetch_add(1, Ordering::Relaxed),
+                capture_info: Arc::new(capture_info),
+                dropped_frames: Arc::default(),
+                rate_control: Arc::new(Mutex::new(rate_control)),
+                replay,
             }
         });
         let leaked = Box::leak(swapchain_data);
@@ -387,7 +956,7 @@ pub unsafe fn record_vk_create_swapchain(
             .is_err()
         {
             error!("Could not set private data!");
-            Box::from_raw(leaked).destroy(device, extensions.video_queue_fn(), allocator);
+            Box::from_raw(leaked).destroy(device, &extensions, allocator);
         }
     } else {
         warn!("Failed to create swapchain");
@@ -441,7 +1010,7 @@ pub unsafe extern "system" fn record_vk_destroy_swapchain(
         let device = lock.as_ref().unwrap();
         let mut swapchain_data =
             Box::from_raw(device.get_private_data(swapchain, *slot) as *mut SwapChainData);
-        swapchain_data.destroy(device, extensions.video_queue_fn(), allocator);
+        swapchain_data.destroy(device, &extensions, allocator);
     }
     (extensions.swapchain_fn().destroy_swapchain_khr)(device, swapchain, p_allocator)
 }
@@ -451,6 +1020,7 @@ pub unsafe extern "system" fn record_vk_queue_present(
     p_present_info: *const vk::PresentInfoKHR,
 ) -> vk::Result {
     trace!("record_vk_queue_present");
+    let present_time = Instant::now();
     let lock = get_state().device.read().unwrap();
     let device = lock.as_ref().unwrap();
     let slot = get_state().private_slot.read().unwrap();
@@ -463,36 +1033,38 @@ pub unsafe extern "system" fn record_vk_queue_present(
 
     let compute_queue = *get_state().compute_queue.read().unwrap();
     let encode_queue = *get_state().encode_queue.read().unwrap();
+    swapchain_data.apply_control(device, &extensions, present_time);
+    let mut encoded = false;
     if let (Some(compute_queue), Some(encode_queue)) = (compute_queue, encode_queue) {
-        swapchain_data.encode_image(
+        encoded = swapchain_data.encode_image(
             device,
             &extensions,
-            *present_info.p_image_indices as usize,
             compute_queue,
             encode_queue,
             present_info,
+            present_time,
         );
     }
     let info = p_present_info.as_ref().unwrap();
+    if !encoded {
+        return (extensions.swapchain_fn().queue_present_khr)(queue, info);
+    }
     let semaphores = [swapchain_data.semaphores[info.p_image_indices.read() as usize].unwrap()];
     let info = info.wait_semaphores(&semaphores);
     (extensions.swapchain_fn().queue_present_khr)(queue, &info)
 }
 
+/// `extent` is the requested size of the pictures, the encoder config may reduce it to what the
+/// device supports.
 fn create_video_session<'video_session>(
     queue_family_idx: u32,
-    max_coded_extent: vk::Extent2D,
-    coded_extent: vk::Extent2D,
+    extent: vk::Extent2D,
     video_format: vk::Format,
     is_encode: bool,
-    output_file: Option<&File>,
     p_allocator: *const vk::AllocationCallbacks,
 ) -> VkResult<VideoSession<'video_session>> {
     let state = get_state();
-    trace!(
-        "create_video_session {:?} {coded_extent:?}",
-        state.settings.codec
-    );
+    trace!("create_video_session {:?} {extent:?}", state.settings.codec);
 
     let header_version = match (is_encode, state.settings.codec) {
         (true, Codec::H264) => vk::ExtensionProperties::default()
@@ -509,7 +1081,12 @@ fn create_video_session<'video_session>(
             //.extension_name(c"VK_STD_vulkan_video_codec_h265_encode".into())
             .unwrap()
             .spec_version(vk::make_api_version(0, 1, 0, 0)),
-        (true, Codec::AV1) => todo!(),
+        (true, Codec::AV1) => vk::ExtensionProperties::default()
+            .extension_name(
+                CStr::from_bytes_until_nul(b"VK_STD_vulkan_video_codec_av1_encode\0").unwrap(),
+            )
+            .unwrap()
+            .spec_version(vk::make_api_version(0, 1, 0, 0)),
         (false, Codec::H264) => vk::ExtensionProperties::default()
             //.extension_name(c"VK_STD_vulkan_video_codec_h264_decode")
             .extension_name(
@@ -524,18 +1101,31 @@ fn create_video_session<'video_session>(
             )
             .unwrap()
             .spec_version(vk::make_api_version(0, 1, 0, 0)),
-        (false, Codec::AV1) => todo!(),
+        (false, Codec::AV1) => vk::ExtensionProperties::default()
+            .extension_name(
+                CStr::from_bytes_until_nul(b"VK_STD_vulkan_video_codec_av1_decode\0").unwrap(),
+            )
+            .unwrap()
+            .spec_version(vk::make_api_version(0, 1, 0, 0)),
     };
 
     let profile = VideoProfile::new(video_format, state.settings.codec, is_encode)?;
+    let capabilities = if is_encode {
+        (*state.physical_device.read().unwrap()).and_then(|physical_device| {
+            EncodeCapabilities::query(physical_device, &profile, state.settings.codec).ok()
+        })
+    } else {
+        None
+    };
+    let config = EncoderConfig::new(&state.settings, extent, video_format, capabilities.as_ref())?;
     let info = vk::VideoSessionCreateInfoKHR::default()
         .flags(unsafe { transmute(2) }) // VK_VIDEO_SESSION_CREATE_ALLOW_ENCODE_PARAMETER_OPTIMIZATIONS_BIT_KHR
         .queue_family_index(queue_family_idx)
-        .max_coded_extent(max_coded_extent)
-        .picture_format(vk::Format::G8_B8R8_2PLANE_420_UNORM)
-        .reference_picture_format(vk::Format::G8_B8R8_2PLANE_420_UNORM)
-        .max_dpb_slots(2)
-        .max_active_reference_pictures(1)
+        .max_coded_extent(config.coded_extent())
+        .picture_format(video_format)
+        .reference_picture_format(video_format)
+        .max_dpb_slots(config.dpb_slots)
+        .max_active_reference_pictures(config.max_active_reference_pictures)
         .std_header_version(&header_version)
         .video_profile(profile.profile());
 
@@ -575,6 +1165,8 @@ fn create_video_session<'video_session>(
         );
     }
 
+    let mut parameter_sets = Vec::new();
+    let mut parameter_overrides = ParameterOverrides::default();
     res.and_then(|session| {
         Ok(VideoSession {
             needs_reset: true,
@@ -668,15 +1260,15 @@ fn create_video_session<'video_session>(
                 memories
             },
             parameters: {
-                match (is_encode, state.settings.codec) {
+                let parameters = match (is_encode, state.settings.codec) {
                     (true, Codec::H264) => make_h264_video_session_parameters(
                         device,
                         video_queue_fn,
                         encode_queue_fn,
                         session,
                         video_format,
-                        coded_extent,
-                        output_file,
+                        &config,
+                        Some(&mut parameter_sets),
                         unsafe { p_allocator.as_ref() },
                     )
                     .ok(),
@@ -686,17 +1278,131 @@ fn create_video_session<'video_session>(
                         encode_queue_fn,
                         session,
                         video_format,
-                        coded_extent,
-                        output_file,
+                        &config,
+                        Some(&mut parameter_sets),
+                        unsafe { p_allocator.as_ref() },
+                    )
+                    .ok(),
+                    (true, Codec::AV1) => make_av1_video_session_parameters(
+                        device,
+                        video_queue_fn,
+                        encode_queue_fn,
+                        session,
+                        &config,
+                        Some(&mut parameter_sets),
                         unsafe { p_allocator.as_ref() },
                     )
                     .ok(),
-                    (true, Codec::AV1) => todo!(),
                     (false, Codec::H264) => None,
                     (false, Codec::H265) => None,
                     (false, Codec::AV1) => None,
-                }
+                };
+                parameters.map(|(parameters, overrides)| {
+                    parameter_overrides = overrides;
+                    parameters
+                })
             },
+            parameter_sets,
+            parameter_overrides,
+            config,
+            capabilities,
         })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pace_frame_test() {
+        let settings = Settings {
+            frame_rate_numerator: 50,
+            frame_rate_denominator: 1,
+            ..Default::default()
+        };
+        let ms = Duration::from_millis;
+        let encode = |tick, repeated, skipped| FramePacing::Encode {
+            tick,
+            repeated,
+            skipped,
+        };
+        assert_eq!(capture_tick_time(3, &settings), ms(60));
+        // on time
+        assert_eq!(pace_frame(ms(0), 0, &settings), encode(0, 0..0, 0));
+        assert_eq!(pace_frame(ms(25), 1, &settings), encode(1, 1..1, 0));
+        // a second frame within the same tick
+        assert_eq!(pace_frame(ms(39), 2, &settings), FramePacing::Drop);
+        // late, the previous frame covers the missed ticks
+        assert_eq!(pace_frame(ms(100), 2, &settings), encode(5, 2..5, 0));
+        // after a long stall only the last MAX_REPEATED_FRAMES ticks are repeated
+        assert_eq!(pace_frame(ms(1000), 6, &settings), encode(50, 42..50, 36));
+    }
+
+    #[test]
+    fn requested_rate_control_test() {
+        let bitrate = |average_bitrate, max_bitrate| BitrateOptions {
+            max_bitrate,
+            average_bitrate,
+            frame_rate_numerator: 60,
+            frame_rate_denominator: 1,
+        };
+        let control = ControlState::default();
+        let cbr = RateControlKind::Cbr(bitrate(8, 8));
+        assert_eq!(requested_rate_control(&cbr, &control, Codec::H264), None);
+
+        // constant bitrate ignores the maximum and keeps it at the average
+        control.max_bitrate.store(20, Ordering::Relaxed);
+        assert_eq!(requested_rate_control(&cbr, &control, Codec::H264), None);
+        control.average_bitrate.store(4, Ordering::Relaxed);
+        assert_eq!(
+            requested_rate_control(&cbr, &control, Codec::H264),
+            Some(RateControlKind::Cbr(bitrate(4, 4)))
+        );
+        let vbr = RateControlKind::Vbr(bitrate(8, 16));
+        assert_eq!(
+            requested_rate_control(&vbr, &control, Codec::H264),
+            Some(RateControlKind::Vbr(bitrate(4, 20)))
+        );
+        control.max_bitrate.store(2, Ordering::Relaxed);
+        assert_eq!(
+            requested_rate_control(&vbr, &control, Codec::H264),
+            Some(RateControlKind::Vbr(bitrate(4, 4)))
+        );
+
+        let cqp = RateControlKind::Cqp(CqpOptions {
+            intra: 20,
+            predictive: 23,
+            bipredictive: 25,
+        });
+        assert_eq!(requested_rate_control(&cqp, &control, Codec::H264), None);
+        *control.qp.lock().unwrap() = Some(30);
+        let requested = RateControlKind::Cqp(CqpOptions {
+            intra: 30,
+            predictive: 30,
+            bipredictive: 30,
+        });
+        assert_eq!(
+            requested_rate_control(&cqp, &control, Codec::H265),
+            Some(requested.clone())
+        );
+        assert_eq!(
+            requested_rate_control(&requested, &control, Codec::H265),
+            None
+        );
+        // AV1 quantizer indices
+        assert_eq!(
+            requested_rate_control(&cqp, &control, Codec::AV1),
+            Some(RateControlKind::Cqp(CqpOptions {
+                intra: 150,
+                predictive: 150,
+                bipredictive: 150,
+            }))
+        );
+        // rate control left to the implementation takes neither
+        assert_eq!(
+            requested_rate_control(&RateControlKind::Disabled, &control, Codec::H264),
+            None
+        );
+    }
+}
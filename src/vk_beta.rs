@@ -1 +1,483 @@
 #![allow(warnings)]
+//! Hand-written bindings for VK_KHR_video_encode_av1 which is not yet part of ash 0.38.
+//! Layouts follow vulkan_core.h and vulkan_video_codec_av1std_encode.h of Vulkan 1.3.302.
+
+use std::ffi::{c_void, CStr};
+use std::marker::PhantomData;
+
+use ash::vk;
+use ash::vk::native::{
+    StdVideoAV1CDEF, StdVideoAV1FrameType, StdVideoAV1GlobalMotion, StdVideoAV1InterpolationFilter,
+    StdVideoAV1Level, StdVideoAV1LoopFilter, StdVideoAV1LoopRestoration, StdVideoAV1Profile,
+    StdVideoAV1Quantization, StdVideoAV1Segmentation, StdVideoAV1SequenceHeader,
+    StdVideoAV1TileInfo, StdVideoAV1TxMode,
+};
+
+pub const VK_KHR_VIDEO_ENCODE_AV1_NAME: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_video_encode_av1\0") };
+pub const VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR: usize = 7;
+
+pub const VIDEO_CODEC_OPERATION_ENCODE_AV1: vk::VideoCodecOperationFlagsKHR =
+    vk::VideoCodecOperationFlagsKHR::from_raw(0x0004_0000);
+
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_CAPABILITIES_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_000);
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_SESSION_PARAMETERS_CREATE_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_001);
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_PICTURE_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_002);
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_DPB_SLOT_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_003);
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_VIDEO_ENCODE_AV1_FEATURES_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_004);
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_PROFILE_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_005);
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_RATE_CONTROL_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_006);
+pub const STRUCTURE_TYPE_VIDEO_ENCODE_AV1_RATE_CONTROL_LAYER_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_513_007);
+
+pub type VideoEncodeAV1PredictionModeKHR = i32;
+pub const VIDEO_ENCODE_AV1_PREDICTION_MODE_INTRA_ONLY_KHR: VideoEncodeAV1PredictionModeKHR = 0;
+pub const VIDEO_ENCODE_AV1_PREDICTION_MODE_SINGLE_REFERENCE_KHR: VideoEncodeAV1PredictionModeKHR =
+    1;
+pub const VIDEO_ENCODE_AV1_PREDICTION_MODE_UNIDIRECTIONAL_COMPOUND_KHR:
+    VideoEncodeAV1PredictionModeKHR = 2;
+pub const VIDEO_ENCODE_AV1_PREDICTION_MODE_BIDIRECTIONAL_COMPOUND_KHR:
+    VideoEncodeAV1PredictionModeKHR = 3;
+
+pub type VideoEncodeAV1RateControlGroupKHR = i32;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_INTRA_KHR: VideoEncodeAV1RateControlGroupKHR = 0;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_PREDICTIVE_KHR: VideoEncodeAV1RateControlGroupKHR = 1;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_GROUP_BIPREDICTIVE_KHR: VideoEncodeAV1RateControlGroupKHR =
+    2;
+
+pub type VideoEncodeAV1RateControlFlagsKHR = u32;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_REGULAR_GOP_BIT_KHR: VideoEncodeAV1RateControlFlagsKHR =
+    0x1;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_TEMPORAL_LAYER_PATTERN_DYADIC_BIT_KHR:
+    VideoEncodeAV1RateControlFlagsKHR = 0x2;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_REFERENCE_PATTERN_FLAT_BIT_KHR:
+    VideoEncodeAV1RateControlFlagsKHR = 0x4;
+pub const VIDEO_ENCODE_AV1_RATE_CONTROL_REFERENCE_PATTERN_DYADIC_BIT_KHR:
+    VideoEncodeAV1RateControlFlagsKHR = 0x8;
+
+/// Bit field structs of the std headers, stored as a plain `u32`
+macro_rules! std_flags {
+    ($name:ident { $($setter:ident = $bit:expr),* $(,)? }) => {
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug, Default)]
+        pub struct $name {
+            bits: u32,
+        }
+
+        impl $name {
+            $(
+                pub fn $setter(&mut self, val: u32) {
+                    self.bits = (self.bits & !(1 << $bit)) | ((val & 1) << $bit);
+                }
+            )*
+        }
+    };
+}
+
+std_flags!(StdVideoEncodeAV1OperatingPointInfoFlags {
+    set_decoder_model_present_for_this_op = 0,
+    set_low_delay_mode_flag = 1,
+    set_initial_display_delay_present_for_this_op = 2,
+});
+
+std_flags!(StdVideoEncodeAV1PictureInfoFlags {
+    set_error_resilient_mode = 0,
+    set_disable_cdf_update = 1,
+    set_use_superres = 2,
+    set_render_and_frame_size_different = 3,
+    set_allow_screen_content_tools = 4,
+    set_is_filter_switchable = 5,
+    set_force_integer_mv = 6,
+    set_frame_size_override_flag = 7,
+    set_buffer_removal_time_present_flag = 8,
+    set_allow_intrabc = 9,
+    set_frame_refs_short_signaling = 10,
+    set_allow_high_precision_mv = 11,
+    set_is_motion_mode_switchable = 12,
+    set_use_ref_frame_mvs = 13,
+    set_disable_frame_end_update_cdf = 14,
+    set_allow_warped_motion = 15,
+    set_reduced_tx_set = 16,
+    set_skip_mode_present = 17,
+    set_delta_q_present = 18,
+    set_delta_lf_present = 19,
+    set_delta_lf_multi = 20,
+    set_show_existing_frame = 21,
+});
+
+std_flags!(StdVideoEncodeAV1ReferenceInfoFlags {
+    set_disable_frame_end_update_cdf = 0,
+    set_segmentation_enabled = 1,
+});
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StdVideoEncodeAV1DecoderModelInfo {
+    pub buffer_delay_length_minus_1: u8,
+    pub buffer_removal_time_length_minus_1: u8,
+    pub frame_presentation_time_length_minus_1: u8,
+    pub reserved1: u8,
+    pub num_units_in_decoding_tick: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StdVideoEncodeAV1ExtensionHeader {
+    pub temporal_id: u8,
+    pub spatial_id: u8,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StdVideoEncodeAV1OperatingPointInfo {
+    pub flags: StdVideoEncodeAV1OperatingPointInfoFlags,
+    pub operating_point_idc: u16,
+    pub seq_level_idx: u8,
+    pub seq_tier: u8,
+    pub decoder_buffer_delay: u32,
+    pub encoder_buffer_delay: u32,
+    pub initial_display_delay_minus_1: u8,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct StdVideoEncodeAV1PictureInfo {
+    pub flags: StdVideoEncodeAV1PictureInfoFlags,
+    pub frame_type: StdVideoAV1FrameType,
+    pub frame_presentation_time: u32,
+    pub current_frame_id: u32,
+    pub order_hint: u8,
+    pub primary_ref_frame: u8,
+    pub refresh_frame_flags: u8,
+    pub coded_denom: u8,
+    pub render_width_minus_1: u16,
+    pub render_height_minus_1: u16,
+    pub interpolation_filter: StdVideoAV1InterpolationFilter,
+    pub TxMode: StdVideoAV1TxMode,
+    pub delta_q_res: u8,
+    pub delta_lf_res: u8,
+    pub ref_order_hint: [u8; 8],
+    pub ref_frame_idx: [i8; 7],
+    pub reserved1: [u8; 3],
+    pub delta_frame_id_minus_1: [u32; 7],
+    pub pTileInfo: *const StdVideoAV1TileInfo,
+    pub pQuantization: *const StdVideoAV1Quantization,
+    pub pSegmentation: *const StdVideoAV1Segmentation,
+    pub pLoopFilter: *const StdVideoAV1LoopFilter,
+    pub pCDEF: *const StdVideoAV1CDEF,
+    pub pLoopRestoration: *const StdVideoAV1LoopRestoration,
+    pub pGlobalMotion: *const StdVideoAV1GlobalMotion,
+    pub pExtensionHeader: *const StdVideoEncodeAV1ExtensionHeader,
+    pub pBufferRemovalTimes: *const u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct StdVideoEncodeAV1ReferenceInfo {
+    pub flags: StdVideoEncodeAV1ReferenceInfoFlags,
+    pub RefFrameId: u32,
+    pub frame_type: StdVideoAV1FrameType,
+    pub OrderHint: u8,
+    pub reserved1: [u8; 3],
+    pub pExtensionHeader: *const StdVideoEncodeAV1ExtensionHeader,
+}
+
+macro_rules! tagged_structure {
+    ($name:ident, $s_type:expr, [$($extends:ident),*]) => {
+        impl Default for $name<'_> {
+            fn default() -> Self {
+                // all members are plain numbers or pointers
+                let mut rtn: Self = unsafe { std::mem::zeroed() };
+                rtn.s_type = $s_type;
+                rtn
+            }
+        }
+        unsafe impl vk::TaggedStructure for $name<'_> {
+            const STRUCTURE_TYPE: vk::StructureType = $s_type;
+        }
+        $(unsafe impl vk::$extends for $name<'_> {})*
+    };
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalDeviceVideoEncodeAV1FeaturesKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub video_encode_av1: vk::Bool32,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    PhysicalDeviceVideoEncodeAV1FeaturesKHR,
+    STRUCTURE_TYPE_PHYSICAL_DEVICE_VIDEO_ENCODE_AV1_FEATURES_KHR,
+    [ExtendsPhysicalDeviceFeatures2, ExtendsDeviceCreateInfo]
+);
+
+impl<'a> PhysicalDeviceVideoEncodeAV1FeaturesKHR<'a> {
+    pub fn video_encode_av1(mut self, video_encode_av1: bool) -> Self {
+        self.video_encode_av1 = video_encode_av1.into();
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1ProfileInfoKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub std_profile: StdVideoAV1Profile,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1ProfileInfoKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_PROFILE_INFO_KHR,
+    [ExtendsVideoProfileInfoKHR, ExtendsQueryPoolCreateInfo]
+);
+
+impl<'a> VideoEncodeAV1ProfileInfoKHR<'a> {
+    pub fn std_profile(mut self, std_profile: StdVideoAV1Profile) -> Self {
+        self.std_profile = std_profile;
+        self
+    }
+}
+
+pub type VideoEncodeAV1CapabilityFlagsKHR = u32;
+pub type VideoEncodeAV1SuperblockSizeFlagsKHR = u32;
+pub type VideoEncodeAV1StdFlagsKHR = u32;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1CapabilitiesKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub flags: VideoEncodeAV1CapabilityFlagsKHR,
+    pub max_level: StdVideoAV1Level,
+    pub coded_picture_alignment: vk::Extent2D,
+    pub max_tiles: vk::Extent2D,
+    pub min_tile_size: vk::Extent2D,
+    pub max_tile_size: vk::Extent2D,
+    pub superblock_sizes: VideoEncodeAV1SuperblockSizeFlagsKHR,
+    pub max_single_reference_count: u32,
+    pub single_reference_name_mask: u32,
+    pub max_unidirectional_compound_reference_count: u32,
+    pub max_unidirectional_compound_group1_reference_count: u32,
+    pub unidirectional_compound_reference_name_mask: u32,
+    pub max_bidirectional_compound_reference_count: u32,
+    pub max_bidirectional_compound_group1_reference_count: u32,
+    pub max_bidirectional_compound_group2_reference_count: u32,
+    pub bidirectional_compound_reference_name_mask: u32,
+    pub max_temporal_layer_count: u32,
+    pub max_spatial_layer_count: u32,
+    pub max_operating_points: u32,
+    pub min_q_index: u32,
+    pub max_q_index: u32,
+    pub prefers_gop_remaining_frames: vk::Bool32,
+    pub requires_gop_remaining_frames: vk::Bool32,
+    pub std_syntax_flags: VideoEncodeAV1StdFlagsKHR,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1CapabilitiesKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_CAPABILITIES_KHR,
+    [ExtendsVideoCapabilitiesKHR]
+);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1SessionParametersCreateInfoKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub p_std_sequence_header: *const StdVideoAV1SequenceHeader,
+    pub p_std_decoder_model_info: *const StdVideoEncodeAV1DecoderModelInfo,
+    pub std_operating_point_count: u32,
+    pub p_std_operating_points: *const StdVideoEncodeAV1OperatingPointInfo,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1SessionParametersCreateInfoKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_SESSION_PARAMETERS_CREATE_INFO_KHR,
+    [ExtendsVideoSessionParametersCreateInfoKHR]
+);
+
+impl<'a> VideoEncodeAV1SessionParametersCreateInfoKHR<'a> {
+    pub fn std_sequence_header(mut self, header: &'a StdVideoAV1SequenceHeader) -> Self {
+        self.p_std_sequence_header = header;
+        self
+    }
+
+    pub fn std_operating_points(
+        mut self,
+        operating_points: &'a [StdVideoEncodeAV1OperatingPointInfo],
+    ) -> Self {
+        self.std_operating_point_count = operating_points.len() as u32;
+        self.p_std_operating_points = operating_points.as_ptr();
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1PictureInfoKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub prediction_mode: VideoEncodeAV1PredictionModeKHR,
+    pub rate_control_group: VideoEncodeAV1RateControlGroupKHR,
+    pub constant_q_index: u32,
+    pub p_std_picture_info: *const StdVideoEncodeAV1PictureInfo,
+    pub reference_name_slot_indices: [i32; VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR],
+    pub primary_reference_cdf_only: vk::Bool32,
+    pub generate_obu_extension_header: vk::Bool32,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1PictureInfoKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_PICTURE_INFO_KHR,
+    [ExtendsVideoEncodeInfoKHR]
+);
+
+impl<'a> VideoEncodeAV1PictureInfoKHR<'a> {
+    pub fn prediction_mode(mut self, prediction_mode: VideoEncodeAV1PredictionModeKHR) -> Self {
+        self.prediction_mode = prediction_mode;
+        self
+    }
+
+    pub fn rate_control_group(
+        mut self,
+        rate_control_group: VideoEncodeAV1RateControlGroupKHR,
+    ) -> Self {
+        self.rate_control_group = rate_control_group;
+        self
+    }
+
+    pub fn constant_q_index(mut self, constant_q_index: u32) -> Self {
+        self.constant_q_index = constant_q_index;
+        self
+    }
+
+    pub fn std_picture_info(mut self, picture_info: &'a StdVideoEncodeAV1PictureInfo) -> Self {
+        self.p_std_picture_info = picture_info;
+        self
+    }
+
+    pub fn reference_name_slot_indices(
+        mut self,
+        indices: [i32; VK_MAX_VIDEO_AV1_REFERENCES_PER_FRAME_KHR],
+    ) -> Self {
+        self.reference_name_slot_indices = indices;
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1DpbSlotInfoKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub p_std_reference_info: *const StdVideoEncodeAV1ReferenceInfo,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1DpbSlotInfoKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_DPB_SLOT_INFO_KHR,
+    [ExtendsVideoReferenceSlotInfoKHR]
+);
+
+impl<'a> VideoEncodeAV1DpbSlotInfoKHR<'a> {
+    pub fn std_reference_info(
+        mut self,
+        reference_info: &'a StdVideoEncodeAV1ReferenceInfo,
+    ) -> Self {
+        self.p_std_reference_info = reference_info;
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1RateControlInfoKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub flags: VideoEncodeAV1RateControlFlagsKHR,
+    pub gop_frame_count: u32,
+    pub key_frame_period: u32,
+    pub consecutive_bipredictive_frame_count: u32,
+    pub temporal_layer_count: u32,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1RateControlInfoKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_RATE_CONTROL_INFO_KHR,
+    [
+        ExtendsVideoCodingControlInfoKHR,
+        ExtendsVideoBeginCodingInfoKHR
+    ]
+);
+
+impl<'a> VideoEncodeAV1RateControlInfoKHR<'a> {
+    pub fn flags(mut self, flags: VideoEncodeAV1RateControlFlagsKHR) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn gop_frame_count(mut self, gop_frame_count: u32) -> Self {
+        self.gop_frame_count = gop_frame_count;
+        self
+    }
+
+    pub fn key_frame_period(mut self, key_frame_period: u32) -> Self {
+        self.key_frame_period = key_frame_period;
+        self
+    }
+
+    pub fn consecutive_bipredictive_frame_count(mut self, count: u32) -> Self {
+        self.consecutive_bipredictive_frame_count = count;
+        self
+    }
+
+    pub fn temporal_layer_count(mut self, temporal_layer_count: u32) -> Self {
+        self.temporal_layer_count = temporal_layer_count;
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct VideoEncodeAV1QIndexKHR {
+    pub intra_q_index: u32,
+    pub predictive_q_index: u32,
+    pub bipredictive_q_index: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct VideoEncodeAV1FrameSizeKHR {
+    pub intra_frame_size: u32,
+    pub predictive_frame_size: u32,
+    pub bipredictive_frame_size: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VideoEncodeAV1RateControlLayerInfoKHR<'a> {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub use_min_q_index: vk::Bool32,
+    pub min_q_index: VideoEncodeAV1QIndexKHR,
+    pub use_max_q_index: vk::Bool32,
+    pub max_q_index: VideoEncodeAV1QIndexKHR,
+    pub use_max_frame_size: vk::Bool32,
+    pub max_frame_size: VideoEncodeAV1FrameSizeKHR,
+    pub _marker: PhantomData<&'a ()>,
+}
+tagged_structure!(
+    VideoEncodeAV1RateControlLayerInfoKHR,
+    STRUCTURE_TYPE_VIDEO_ENCODE_AV1_RATE_CONTROL_LAYER_INFO_KHR,
+    [ExtendsVideoEncodeRateControlLayerInfoKHR]
+);